@@ -2,10 +2,13 @@
 
 use std::{collections::HashSet, sync::Arc};
 
-use wdc65816::{Cpu, Mem};
+use wdc65816::{Cpu, CpuRegs, Mem};
 
 use crate::rom::Rom;
 
+/// Size of VRAM in bytes, enough to be addressed by any 16-bit word address in $2116.
+pub const VRAM_SIZE: usize = 0x20000;
+
 #[derive(Debug, Clone)]
 pub struct CheckedMem {
     pub cart:       Arc<Rom>,
@@ -14,28 +17,144 @@ pub struct CheckedMem {
     pub vram:       Vec<u8>,
     pub cgram:      Vec<u8>,
     pub extram:     Vec<u8>,
-    pub uninit:     HashSet<usize>,
     pub error:      Option<u32>,
     pub err_value:  Option<u8>,
     pub last_store: Option<u32>,
+    /// Whether the next write to $2122 goes to the high byte of the CGRAM word at the address in $2121.
+    cgram_high:     bool,
+
+    // Uninitialized read tracking
+    track_uninit: bool,
+    /// Addresses accessed since tracking was enabled, with low RAM mirrors mapped to their address in bank $7E.
+    accessed:     HashSet<u32>,
+    uninit_reads: Vec<UninitRead>,
+    /// Address of the instruction being executed by [`run`].
+    current_pc:   u32,
+}
+
+/// Part of the memory map that an [`UninitRead`] was from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MemRegion {
+    /// WRAM in banks $7E-$7F.
+    Wram,
+    /// Mirror of the first $2000 bytes of WRAM in other banks.
+    LowRam,
+    /// Registers at $2000-$7FFF.
+    Io,
+}
+
+/// A read from memory that hadn't been written to since tracking was enabled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UninitRead {
+    pub addr:   u32,
+    pub region: MemRegion,
+    /// Full 24-bit address of the instruction that read it.
+    pub pc:     u32,
+}
+
+/// Copy of the mutable parts of [`CheckedMem`]. The cartridge is shared, so it isn't included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemSnapshot {
+    wram:       Vec<u8>,
+    regs:       Vec<u8>,
+    vram:       Vec<u8>,
+    cgram:      Vec<u8>,
+    extram:     Vec<u8>,
+    cgram_high: bool,
+}
+
+/// Copy of the state of a CPU and its memory, taken with [`snapshot`] and rolled back to with [`restore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuSnapshot {
+    pub regs: CpuRegs,
+    pub mem:  MemSnapshot,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HdmaChannel {
+    pub channel: u8,
+    /// DMA parameters ($43x0)
+    pub params:  u8,
+    /// Low byte of the destination register in bank $21 ($43x1)
+    pub dest:    u8,
+    /// Address of the HDMA table ($43x2-$43x4)
+    pub table:   u32,
 }
 
 impl CheckedMem {
     pub fn new(rom: Arc<Rom>) -> Self {
+        Self::with_vram_size(rom, VRAM_SIZE)
+    }
+
+    /// Creates memory with a VRAM buffer of `vram_size` bytes. Writes past the end of the buffer set `error` instead of
+    /// panicking.
+    ///
+    /// Panics if `vram_size` is odd or larger than [`VRAM_SIZE`].
+    pub fn with_vram_size(rom: Arc<Rom>, vram_size: usize) -> Self {
+        assert!(vram_size % 2 == 0, "VRAM size must be a whole number of words, got {vram_size:#X}");
+        assert!(vram_size <= VRAM_SIZE, "VRAM size must be at most {VRAM_SIZE:#X} bytes, got {vram_size:#X}");
         Self {
             cart:       rom,
             wram:       Vec::from([0; 0x20000]),
             regs:       Vec::from([0; 0x6000]),
-            vram:       Vec::from([0; 0x10000]),
+            vram:       vec![0; vram_size],
             cgram:      Vec::from([0; 0x200]),
             extram:     Vec::from([0; 0x10000]),
-            uninit:     HashSet::new(),
             error:      None,
             err_value:  None,
             last_store: None,
+            cgram_high: false,
+
+            track_uninit: false,
+            accessed:     HashSet::new(),
+            uninit_reads: Vec::new(),
+            current_pc:   0,
+        }
+    }
+
+    /// Enables or disables reporting reads from memory that hasn't been written to. Enabling it forgets about
+    /// previous accesses and reports, so only writes made from then on count.
+    pub fn track_uninitialized(&mut self, enabled: bool) {
+        self.track_uninit = enabled;
+        if enabled {
+            self.accessed.clear();
+            self.uninit_reads.clear();
         }
     }
 
+    /// Uninitialized reads found since tracking was enabled, in order. Each address is reported only once.
+    pub fn uninit_reads(&self) -> &[UninitRead] {
+        &self.uninit_reads
+    }
+
+    fn track_access(&mut self, addr: u32, key: u32, region: MemRegion, write: Option<u8>) {
+        if self.track_uninit && self.accessed.insert(key) && write.is_none() {
+            self.uninit_reads.push(UninitRead { addr, region, pc: self.current_pc });
+        }
+    }
+
+    pub fn snapshot(&self) -> MemSnapshot {
+        MemSnapshot {
+            wram:       self.wram.clone(),
+            regs:       self.regs.clone(),
+            vram:       self.vram.clone(),
+            cgram:      self.cgram.clone(),
+            extram:     self.extram.clone(),
+            cgram_high: self.cgram_high,
+        }
+    }
+
+    /// Restores the memory to the snapshot's state. Error, store and uninitialized read tracking aren't part of
+    /// snapshots, so they're kept as they are.
+    pub fn restore(&mut self, snapshot: &MemSnapshot) {
+        self.wram.clone_from(&snapshot.wram);
+        self.regs.clone_from(&snapshot.regs);
+        self.vram.clone_from(&snapshot.vram);
+        self.cgram.clone_from(&snapshot.cgram);
+        self.extram.clone_from(&snapshot.extram);
+        self.cgram_high = snapshot.cgram_high;
+    }
+
     pub fn load_u8(&mut self, addr: u32) -> u8 {
         self.load(addr)
     }
@@ -76,45 +195,59 @@ impl CheckedMem {
         let b = self.load(0x4301 + ch);
         let params = self.load(0x4300 + ch);
         // TODO: turn this into reg writes
-        #[allow(clippy::overly_complex_bool_expr)]
         if b == 0x18 {
             let dest = self.load_u16(0x2116) as u32;
             //println!("DMA size {:04X}: VRAM ${:02X}:{:04X} => ${:04X}", size, a_bank, a, dest);
-            if params & 0x8 != 0 { // fill transfer
-                 /*let value = self.load(a_bank, a);
-                 for i in dest..dest+size {
-                     self.vram[i as usize * 2] = value;
-                 }*/
+            if params & 0x8 != 0 {
+                // fill transfer
+                let value = self.load(a);
+                let two_registers = params & 0x7 == 1;
+                for i in 0..size {
+                    let index = if two_registers { dest * 2 + i } else { (dest + i) * 2 };
+                    self.store_dma_vram(index, value, 0x2118);
+                }
+                let words = if two_registers { size / 2 } else { size };
+                self.store_u16(0x2116, (dest + words) as u16);
             } else {
                 for i in 0..size {
-                    self.vram[(dest * 2 + i) as usize] = self.load(a + i);
+                    let value = self.load(a + i);
+                    self.store_dma_vram(dest * 2 + i, value, 0x2118);
                 }
+                self.store_u16(0x2116, (dest + size) as u16);
             }
-            self.store_u16(0x2116, (dest + size) as u16);
-        } else if false && b == 0x19 {
-            let _dest = self.load_u16(0x2116);
+        } else if b == 0x19 {
+            let dest = self.load_u16(0x2116) as u32;
             //println!("DMA size {:04X}: VRAMh ${:02X}:{:04X} => ${:04X}", size, a_bank, a, dest);
-            if params & 0x8 != 0 { // fill transfer
-                 /*let value = self.load(a_bank, a);
-                 for i in dest..dest+size {
-                     self.vram[i as usize * 2] = value;
-                 }*/
+            if params & 0x8 != 0 {
+                // fill transfer
+                let value = self.load(a);
+                for i in 0..size {
+                    self.store_dma_vram((dest + i) * 2 + 1, value, 0x2119);
+                }
+                self.store_u16(0x2116, (dest + size) as u16);
+            } else {
+                for i in 0..size {
+                    let value = self.load(a + i);
+                    self.store_dma_vram((dest + i) * 2 + 1, value, 0x2119);
+                }
+                self.store_u16(0x2116, (dest + size) as u16);
             }
         } else if b == 0x22 {
-            let dest = self.load(0x2121) as u32;
-            // cgram
             for i in 0..size {
-                self.cgram[(dest * 2 + i) as usize] = self.load(a + i);
+                let value = self.load(a + i);
+                self.write_cgram(value);
             }
-            self.store_u16(0x2121, (dest + size) as u16);
         } else {
-            println!("DMA size {size:04X}: ${b:02X} ${a:06X}");
+            log::warn!("Unsupported DMA of {size:04X} bytes from ${a:06X} to $21{b:02X}");
         }
     }
 
     pub fn process_dma(&mut self) {
-        let dma = self.load(0x420B);
+        // Polled after every instruction, so it's read directly to keep it out of uninitialized read tracking.
+        let dma = self.regs[0x420B - 0x2000];
         if dma != 0 {
+            // Channels enabled for HDMA are driven by the scanline timing instead, so they're not processed here.
+            let dma = dma & !self.load(0x420C);
             for i in 0..8 {
                 if dma & (1 << i) != 0 {
                     self.process_dma_ch(i * 0x10);
@@ -124,47 +257,101 @@ impl CheckedMem {
         }
     }
 
+    /// Returns the configuration of channels currently enabled for HDMA in $420C.
+    pub fn hdma_channels(&mut self) -> Vec<HdmaChannel> {
+        let hdma = self.load(0x420C);
+        (0..8)
+            .filter(|i| hdma & (1 << i) != 0)
+            .map(|i| {
+                let ch = i as u32 * 0x10;
+                HdmaChannel {
+                    channel: i,
+                    params:  self.load(0x4300 + ch),
+                    dest:    self.load(0x4301 + ch),
+                    table:   self.load_u24(0x4302 + ch),
+                }
+            })
+            .collect()
+    }
+
+    /// Writes a byte transferred by DMA to `index` in VRAM, or sets `error` to `port` if it's out of range.
+    fn store_dma_vram(&mut self, index: u32, value: u8, port: u32) {
+        match self.vram.get_mut(index as usize) {
+            Some(byte) => *byte = value,
+            None => self.error = Some(port),
+        }
+    }
+
+    /// Writes a byte to VRAM at the address in $2116, honouring the address remapping and increment settings of
+    /// VMAIN ($2115).
+    fn write_vram(&mut self, value: u8, high: bool) {
+        let vmain = self.load(0x2115);
+        let addr = self.load_u16(0x2116);
+
+        // Address remapping: rotate the lowest 8, 9 or 10 bits of the address left by 3.
+        let remapped = match (vmain >> 2) & 0b11 {
+            0 => addr,
+            mode => {
+                let bits = 7 + mode as u16;
+                let mask = (1 << bits) - 1;
+                let low = addr & mask;
+                (addr & !mask) | (((low << 3) | (low >> (bits - 3))) & mask)
+            }
+        };
+        // Bit 15 of the word address is ignored, so the upper half of the address space mirrors the lower one.
+        match self.vram.get_mut((remapped as usize & 0x7FFF) * 2 + high as usize) {
+            Some(byte) => *byte = value,
+            None => self.error = Some(if high { 0x2119 } else { 0x2118 }),
+        }
+
+        let increment_on_high = vmain & 0x80 != 0;
+        if high == increment_on_high {
+            let step = match vmain & 0b11 {
+                0 => 1,
+                1 => 32,
+                _ => 128,
+            };
+            self.store_u16(0x2116, addr.wrapping_add(step));
+        }
+    }
+
+    /// Writes a byte to CGRAM at the word address in $2121, alternating between the low and high byte. The address
+    /// is incremented after the high byte is written.
+    fn write_cgram(&mut self, value: u8) {
+        let addr = self.load(0x2121);
+        self.cgram[addr as usize * 2 + self.cgram_high as usize] = value;
+        if self.cgram_high {
+            self.regs[0x0121] = addr.wrapping_add(1);
+        }
+        self.cgram_high = !self.cgram_high;
+    }
+
     pub fn map(&mut self, addr: u32, write: Option<u8>) -> u8 {
-        let track_uninit = false;
         let bank = addr >> 16;
         let mutable = if bank & 0xFE == 0x7E {
             let ptr = (addr & 0x1FFFF) as usize;
-            if track_uninit {
-                if write.is_none() && !self.uninit.contains(&ptr) {
-                    println!("Uninit read: ${:06X}", 0x7E0000 + ptr);
-                }
-                self.uninit.insert(ptr);
-            }
+            self.track_access(addr, 0x7E0000 + ptr as u32, MemRegion::Wram, write);
             &mut self.wram[ptr]
         } else if bank == 0x60 {
             let ptr = (addr & 0xFFFF) as usize;
             &mut self.extram[ptr]
         } else if addr & 0xFFFF < 0x2000 {
             let ptr = (addr & 0x1FFF) as usize;
-            if track_uninit {
-                if write.is_none() && !self.uninit.contains(&ptr) {
-                    println!("Uninit read: ${:06X}", 0x7E0000 + ptr);
-                }
-                self.uninit.insert(ptr);
-            }
+            self.track_access(addr, 0x7E0000 + ptr as u32, MemRegion::LowRam, write);
             &mut self.wram[ptr]
         } else if addr & 0xFFFF < 0x8000 {
             let ptr = (addr & 0x7FFF) as usize;
-            if track_uninit {
-                if write.is_none() && !self.uninit.contains(&ptr) {
-                    //println!("Uninit read: ${:04X}", ptr);
-                }
-                self.uninit.insert(ptr);
-            }
+            self.track_access(addr, ptr as u32, MemRegion::Io, write);
             // TODO: be more accurate
             if let Some(value) = write {
                 if ptr == 0x2118 {
-                    let addr = self.load_u16(0x2116);
-                    self.vram[(addr as usize) * 2 + 0] = value;
+                    self.write_vram(value, false);
                 } else if ptr == 0x2119 {
-                    let addr = self.load_u16(0x2116);
-                    self.vram[(addr as usize) * 2 + 1] = value;
-                    self.store_u16(0x2116, addr + 1);
+                    self.write_vram(value, true);
+                } else if ptr == 0x2121 {
+                    self.cgram_high = false;
+                } else if ptr == 0x2122 {
+                    self.write_cgram(value);
                 }
             }
             &mut self.regs[ptr - 0x2000]
@@ -200,6 +387,66 @@ impl Mem for CheckedMem {
     }
 }
 
+pub fn snapshot(cpu: &Cpu<CheckedMem>) -> CpuSnapshot {
+    CpuSnapshot { regs: cpu.regs(), mem: cpu.mem.snapshot() }
+}
+
+pub fn restore(cpu: &mut Cpu<CheckedMem>, snapshot: &CpuSnapshot) {
+    cpu.set_regs(&snapshot.regs);
+    cpu.mem.restore(&snapshot.mem);
+}
+
+/// Why [`run`] stopped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    /// At least [`RunConfig::max_cycles`] cycles have been run.
+    BudgetExhausted,
+    /// The next instruction is at the breakpoint.
+    Breakpoint(u32),
+    /// An illegal instruction has been executed.
+    Illegal,
+    /// The next instruction is at [`RunConfig::stop_at`].
+    ReachedPc,
+}
+
+/// Conditions for [`run`] to stop. All addresses are full 24-bit `pbr:pc` ones.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct RunConfig<'a> {
+    /// Number of cycles after which to stop, or `None` to run without limit.
+    pub max_cycles:      Option<u64>,
+    pub breakpoints:     &'a [u32],
+    pub stop_on_illegal: bool,
+    /// Address at which the code being run is finished, such as the end of the loader bytecode.
+    pub stop_at:         Option<u32>,
+}
+
+/// Executes instructions, processing any DMA they start, until one of the conditions in `config` is met. Returns why
+/// it stopped and the number of cycles run.
+///
+/// Breakpoints are checked after each instruction, so running again from a breakpoint executes at least one
+/// instruction before stopping at it again.
+pub fn run(cpu: &mut Cpu<CheckedMem>, config: &RunConfig) -> (StopReason, u64) {
+    let mut cy = 0;
+    loop {
+        cpu.mem.current_pc = (cpu.pbr as u32) << 16 | cpu.pc as u32;
+        cy += cpu.dispatch() as u64;
+        cpu.mem.process_dma();
+        let pc = (cpu.pbr as u32) << 16 | cpu.pc as u32;
+        if cpu.ill && config.stop_on_illegal {
+            return (StopReason::Illegal, cy);
+        }
+        if config.stop_at == Some(pc) {
+            return (StopReason::ReachedPc, cy);
+        }
+        if config.breakpoints.contains(&pc) {
+            return (StopReason::Breakpoint(pc), cy);
+        }
+        if config.max_cycles.is_some_and(|max_cycles| cy >= max_cycles) {
+            return (StopReason::BudgetExhausted, cy);
+        }
+    }
+}
+
 pub fn fetch_anim_frame(cpu: &mut Cpu<CheckedMem>) -> u64 {
     cpu.s = 0x1FF;
     cpu.pc = 0x2000;
@@ -217,18 +464,9 @@ pub fn fetch_anim_frame(cpu: &mut Cpu<CheckedMem>) -> u64 {
         cpu.mem.store_u24(addr + 1, cpu.mem.cart.resolve(symbol).unwrap_or_else(|| panic!("no symbol: {symbol}")));
         addr += 4;
     }
-    let mut cy = 0;
-    loop {
-        cy += cpu.dispatch() as u64;
-        //if cy > cy_limit { break; }
-        if cpu.ill {
-            println!("ILLEGAL INSTR");
-            break;
-        }
-        if cpu.pc == addr as u16 {
-            break;
-        }
-        cpu.mem.process_dma();
+    let (reason, cy) = run(cpu, &RunConfig { stop_on_illegal: true, stop_at: Some(addr), ..RunConfig::default() });
+    if reason == StopReason::Illegal {
+        println!("ILLEGAL INSTR");
     }
     cy
 }
@@ -260,22 +498,13 @@ pub fn exec_sprite_id(cpu: &mut Cpu<CheckedMem>, id: u8) -> u64 {
         cpu.mem.store_u24(addr + 1, cpu.mem.cart.resolve(i).unwrap_or_else(|| panic!("no symbol: {}", i)));
         addr += 4;
     }
-    let mut cy = 0;
-    loop {
-        cy += cpu.dispatch() as u64;
-        //if cy > cy_limit { break; }
-        if cpu.ill {
-            println!("ILLEGAL INSTR");
-            break;
-        }
-        if cpu.pc == addr as u16 {
-            break;
-        }
-        if cy > 10000000 {
-            println!("took too long");
-            break;
-        }
-        cpu.mem.process_dma();
+    let config =
+        RunConfig { max_cycles: Some(10000000), stop_on_illegal: true, stop_at: Some(addr), ..RunConfig::default() };
+    let (reason, cy) = run(cpu, &config);
+    match reason {
+        StopReason::Illegal => println!("ILLEGAL INSTR"),
+        StopReason::BudgetExhausted => println!("took too long"),
+        _ => {}
     }
     println!("took {}µs", now.elapsed().as_micros());
     cy
@@ -302,18 +531,9 @@ pub fn exec_sprites(cpu: &mut Cpu<CheckedMem>) -> u64 {
         cpu.mem.store_u24(addr + 1, cpu.mem.cart.resolve(i).unwrap_or_else(|| panic!("no symbol: {}", i)));
         addr += 4;
     }
-    let mut cy = 0;
-    loop {
-        cy += cpu.dispatch() as u64;
-        //if cy > cy_limit { break; }
-        if cpu.ill {
-            println!("ILLEGAL INSTR");
-            break;
-        }
-        if cpu.pc == addr as u16 {
-            break;
-        }
-        cpu.mem.process_dma();
+    let (reason, cy) = run(cpu, &RunConfig { stop_on_illegal: true, stop_at: Some(addr), ..RunConfig::default() });
+    if reason == StopReason::Illegal {
+        println!("ILLEGAL INSTR");
     }
     println!("took {}µs", now.elapsed().as_micros());
     cy
@@ -347,27 +567,27 @@ pub fn decompress_sublevel(cpu: &mut Cpu<CheckedMem>, id: u16) -> u64 {
     }
     let mut cy = 0;
     let layer1_data_ptr = cpu.mem.cart.resolve("Layer1DataPtr").unwrap();
+    let breakpoints = [0x05D8B7, 0x00200C];
+    let config =
+        RunConfig { breakpoints: &breakpoints, stop_on_illegal: true, stop_at: Some(addr), ..RunConfig::default() };
     loop {
-        cy += cpu.dispatch() as u64;
-        //if cy > cy_limit { break; }
-        if cpu.ill {
-            println!("ILLEGAL INSTR");
-            break;
-        }
-        if cpu.pc == 0xD8B7 && cpu.pbr == 0x05 {
-            cpu.mem.store_u16(0xE, id);
-        }
-        if cpu.pc == addr as u16 {
-            break;
-        }
-        if cpu.pc == 0x200C {
-            let _layer1_ptr = cpu.mem.load_u24(layer1_data_ptr);
-            cpu.mem.store_u24(layer1_data_ptr, 0x600000);
-            // todo: properly load level data from RAM at `layer1_ptr`
-            let level_data = std::fs::read("debug/levels/105_YI1main.bin").unwrap();
-            cpu.mem.extram[..level_data.len()].copy_from_slice(&level_data);
+        let (reason, cycles) = run(cpu, &config);
+        cy += cycles;
+        match reason {
+            StopReason::Breakpoint(0x05D8B7) => cpu.mem.store_u16(0xE, id),
+            StopReason::Breakpoint(_) => {
+                let _layer1_ptr = cpu.mem.load_u24(layer1_data_ptr);
+                cpu.mem.store_u24(layer1_data_ptr, 0x600000);
+                // todo: properly load level data from RAM at `layer1_ptr`
+                let level_data = std::fs::read("debug/levels/105_YI1main.bin").unwrap();
+                cpu.mem.extram[..level_data.len()].copy_from_slice(&level_data);
+            }
+            StopReason::Illegal => {
+                println!("ILLEGAL INSTR");
+                break;
+            }
+            _ => break,
         }
-        cpu.mem.process_dma();
     }
     println!("took {}µs", now.elapsed().as_micros());
     cy
@@ -401,24 +621,302 @@ pub fn decompress_extram(cpu: &mut Cpu<CheckedMem>, id: u16) -> u64 {
     }
     let mut cy = 0;
     let layer1_data_ptr = cpu.mem.cart.resolve("Layer1DataPtr").unwrap();
+    let breakpoints = [0x05D8B7, 0x00200C];
+    let config =
+        RunConfig { breakpoints: &breakpoints, stop_on_illegal: true, stop_at: Some(addr), ..RunConfig::default() };
     loop {
-        cy += cpu.dispatch() as u64;
-        //if cy > cy_limit { break; }
-        if cpu.ill {
-            println!("ILLEGAL INSTR");
-            break;
+        let (reason, cycles) = run(cpu, &config);
+        cy += cycles;
+        match reason {
+            StopReason::Breakpoint(0x05D8B7) => cpu.mem.store_u16(0xE, id),
+            StopReason::Breakpoint(_) => {
+                cpu.mem.store_u24(layer1_data_ptr, 0x600000);
+            }
+            StopReason::Illegal => {
+                println!("ILLEGAL INSTR");
+                break;
+            }
+            _ => break,
+        }
+    }
+    println!("took {}µs", now.elapsed().as_micros());
+    cy
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use wdc65816::{Cpu, Mem};
+
+    use super::{restore, run, snapshot, CheckedMem, HdmaChannel, MemRegion, RunConfig, StopReason, UninitRead};
+    use crate::rom::Rom;
+
+    #[test]
+    fn test_dma_skips_hdma_channels() {
+        let mut mem = CheckedMem::new(Arc::new(Rom::new(Vec::new())));
+        mem.store_u16(0x0000, 0x7FFF);
+        for ch in [0x00, 0x10] {
+            // 2 bytes from $7E:0000 to CGRAM
+            mem.store(0x4300 + ch, 0x02);
+            mem.store(0x4301 + ch, 0x22);
+            mem.store_u24(0x4302 + ch, 0x7E0000);
+            mem.store_u16(0x4305 + ch, 2);
+        }
+        mem.store(0x420C, 0x01);
+
+        mem.store(0x2121, 0x00);
+        mem.store(0x420B, 0x01);
+        mem.process_dma();
+        assert_eq!(&mem.cgram[0..2], &[0, 0]);
+        assert_eq!(mem.load(0x420B), 0);
+
+        mem.store(0x420B, 0x02);
+        mem.process_dma();
+        assert_eq!(&mem.cgram[0..2], &[0xFF, 0x7F]);
+
+        let hdma = mem.hdma_channels();
+        assert_eq!(hdma, vec![HdmaChannel { channel: 0, params: 0x02, dest: 0x22, table: 0x7E0000 }]);
+    }
+
+    fn cpu_with_code(code: &[u8]) -> Cpu<CheckedMem> {
+        let mut cpu = Cpu::new(CheckedMem::new(Arc::new(Rom::new(Vec::new()))));
+        cpu.mem.wram[0x1000..0x1000 + code.len()].copy_from_slice(code);
+        cpu.pbr = 0x00;
+        cpu.pc = 0x1000;
+        cpu
+    }
+
+    #[test]
+    fn test_run_to_breakpoint() {
+        // NOP; NOP; NOP; NOP
+        let mut cpu = cpu_with_code(&[0xEA, 0xEA, 0xEA, 0xEA]);
+        let config = RunConfig { breakpoints: &[0x7E1000, 0x001002], stop_at: Some(0x001004), ..RunConfig::default() };
+        assert_eq!(run(&mut cpu, &config), (StopReason::Breakpoint(0x001002), 4));
+        assert_eq!(cpu.pc, 0x1002);
+        assert_eq!(run(&mut cpu, &config), (StopReason::ReachedPc, 4));
+        assert_eq!(cpu.pc, 0x1004);
+    }
+
+    #[test]
+    fn test_run_cycle_budget() {
+        // loop: BRA loop
+        let mut cpu = cpu_with_code(&[0x80, 0xFE]);
+        let config = RunConfig { max_cycles: Some(100), breakpoints: &[0x001002], ..RunConfig::default() };
+        let (reason, cycles) = run(&mut cpu, &config);
+        assert_eq!(reason, StopReason::BudgetExhausted);
+        assert!((100..110).contains(&cycles), "ran for {cycles} cycles");
+        assert_eq!(cpu.pc, 0x1000);
+    }
+
+    #[test]
+    fn test_uninit_reads() {
+        #[rustfmt::skip]
+        let code = [
+            0xAF, 0x00, 0x01, 0x7E, // $1000: LDA $7E0100
+            0xAD, 0x00, 0x01,       // $1004: LDA $0100
+            0x8D, 0x00, 0x02,       // $1007: STA $0200
+            0xAD, 0x00, 0x02,       // $100A: LDA $0200
+            0xAD, 0x12, 0x42,       // $100D: LDA $4212
+            0xAD, 0x50, 0x00,       // $1010: LDA $0050
+        ];
+        let mut cpu = cpu_with_code(&[]);
+        cpu.mem.track_uninitialized(true);
+        for (i, &byte) in code.iter().enumerate() {
+            cpu.mem.store(0x1000 + i as u32, byte);
+        }
+        run(&mut cpu, &RunConfig { stop_at: Some(0x001013), ..RunConfig::default() });
+
+        assert_eq!(cpu.mem.uninit_reads(), [
+            UninitRead { addr: 0x7E0100, region: MemRegion::Wram, pc: 0x001000 },
+            UninitRead { addr: 0x004212, region: MemRegion::Io, pc: 0x00100D },
+            UninitRead { addr: 0x000050, region: MemRegion::LowRam, pc: 0x001010 },
+        ]);
+
+        cpu.mem.track_uninitialized(false);
+        cpu.mem.load(0x7E0300);
+        assert_eq!(cpu.mem.uninit_reads().len(), 3, "reads aren't tracked when disabled");
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut cpu = Cpu::new(CheckedMem::new(Arc::new(Rom::new(Vec::new()))));
+        cpu.mem.store_u16(0x0100, 0x1234);
+        cpu.mem.store(0x2121, 0x00);
+        cpu.mem.store(0x2122, 0x1F);
+        cpu.a = 0x42;
+        cpu.pc = 0x8000;
+        let snap = snapshot(&cpu);
+
+        cpu.mem.store_u16(0x0100, 0xFFFF);
+        cpu.mem.store(0x7F0000, 0xAB);
+        cpu.mem.store(0x2122, 0x7C);
+        cpu.a = 0;
+        cpu.pc = 0x9000;
+        assert_eq!(cpu.mem.cgram[1], 0x7C);
+
+        restore(&mut cpu, &snap);
+        assert_eq!(cpu.mem.load_u16(0x0100), 0x1234);
+        assert_eq!(cpu.mem.load(0x7F0000), 0x00);
+        assert_eq!(&cpu.mem.cgram[0..2], &[0x1F, 0x00]);
+        assert_eq!((cpu.a, cpu.pc), (0x42, 0x8000));
+        assert_eq!(snapshot(&cpu), snap);
+
+        // The CGRAM write toggle is restored too, so the next write goes to the high byte again
+        cpu.mem.store(0x2122, 0x03);
+        assert_eq!(&cpu.mem.cgram[0..2], &[0x1F, 0x03]);
+    }
+
+    #[test]
+    fn test_cgram_writes() {
+        let mut mem = CheckedMem::new(Arc::new(Rom::new(Vec::new())));
+        mem.store(0x2121, 0x10);
+        for byte in [0x1F, 0x00, 0xE0, 0x03] {
+            mem.store(0x2122, byte);
+        }
+        assert_eq!(&mem.cgram[0x20..0x24], &[0x1F, 0x00, 0xE0, 0x03]);
+        assert_eq!(mem.load(0x2121), 0x12);
+
+        // Setting the address resets the low/high byte toggle
+        mem.store(0x2121, 0xFF);
+        mem.store(0x2122, 0x00);
+        mem.store(0x2121, 0x01);
+        mem.store(0x2122, 0xFF);
+        mem.store(0x2122, 0x7F);
+        assert_eq!(&mem.cgram[0x02..0x04], &[0xFF, 0x7F]);
+        assert_eq!(mem.cgram[0x1FE], 0x00);
+    }
+
+    #[test]
+    fn test_dma_vram_fill() {
+        let mut mem = CheckedMem::new(Arc::new(Rom::new(Vec::new())));
+        mem.store(0x0000, 0xAA);
+        mem.store(0x0001, 0xBB);
+        // Fill 8 bytes with the byte at $7E:0000, alternating between $2118 and $2119
+        mem.store(0x4300, 0x09);
+        mem.store(0x4301, 0x18);
+        mem.store_u24(0x4302, 0x7E0000);
+        mem.store_u16(0x4305, 8);
+        mem.store_u16(0x2116, 0x1000);
+        mem.store(0x420B, 0x01);
+        mem.process_dma();
+        assert_eq!(&mem.vram[0x2000..0x2008], &[0xAA; 8]);
+        assert_eq!(mem.vram[0x2008], 0);
+        assert_eq!(mem.load_u16(0x2116), 0x1004);
+
+        // Fill the high bytes of 4 words with the byte at $7E:0001
+        mem.store(0x4300, 0x08);
+        mem.store(0x4301, 0x19);
+        mem.store_u24(0x4302, 0x7E0001);
+        mem.store_u16(0x4305, 4);
+        mem.store_u16(0x2116, 0x1000);
+        mem.store(0x420B, 0x01);
+        mem.process_dma();
+        assert_eq!(&mem.vram[0x2000..0x2008], &[0xAA, 0xBB, 0xAA, 0xBB, 0xAA, 0xBB, 0xAA, 0xBB]);
+        assert_eq!(mem.load_u16(0x2116), 0x1004);
+        assert_eq!(mem.error, None);
+    }
+
+    #[test]
+    fn test_dma_vram_high_bytes() {
+        let mut mem = CheckedMem::new(Arc::new(Rom::new(Vec::new())));
+        mem.store_u24(0x0000, 0x332211);
+        // Copy 3 bytes from $7E:0000 to the high bytes of 3 words
+        mem.store(0x4300, 0x00);
+        mem.store(0x4301, 0x19);
+        mem.store_u24(0x4302, 0x7E0000);
+        mem.store_u16(0x4305, 3);
+        mem.store_u16(0x2116, 0x1000);
+        mem.store(0x420B, 0x01);
+        mem.process_dma();
+        assert_eq!(&mem.vram[0x2000..0x2007], &[0x00, 0x11, 0x00, 0x22, 0x00, 0x33, 0x00]);
+        assert_eq!(mem.load_u16(0x2116), 0x1003);
+        assert_eq!(mem.error, None);
+    }
+
+    #[test]
+    fn test_vram_increment_after_high() {
+        let mut mem = CheckedMem::new(Arc::new(Rom::new(Vec::new())));
+        mem.store(0x2115, 0x80);
+        mem.store_u16(0x2116, 0x1000);
+        for i in 0..4 {
+            mem.store(0x2118, i);
+            mem.store(0x2119, 0x10 + i);
         }
-        if cpu.pc == 0xD8B7 && cpu.pbr == 0x05 {
-            cpu.mem.store_u16(0xE, id);
+        assert_eq!(&mem.vram[0x2000..0x2008], &[0x00, 0x10, 0x01, 0x11, 0x02, 0x12, 0x03, 0x13]);
+        assert_eq!(mem.load_u16(0x2116), 0x1004);
+    }
+
+    #[test]
+    fn test_vram_increment_32_words() {
+        let mut mem = CheckedMem::new(Arc::new(Rom::new(Vec::new())));
+        mem.store(0x2115, 0x81);
+        mem.store_u16(0x2116, 0x1000);
+        for i in 0..4 {
+            mem.store(0x2118, i);
+            mem.store(0x2119, 0x10 + i);
         }
-        if cpu.pc == addr as u16 {
-            break;
+        for i in 0..4 {
+            let word = (0x1000 + 32 * i) * 2;
+            assert_eq!(&mem.vram[word..word + 2], &[i as u8, 0x10 + i as u8]);
         }
-        if cpu.pc == 0x200C {
-            cpu.mem.store_u24(layer1_data_ptr, 0x600000);
+        assert_eq!(mem.vram[0x2002], 0);
+        assert_eq!(mem.load_u16(0x2116), 0x1080);
+    }
+
+    #[test]
+    fn test_vram_increment_after_low() {
+        let mut mem = CheckedMem::new(Arc::new(Rom::new(Vec::new())));
+        mem.store(0x2115, 0x00);
+        mem.store_u16(0x2116, 0x0100);
+        for i in 0..4 {
+            mem.store(0x2118, i);
         }
-        cpu.mem.process_dma();
+        assert_eq!(&mem.vram[0x200..0x208], &[0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00]);
+        assert_eq!(mem.load_u16(0x2116), 0x0104);
+    }
+
+    #[test]
+    fn test_vram_write_at_top() {
+        let mut mem = CheckedMem::new(Arc::new(Rom::new(Vec::new())));
+        mem.store(0x2115, 0x80);
+        mem.store_u16(0x2116, 0x7FFF);
+        mem.store(0x2118, 0x12);
+        mem.store(0x2119, 0x34);
+        assert_eq!(&mem.vram[0xFFFE..0x10000], &[0x12, 0x34]);
+        assert_eq!(mem.load_u16(0x2116), 0x8000);
+
+        // Word addresses from $8000 on mirror the ones below.
+        mem.store_u16(0x2116, 0xFFFF);
+        mem.store(0x2118, 0x56);
+        mem.store(0x2119, 0x78);
+        assert_eq!(&mem.vram[0xFFFE..0x10000], &[0x56, 0x78]);
+        assert_eq!(mem.load_u16(0x2116), 0x0000);
+        assert_eq!(mem.error, None);
+    }
+
+    #[test]
+    fn test_vram_write_out_of_range() {
+        let mut mem = CheckedMem::with_vram_size(Arc::new(Rom::new(Vec::new())), 0x8000);
+        mem.store(0x2115, 0x80);
+        mem.store_u16(0x2116, 0x7FFF);
+        mem.store(0x2118, 0x12);
+        assert_eq!(mem.error, Some(0x2118));
+        mem.store(0x2119, 0x34);
+        assert_eq!(mem.error, Some(0x2119));
+        assert_eq!(mem.vram.len(), 0x8000);
+    }
+
+    #[test]
+    fn test_vram_address_remapping() {
+        let mut mem = CheckedMem::new(Arc::new(Rom::new(Vec::new())));
+        // 8-bit remapping: aaaaaaaaBBBccccc -> aaaaaaaacccccBBB
+        mem.store(0x2115, 0x84);
+        mem.store_u16(0x2116, 0x1021);
+        mem.store(0x2118, 0xAB);
+        mem.store(0x2119, 0xCD);
+        let word = 0x1009 * 2;
+        assert_eq!(&mem.vram[word..word + 2], &[0xAB, 0xCD]);
+        assert_eq!(mem.load_u16(0x2116), 0x1022);
     }
-    println!("took {}µs", now.elapsed().as_micros());
-    cy
 }
@@ -1,7 +1,27 @@
+use duplicate::duplicate_item;
 use emath::*;
 
 use super::{OnCanvas, OnGrid, OnScreen};
 
+#[duplicate_item(wrapper; [OnScreen]; [OnCanvas]; [OnGrid])]
+impl wrapper<Rect> {
+    /// Part of the rectangles that overlaps, or `None` if they don't overlap. Rectangles that only touch at an edge
+    /// overlap with a zero-size rectangle.
+    #[inline]
+    pub fn intersection(self, other: Self) -> Option<Self> {
+        self.intersects(other).then(|| self.intersect(other))
+    }
+
+    /// Smallest rectangle containing all the points, or `None` if there are none.
+    pub fn bounding_box(points: impl IntoIterator<Item = wrapper<Pos2>>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let mut bounds = Self(Rect::from_min_max(first.0, first.0));
+        points.for_each(|point| bounds.extend_with(point));
+        Some(bounds)
+    }
+}
+
 impl OnScreen<Rect> {
     #[inline(always)]
     pub fn to_canvas(self, pixels_per_point: f32, zoom: f32) -> OnCanvas<Rect> {
@@ -56,3 +76,37 @@ impl OnGrid<Rect> {
         ))
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(min: [f32; 2], max: [f32; 2]) -> OnCanvas<Rect> {
+        OnCanvas(Rect::from_min_max(min.into(), max.into()))
+    }
+
+    #[test]
+    fn test_union() {
+        assert_eq!(rect([0., 0.], [8., 8.]).union(rect([16., 4.], [32., 12.])), rect([0., 0.], [32., 12.]));
+        assert_eq!(rect([0., 0.], [16., 16.]).union(rect([4., 4.], [8., 8.])), rect([0., 0.], [16., 16.]));
+    }
+
+    #[test]
+    fn test_intersection() {
+        let a = rect([0., 0.], [16., 16.]);
+        assert_eq!(a.intersection(rect([8., 4.], [24., 8.])), Some(rect([8., 4.], [16., 8.])));
+        assert_eq!(a.intersection(rect([4., 4.], [8., 8.])), Some(rect([4., 4.], [8., 8.])));
+        assert_eq!(a.intersection(rect([16., 0.], [24., 16.])), Some(rect([16., 0.], [16., 16.])), "touching");
+        assert_eq!(a.intersection(rect([17., 0.], [24., 16.])), None);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let points = [Pos2::new(8., 4.), Pos2::new(-2., 10.), Pos2::new(3., 0.)].map(OnCanvas);
+        assert_eq!(OnCanvas::<Rect>::bounding_box(points), Some(rect([-2., 0.], [8., 10.])));
+        assert_eq!(OnCanvas::<Rect>::bounding_box(points.into_iter().take(1)), Some(rect([8., 4.], [8., 4.])));
+        assert_eq!(OnCanvas::<Rect>::bounding_box([]), None);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod basic_renderer;
 pub mod color;
 pub mod gfx_buffers;
+pub mod oam;
 pub mod palette_renderer;
 pub mod tile_renderer;
@@ -0,0 +1,235 @@
+use crate::tile_renderer::Tile;
+
+/// Number of sprites in OAM.
+pub const OAM_SPRITE_COUNT: usize = 128;
+/// Size of OAM in bytes: 4 bytes per sprite in the low table, 2 bits per sprite in the high table.
+pub const OAM_SIZE: usize = OAM_SPRITE_COUNT * 4 + OAM_SPRITE_COUNT / 4;
+
+/// Index of the first sprite tile in VRAM, as used by the tile renderer.
+const SPRITE_TILES_OFFSET: u32 = 0x600;
+/// Sprites use the second half of CGRAM.
+const SPRITE_PALETTES_OFFSET: u32 = 8;
+/// Y position used to hide unused sprites.
+const OFFSCREEN_Y: u8 = 0xF0;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct OamSprite {
+    /// Signed 9-bit horizontal position.
+    pub x:        i16,
+    pub y:        u8,
+    /// 9-bit tile number, including the name table select bit.
+    pub tile:     u16,
+    pub palette:  u8,
+    pub priority: u8,
+    pub flip_x:   bool,
+    pub flip_y:   bool,
+    /// Whether the sprite uses the large size selected in OBSEL ($2101).
+    pub large:    bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Oam {
+    pub sprites: [OamSprite; OAM_SPRITE_COUNT],
+}
+
+impl OamSprite {
+    /// Unpacks a sprite from its four bytes in the low table and its two bits from the high table.
+    pub fn from_bytes(low: [u8; 4], high: u8) -> Self {
+        let [x, y, tile, attributes] = low;
+        Self {
+            x: (((high & 1) as i16) << 8 | x as i16) << 7 >> 7,
+            y,
+            tile: ((attributes as u16 & 1) << 8) | tile as u16,
+            palette: (attributes >> 1) & 0b111,
+            priority: (attributes >> 4) & 0b11,
+            flip_x: (attributes & 0x40) != 0,
+            flip_y: (attributes & 0x80) != 0,
+            large: (high & 2) != 0,
+        }
+    }
+
+    /// Packs the sprite into its four bytes in the low table and its two bits in the high table.
+    pub fn to_bytes(self) -> ([u8; 4], u8) {
+        let attributes = ((self.tile >> 8) as u8 & 1)
+            | ((self.palette & 0b111) << 1)
+            | ((self.priority & 0b11) << 4)
+            | (0x40 * self.flip_x as u8)
+            | (0x80 * self.flip_y as u8);
+        let high = ((self.x >> 8) as u8 & 1) | (2 * self.large as u8);
+        ([self.x as u8, self.y, self.tile as u8, attributes], high)
+    }
+
+    /// Splits the sprite into 8x8 or 16x16 tiles, given the small and large sprite sizes in pixels.
+    pub fn to_tiles(self, sizes: (u32, u32)) -> Vec<Tile> {
+        let size = if self.large { sizes.1 } else { sizes.0 };
+        let tile_size = if size >= 16 { 16 } else { 8 };
+        let tiles_per_side = (size / tile_size).max(1);
+        let tile_step = tile_size / 8;
+        let mut tiles = Vec::with_capacity((tiles_per_side * tiles_per_side) as usize);
+        for row in 0..tiles_per_side {
+            for column in 0..tiles_per_side {
+                // Flipping a large sprite also swaps the positions of its tiles.
+                let src_column = if self.flip_x { tiles_per_side - 1 - column } else { column };
+                let src_row = if self.flip_y { tiles_per_side - 1 - row } else { row };
+                // Tile numbers wrap around within the 16x16 grid of the name table.
+                let tile = (self.tile as u32 & 0x100)
+                    | ((self.tile as u32 + src_column * tile_step) & 0xF)
+                    | ((((self.tile as u32 >> 4) + src_row * tile_step) & 0xF) << 4);
+                let params = 8
+                    | ((self.palette as u32 + SPRITE_PALETTES_OFFSET) << 8)
+                    | ((self.priority as u32 & 0b11) << 12)
+                    | (0x4000 * self.flip_x as u32)
+                    | (0x8000 * self.flip_y as u32)
+                    | (0x10000 * (tile_size == 16) as u32);
+                tiles.push(Tile([
+                    (self.x as i32 + (tile_size * column) as i32) as u32,
+                    self.y as u32 + tile_size * row,
+                    tile + SPRITE_TILES_OFFSET,
+                    params,
+                ]));
+            }
+        }
+        tiles
+    }
+
+    /// Creates a sprite out of a tile, which is large if the tile is 16x16.
+    pub fn from_tile(tile: Tile) -> Self {
+        Self {
+            x:        ((tile.0[0] as i32) << 23 >> 23) as i16,
+            y:        tile.0[1] as u8,
+            tile:     (tile.tile_num().wrapping_sub(SPRITE_TILES_OFFSET) & 0x1FF) as u16,
+            palette:  (tile.color_row().wrapping_sub(SPRITE_PALETTES_OFFSET) & 0b111) as u8,
+            priority: tile.priority() as u8,
+            flip_x:   tile.flip_x(),
+            flip_y:   tile.flip_y(),
+            large:    tile.is_large(),
+        }
+    }
+}
+
+impl Oam {
+    /// Unpacks all sprites from the 544 bytes of OAM.
+    pub fn from_bytes(bytes: &[u8; OAM_SIZE]) -> Self {
+        let (low_table, high_table) = bytes.split_at(OAM_SPRITE_COUNT * 4);
+        let mut sprites = [OamSprite::default(); OAM_SPRITE_COUNT];
+        for (i, sprite) in sprites.iter_mut().enumerate() {
+            let low = low_table[i * 4..i * 4 + 4].try_into().unwrap();
+            let high = (high_table[i / 4] >> ((i % 4) * 2)) & 0b11;
+            *sprite = OamSprite::from_bytes(low, high);
+        }
+        Self { sprites }
+    }
+
+    /// Packs all sprites into the 544 bytes of OAM.
+    pub fn to_bytes(&self) -> [u8; OAM_SIZE] {
+        let mut bytes = [0; OAM_SIZE];
+        for (i, sprite) in self.sprites.iter().enumerate() {
+            let (low, high) = sprite.to_bytes();
+            bytes[i * 4..i * 4 + 4].copy_from_slice(&low);
+            bytes[OAM_SPRITE_COUNT * 4 + i / 4] |= high << ((i % 4) * 2);
+        }
+        bytes
+    }
+
+    /// Creates OAM with a sprite for each of the first 128 `tiles`. The remaining sprites are hidden off-screen.
+    pub fn from_tiles(tiles: &[Tile]) -> Self {
+        let mut sprites = [OamSprite { y: OFFSCREEN_Y, ..Default::default() }; OAM_SPRITE_COUNT];
+        for (sprite, &tile) in sprites.iter_mut().zip(tiles.iter()) {
+            *sprite = OamSprite::from_tile(tile);
+        }
+        Self { sprites }
+    }
+
+    /// Splits all sprites into tiles, using the sprite sizes selected in OBSEL ($2101). Sprites that are hidden
+    /// below the bottom of the screen are skipped.
+    pub fn to_tiles(&self, obsel: u8) -> Vec<Tile> {
+        let sizes = sprite_sizes(obsel);
+        self.sprites.iter().filter(|sprite| sprite.y < 0xE0).flat_map(|sprite| sprite.to_tiles(sizes)).collect()
+    }
+}
+
+impl Default for Oam {
+    fn default() -> Self {
+        Self::from_tiles(&[])
+    }
+}
+
+/// Small and large sprite sizes in pixels selected by OBSEL ($2101). Rectangular sizes are approximated by their
+/// widths.
+pub fn sprite_sizes(obsel: u8) -> (u32, u32) {
+    match obsel >> 5 {
+        0 => (8, 16),
+        1 => (8, 32),
+        2 => (8, 64),
+        3 => (16, 32),
+        4 => (16, 64),
+        5 => (32, 64),
+        6 => (16, 32),
+        _ => (16, 32),
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sprite_packing() {
+        let sprite = OamSprite {
+            x:        -3,
+            y:        0x40,
+            tile:     0x1A3,
+            palette:  3,
+            priority: 2,
+            flip_x:   true,
+            flip_y:   false,
+            large:    true,
+        };
+        let (low, high) = sprite.to_bytes();
+        assert_eq!(low, [0xFD, 0x40, 0xA3, 0b0110_0111]);
+        assert_eq!(high, 0b11);
+        assert_eq!(OamSprite::from_bytes(low, high), sprite);
+    }
+
+    #[test]
+    fn test_oam_packing() {
+        let mut oam = Oam::default();
+        oam.sprites[5] = OamSprite { x: -0xE0, y: 0x10, tile: 0x02, priority: 3, ..Default::default() };
+        oam.sprites[6] = OamSprite { x: 0x20, y: 0x10, tile: 0x04, large: true, ..Default::default() };
+
+        let bytes = oam.to_bytes();
+        assert_eq!(&bytes[20..28], &[0x20, 0x10, 0x02, 0x30, 0x20, 0x10, 0x04, 0x00]);
+        assert_eq!(bytes[OAM_SPRITE_COUNT * 4 + 1], 0b1001 << 2);
+        assert_eq!(Oam::from_bytes(&bytes), oam);
+    }
+
+    #[test]
+    fn test_large_sprite_to_tiles() {
+        let sprite = OamSprite { x: 0x10, y: 0x20, tile: 0x0F, flip_x: true, large: true, ..Default::default() };
+        let tiles = sprite.to_tiles(sprite_sizes(0));
+        assert_eq!(tiles.len(), 1);
+        assert_eq!(tiles[0].tile_num() - SPRITE_TILES_OFFSET, 0x0F);
+        assert!(tiles[0].is_large() && tiles[0].flip_x());
+        assert_eq!(Oam::from_tiles(&tiles).sprites[0], sprite);
+
+        let tiles = sprite.to_tiles(sprite_sizes(1 << 5));
+        let tile_nums: Vec<_> = tiles.iter().map(|t| t.tile_num() - SPRITE_TILES_OFFSET).collect();
+        assert_eq!(tile_nums, vec![0x01, 0x0F, 0x21, 0x2F]);
+        assert_eq!(tiles[3].pos().0, emath::pos2(32., 48.));
+        assert!(tiles.iter().all(|t| t.size() == 16 && t.color_row() == 8 && t.flip_x()));
+    }
+
+    #[test]
+    fn test_tiles_round_trip() {
+        let sprite =
+            OamSprite { x: -8, y: 0x30, tile: 0x123, palette: 5, priority: 1, flip_y: true, ..Default::default() };
+        let tiles = sprite.to_tiles(sprite_sizes(0));
+        assert_eq!(tiles.len(), 1);
+        let oam = Oam::from_tiles(&tiles);
+        assert_eq!(oam.sprites[0], sprite);
+        assert_eq!(oam.sprites[1].y, OFFSCREEN_Y);
+        assert_eq!(oam.to_tiles(0).len(), 1);
+    }
+}
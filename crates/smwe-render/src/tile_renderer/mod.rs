@@ -31,15 +31,19 @@ pub struct TileUniforms {
 #[shrinkwrap(mutable)]
 pub struct Tile(pub [u32; 4]);
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct TileJson {
     x:         u32,
     y:         u32,
     tile_id:   u32,
     scale:     u8,
     color_row: u8,
+    #[serde(default)]
+    priority:  u8,
     flip_x:    bool,
     flip_y:    bool,
+    #[serde(default)]
+    large:     bool,
 }
 
 #[derive(Debug, Error)]
@@ -114,7 +118,7 @@ impl Tile {
     #[inline]
     pub fn rect(self) -> OnCanvas<Rect> {
         let min = self.pos().0;
-        let size = Vec2::splat(self.scale() as f32);
+        let size = Vec2::splat(self.size() as f32);
         OnCanvas(Rect::from_min_size(min, size))
     }
 
@@ -123,16 +127,53 @@ impl Tile {
         self.0[2]
     }
 
+    #[inline]
+    pub fn set_tile_num(&mut self, tile_num: u32) {
+        self.0[2] = tile_num;
+    }
+
     #[inline]
     pub fn scale(self) -> u32 {
         self.0[3] & 0xFF
     }
 
+    /// Size of the tile on the canvas, which is twice the scale for 16x16 sprites.
+    #[inline]
+    pub fn size(self) -> u32 {
+        if self.is_large() {
+            self.scale() * 2
+        } else {
+            self.scale()
+        }
+    }
+
+    /// Whether this is a 16x16 sprite made of `tile_num`, the tile to its right, and the two tiles below them.
+    #[inline]
+    pub fn is_large(self) -> bool {
+        (self.0[3] & 0x10000) != 0
+    }
+
+    #[inline]
+    pub fn set_large(&mut self, large: bool) {
+        self.0[3] = (self.0[3] & !0x10000) | (0x10000 * large as u32);
+    }
+
     #[inline]
     pub fn color_row(self) -> u32 {
         (self.0[3] >> 8) & 0xF
     }
 
+    #[inline]
+    pub fn set_color_row(&mut self, color_row: u32) {
+        self.0[3] = (self.0[3] & !0xF00) | ((color_row & 0xF) << 8);
+    }
+
+    /// Sprite priority relative to the background layers, as stored in OAM.
+    #[inline]
+    pub fn priority(self) -> u32 {
+        (self.0[3] >> 12) & 0x3
+    }
+
     #[inline]
     pub fn flip_x(self) -> bool {
         (self.0[3] & 0x4000) != 0
@@ -203,8 +244,10 @@ impl From<Tile> for TileJson {
             tile_id:   value.tile_num(),
             scale:     value.scale() as u8,
             color_row: value.color_row() as u8,
+            priority:  value.priority() as u8,
             flip_x:    value.flip_x(),
             flip_y:    value.flip_y(),
+            large:     value.is_large(),
         }
     }
 }
@@ -217,8 +260,53 @@ impl From<TileJson> for Tile {
             value.tile_id,
             value.scale as u32
                 | ((value.color_row as u32 & 0xF) << 8)
+                | ((value.priority as u32 & 0x3) << 12)
                 | (0x4000 * value.flip_x as u32)
-                | (0x8000 * value.flip_y as u32),
+                | (0x8000 * value.flip_y as u32)
+                | (0x10000 * value.large as u32),
         ])
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use emath::{pos2, vec2, Rect};
+    use smwe_math::coordinates::{OnCanvas, OnScreen};
+
+    use super::{Tile, TileJson};
+
+    fn large_tile_at(x: u32, y: u32) -> Tile {
+        let mut tile = Tile([x, y, 0x600, 8 | (8 << 8)]);
+        tile.set_large(true);
+        tile
+    }
+
+    #[test]
+    fn test_select_large_tile() {
+        let tile = large_tile_at(16, 16);
+        assert_eq!(tile.size(), 16);
+        assert_eq!(tile.rect().0, Rect::from_min_size(pos2(16., 16.), vec2(16., 16.)));
+        assert!(tile.contains_point(OnCanvas(pos2(30., 30.))));
+        assert!(!tile.contains_point(OnCanvas(pos2(33., 20.))));
+        assert!(tile.intersects_rect(OnCanvas(Rect::from_min_max(pos2(28., 28.), pos2(40., 40.)))));
+        assert!(!tile.intersects_rect(OnCanvas(Rect::from_min_max(pos2(33., 0.), pos2(40., 40.)))));
+    }
+
+    #[test]
+    fn test_move_large_tile() {
+        let mut tile = large_tile_at(16, 16);
+        tile.move_by(OnCanvas(vec2(-4., 13.)));
+        assert_eq!(tile.pos().0, pos2(12., 29.));
+        assert!(tile.is_large());
+
+        tile.snap_to_grid(8, OnScreen(vec2(0., 0.)));
+        assert_eq!(tile.pos().0, pos2(8., 24.));
+        assert!(tile.contains_point(OnCanvas(pos2(23., 39.))));
+
+        let tile = Tile::from(TileJson::from(tile));
+        assert!(tile.is_large());
+        assert_eq!(tile.size(), 16);
+    }
+}
@@ -20,18 +20,23 @@ void main() {
     
     int tile_id = g_tile_id;
     int color_row = (g_params >> 8) & 0xF;
+    int size = (g_params & 0x10000) != 0 ? 16 : 8;
     ivec2 icoord = ivec2(g_tex_coords) * 8 / int(scale * zoom);
     
     bool flip_x = (g_params & 0x4000) != 0;
     bool flip_y = (g_params & 0x8000) != 0;
     
     if (flip_y) {
-        icoord.y = 7 - icoord.y;
+        icoord.y = size - 1 - icoord.y;
     }
     if (flip_x) {
-        icoord.x = 7 - icoord.x;
+        icoord.x = size - 1 - icoord.x;
     }
 
+    // 16x16 sprites are made of four tiles, two of which are in the next row of VRAM.
+    tile_id += icoord.x / 8 + (icoord.y / 8) * 16;
+    icoord %= 8;
+
     uvec4 part1 = graphics[tile_id * 2 + 0];
     uvec4 part2 = graphics[tile_id * 2 + 1];
 
@@ -17,6 +17,9 @@ void main() {
 	g_tile_id = v_tile_id[0];
 	g_params = v_params[0];
     float scale = float(v_params[0] & 0xFF) * zoom;
+    if ((v_params[0] & 0x10000) != 0) {
+        scale *= 2.0;
+    }
 
 	vec2 pos;
 	vec2 p;
@@ -0,0 +1,404 @@
+//! A minimal 65816 assembler for trying out short snippets of code.
+//!
+//! Every instruction in [`SNES_OPCODES`] is supported except for block moves, along with labels, comments starting
+//! with `;`, and `db`/`dw` directives. Operands are written as `$` hexadecimal, `%` binary or decimal numbers, or
+//! labels. The operand's size is taken from the number of digits unless the mnemonic has a `.b`, `.w` or `.l` suffix,
+//! and addressing modes the instruction doesn't have are widened to the next bigger one, e.g. `JSL $8000` is long.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::disassembler::opcodes::{AddressingMode, AddressingMode::*, Opcode, SNES_OPCODES};
+
+// -------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+#[error("Line {line}: {message}")]
+pub struct AssemblyError {
+    /// One-based line number.
+    pub line:    usize,
+    pub message: String,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Size of an operand in bytes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Width {
+    Byte = 1,
+    Word = 2,
+    Long = 3,
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Number(u32, Width),
+    Label(String),
+}
+
+/// Shape of an operand, without the value it holds.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Syntax {
+    None,
+    Accumulator,
+    Immediate,
+    Direct,
+    XIndex,
+    YIndex,
+    SIndex,
+    Indirect,
+    XIndexIndirect,
+    IndirectYIndex,
+    SIndexIndirectYIndex,
+    LongIndirect,
+    LongIndirectYIndex,
+}
+
+#[derive(Clone, Debug)]
+enum Statement {
+    Data { values: Vec<Value>, width: Width },
+    Instruction { opcode: u8, operand: Option<Value>, operand_size: usize },
+}
+
+struct Line {
+    number:    usize,
+    address:   u32,
+    statement: Statement,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Assembles the source into machine code to be placed at the SNES address `origin`. Returns all errors found
+/// instead if there are any.
+pub fn assemble(source: &str, origin: u32) -> Result<Vec<u8>, Vec<AssemblyError>> {
+    let mut errors = Vec::new();
+    let mut labels = HashMap::new();
+    let mut lines = Vec::new();
+    let mut address = origin;
+
+    for (idx, text) in source.lines().enumerate() {
+        let number = idx + 1;
+        let mut text = text.split(';').next().unwrap_or_default().trim();
+        while let Some((label, rest)) = split_label(text) {
+            if labels.insert(label, address).is_some() {
+                errors.push(AssemblyError { line: number, message: format!("Label '{label}' is already defined") });
+            }
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        match parse_statement(text) {
+            Ok(statement) => {
+                let size = statement.size() as u32;
+                lines.push(Line { number, address, statement });
+                address = address.wrapping_add(size);
+            }
+            Err(message) => errors.push(AssemblyError { line: number, message }),
+        }
+    }
+
+    let mut bytes = Vec::new();
+    for line in lines {
+        if let Err(message) = line.statement.encode(line.address, &labels, &mut bytes) {
+            errors.push(AssemblyError { line: line.number, message });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(bytes)
+    } else {
+        errors.sort_by_key(|error| error.line);
+        Err(errors)
+    }
+}
+
+/// Splits `label: rest` into the label and the rest of the line.
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let (label, rest) = text.split_once(':')?;
+    is_identifier(label).then_some((label, rest))
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_statement(text: &str) -> Result<Statement, String> {
+    let (mnemonic, operand) = match text.split_once(char::is_whitespace) {
+        Some((mnemonic, operand)) => (mnemonic, operand.trim()),
+        None => (text, ""),
+    };
+    let mnemonic = mnemonic.to_ascii_lowercase();
+
+    match mnemonic.trim_start_matches('.') {
+        "db" => return parse_data(operand, Width::Byte),
+        "dw" => return parse_data(operand, Width::Word),
+        _ => {}
+    }
+
+    let (name, forced_width) = match mnemonic.split_once('.') {
+        Some((name, "b")) => (name, Some(Width::Byte)),
+        Some((name, "w")) => (name, Some(Width::Word)),
+        Some((name, "l")) => (name, Some(Width::Long)),
+        Some((_, suffix)) => return Err(format!("Invalid size suffix '.{suffix}'")),
+        None => (mnemonic.as_str(), None),
+    };
+    let opcodes: Vec<_> = (0..=0xFF)
+        .filter(|&op| SNES_OPCODES[op as usize].mnemonic.to_string().eq_ignore_ascii_case(name))
+        .map(|op: u8| (op, SNES_OPCODES[op as usize]))
+        .collect();
+    let Some(&(_, Opcode { mnemonic, .. })) = opcodes.first() else {
+        return Err(format!("Unknown instruction '{name}'"));
+    };
+    let find = |mode: AddressingMode| opcodes.iter().find(|(_, opcode)| opcode.mode == mode).map(|&(op, _)| op);
+
+    let (syntax, value) = parse_operand(operand)?;
+    let value_width = match (&value, forced_width) {
+        (_, Some(width)) => width,
+        (Some(Value::Number(_, width)), None) => *width,
+        (Some(Value::Label(_)), None) => Width::Word,
+        (None, None) => Width::Byte,
+    };
+
+    let found = match syntax {
+        Syntax::None => find(Implied).or_else(|| find(Accumulator)).map(|op| (op, 0)),
+        Syntax::Accumulator => find(Accumulator).map(|op| (op, 0)),
+        Syntax::Immediate => match value_width {
+            Width::Byte => find(Immediate8).or_else(|| find(Constant8)),
+            Width::Word => find(Immediate16),
+            Width::Long => None,
+        }
+        .or_else(|| find(ImmediateMFlagDependent).or_else(|| find(ImmediateXFlagDependent)))
+        .filter(|_| value_width != Width::Long)
+        .map(|op| (op, value_width as usize)),
+        Syntax::Direct => find(Relative8)
+            .map(|op| (op, 1))
+            .or_else(|| find(Relative16).map(|op| (op, 2)))
+            .or_else(|| widest_mode(&find, &[DirectPage, Address, Long], value_width))
+            .or_else(|| find(Constant8).filter(|_| value_width == Width::Byte).map(|op| (op, 1))),
+        Syntax::XIndex => widest_mode(&find, &[DirectPageXIndex, AddressXIndex, LongXIndex], value_width),
+        Syntax::YIndex => widest_mode(&find, &[DirectPageYIndex, AddressYIndex], value_width),
+        Syntax::SIndex => widest_mode(&find, &[DirectPageSIndex], value_width),
+        Syntax::Indirect => widest_mode(&find, &[DirectPageIndirect, AddressIndirect], value_width),
+        Syntax::XIndexIndirect => widest_mode(&find, &[DirectPageXIndexIndirect, AddressXIndexIndirect], value_width),
+        Syntax::IndirectYIndex => widest_mode(&find, &[DirectPageIndirectYIndex], value_width),
+        Syntax::SIndexIndirectYIndex => widest_mode(&find, &[DirectPageSIndexIndirectYIndex], value_width),
+        Syntax::LongIndirect => widest_mode(&find, &[DirectPageLongIndirect, AddressLongIndirect], value_width),
+        Syntax::LongIndirectYIndex => widest_mode(&find, &[DirectPageLongIndirectYIndex], value_width),
+    };
+
+    match found {
+        Some((opcode, operand_size)) => Ok(Statement::Instruction { opcode, operand: value, operand_size }),
+        None => Err(format!("Invalid operand '{operand}' for {mnemonic}")),
+    }
+}
+
+/// Returns the first of the modes, ordered from the smallest operand, that the instruction has and whose operand
+/// fits a value of the given width.
+fn widest_mode(
+    find: &impl Fn(AddressingMode) -> Option<u8>, modes: &[AddressingMode], width: Width,
+) -> Option<(u8, usize)> {
+    modes
+        .iter()
+        .filter(|mode| mode.operands_size() >= width as usize)
+        .find_map(|&mode| find(mode).map(|op| (op, mode.operands_size())))
+}
+
+fn parse_operand(operand: &str) -> Result<(Syntax, Option<Value>), String> {
+    let operand: String = operand.chars().filter(|c| !c.is_whitespace()).collect();
+    let lower = operand.to_ascii_lowercase();
+    let inner = |prefix: usize, suffix: usize| &operand[prefix..operand.len() - suffix];
+
+    let (syntax, value) = if operand.is_empty() {
+        return Ok((Syntax::None, None));
+    } else if lower == "a" {
+        return Ok((Syntax::Accumulator, None));
+    } else if lower.starts_with('#') {
+        (Syntax::Immediate, inner(1, 0))
+    } else if lower.starts_with('(') && lower.ends_with(",s),y") {
+        (Syntax::SIndexIndirectYIndex, inner(1, 5))
+    } else if lower.starts_with('(') && lower.ends_with("),y") {
+        (Syntax::IndirectYIndex, inner(1, 3))
+    } else if lower.starts_with('(') && lower.ends_with(",x)") {
+        (Syntax::XIndexIndirect, inner(1, 3))
+    } else if lower.starts_with('(') && lower.ends_with(')') {
+        (Syntax::Indirect, inner(1, 1))
+    } else if lower.starts_with('[') && lower.ends_with("],y") {
+        (Syntax::LongIndirectYIndex, inner(1, 3))
+    } else if lower.starts_with('[') && lower.ends_with(']') {
+        (Syntax::LongIndirect, inner(1, 1))
+    } else if lower.ends_with(",x") {
+        (Syntax::XIndex, inner(0, 2))
+    } else if lower.ends_with(",y") {
+        (Syntax::YIndex, inner(0, 2))
+    } else if lower.ends_with(",s") {
+        (Syntax::SIndex, inner(0, 2))
+    } else {
+        (Syntax::Direct, inner(0, 0))
+    };
+    Ok((syntax, Some(parse_value(value)?)))
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    let number = |digits: &str, radix: u32, digits_per_byte: usize| {
+        let value = u32::from_str_radix(digits, radix).map_err(|_| format!("Invalid number '{text}'"))?;
+        let width = match digits.len() {
+            n if n <= digits_per_byte => Width::Byte,
+            n if n <= 2 * digits_per_byte => Width::Word,
+            n if n <= 3 * digits_per_byte => Width::Long,
+            _ => return Err(format!("Number '{text}' is too large")),
+        };
+        Ok(Value::Number(value, width))
+    };
+    if let Some(digits) = text.strip_prefix('$') {
+        number(digits, 16, 2)
+    } else if let Some(digits) = text.strip_prefix('%') {
+        number(digits, 2, 8)
+    } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+        let value: u32 = text.parse().map_err(|_| format!("Invalid number '{text}'"))?;
+        let width = match value {
+            0..=0xFF => Width::Byte,
+            0x100..=0xFFFF => Width::Word,
+            0x10000..=0xFFFFFF => Width::Long,
+            _ => return Err(format!("Number '{text}' is too large")),
+        };
+        Ok(Value::Number(value, width))
+    } else if is_identifier(text) {
+        Ok(Value::Label(String::from(text)))
+    } else {
+        Err(format!("Invalid operand '{text}'"))
+    }
+}
+
+fn parse_data(operands: &str, width: Width) -> Result<Statement, String> {
+    let values = operands.split(',').map(|value| parse_value(value.trim())).collect::<Result<Vec<_>, _>>()?;
+    Ok(Statement::Data { values, width })
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self {
+            Statement::Data { values, width } => values.len() * *width as usize,
+            Statement::Instruction { operand_size, .. } => 1 + operand_size,
+        }
+    }
+
+    fn encode(&self, address: u32, labels: &HashMap<&str, u32>, out: &mut Vec<u8>) -> Result<(), String> {
+        let resolve = |value: &Value| match value {
+            Value::Number(number, _) => Ok(*number),
+            Value::Label(label) => {
+                labels.get(label.as_str()).copied().ok_or_else(|| format!("Unknown label '{label}'"))
+            }
+        };
+        match self {
+            Statement::Data { values, width } => {
+                for value in values {
+                    let number = resolve(value)?;
+                    if *width == Width::Byte && number > 0xFF {
+                        return Err(format!("Value {number:#X} doesn't fit in a byte"));
+                    }
+                    // Labels are addresses, of which a word holds the part within their bank.
+                    if *width == Width::Word && number > 0xFFFF && matches!(value, Value::Number(..)) {
+                        return Err(format!("Value {number:#X} doesn't fit in a word"));
+                    }
+                    out.extend_from_slice(&number.to_le_bytes()[..*width as usize]);
+                }
+            }
+            Statement::Instruction { opcode, operand, operand_size } => {
+                out.push(*opcode);
+                let Some(operand) = operand else { return Ok(()) };
+                let mut number = resolve(operand)?;
+                let mode = SNES_OPCODES[*opcode as usize].mode;
+                if matches!(mode, Relative8 | Relative16) {
+                    let next = address.wrapping_add(self.size() as u32);
+                    let offset = (number as i64 & 0xFFFF) - (next as i64 & 0xFFFF);
+                    let range = if mode == Relative8 { -0x80..=0x7F } else { -0x8000..=0x7FFF };
+                    if !range.contains(&offset) {
+                        return Err(format!("Branch target ${number:06X} is out of range"));
+                    }
+                    number = offset as u32;
+                }
+                out.extend_from_slice(&number.to_le_bytes()[..*operand_size]);
+            }
+        }
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[track_caller]
+    fn assert_assembles(source: &str, expected: &[u8]) {
+        assert_eq!(assemble(source, 0x008000), Ok(expected.to_vec()), "{source}");
+    }
+
+    #[test]
+    fn test_instructions() {
+        assert_assembles("NOP", &[0xEA]);
+        assert_assembles("lda #$12", &[0xA9, 0x12]);
+        assert_assembles("LDA #$1234", &[0xA9, 0x34, 0x12]);
+        assert_assembles("REP #$30", &[0xC2, 0x30]);
+        assert_assembles("LDA $19", &[0xA5, 0x19]);
+        assert_assembles("STA $0DBF", &[0x8D, 0xBF, 0x0D]);
+        assert_assembles("STA.w $19", &[0x8D, 0x19, 0x00]);
+        assert_assembles("LDA $7E0019", &[0xAF, 0x19, 0x00, 0x7E]);
+        assert_assembles("LDA $9E,x", &[0xB5, 0x9E]);
+        assert_assembles("LDA $1234, Y", &[0xB9, 0x34, 0x12]);
+        assert_assembles("LDA ($00),y", &[0xB1, 0x00]);
+        assert_assembles("LDA [$00],y", &[0xB7, 0x00]);
+        assert_assembles("LDA ($01,s),y", &[0xB3, 0x01]);
+        assert_assembles("JMP ($0000,x)", &[0x7C, 0x00, 0x00]);
+        assert_assembles("JML [$0000]", &[0xDC, 0x00, 0x00]);
+        assert_assembles("ASL", &[0x0A]);
+        assert_assembles("ASL A", &[0x0A]);
+        assert_assembles("JSL $8000", &[0x22, 0x00, 0x80, 0x00]);
+        assert_assembles("JSR $12", &[0x20, 0x12, 0x00]);
+        assert_assembles("BRK #$00", &[0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_labels_and_data() {
+        let source = "
+            start:  LDX #$03    ; Count down
+            loop:   DEX
+                    BNE loop
+                    BRA end
+            table:  db $01, 2, %11
+                    .dw $1234, end
+            end:    JSL start
+                    LDA table,x
+        ";
+        assert_assembles(source, &[
+            0xA2, 0x03, // LDX #$03
+            0xCA, // DEX
+            0xD0, 0xFD, // BNE loop
+            0x80, 0x07, // BRA end
+            0x01, 0x02, 0x03, 0x34, 0x12, 0x0E, 0x80, // data
+            0x22, 0x00, 0x80, 0x00, // JSL start
+            0xBD, 0x07, 0x80, // LDA table,x
+        ]);
+        assert_assembles("BRL next\nnext:", &[0x82, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let source = "FOO\nLDA #$12\nBRA far\nSTA ($12),x\ndb $100\nLDA #$123456\nx: NOP\nx: NOP\ndw $12345";
+        let errors = assemble(source, 0x008000).unwrap_err();
+        let lines: Vec<_> = errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, [1, 3, 4, 5, 6, 8, 9]);
+        assert_eq!(errors[0].message, "Unknown instruction 'foo'");
+        assert_eq!(errors[1].message, "Unknown label 'far'");
+        assert_eq!(errors[1].to_string(), "Line 3: Unknown label 'far'");
+        assert_eq!(errors[6].message, "Value 0x12345 doesn't fit in a word");
+
+        let far_branch = format!("BRA far\nds: {}\nfar: NOP", "NOP\n".repeat(0x80));
+        assert_eq!(assemble(&far_branch, 0x008000).unwrap_err()[0].line, 1);
+    }
+}
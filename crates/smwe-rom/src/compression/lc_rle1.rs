@@ -18,6 +18,7 @@ pub enum LcRle1Error {
 
 const COMMAND_DIRECT_COPY: u8 = 0;
 const COMMAND_BYTE_FILL: u8 = 1;
+const MAX_CHUNK_LENGTH: usize = 128;
 
 // -------------------------------------------------------------------------------------------------
 
@@ -58,3 +59,58 @@ pub fn decompress(input: &[u8]) -> Result<(Vec<u8>, usize), DecompressionError>
     let bytes_consumed = input.len() - in_it.len();
     Ok((output, bytes_consumed))
 }
+
+/// Returns data compressed with LC-RLE1, followed by the end of data marker.
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    fn flush_direct_copy(output: &mut Vec<u8>, bytes: &[u8]) {
+        if !bytes.is_empty() {
+            output.push((COMMAND_DIRECT_COPY << 7) | (bytes.len() - 1) as u8);
+            output.extend_from_slice(bytes);
+        }
+    }
+
+    let mut output = Vec::with_capacity(input.len() + 2);
+    let mut copy_start = 0;
+    let mut i = 0;
+    while i < input.len() {
+        let byte = input[i];
+        // Filling 128 bytes with 0xFF would be encoded the same way as the end of data marker.
+        let max_run = if byte == 0xFF { MAX_CHUNK_LENGTH - 1 } else { MAX_CHUNK_LENGTH };
+        let run = input[i..].iter().take(max_run).take_while(|&&b| b == byte).count();
+        if run >= 3 {
+            flush_direct_copy(&mut output, &input[copy_start..i]);
+            output.extend_from_slice(&[(COMMAND_BYTE_FILL << 7) | (run - 1) as u8, byte]);
+            i += run;
+            copy_start = i;
+        } else {
+            i += 1;
+            if i - copy_start == MAX_CHUNK_LENGTH {
+                flush_direct_copy(&mut output, &input[copy_start..i]);
+                copy_start = i;
+            }
+        }
+    }
+    flush_direct_copy(&mut output, &input[copy_start..]);
+    output.extend_from_slice(&[0xFF, 0xFF]);
+    output
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_compression_round_trip() {
+        let mut input = vec![1, 2, 3, 3, 3, 3, 4];
+        input.extend([0xFF; 200]);
+        input.extend((0..=255).cycle().take(300));
+        input.extend([7; 130]);
+
+        let compressed = super::compress(&input);
+        assert_eq!(&compressed[..4], &[0x01, 1, 2, 0x83]);
+        assert!(compressed.ends_with(&[0xFF, 0xFF]));
+        let (decompressed, bytes_consumed) = super::decompress(&compressed).unwrap();
+        assert_eq!(decompressed, input);
+        assert_eq!(bytes_consumed, compressed.len() - 2);
+    }
+}
@@ -1,5 +1,13 @@
+use std::fmt::Write;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    disassembler::{instruction::Instruction, processor::Processor},
+    disassembler::{
+        instruction::{DisplaySyntax, Instruction},
+        processor::Processor,
+    },
     snes_utils::{
         addr::{Addr, AddrPc, AddrSnes},
         rom_slice::SnesSlice,
@@ -8,7 +16,7 @@ use crate::{
 
 // -------------------------------------------------------------------------------------------------
 
-#[derive(Clone)]
+#[derive(Clone, Deserialize, Serialize)]
 pub enum BinaryBlock {
     Code(CodeBlock),
     Data(DataBlock),
@@ -16,7 +24,7 @@ pub enum BinaryBlock {
     EndOfRom,
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Deserialize, Serialize)]
 pub struct CodeBlock {
     pub instructions:          Vec<Instruction>,
     pub exits:                 Vec<AddrSnes>,
@@ -25,13 +33,13 @@ pub struct CodeBlock {
     pub final_processor_state: Processor,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
 pub struct DataBlock {
     pub slice: SnesSlice,
     pub kind:  DataKind,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
 pub enum DataKind {
     Empty,
 
@@ -46,6 +54,10 @@ pub enum DataKind {
     JumpTableShort,
     JumpTableLong,
 
+    // Pointer tables
+    PointerTableShort,
+    PointerTableLong,
+
     // Level
     LevelPointersLayer1,
     LevelPointersLayer2,
@@ -121,18 +133,36 @@ impl BinaryBlock {
 }
 
 impl CodeBlock {
-    /// Returns parsed code block and the address of the next byte after the block end
+    /// Returns parsed basic block and the address of the next byte after the block end. Subroutine calls don't end the
+    /// block, since execution continues after them.
+    ///
+    /// This only affects callers of this function: the blocks of a [`RomDisassembly`] are built with
+    /// [`from_bytes_until_call`](Self::from_bytes_until_call) and still end at every call.
+    ///
+    /// [`RomDisassembly`]: super::RomDisassembly
     pub fn from_bytes(base: AddrPc, bytes: &[u8], processor: &mut Processor) -> (Self, AddrPc) {
+        Self::parse(base, bytes, processor, Instruction::ends_basic_block)
+    }
+
+    /// Like [`from_bytes`](Self::from_bytes), but also ends the block after a subroutine call, as the code following it
+    /// can only be analysed once the processor state the subroutine returns with is known.
+    pub(crate) fn from_bytes_until_call(base: AddrPc, bytes: &[u8], processor: &mut Processor) -> (Self, AddrPc) {
+        Self::parse(base, bytes, processor, Instruction::can_change_program_counter)
+    }
+
+    fn parse(
+        base: AddrPc, bytes: &[u8], processor: &mut Processor, ends_block: impl Fn(Instruction) -> bool,
+    ) -> (Self, AddrPc) {
         let mut instructions = Vec::with_capacity(bytes.len() / 2);
         let mut addr = base;
         let mut rest = bytes;
         let original_processor = processor.clone();
-        while let Ok((i, new_rest)) = Instruction::parse(rest, addr, processor.p_reg) {
+        while let Ok((i, new_rest)) = Instruction::parse(rest, addr, processor) {
             instructions.push(i);
             rest = new_rest;
             addr += i.opcode.instruction_size() as u32;
             processor.execute(i);
-            if i.can_change_program_counter() {
+            if ends_block(i) {
                 break;
             }
         }
@@ -148,6 +178,52 @@ impl CodeBlock {
         )
     }
 
+    /// Estimates the number of CPU cycles taken to execute the whole block when entered with the given processor
+    /// state. Conditional branches at the end of the block are assumed not to be taken.
+    pub fn estimate_cycles(&self, processor: &Processor) -> u32 {
+        let mut processor = processor.clone();
+        let mut cycles = 0;
+        for &insn in self.instructions.iter() {
+            cycles += insn.opcode.estimate_cycles(processor.p_reg.m_flag(), processor.p_reg.x_flag(), processor.dp);
+            processor.execute(insn);
+        }
+        cycles
+    }
+
+    /// Returns assembler source for the block: a label at its start followed by its instructions, one per line.
+    ///
+    /// Immediate operands are written with as many digits as their width, which is how asar tells the width apart,
+    /// and changes of the M and X flags are noted in comments. Relative branches are written with the address they
+    /// branch to.
+    pub fn to_asm(&self, syntax: DisplaySyntax) -> String {
+        let mut asm = String::new();
+        let Some(first) = self.instructions.first() else { return asm };
+        let start = AddrSnes::try_from(first.offset).expect("Invalid instruction address");
+        writeln!(asm, "CODE_{:06X}:", start.0).unwrap();
+        if !self.entrances.is_empty() {
+            let entrances = self.entrances.iter().map(|e| format!("${:06X}", e.0)).join(", ");
+            writeln!(asm, "    ; Entered from {entrances}").unwrap();
+        }
+
+        let mut flags = None;
+        for &insn in self.instructions.iter() {
+            if flags != Some((insn.m_flag, insn.x_flag)) {
+                flags = Some((insn.m_flag, insn.x_flag));
+                let bits = |flag: bool| if flag { 8 } else { 16 };
+                writeln!(asm, "    ; A: {}-bit, X/Y: {}-bit", bits(insn.m_flag), bits(insn.x_flag)).unwrap();
+            }
+            match insn.relative_target() {
+                Some(target) => {
+                    let text =
+                        format!("{} {}{:04X}", insn.opcode.mnemonic, syntax.hex_prefix.as_str(), target.absolute());
+                    writeln!(asm, "    {}", if syntax.lowercase { text.to_lowercase() } else { text }).unwrap();
+                }
+                None => writeln!(asm, "    {}", insn.display().with_syntax(syntax)).unwrap(),
+            }
+        }
+        asm
+    }
+
     pub fn recalculate_final_processor_state(&mut self) {
         let mut processor = self.entry_processor_state.clone();
         for &insn in self.instructions.iter() {
@@ -186,3 +262,64 @@ impl DataBlock {
         self
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disassembler::{instruction::HexPrefix, opcodes::Mnemonic};
+
+    #[test]
+    fn test_code_block_to_asm() {
+        #[rustfmt::skip]
+        let bytes = [
+            0xA9, 0x01,       // $008000: LDA #$01
+            0xC2, 0x20,       // $008002: REP #$20
+            0xA9, 0x34, 0x12, // $008004: LDA #$1234
+            0xF0, 0xFA,       // $008007: BEQ $8003
+            0x60,             // $008009: RTS
+        ];
+        let (mut block, _) = CodeBlock::from_bytes(AddrPc(0), &bytes, &mut Processor::new());
+        block.entrances.push(AddrSnes(0x00A000));
+
+        assert_eq!(
+            block.to_asm(DisplaySyntax::default()),
+            "\
+CODE_008000:
+    ; Entered from $00A000
+    ; A: 8-bit, X/Y: 8-bit
+    LDA #$01
+    REP #$20
+    ; A: 16-bit, X/Y: 8-bit
+    LDA #$1234
+    BEQ $8003
+"
+        );
+
+        let syntax = DisplaySyntax { lowercase: true, hex_prefix: HexPrefix::ZeroX, ..Default::default() };
+        assert!(block.to_asm(syntax).ends_with("    lda #0x1234\n    beq 0x8003\n"));
+        assert_eq!(CodeBlock::default().to_asm(syntax), "");
+    }
+
+    #[test]
+    fn test_calls_fall_through() {
+        #[rustfmt::skip]
+        let bytes = [
+            0x20, 0x10, 0x80,       // $008000: JSR $8010
+            0xA9, 0x01,             // $008003: LDA #$01
+            0x22, 0x20, 0x80, 0x00, // $008005: JSL $008020
+            0xF0, 0x01,             // $008009: BEQ $800C
+            0x60,                   // $00800B: RTS
+        ];
+        let mnemonics = |block: &CodeBlock| block.instructions.iter().map(|i| i.opcode.mnemonic).collect_vec();
+
+        let (block, addr_after_block) = CodeBlock::from_bytes(AddrPc(0), &bytes, &mut Processor::new());
+        assert_eq!(mnemonics(&block), [Mnemonic::JSR, Mnemonic::LDA, Mnemonic::JSL, Mnemonic::BEQ]);
+        assert_eq!(addr_after_block, AddrPc(0x000B));
+
+        let (block, addr_after_block) = CodeBlock::from_bytes_until_call(AddrPc(0), &bytes, &mut Processor::new());
+        assert_eq!(mnemonics(&block), [Mnemonic::JSR]);
+        assert_eq!(addr_after_block, AddrPc(0x0003));
+    }
+}
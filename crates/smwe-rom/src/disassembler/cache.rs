@@ -0,0 +1,193 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    disassembler::{
+        binary_block::BinaryBlock,
+        jump_tables::RejectedJumpTableEntry,
+        serialization::LineKind,
+        ParseWarning,
+        Result,
+        RomDisassembly,
+    },
+    snes_utils::addr::AddrPc,
+    Rom,
+    RomInternalHeader,
+};
+
+/// Bumped whenever the analysis or the cached types change, so that caches written by older versions are ignored.
+const CACHE_VERSION: u32 = 1;
+
+/// Analysis results as written to the cache file.
+#[derive(Serialize)]
+struct CacheRef<'a> {
+    version:                     u32,
+    rom_hash:                    u64,
+    chunks:                      &'a [(AddrPc, BinaryBlock)],
+    code_lines:                  &'a [LineKind],
+    rejected_jump_table_entries: &'a [RejectedJumpTableEntry],
+    warnings:                    &'a [ParseWarning],
+}
+
+/// Analysis results as read from the cache file.
+#[derive(Deserialize)]
+struct Cache {
+    version:                     u32,
+    rom_hash:                    u64,
+    chunks:                      Vec<(AddrPc, BinaryBlock)>,
+    code_lines:                  Vec<LineKind>,
+    rejected_jump_table_entries: Vec<RejectedJumpTableEntry>,
+    warnings:                    Vec<ParseWarning>,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl RomDisassembly {
+    /// Returns the disassembly cached in `cache_dir` for this ROM, or disassembles it and caches the result if there is
+    /// no valid cache for it. Caches are keyed by a hash of the ROM's bytes, so a patched ROM is analysed again.
+    ///
+    /// Failing to read or write the cache is not an error, only a slower load.
+    pub fn load_or_analyze(rom: Rom, rih: &RomInternalHeader, cache_dir: &Path) -> Result<Self> {
+        let rom_hash = rom_hash(&rom.0);
+        let path = cache_path(cache_dir, rom_hash);
+        if let Some(cache) = read_cache(&path, rom_hash) {
+            log::info!("Using cached disassembly from {}", path.display());
+            return Ok(Self {
+                rom,
+                chunks: cache.chunks,
+                code_lines: cache.code_lines,
+                rejected_jump_table_entries: cache.rejected_jump_table_entries,
+                warnings: cache.warnings,
+                cached_data_blocks: HashSet::new(),
+                subroutine_states: HashMap::new(),
+            });
+        }
+
+        let disasm = Self::try_new(rom, rih)?;
+        if let Err(e) = disasm.write_cache(&path, rom_hash) {
+            log::warn!("Cannot write disassembly cache to {}: {e}", path.display());
+        }
+        Ok(disasm)
+    }
+
+    fn write_cache(&self, path: &Path, rom_hash: u64) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let cache = CacheRef {
+            version: CACHE_VERSION,
+            rom_hash,
+            chunks: &self.chunks,
+            code_lines: &self.code_lines,
+            rejected_jump_table_entries: &self.rejected_jump_table_entries,
+            warnings: &self.warnings,
+        };
+        serde_json::to_writer(BufWriter::new(File::create(path)?), &cache)?;
+        Ok(())
+    }
+}
+
+/// Reads the cache at `path`, returning `None` if it's missing, malformed, or not for the ROM with `rom_hash`.
+fn read_cache(path: &Path, rom_hash: u64) -> Option<Cache> {
+    let file = File::open(path).ok()?;
+    let cache: Cache = match serde_json::from_reader(BufReader::new(file)) {
+        Ok(cache) => cache,
+        Err(e) => {
+            log::warn!("Ignoring malformed disassembly cache {}: {e}", path.display());
+            return None;
+        }
+    };
+    if cache.version != CACHE_VERSION || cache.rom_hash != rom_hash {
+        log::info!("Disassembly cache {} is out of date", path.display());
+        return None;
+    }
+    Some(cache)
+}
+
+fn cache_path(cache_dir: &Path, rom_hash: u64) -> PathBuf {
+    cache_dir.join(format!("{rom_hash:016x}.json"))
+}
+
+/// 64-bit FNV-1a hash of the ROM's bytes. It's stable across builds and platforms, unlike the standard library's
+/// hashers.
+fn rom_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF29CE484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001B3))
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        disassembler::{tests::header_with_vectors, DisassemblyError},
+        snes_utils::addr::AddrSnes,
+    };
+
+    fn test_rom() -> (Rom, RomInternalHeader) {
+        // All entry points, including the reset vector at $008000, return right away.
+        let bytes = vec![0x60; 0x8000];
+        (Rom::new(bytes).unwrap(), header_with_vectors(vec![AddrSnes(0x008000); 12]))
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("smwe-test-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn test_matching_cache_is_reused() {
+        let dir = test_dir("disasm-cache-reuse");
+        let (rom, rih) = test_rom();
+        let disasm = RomDisassembly::load_or_analyze(rom.clone(), &rih, &dir).unwrap();
+        let path = cache_path(&dir, rom_hash(&rom.0));
+        assert!(path.exists());
+
+        // A cache that wasn't produced by analysis shows that it's read instead of the ROM being analysed again.
+        let warning = ParseWarning::JumpTableNotFound(AddrSnes(0x123456));
+        let tampered = RomDisassembly { warnings: vec![warning], ..disasm };
+        tampered.write_cache(&path, rom_hash(&rom.0)).unwrap();
+
+        let cached = RomDisassembly::load_or_analyze(rom, &rih, &dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(cached.warnings, [warning]);
+        assert_eq!(cached.chunks.len(), tampered.chunks.len());
+        assert!(matches!(cached.chunks[0], (AddrPc(0), BinaryBlock::Code(_))));
+    }
+
+    #[test]
+    fn test_mismatched_cache_is_ignored() {
+        let dir = test_dir("disasm-cache-mismatch");
+        let (rom, rih) = test_rom();
+        let disasm = RomDisassembly::load_or_analyze(rom.clone(), &rih, &dir).unwrap();
+
+        // Store a cache for another ROM where this one's would be.
+        let path = cache_path(&dir, rom_hash(&rom.0));
+        let warning = ParseWarning::JumpTableNotFound(AddrSnes(0x123456));
+        let stale = RomDisassembly { warnings: vec![warning], ..disasm };
+        stale.write_cache(&path, rom_hash(&rom.0) ^ 1).unwrap();
+
+        let reanalysed = RomDisassembly::load_or_analyze(rom.clone(), &rih, &dir).unwrap();
+        assert_ne!(reanalysed.warnings, [warning]);
+        let rewritten = read_cache(&path, rom_hash(&rom.0));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(rewritten.is_some(), "the stale cache is replaced");
+    }
+
+    #[test]
+    fn test_analysis_errors_are_not_cached() {
+        let dir = test_dir("disasm-cache-error");
+        let mut bytes = vec![0; 0x8000];
+        bytes[0x7FFE] = 0xAD;
+        let rom = Rom::new(bytes).unwrap();
+        let result =
+            RomDisassembly::load_or_analyze(rom.clone(), &header_with_vectors(vec![AddrSnes(0x00FFFE); 12]), &dir);
+        assert!(matches!(result, Err(DisassemblyError::EmptyCodeBlock { .. })));
+        assert!(!cache_path(&dir, rom_hash(&rom.0)).exists());
+    }
+}
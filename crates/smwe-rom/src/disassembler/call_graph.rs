@@ -0,0 +1,152 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    io::Write,
+};
+
+use crate::{
+    disassembler::{binary_block::CodeBlock, RomDisassembly},
+    snes_utils::addr::{AddrPc, AddrSnes},
+};
+
+// -------------------------------------------------------------------------------------------------
+
+impl RomDisassembly {
+    /// Maps each subroutine and entry point (e.g. interrupt handler) to the set of subroutines it calls with JSR or
+    /// JSL. Calls through `ExecutePtr` and `ExecutePtrLong` count as calls to every subroutine in the jump table.
+    pub fn call_graph(&self) -> BTreeMap<AddrSnes, BTreeSet<AddrSnes>> {
+        let code_blocks: BTreeMap<AddrPc, &CodeBlock> =
+            self.chunks.iter().filter_map(|(addr, block)| Some((*addr, block.code_block()?))).collect();
+
+        // Entry points are the blocks that the analysis started from, which are their own entrances.
+        let mut subroutines: BTreeSet<AddrPc> = code_blocks
+            .iter()
+            .filter(|(&addr, block)| AddrSnes::try_from(addr).is_ok_and(|addr| block.entrances.contains(&addr)))
+            .map(|(&addr, _)| addr)
+            .collect();
+        for block in code_blocks.values() {
+            if block.instructions.last().is_some_and(|i| i.is_subroutine_call()) {
+                subroutines.extend(block.exits.iter().filter_map(|&addr| AddrPc::try_from(addr).ok()));
+            }
+        }
+
+        let mut graph = BTreeMap::new();
+        for &sub in subroutines.iter() {
+            let callees = graph.entry(AddrSnes::try_from(sub).unwrap()).or_insert_with(BTreeSet::new);
+            let mut visited = HashSet::new();
+            let mut remaining = vec![sub];
+            while let Some(addr) = remaining.pop() {
+                if !visited.insert(addr) {
+                    continue;
+                }
+                let Some(block) = code_blocks.get(&addr) else { continue };
+                let Some(last_instruction) = block.instructions.last() else { continue };
+                let addr_after_block = last_instruction.offset + last_instruction.opcode.instruction_size() as u32;
+
+                if last_instruction.is_subroutine_call() {
+                    // Called subroutines are not followed, which also keeps recursive calls from looping.
+                    callees.extend(block.exits.iter().copied());
+                    if !last_instruction.uses_jump_table() {
+                        remaining.push(addr_after_block);
+                    }
+                } else if !last_instruction.is_subroutine_return() {
+                    remaining.extend(block.exits.iter().filter_map(|&addr| AddrPc::try_from(addr).ok()));
+                    if !last_instruction.can_change_program_counter() {
+                        remaining.push(addr_after_block);
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    /// Writes the [call graph](Self::call_graph) as a Graphviz DOT digraph. Nodes are labelled with names from
+    /// `symbols` or with their addresses if not found there.
+    pub fn export_call_graph_dot<W: Write>(
+        &self, writer: &mut W, symbols: &HashMap<AddrSnes, String>,
+    ) -> std::io::Result<()> {
+        let graph = self.call_graph();
+        let nodes: BTreeSet<AddrSnes> =
+            graph.iter().flat_map(|(&caller, callees)| [caller].into_iter().chain(callees.iter().copied())).collect();
+
+        writeln!(writer, "digraph call_graph {{")?;
+        for node in nodes {
+            let label = match symbols.get(&node) {
+                Some(symbol) => symbol.replace('"', "\\\""),
+                None => format!("${:06X}", node.0),
+            };
+            writeln!(writer, "    sub_{:06X} [label=\"{label}\"];", node.0)?;
+        }
+        for (caller, callees) in graph.iter() {
+            for callee in callees.iter() {
+                writeln!(writer, "    sub_{:06X} -> sub_{:06X};", caller.0, callee.0)?;
+            }
+        }
+        writeln!(writer, "}}")
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        disassembler::{
+            binary_block::{BinaryBlock, CodeBlock},
+            processor::Processor,
+            RomDisassembly,
+        },
+        snes_utils::addr::{AddrPc, AddrSnes},
+        Rom,
+    };
+
+    fn disassembly_with_code(code: &[(usize, &[u8])]) -> RomDisassembly {
+        let mut bytes = vec![0; 0x8000];
+        for &(pc, code_bytes) in code {
+            bytes[pc..pc + code_bytes.len()].copy_from_slice(code_bytes);
+        }
+
+        let mut chunks = Vec::new();
+        for &(pc, code_bytes) in code {
+            let (mut block, addr_after_block) =
+                CodeBlock::from_bytes(AddrPc(pc as _), code_bytes, &mut Processor::new());
+            block.exits = block.instructions.last().unwrap().next_instructions().to_vec();
+            chunks.push((AddrPc(pc as _), BinaryBlock::Code(block)));
+            chunks.push((addr_after_block, BinaryBlock::Unknown));
+        }
+        chunks.push((AddrPc(bytes.len() as _), BinaryBlock::EndOfRom));
+
+        RomDisassembly { chunks, ..RomDisassembly::unanalysed(Rom::new(bytes).unwrap()) }
+    }
+
+    #[test]
+    fn test_call_graph_dot() {
+        let mut disasm = disassembly_with_code(&[
+            (0x00, &[0x20, 0x10, 0x80]),       // $8000: JSR $8010
+            (0x03, &[0x60]),                   // $8003: RTS
+            (0x10, &[0x22, 0x20, 0x80, 0x00]), // $8010: JSL $008020
+            (0x14, &[0x60]),                   // $8014: RTS
+            (0x20, &[0xF0, 0x03]),             // $8020: BEQ $8025
+            (0x22, &[0x20, 0x20, 0x80]),       // $8022: JSR $8020
+            (0x25, &[0x60]),                   // $8025: RTS
+        ]);
+        // Mark $8000 as an entry point.
+        disasm.chunks[0].1.code_block_mut().unwrap().entrances.push(AddrSnes(0x008000));
+
+        let symbols = HashMap::from([(AddrSnes(0x008000), String::from("Reset"))]);
+        let mut dot = Vec::new();
+        disasm.export_call_graph_dot(&mut dot, &symbols).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+
+        assert!(dot.starts_with("digraph call_graph {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("    sub_008000 [label=\"Reset\"];\n"));
+        assert!(dot.contains("    sub_008010 [label=\"$008010\"];\n"));
+        assert!(dot.contains("    sub_008020 [label=\"$008020\"];\n"));
+        assert!(dot.contains("    sub_008000 -> sub_008010;\n"));
+        assert!(dot.contains("    sub_008010 -> sub_008020;\n"));
+        assert!(dot.contains("    sub_008020 -> sub_008020;\n"));
+        assert_eq!(dot.matches("->").count(), 3);
+    }
+}
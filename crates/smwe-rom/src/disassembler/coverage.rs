@@ -0,0 +1,117 @@
+use itertools::Itertools;
+
+use crate::{
+    disassembler::{binary_block::BinaryBlock, RomDisassembly},
+    snes_utils::{
+        addr::{AddrPc, AddrSnes},
+        rom_slice::SnesSlice,
+    },
+};
+
+/// Number of bytes in a LoROM bank, beyond which PC addresses stop being contiguous in SNES address space.
+const LOROM_BANK_SIZE: u32 = 0x8000;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CoverageReport {
+    pub total_bytes:  usize,
+    pub code_bytes:   usize,
+    pub data_bytes:   usize,
+    /// Regions that were neither reached by code analysis nor marked as data, with adjacent unknown chunks merged.
+    pub unknown_gaps: Vec<SnesSlice>,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl CoverageReport {
+    pub fn unknown_bytes(&self) -> usize {
+        self.total_bytes - self.code_bytes - self.data_bytes
+    }
+}
+
+impl RomDisassembly {
+    /// Summarises how much of the ROM has been identified as code or data by the analysis.
+    pub fn coverage(&self) -> CoverageReport {
+        let mut report = CoverageReport::default();
+        let mut unknown_pc_ranges: Vec<(AddrPc, AddrPc)> = Vec::new();
+        for ((address, block), (next_address, _)) in self.chunks.iter().tuple_windows() {
+            let size = next_address.0.saturating_sub(address.0) as usize;
+            report.total_bytes += size;
+            match block {
+                BinaryBlock::Code(_) => report.code_bytes += size,
+                BinaryBlock::Data(_) => report.data_bytes += size,
+                BinaryBlock::Unknown => match unknown_pc_ranges.last_mut() {
+                    Some((_, end)) if end == address => *end = *next_address,
+                    _ => unknown_pc_ranges.push((*address, *next_address)),
+                },
+                BinaryBlock::EndOfRom => {}
+            }
+        }
+
+        // Gaps crossing a bank boundary are split, since they're not contiguous in SNES address space.
+        for (start, end) in unknown_pc_ranges {
+            let mut start = start.0;
+            while start < end.0 {
+                let bank_end = (start / LOROM_BANK_SIZE + 1) * LOROM_BANK_SIZE;
+                let part_end = bank_end.min(end.0);
+                match AddrSnes::try_from(AddrPc(start)) {
+                    Ok(begin) => report.unknown_gaps.push(SnesSlice::new(begin, (part_end - start) as usize)),
+                    Err(e) => log::warn!("Skipping unknown region at {}: {e}", AddrPc(start)),
+                }
+                start = part_end;
+            }
+        }
+
+        report
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::CoverageReport;
+    use crate::{
+        disassembler::{
+            binary_block::{BinaryBlock, CodeBlock, DataBlock, DataKind},
+            RomDisassembly,
+        },
+        snes_utils::{
+            addr::{AddrPc, AddrSnes},
+            rom_slice::SnesSlice,
+        },
+        Rom,
+    };
+
+    #[test]
+    fn test_coverage_merges_unknown_chunks() {
+        let data =
+            BinaryBlock::Data(DataBlock { slice: SnesSlice::new(AddrSnes(0x008100), 0x100), kind: DataKind::Empty });
+        let disasm = RomDisassembly {
+            chunks: vec![
+                (AddrPc(0x0000), BinaryBlock::Code(CodeBlock::default())),
+                (AddrPc(0x0100), data),
+                (AddrPc(0x0200), BinaryBlock::Unknown),
+                (AddrPc(0x0280), BinaryBlock::Unknown),
+                (AddrPc(0x0300), BinaryBlock::Code(CodeBlock::default())),
+                (AddrPc(0x7F00), BinaryBlock::Unknown),
+                (AddrPc(0x8000), BinaryBlock::Unknown),
+                (AddrPc(0x8100), BinaryBlock::Code(CodeBlock::default())),
+                (AddrPc(0x10000), BinaryBlock::EndOfRom),
+            ],
+            ..RomDisassembly::unanalysed(Rom::new(vec![0; 0x10000]).unwrap())
+        };
+
+        let report = disasm.coverage();
+        assert_eq!(report, CoverageReport {
+            total_bytes:  0x10000,
+            code_bytes:   0x100 + 0x7C00 + 0x7F00,
+            data_bytes:   0x100,
+            unknown_gaps: vec![
+                SnesSlice::new(AddrSnes(0x008200), 0x100),
+                SnesSlice::new(AddrSnes(0x00FF00), 0x100),
+                SnesSlice::new(AddrSnes(0x018000), 0x100),
+            ],
+        });
+        assert_eq!(report.unknown_bytes(), 0x300);
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt::{Display, Formatter};
 
+use serde::{Deserialize, Serialize};
 use smallvec::{smallvec, SmallVec};
 use thiserror::Error;
 
@@ -7,7 +8,7 @@ use crate::{
     disassembler::{
         jump_tables::{EXECUTE_PTR_LONG_TRAMPOLINE_ADDR, EXECUTE_PTR_TRAMPOLINE_ADDR},
         opcodes::{AddressingMode::*, Mnemonic, Opcode, SNES_OPCODES},
-        registers::PRegister,
+        processor::Processor,
     },
     snes_utils::addr::*,
 };
@@ -24,27 +25,54 @@ pub enum InstructionParseError {
 
 // -------------------------------------------------------------------------------------------------
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize, Serialize)]
 pub struct Instruction {
     pub offset: AddrPc,
     pub opcode: Opcode,
     pub m_flag: bool,
     pub x_flag: bool,
+    pub dbr:    Option<u8>,
+    pub dp:     Option<u16>,
 
     // Length might be shorter than 4, needs to be looked up by opcode
     operands: [u8; 4],
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum HexPrefix {
+    /// `$1234`, as used by asar and ca65.
+    #[default]
+    Dollar,
+    /// `0x1234`
+    ZeroX,
+}
+
+/// Conventions used when displaying instructions. The default matches asar.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct DisplaySyntax {
+    /// Whether mnemonics, register names and hex digits are written in lowercase.
+    pub lowercase:       bool,
+    pub hex_prefix:      HexPrefix,
+    /// Whether long addresses are written with their bank, or only with their lower 16 bits.
+    pub hide_long_banks: bool,
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
-pub struct DisplayInstruction(Instruction);
+pub struct DisplayInstruction(Instruction, DisplaySyntax);
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
-pub struct DisplayInstructionWithFlags(Instruction);
+pub struct DisplayInstructionWithFlags(Instruction, DisplaySyntax);
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct DisplayInstructionRelative(Instruction, DisplaySyntax);
 
 // -------------------------------------------------------------------------------------------------
 
 impl Instruction {
-    pub fn parse(bytes: &[u8], offset: AddrPc, p_reg: PRegister) -> Result<(Self, &[u8]), InstructionParseError> {
+    pub fn parse<'a>(
+        bytes: &'a [u8], offset: AddrPc, processor: &Processor,
+    ) -> Result<(Self, &'a [u8]), InstructionParseError> {
+        let p_reg = processor.p_reg;
         let (&opcode_raw, rest) = bytes.split_first().ok_or(InstructionParseError::InputEmpty)?;
         let mut opcode = SNES_OPCODES[opcode_raw as usize];
 
@@ -62,15 +90,32 @@ impl Instruction {
         let mut operands = [0u8; 4];
         operands[..operands_v.len()].copy_from_slice(operands_v);
 
-        Ok((Self { offset, opcode, operands, m_flag: p_reg.m_flag(), x_flag: p_reg.x_flag() }, rest))
+        Ok((
+            Self {
+                offset,
+                opcode,
+                operands,
+                m_flag: p_reg.m_flag(),
+                x_flag: p_reg.x_flag(),
+                dbr: processor.dbr,
+                dp: processor.dp,
+            },
+            rest,
+        ))
     }
 
     pub fn display(self) -> DisplayInstruction {
-        DisplayInstruction(self)
+        DisplayInstruction(self, DisplaySyntax::default())
     }
 
     pub fn display_with_flags(self) -> DisplayInstructionWithFlags {
-        DisplayInstructionWithFlags(self)
+        DisplayInstructionWithFlags(self, DisplaySyntax::default())
+    }
+
+    /// Like [`display`](Self::display), except that branches are shown with their displacement from the instruction's
+    /// own address followed by the resolved target, e.g. `BRA +$05 ($008005)`.
+    pub fn display_relative(self) -> DisplayInstructionRelative {
+        DisplayInstructionRelative(self, DisplaySyntax::default())
     }
 
     pub fn operands(&self) -> &[u8] {
@@ -103,10 +148,13 @@ impl Instruction {
                 }
             }
             // Returns and interrupts
-            RTS | RTL | RTI | BRK | COP => {
+            RTS | RTL | RTI | BRK => {
                 // Interrupt handler destinations are read from internal header and enqueued at the start of disassembly.
                 smallvec![]
             }
+            // COP handlers return right after the instruction, and hacks use WDM as a breakpoint or marker, so both
+            // end blocks without derailing the analysis.
+            COP | WDM => smallvec![next_instruction],
             _ => {
                 if self.can_change_program_counter() {
                     log::error!("Unhandled branching instruction {self:?} at ${offset_snes:06X}");
@@ -126,6 +174,51 @@ impl Instruction {
         matches!(self.opcode.mnemonic, JSR | JSL | BRK | COP).then_some(offset + self.opcode.instruction_size() as u32)
     }
 
+    /// Gets the full 24-bit address of the memory operand, using the data bank and direct page registers that were
+    /// in effect when this instruction was parsed. For indirect modes this is the location of the pointer rather than
+    /// the final target.
+    ///
+    /// Returns `None` if the instruction doesn't access memory through its operand or if the value of the relevant
+    /// register couldn't be determined.
+    pub fn resolved_address(self) -> Option<AddrSnes> {
+        use Mnemonic::*;
+
+        let op_bytes = self.operands();
+        match self.opcode.mode {
+            Address if self.opcode.mnemonic == PEA => None,
+            Address if self.is_branch_or_jump() || self.is_subroutine_call() => Some(self.get_intermediate_address()),
+            Address | AddressXIndex | AddressYIndex => {
+                let operand = u16::from_le_bytes([op_bytes[0], op_bytes[1]]);
+                self.dbr.map(|bank| AddrSnes(((bank as u32) << 16) | (operand as u32)))
+            }
+            AddressIndirect | AddressLongIndirect | AddressXIndexIndirect | Long | LongXIndex => {
+                Some(self.get_intermediate_address())
+            }
+            m if (DirectPage..=DirectPageYIndex).contains(&m) => {
+                self.dp.map(|dp| AddrSnes(dp.wrapping_add(op_bytes[0] as u16) as u32))
+            }
+            _ => None,
+        }
+    }
+
+    /// Gets the address that a relative branch, or `PER`, points to.
+    pub fn relative_target(self) -> Option<AddrSnes> {
+        matches!(self.opcode.mode, Relative8 | Relative16).then(|| self.get_intermediate_address())
+    }
+
+    /// Gets the address that a branch, jump or subroutine call goes to, if it's given directly by the operand.
+    /// Indirect jumps return `None`, as their targets are only known at runtime.
+    pub fn static_target(self) -> Option<AddrSnes> {
+        if !self.is_branch_or_jump() && !self.is_subroutine_call() {
+            return None;
+        }
+        match self.opcode.mode {
+            Relative8 | Relative16 => self.relative_target(),
+            Address | Long => Some(self.get_intermediate_address()),
+            _ => None,
+        }
+    }
+
     fn get_intermediate_address(self) -> AddrSnes {
         let offset_snes = AddrSnes::try_from(self.offset).expect("Invalid instruction address");
         let op_bytes = self.operands();
@@ -140,24 +233,32 @@ impl Instruction {
             AddressIndirect | AddressLongIndirect => u16::from_le_bytes([op_bytes[0], op_bytes[1]]) as _,
             Long | LongXIndex => u32::from_le_bytes([op_bytes[0], op_bytes[1], op_bytes[2], 0]),
             Relative8 | Relative16 => {
-                let operand_size = self.opcode.instruction_size() - 1;
-                let program_counter = (offset_snes + 1usize + operand_size).0 as i32;
-                let bank = program_counter >> 16;
-                let jump_amount = match operand_size {
-                    1 => op_bytes[0] as i8 as i32, // u8->i8 for the sign, i8->i32 for the size.
-                    2 => i16::from_le_bytes([op_bytes[0], op_bytes[1]]) as i32,
+                // The program counter wraps around within the program bank.
+                let program_counter = offset_snes.absolute().wrapping_add(self.opcode.instruction_size() as u16);
+                let jump_amount = match op_bytes.len() {
+                    1 => op_bytes[0] as i8 as i16, // u8->i8 for the sign, i8->i16 for the size.
+                    2 => i16::from_le_bytes([op_bytes[0], op_bytes[1]]),
                     _ => unreachable!(),
                 };
-                ((bank << 16) | (program_counter.wrapping_add(jump_amount) & 0xFFFF)) as _
+                offset_snes.with_absolute(program_counter.wrapping_add_signed(jump_amount)).0
             }
             _ => 0,
         })
     }
 
+    /// Estimates the number of CPU cycles taken by the instruction using the processor state it was parsed with.
+    pub fn estimate_cycles(self) -> u32 {
+        self.opcode.estimate_cycles(self.m_flag, self.x_flag, self.dp)
+    }
+
     pub fn can_change_program_counter(self) -> bool {
         self.opcode.mnemonic.can_change_program_counter()
     }
 
+    pub fn ends_basic_block(self) -> bool {
+        self.opcode.mnemonic.ends_basic_block()
+    }
+
     pub fn is_single_path_leap(self) -> bool {
         self.opcode.mnemonic.is_single_path_leap()
     }
@@ -185,10 +286,61 @@ impl Instruction {
     }
 }
 
+impl HexPrefix {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HexPrefix::Dollar => "$",
+            HexPrefix::ZeroX => "0x",
+        }
+    }
+}
+
+impl DisplayInstruction {
+    pub fn with_syntax(self, syntax: DisplaySyntax) -> Self {
+        Self(self.0, syntax)
+    }
+}
+
+impl DisplayInstructionWithFlags {
+    pub fn with_syntax(self, syntax: DisplaySyntax) -> Self {
+        Self(self.0, syntax)
+    }
+}
+
+impl DisplayInstructionRelative {
+    pub fn with_syntax(self, syntax: DisplaySyntax) -> Self {
+        Self(self.0, syntax)
+    }
+}
+
 impl Display for DisplayInstructionWithFlags {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "[{}{}] ", ['m', 'M'][self.0.m_flag as usize], ['x', 'X'][self.0.x_flag as usize])?;
-        self.0.display().fmt(f)
+        self.0.display().with_syntax(self.1).fmt(f)
+    }
+}
+
+impl Display for DisplayInstructionRelative {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.0.opcode.mode {
+            Relative8 | Relative16 => {
+                let offset_snes = AddrSnes::try_from(self.0.offset).expect("Invalid instruction address");
+                let target = self.0.get_intermediate_address();
+                let displacement = target.absolute().wrapping_sub(offset_snes.absolute()) as i16;
+                let sign = if displacement < 0 { '-' } else { '+' };
+                let digits = if self.0.opcode.mode == Relative8 { 2 } else { 4 };
+                let prefix = self.1.hex_prefix.as_str();
+                let target = if self.1.hide_long_banks {
+                    format!("{prefix}{:04X}", target.absolute())
+                } else {
+                    format!("{prefix}{:06X}", target.0)
+                };
+                let displacement = displacement.unsigned_abs();
+                let text = format!("{} {sign}{prefix}{displacement:0digits$X} ({target})", self.0.opcode.mnemonic);
+                f.pad(&if self.1.lowercase { text.to_lowercase() } else { text })
+            }
+            _ => self.0.display().with_syntax(self.1).fmt(f),
+        }
     }
 }
 
@@ -197,11 +349,14 @@ impl Display for DisplayInstruction {
         use std::io::Write;
         let mut fmt: SmallVec<[u8; 64]> = Default::default();
         let Instruction { x_flag, m_flag, .. } = self.0;
+        let p = self.1.hex_prefix.as_str();
 
         let (address_long, address_short, address_dp) = {
             let a = self.0.get_intermediate_address();
             (a.0, a.absolute(), a.low())
         };
+        let long_digits = if self.1.hide_long_banks { 4 } else { 6 };
+        let address_long = if self.1.hide_long_banks { address_short as u32 } else { address_long };
 
         write!(fmt, "{}", self.0.opcode.mnemonic).unwrap();
         match self.0.opcode.mode {
@@ -212,79 +367,195 @@ impl Display for DisplayInstruction {
                 write!(fmt, " A").unwrap();
             }
             Constant8 | Immediate8 => {
-                write!(fmt, " #${:02X}", self.0.operands[0]).unwrap();
+                write!(fmt, " #{p}{:02X}", self.0.operands[0]).unwrap();
             }
             Immediate16 => {
-                write!(fmt, " #${:04X}", u16::from_le_bytes([self.0.operands[0], self.0.operands[1]])).unwrap();
+                write!(fmt, " #{p}{:04X}", u16::from_le_bytes([self.0.operands[0], self.0.operands[1]])).unwrap();
             }
             ImmediateXFlagDependent | ImmediateMFlagDependent => {
                 let x = self.0.opcode.mode == ImmediateXFlagDependent && x_flag;
                 let m = self.0.opcode.mode == ImmediateMFlagDependent && m_flag;
                 if x || m {
-                    write!(fmt, " #${:02X}", self.0.operands[0]).unwrap();
+                    write!(fmt, " #{p}{:02X}", self.0.operands[0]).unwrap();
                 } else {
-                    write!(fmt, " #${:04X}", u16::from_le_bytes([self.0.operands[0], self.0.operands[1]])).unwrap();
+                    write!(fmt, " #{p}{:04X}", u16::from_le_bytes([self.0.operands[0], self.0.operands[1]])).unwrap();
                 }
             }
             DirectPage => {
-                write!(fmt, " ${address_dp:02X}").unwrap();
+                write!(fmt, " {p}{address_dp:02X}").unwrap();
             }
             Relative8 => {
                 let address = self.0.operands[0] as u32;
                 let address = address & !(-1i32 << 8) as u32;
-                write!(fmt, " ${address:02X}").unwrap();
+                write!(fmt, " {p}{address:02X}").unwrap();
             }
             Relative16 => {
                 let address = u16::from_le_bytes([self.0.operands[0], self.0.operands[1]]) as u32;
                 let address = address & !(-1i32 << 16) as u32;
-                write!(fmt, " ${address:04X}").unwrap();
+                write!(fmt, " {p}{address:04X}").unwrap();
             }
             Address => {
-                write!(fmt, " ${address_short:04X}").unwrap();
+                write!(fmt, " {p}{address_short:04X}").unwrap();
             }
             Long => {
-                write!(fmt, " ${address_long:06X}").unwrap();
+                write!(fmt, " {p}{address_long:0long_digits$X}").unwrap();
             }
             DirectPageXIndex | AddressXIndex | LongXIndex => {
-                write!(fmt, " ${address_dp:02X}, X").unwrap();
+                write!(fmt, " {p}{address_dp:02X}, X").unwrap();
             }
             DirectPageYIndex | AddressYIndex => {
-                write!(fmt, " ${address_dp:02X}, Y").unwrap();
+                write!(fmt, " {p}{address_dp:02X}, Y").unwrap();
             }
             DirectPageSIndex => {
-                write!(fmt, " ${address_dp:02X}, S").unwrap();
+                write!(fmt, " {p}{address_dp:02X}, S").unwrap();
             }
             DirectPageIndirect => {
-                write!(fmt, " (${address_dp:02X})").unwrap();
+                write!(fmt, " ({p}{address_dp:02X})").unwrap();
             }
             AddressIndirect => {
-                write!(fmt, " (${address_short:04X})").unwrap();
+                write!(fmt, " ({p}{address_short:04X})").unwrap();
             }
             DirectPageXIndexIndirect => {
-                write!(fmt, " (${address_dp:02X}, X)").unwrap();
+                write!(fmt, " ({p}{address_dp:02X}, X)").unwrap();
             }
             AddressXIndexIndirect => {
-                write!(fmt, " (${address_short:04X}, X)").unwrap();
+                write!(fmt, " ({p}{address_short:04X}, X)").unwrap();
             }
             DirectPageIndirectYIndex => {
-                write!(fmt, " (${address_dp:02X}), Y").unwrap();
+                write!(fmt, " ({p}{address_dp:02X}), Y").unwrap();
             }
             DirectPageSIndexIndirectYIndex => {
-                write!(fmt, " (${address_dp:02X}, S), Y").unwrap();
+                write!(fmt, " ({p}{address_dp:02X}, S), Y").unwrap();
             }
             DirectPageLongIndirect => {
-                write!(fmt, " [${address_dp:02X}]").unwrap();
+                write!(fmt, " [{p}{address_dp:02X}]").unwrap();
             }
             AddressLongIndirect => {
-                write!(fmt, " [${address_short:04X}]").unwrap();
+                write!(fmt, " [{p}{address_short:04X}]").unwrap();
             }
             DirectPageLongIndirectYIndex => {
-                write!(fmt, " [${address_dp:02X}], Y").unwrap();
+                write!(fmt, " [{p}{address_dp:02X}], Y").unwrap();
             }
             BlockMove => {
-                write!(fmt, " ${:02X}, ${:02X}", self.0.operands[0], self.0.operands[1]).unwrap();
+                // The destination bank is encoded first, but assemblers expect the source bank first.
+                write!(fmt, " {p}{:02X}, {p}{:02X}", self.0.operands[1], self.0.operands[0]).unwrap();
             }
         };
-        outer_fmt.pad(std::str::from_utf8(&fmt).unwrap())
+        let text = std::str::from_utf8(&fmt).unwrap();
+        if self.1.lowercase {
+            outer_fmt.pad(&text.to_lowercase())
+        } else {
+            outer_fmt.pad(text)
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{DisplaySyntax, HexPrefix, Instruction};
+    use crate::{
+        disassembler::processor::Processor,
+        snes_utils::addr::{AddrPc, AddrSnes},
+    };
+
+    fn parse_at(bytes: &[u8], offset: AddrPc) -> Instruction {
+        Instruction::parse(bytes, offset, &Processor::new()).unwrap().0
+    }
+
+    #[test]
+    fn test_forward_branch_across_bank_end() {
+        // $00FFFE: BRA $0004
+        let i = parse_at(&[0x80, 0x04], AddrPc(0x007FFE));
+        assert_eq!(i.next_instructions().as_slice(), &[AddrSnes(0x000004)]);
+        assert_eq!(i.display_relative().to_string(), "BRA +$06 ($000004)");
+    }
+
+    #[test]
+    fn test_backward_branch_at_bank_start() {
+        // $018000: BNE $7FFC
+        let i = parse_at(&[0xD0, 0xFA], AddrPc(0x008000));
+        assert_eq!(i.next_instructions().as_slice(), &[AddrSnes(0x017FFC), AddrSnes(0x018002)]);
+        assert_eq!(i.display_relative().to_string(), "BNE -$04 ($017FFC)");
+
+        // $018000: BRL $7F00
+        let i = parse_at(&[0x82, 0xFD, 0xFE], AddrPc(0x008000));
+        assert_eq!(i.display_relative().to_string(), "BRL -$0100 ($017F00)");
+    }
+
+    #[test]
+    fn test_relative_display_of_non_branch() {
+        let i = parse_at(&[0xA9, 0x12], AddrPc(0x008000));
+        assert_eq!(i.display_relative().to_string(), i.display().to_string());
+    }
+
+    #[test]
+    fn test_block_move() {
+        // $008000: MVN $00, $7E
+        let i = parse_at(&[0x54, 0x7E, 0x00, 0xEA], AddrPc(0x000000));
+        assert_eq!(i.operands(), &[0x7E, 0x00]);
+        assert_eq!(i.opcode.instruction_size(), 3);
+        assert_eq!(i.display().to_string(), "MVN $00, $7E");
+        assert_eq!(i.next_instructions().as_slice(), &[AddrSnes(0x008003)]);
+        assert_eq!(i.resolved_address(), None);
+
+        // $008000: MVP $7F, $7E
+        let i = parse_at(&[0x44, 0x7E, 0x7F], AddrPc(0x000000));
+        assert_eq!(i.display().to_string(), "MVP $7F, $7E");
+    }
+
+    #[test]
+    fn test_stack_relative() {
+        // $008000: LDA $03, S
+        let i = parse_at(&[0xA3, 0x03], AddrPc(0x000000));
+        assert_eq!(i.display().to_string(), "LDA $03, S");
+        assert_eq!(i.next_instructions().as_slice(), &[AddrSnes(0x008002)]);
+        assert_eq!(i.resolved_address(), None, "stack-relative operands don't depend on the direct page");
+
+        // $008000: STA ($05, S), Y
+        let i = parse_at(&[0x93, 0x05], AddrPc(0x000000));
+        assert_eq!(i.display().to_string(), "STA ($05, S), Y");
+        assert_eq!(i.opcode.instruction_size(), 2);
+    }
+
+    #[test]
+    fn test_static_target() {
+        // $008000: BNE $8007
+        assert_eq!(parse_at(&[0xD0, 0x05], AddrPc(0x000000)).static_target(), Some(AddrSnes(0x008007)));
+        // $018000: BRL $8000
+        assert_eq!(parse_at(&[0x82, 0xFD, 0xFF], AddrPc(0x008000)).static_target(), Some(AddrSnes(0x018000)));
+        // $018000: JSR $9234
+        assert_eq!(parse_at(&[0x20, 0x34, 0x92], AddrPc(0x008000)).static_target(), Some(AddrSnes(0x019234)));
+        // $008000: JSL $0FF600
+        assert_eq!(parse_at(&[0x22, 0x00, 0xF6, 0x0F], AddrPc(0x000000)).static_target(), Some(AddrSnes(0x0FF600)));
+        // $008000: JMP ($0000)
+        assert_eq!(parse_at(&[0x6C, 0x00, 0x00], AddrPc(0x000000)).static_target(), None);
+        // $008000: LDA $9234
+        assert_eq!(parse_at(&[0xAD, 0x34, 0x92], AddrPc(0x000000)).static_target(), None);
+        // $008000: PER $8005
+        assert_eq!(parse_at(&[0x62, 0x02, 0x00], AddrPc(0x000000)).static_target(), None);
+    }
+
+    #[test]
+    fn test_display_syntax() {
+        let lowercase_0x =
+            DisplaySyntax { lowercase: true, hex_prefix: HexPrefix::ZeroX, hide_long_banks: true };
+
+        // $008000: JSL $0FABCD
+        let i = parse_at(&[0x22, 0xCD, 0xAB, 0x0F], AddrPc(0x000000));
+        assert_eq!(i.display().to_string(), "JSL $0FABCD");
+        assert_eq!(i.display().with_syntax(lowercase_0x).to_string(), "jsl 0xabcd");
+
+        // $008000: LDA #$1F
+        let i = parse_at(&[0xA9, 0x1F], AddrPc(0x000000));
+        assert_eq!(i.display_with_flags().to_string(), "[MX] LDA #$1F");
+        assert_eq!(i.display_with_flags().with_syntax(lowercase_0x).to_string(), "[MX] lda #0x1f");
+
+        // $008000: BEQ $8010
+        let i = parse_at(&[0xF0, 0x0E], AddrPc(0x000000));
+        let syntax = DisplaySyntax { hex_prefix: HexPrefix::ZeroX, ..Default::default() };
+        assert_eq!(i.display_relative().with_syntax(syntax).to_string(), "BEQ +0x10 (0x008010)");
+        assert_eq!(i.display_relative().with_syntax(lowercase_0x).to_string(), "beq +0x10 (0x8010)");
     }
 }
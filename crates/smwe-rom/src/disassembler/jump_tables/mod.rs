@@ -6,9 +6,13 @@ use nom::{
     multi::many1,
     number::complete::{le_u16, le_u24},
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    snes_utils::{addr::AddrSnes, rom_slice::SnesSlice},
+    snes_utils::{
+        addr::{AddrPc, AddrSnes},
+        rom_slice::SnesSlice,
+    },
     Rom,
     RomError,
 };
@@ -18,6 +22,9 @@ use crate::{
 pub const EXECUTE_PTR_TRAMPOLINE_ADDR: AddrSnes = AddrSnes(0x0086DF);
 pub const EXECUTE_PTR_LONG_TRAMPOLINE_ADDR: AddrSnes = AddrSnes(0x0086FA);
 
+/// Upper bound on the number of pointers read when detecting a jump table that isn't listed in [`JUMP_TABLES`].
+pub const MAX_DETECTED_JUMP_TABLE_LENGTH: usize = 0x100;
+
 // -------------------------------------------------------------------------------------------------
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -28,6 +35,14 @@ pub struct JumpTableView {
     pub long_ptrs: bool,
 }
 
+/// A jump table entry that wasn't followed, because it doesn't point to code.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
+pub struct RejectedJumpTableEntry {
+    /// Address of the pointer in the jump table.
+    pub entry:  AddrSnes,
+    pub target: AddrSnes,
+}
+
 // -------------------------------------------------------------------------------------------------
 
 impl JumpTableView {
@@ -51,3 +66,88 @@ pub fn get_jump_table_from_rom(rom: &Rom, jump_table_view: JumpTableView) -> Res
 
     Ok(jump_table)
 }
+
+/// Detects a jump table at `begin` that isn't listed in [`JUMP_TABLES`] by reading pointers until the first one that
+/// doesn't point to code in ROM. At most `max_length` pointers are read, but never more than
+/// [`MAX_DETECTED_JUMP_TABLE_LENGTH`].
+///
+/// Returns `None` if not even the first pointer is valid.
+pub fn detect_jump_table(rom: &Rom, begin: AddrSnes, long_ptrs: bool, max_length: usize) -> Option<JumpTableView> {
+    let ptr_size: usize = if long_ptrs { 3 } else { 2 };
+    let begin_pc = AddrPc::try_from_lorom(begin).ok()?;
+    let bytes = rom.0.get(begin_pc.as_index()..)?;
+
+    let length = bytes
+        .chunks_exact(ptr_size)
+        .take(max_length.min(MAX_DETECTED_JUMP_TABLE_LENGTH))
+        .map(|ptr| {
+            if long_ptrs {
+                AddrSnes(u32::from_le_bytes([ptr[0], ptr[1], ptr[2], 0]))
+            } else {
+                // 16-bit address implies the same bank number as the jump table's address.
+                AddrSnes(u16::from_le_bytes([ptr[0], ptr[1]]) as _) | (begin & 0xFF0000)
+            }
+        })
+        .take_while(|&addr| points_into_rom(rom, addr))
+        .count();
+
+    (length > 0).then_some(JumpTableView::new(begin, length, long_ptrs))
+}
+
+/// Whether `addr` points to the ROM half of a LoROM bank, within the size of `rom`.
+pub fn points_into_rom(rom: &Rom, addr: AddrSnes) -> bool {
+    addr.absolute() >= 0x8000 && AddrPc::try_from_lorom(addr).is_ok_and(|pc| pc.as_index() < rom.0.len())
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom_with_bytes_at(pc: usize, bytes: &[u8]) -> Rom {
+        let mut data = vec![0; 0x10000];
+        data[pc..pc + bytes.len()].copy_from_slice(bytes);
+        Rom::new(data).unwrap()
+    }
+
+    #[test]
+    fn test_detect_short_jump_table() {
+        #[rustfmt::skip]
+        let rom = rom_with_bytes_at(0x100, &[
+            0x00, 0x82, // $8200
+            0x00, 0x83, // $8300
+            0x00, 0xF0, // $F000
+            0x34, 0x12, // $1234 (not in ROM)
+            0x00, 0x84, // $8400
+        ]);
+        let table = detect_jump_table(&rom, AddrSnes(0x008100), false, usize::MAX);
+        assert_eq!(table, Some(JumpTableView::new(AddrSnes(0x008100), 3, false)));
+        let pointers = get_jump_table_from_rom(&rom, table.unwrap()).unwrap();
+        assert_eq!(pointers, vec![AddrSnes(0x008200), AddrSnes(0x008300), AddrSnes(0x00F000)]);
+    }
+
+    #[test]
+    fn test_detect_long_jump_table() {
+        #[rustfmt::skip]
+        let rom = rom_with_bytes_at(0x8100, &[
+            0x00, 0x80, 0x01, // $018000
+            0x00, 0x90, 0x00, // $009000
+            0x00, 0x80, 0x20, // $208000 (past the end of ROM)
+        ]);
+        let table = detect_jump_table(&rom, AddrSnes(0x018100), true, usize::MAX);
+        assert_eq!(table, Some(JumpTableView::new(AddrSnes(0x018100), 2, true)));
+
+        let rom = rom_with_bytes_at(0x8100, &[0x00, 0x00, 0x7E]);
+        assert_eq!(detect_jump_table(&rom, AddrSnes(0x018100), true, usize::MAX), None);
+    }
+
+    #[test]
+    fn test_detect_jump_table_bounded() {
+        let rom = rom_with_bytes_at(0x100, &[0x00, 0x82].repeat(0x1000));
+        let table = detect_jump_table(&rom, AddrSnes(0x008100), false, usize::MAX).unwrap();
+        assert_eq!(table.length, MAX_DETECTED_JUMP_TABLE_LENGTH);
+        let table = detect_jump_table(&rom, AddrSnes(0x008100), false, 5).unwrap();
+        assert_eq!(table.length, 5);
+    }
+}
@@ -2,23 +2,27 @@
 // https://github.com/Dotsarecool/DiztinGUIsh
 
 pub mod binary_block;
+mod cache;
+mod call_graph;
+pub mod coverage;
 pub mod instruction;
 pub mod jump_tables;
 pub mod opcodes;
 pub mod processor;
 pub mod registers;
 pub mod serialization;
+mod unknown_data;
 
 use std::{
-    cell::RefCell,
     cmp::Ordering,
-    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet, VecDeque},
     fmt::{Debug, Formatter, Write},
-    ops::Deref,
-    rc::Rc,
+    ops::Range,
+    sync::{Arc, Mutex},
 };
 
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::{
@@ -26,7 +30,9 @@ use crate::{
         binary_block::{BinaryBlock, CodeBlock, DataBlock, DataKind},
         instruction::Instruction,
         jump_tables::{
+            detect_jump_table,
             get_jump_table_from_rom,
+            RejectedJumpTableEntry,
             EXECUTE_PTR_LONG_TRAMPOLINE_ADDR,
             EXECUTE_PTR_TRAMPOLINE_ADDR,
             JUMP_TABLES,
@@ -47,23 +53,59 @@ use crate::{
 
 // -------------------------------------------------------------------------------------------------
 
-#[derive(Copy, Clone, Debug, Error)]
+#[derive(Clone, Debug, Error)]
 pub enum DisassemblyError {
     #[error("Cannot find a block that returns from subroutine starting at ${0:?}")]
     SubroutineWithoutReturn(AddrSnes),
     #[error("Invalid next PC encountered when parsing basic code block starting at {0:?}, at final instruction {1:?}")]
     InvalidAddrInCodeBlock(AddrPc, Instruction),
+    #[error("Cannot start analysis at {0:?}, which is not in ROM")]
+    InvalidEntryPoint(AddrSnes),
+    #[error("Empty (invalid) code block at {addr}\n{backtrace}")]
+    EmptyCodeBlock { addr: AddrPc, backtrace: String },
+    #[error("Multiple chunks generated at address {0}")]
+    MultipleChunks(AddrPc),
+    #[error("Address {0} is outside of LoROM")]
+    AddrOutsideLoRom(AddrPc),
+    #[error("Subroutine at {0:?} is not in ROM")]
+    SubroutineOutsideRom(AddrSnes),
+    #[error("Jump table at {0:?} runs past the end of the ROM")]
+    TruncatedJumpTable(AddrSnes),
+    #[error("Found jump to {0} into the middle of a non-code block")]
+    JumpIntoNonCode(AddrPc),
+    #[error("Code at {0} is past the end of the ROM")]
+    CodeOutsideRom(AddrPc),
+}
+
+/// A problem found during parsing that doesn't stop it, but leaves some of the ROM unanalysed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Error, Deserialize, Serialize)]
+pub enum ParseWarning {
+    #[error("Cannot mark data at {0:?}, which is not in ROM")]
+    DataOutsideRom(AddrSnes),
+    #[error("Not analysing code at {code}, which is inside a data block at {data}")]
+    CodeInsideData { code: AddrPc, data: AddrPc },
+    #[error("Could not find jump table at {0:?}")]
+    JumpTableNotFound(AddrSnes),
+    #[error("Wrong address of next target {target:?} in code block at {block}")]
+    InvalidNextTarget { block: AddrPc, target: AddrSnes },
 }
 
 // -------------------------------------------------------------------------------------------------
 
 pub struct RomDisassembly {
-    pub rom:        Rom,
+    pub rom:                         Rom,
     /// Start index, Block data
-    pub chunks:     Vec<(AddrPc, BinaryBlock)>,
-    pub code_lines: Vec<LineKind>,
+    pub chunks:                      Vec<(AddrPc, BinaryBlock)>,
+    pub code_lines:                  Vec<LineKind>,
+    /// Jump table entries that weren't followed, because they point outside of ROM or into data.
+    pub rejected_jump_table_entries: Vec<RejectedJumpTableEntry>,
+    pub warnings:                    Vec<ParseWarning>,
 
     cached_data_blocks: HashSet<DataBlock>,
+    /// Processor state at the return of each fully analysed subroutine, reused by [`reanalyze_from`].
+    ///
+    /// [`reanalyze_from`]: Self::reanalyze_from
+    subroutine_states:  HashMap<AddrPc, Processor>,
 }
 
 struct RomAssemblyWalker {
@@ -75,11 +117,16 @@ struct RomAssemblyWalker {
     analysed_chunks: BTreeMap<AddrPc, (AddrPc, usize)>,
 
     // Temporary until code scanning
-    remaining_steps:      VecDeque<RomAssemblyWalkerStep>,
-    analysed_code_starts: HashSet<AddrPc>,
+    remaining_steps:             VecDeque<RomAssemblyWalkerStep>,
+    analysed_code_starts:        HashSet<AddrPc>,
     /// Subroutine start -> addresses of call return points
-    subroutine_returns:   HashMap<AddrPc, Vec<AddrPc>>,
-    analysed_subroutines: HashMap<AddrPc, Rc<RefCell<SubroutineAnalysisState>>>,
+    subroutine_returns:          HashMap<AddrPc, Vec<AddrPc>>,
+    analysed_subroutines:        HashMap<AddrPc, Arc<Mutex<SubroutineAnalysisState>>>,
+    rejected_jump_table_entries: Vec<RejectedJumpTableEntry>,
+    warnings:                    Vec<ParseWarning>,
+    /// Don't follow calls to subroutines that haven't been analysed yet, but collect them in `deferred_subroutines`.
+    defer_calls:                 bool,
+    deferred_subroutines:        Vec<DeferredSubroutine>,
 }
 
 #[derive(Clone)]
@@ -102,6 +149,13 @@ struct StepBasicBlock {
     entrance:   AddrSnes,
 }
 
+/// A subroutine called by code walked with [`RomAssemblyWalker::defer_calls`] set.
+#[derive(Clone)]
+struct DeferredSubroutine {
+    subroutine:  StepSubroutine,
+    first_block: StepBasicBlock,
+}
+
 #[derive(Clone)]
 struct SubroutineAnalysisState {
     /// (block address, block index)
@@ -123,12 +177,88 @@ enum BlockFindResult {
 
 impl RomDisassembly {
     pub fn new(rom: Rom, rih: &RomInternalHeader) -> Self {
+        Self::try_new(rom, rih).unwrap()
+    }
+
+    /// Disassembles the ROM, returning an error instead of panicking if it's malformed in a way that stops analysis.
+    pub fn try_new(rom: Rom, rih: &RomInternalHeader) -> Result<Self> {
+        Self::try_with_known_data(rom, rih, [])
+    }
+
+    /// Disassembles the ROM with `known_data` marked as data before analysis, so that the walker doesn't mistake it
+    /// for code even if it disassembles into valid instructions.
+    pub fn with_known_data(rom: Rom, rih: &RomInternalHeader, known_data: impl IntoIterator<Item = DataBlock>) -> Self {
+        Self::try_with_known_data(rom, rih, known_data).unwrap()
+    }
+
+    /// Fallible version of [`with_known_data`](Self::with_known_data).
+    pub fn try_with_known_data(
+        rom: Rom, rih: &RomInternalHeader, known_data: impl IntoIterator<Item = DataBlock>,
+    ) -> Result<Self> {
+        let known_data = known_data.into_iter().collect_vec();
         let mut walker = RomAssemblyWalker::new(rom.clone(), rih);
-        walker.full_analysis().unwrap();
+        for &data in known_data.iter() {
+            walker.pre_mark_data(data);
+        }
+        walker.full_analysis()?;
+        Ok(Self::from_walker(rom, walker, known_data.into_iter().collect()))
+    }
+
+    /// Disassembles only the code reachable from `entry_points`, e.g. to analyse a relocated routine without walking
+    /// the whole ROM. Entry points that are also among the default ones of `rih` start with the same processor state
+    /// as in a full analysis, so passing them as well extends the full analysis instead.
+    pub fn with_entry_points(rom: Rom, rih: &RomInternalHeader, entry_points: &[AddrSnes]) -> Result<Self> {
+        let default_entry_points = RomAssemblyWalker::entry_points(rih);
+        let steps = entry_points
+            .iter()
+            .map(|&entry| -> Result<StepBasicBlock> {
+                let code_start = AddrPc::try_from(entry)
+                    .ok()
+                    .filter(|pc| pc.as_index() < rom.0.len())
+                    .ok_or(DisassemblyError::InvalidEntryPoint(entry))?;
+                let processor = default_entry_points
+                    .iter()
+                    .find(|step| step.entrance == entry)
+                    .map_or_else(Processor::new, |step| step.processor.clone());
+                Ok(StepBasicBlock { code_start, processor, entrance: entry })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut walker = RomAssemblyWalker::with_analysed_chunks(rom.clone(), Vec::new(), steps);
+        walker.full_analysis()?;
+        Ok(Self::from_walker(rom, walker, HashSet::new()))
+    }
+
+    /// Disassembles the ROM like [`try_new`](Self::try_new), but analyses subroutines that don't share any code on
+    /// separate threads. Warnings and rejected jump table entries can be listed in a different order than with
+    /// `try_new`, and chunks that depend on the order code is found in can differ.
+    pub fn try_new_parallel(rom: Rom, rih: &RomInternalHeader) -> Result<Self> {
+        let walker = RomAssemblyWalker::parallel_analysis(rom.clone(), RomAssemblyWalker::entry_points(rih))?;
+        Ok(Self::from_walker(rom, walker, HashSet::new()))
+    }
+
+    /// A disassembly in which nothing has been analysed yet, with the whole ROM as a single unknown chunk.
+    #[cfg(test)]
+    pub(crate) fn unanalysed(rom: Rom) -> Self {
+        let rom_size = AddrPc(rom.0.len() as u32);
         Self {
             rom,
-            chunks: walker.chunks,
+            chunks: vec![(AddrPc(0), BinaryBlock::Unknown), (rom_size, BinaryBlock::EndOfRom)],
+            code_lines: Vec::new(),
+            rejected_jump_table_entries: Vec::new(),
+            warnings: Vec::new(),
             cached_data_blocks: HashSet::new(),
+            subroutine_states: HashMap::new(),
+        }
+    }
+
+    fn from_walker(rom: Rom, walker: RomAssemblyWalker, cached_data_blocks: HashSet<DataBlock>) -> Self {
+        Self {
+            rom,
+            chunks: walker.chunks,
+            rejected_jump_table_entries: walker.rejected_jump_table_entries,
+            warnings: walker.warnings,
+            cached_data_blocks,
+            subroutine_states: walker.subroutine_states(),
             code_lines: {
                 std::fs::read_to_string("ROM/out.json")
                     .ok()
@@ -144,10 +274,151 @@ impl RomDisassembly {
         }
     }
 
+    /// Re-analyses the code reachable from `entry` after it has been patched in [`rom`](Self::rom), without walking
+    /// the rest of the ROM again.
+    ///
+    /// Only the blocks reachable from the one containing `entry` are discarded and walked again. Subroutines called
+    /// from them are not, as patching the caller doesn't change their code.
+    pub fn reanalyze_from(&mut self, entry: AddrSnes) -> Result<()> {
+        let entry_pc = AddrPc::try_from(entry).map_err(|_| DisassemblyError::InvalidEntryPoint(entry))?;
+
+        let code_chunks: BTreeMap<AddrPc, usize> = self
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, block))| block.code_block().is_some_and(|b| !b.instructions.is_empty()))
+            .map(|(idx, (addr, _))| (*addr, idx))
+            .collect();
+        let addr_after = |block: &CodeBlock| {
+            let last_instruction = block.instructions.last().unwrap();
+            last_instruction.offset + last_instruction.opcode.instruction_size() as u32
+        };
+
+        let first_step = match code_chunks.range(..=entry_pc).next_back() {
+            Some((&block_start, &idx)) if entry_pc < addr_after(self.chunks[idx].1.code_block().unwrap()) => {
+                let block = self.chunks[idx].1.code_block().unwrap();
+                StepBasicBlock {
+                    code_start: block_start,
+                    processor:  block.entry_processor_state.clone(),
+                    entrance:   block.entrances.first().copied().unwrap_or(entry),
+                }
+            }
+            _ => StepBasicBlock { code_start: entry_pc, processor: Processor::new(), entrance: entry },
+        };
+
+        // Blocks reachable from a block without following calls, and the addresses right after them.
+        let reachable_blocks = |start: AddrPc| {
+            let mut blocks = HashSet::new();
+            let mut trailing_addresses = HashSet::new();
+            let mut remaining = vec![start];
+            while let Some(addr) = remaining.pop() {
+                let Some(&idx) = code_chunks.get(&addr) else { continue };
+                if !blocks.insert(addr) {
+                    continue;
+                }
+                let block = self.chunks[idx].1.code_block().unwrap();
+                let last_instruction = *block.instructions.last().unwrap();
+                let addr_after_block = addr_after(block);
+                trailing_addresses.insert(addr_after_block);
+
+                if last_instruction.is_subroutine_call() || last_instruction.uses_jump_table() {
+                    if !last_instruction.uses_jump_table() {
+                        remaining.push(addr_after_block);
+                    }
+                } else {
+                    if !last_instruction.is_subroutine_return() {
+                        remaining.extend(block.exits.iter().filter_map(|&a| AddrPc::try_from(a).ok()));
+                    }
+                    if !last_instruction.is_single_path_leap() {
+                        remaining.push(addr_after_block);
+                    }
+                }
+            }
+            (blocks, trailing_addresses)
+        };
+
+        // Discard blocks reachable from the entry, along with unknown and jump table chunks following them.
+        let (discarded_blocks, trailing_addresses) = reachable_blocks(first_step.code_start);
+        // Subroutines containing none of them return in the same state as before.
+        let subroutine_states = self
+            .subroutine_states
+            .iter()
+            .filter(|(&start, _)| reachable_blocks(start).0.is_disjoint(&discarded_blocks))
+            .map(|(&start, processor)| (start, processor.clone()))
+            .collect_vec();
+        let kept_chunks = self
+            .chunks
+            .iter()
+            .filter(|(addr, block)| match block {
+                BinaryBlock::Code(_) => !discarded_blocks.contains(addr),
+                BinaryBlock::Unknown => !trailing_addresses.contains(addr),
+                BinaryBlock::Data(data) => {
+                    !(trailing_addresses.contains(addr)
+                        && matches!(data.kind, DataKind::JumpTableShort | DataKind::JumpTableLong))
+                }
+                BinaryBlock::EndOfRom => false,
+            })
+            .cloned()
+            .collect();
+
+        let mut walker = RomAssemblyWalker::with_analysed_chunks(self.rom.clone(), kept_chunks, [first_step]);
+        for (start, final_processor_state) in subroutine_states {
+            walker.analysed_subroutines.insert(
+                start,
+                Arc::new(Mutex::new(SubroutineAnalysisState {
+                    code_blocks: Vec::new(),
+                    analysed_blocks: HashSet::new(),
+                    remaining_blocks: Vec::new(),
+                    final_processor_state,
+                })),
+            );
+        }
+        walker.full_analysis()?;
+        self.subroutine_states = walker.subroutine_states();
+        self.chunks = walker.chunks;
+        self.rejected_jump_table_entries.extend(walker.rejected_jump_table_entries);
+        self.rejected_jump_table_entries.sort();
+        self.rejected_jump_table_entries.dedup();
+        for warning in walker.warnings {
+            if !self.warnings.contains(&warning) {
+                self.warnings.push(warning);
+            }
+        }
+        Ok(())
+    }
+
     pub fn rom_bytes(&self) -> &[u8] {
         &self.rom.0
     }
 
+    /// Returns the start of the instruction containing `addr`, so that views scrolled to the middle of an instruction
+    /// can snap to it. Addresses outside of code blocks are returned unchanged.
+    pub fn instruction_boundary_at(&self, addr: AddrPc) -> AddrPc {
+        let chunk_idx = self.chunks.partition_point(|(start, _)| *start <= addr);
+        let Some(block) = chunk_idx.checked_sub(1).and_then(|idx| self.chunks[idx].1.code_block()) else {
+            return addr;
+        };
+        let instruction_idx = block.instructions.partition_point(|i| i.offset <= addr);
+        match instruction_idx.checked_sub(1).map(|idx| block.instructions[idx]) {
+            Some(i) if addr < i.offset + i.opcode.instruction_size() as u32 => i.offset,
+            _ => addr,
+        }
+    }
+
+    /// Iterates over the instructions starting within `range` in ROM order, skipping data and unknown chunks.
+    pub fn instructions_in(&self, range: SnesSlice) -> impl Iterator<Item = &Instruction> {
+        let pc_range =
+            AddrPc::try_from(range.begin).map_or(AddrPc(0)..AddrPc(0), |begin| begin..begin + range.size as u32);
+        let first_chunk = self.chunks.partition_point(|(start, _)| *start <= pc_range.start).saturating_sub(1);
+        let chunks_end = pc_range.end;
+        self.chunks[first_chunk..]
+            .iter()
+            .take_while(move |(start, _)| *start < chunks_end)
+            .filter_map(|(_, block)| block.code_block())
+            .flat_map(|block| block.instructions.iter())
+            .filter(move |i| pc_range.contains(&i.offset))
+    }
+
     /// Parses a data block and marks it with given kind and size determined by the `parse` function. `parse` returns
     /// the parsed data and number of ROM bytes consumed by the parser.
     pub fn parse_and_mark_data<EM, ET, RT, PF>(
@@ -270,20 +541,19 @@ impl RomDisassembly {
                         BinaryBlock::Unknown => {
                             if data_block.slice.contains(next_chunk_start) {
                                 match next_block {
-                                    BinaryBlock::Code(_) => {
-                                        log::error!("Requested data block overlaps with the next code block at: {next_chunk_start:?}");
-                                    }
-                                    BinaryBlock::Data(next_data_block) => {
-                                        log::error!("Requested data block overlaps with the next data block:");
-                                        eprintln!("> data_block = {data_block:?}");
-                                        eprintln!("> next_block = {next_data_block:?}");
-                                    }
-                                    BinaryBlock::Unknown => {
-                                        log::error!("Requested data block overlaps with the next unknown block at: {next_chunk_start:?}");
-                                    }
-                                    BinaryBlock::EndOfRom => {
-                                        log::error!("Requested data block doesn't fit in the ROM");
-                                    }
+                                    BinaryBlock::Code(_) => log::error!(
+                                        "Requested data block overlaps with the next code block at: \
+                                         {next_chunk_start:?}"
+                                    ),
+                                    BinaryBlock::Data(next_data_block) => log::error!(
+                                        "Requested data block {data_block:?} overlaps with the next data block \
+                                         {next_data_block:?}"
+                                    ),
+                                    BinaryBlock::Unknown => log::error!(
+                                        "Requested data block overlaps with the next unknown block at: \
+                                         {next_chunk_start:?}"
+                                    ),
+                                    BinaryBlock::EndOfRom => log::error!("Requested data block doesn't fit in the ROM"),
                                 }
                                 return Err(error_mapper(RomError::DataBlockNotFound(data_block)));
                             }
@@ -369,41 +639,300 @@ impl Debug for RomDisassembly {
 
 impl RomAssemblyWalker {
     fn new(rom: Rom, rih: &RomInternalHeader) -> Self {
-        let remaining_steps = [AddrSnes::MIN, EXECUTE_PTR_TRAMPOLINE_ADDR, EXECUTE_PTR_LONG_TRAMPOLINE_ADDR]
-            .iter()
-            .chain(rih.interrupt_vectors.iter())
-            .filter(|a| a.0 != 0xFFFF)
-            .map(|&addr| StepBasicBlock {
-                code_start: AddrPc::try_from(addr).unwrap(),
-                processor:  Processor::new(),
-                entrance:   addr,
+        Self::with_analysed_chunks(rom, Vec::new(), Self::entry_points(rih))
+    }
+
+    /// Analysis starts from the reset vector, which is where the CPU starts executing in emulation mode, followed by
+    /// the `ExecutePtr` trampolines and the remaining interrupt vectors. Vectors outside of ROM are skipped.
+    fn entry_points(rih: &RomInternalHeader) -> Vec<StepBasicBlock> {
+        // The first half of the interrupt vectors is used in native mode, the other half in emulation mode.
+        let native_vectors_count = rih.interrupt_vectors.len() / 2;
+        [(rih.reset_vector(), Processor::interrupt_handler(false))]
+            .into_iter()
+            .chain([EXECUTE_PTR_TRAMPOLINE_ADDR, EXECUTE_PTR_LONG_TRAMPOLINE_ADDR].map(|addr| (addr, Processor::new())))
+            .chain(
+                rih.interrupt_vectors
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &addr)| (addr, Processor::interrupt_handler(i < native_vectors_count))),
+            )
+            .filter(|(a, _)| a.0 != 0xFFFF)
+            .filter_map(|(addr, processor)| {
+                let code_start = AddrPc::try_from(addr).ok()?;
+                Some(StepBasicBlock { code_start, processor, entrance: addr })
             })
-            .map(RomAssemblyWalkerStep::BasicBlock)
-            .collect();
+            .collect()
+    }
+
+    /// Creates a walker starting at `entry_points` that treats the code and data blocks in `chunks` as already
+    /// analysed.
+    fn with_analysed_chunks(
+        rom: Rom, chunks: Vec<(AddrPc, BinaryBlock)>, entry_points: impl IntoIterator<Item = StepBasicBlock>,
+    ) -> Self {
+        let mut analysed_chunks = BTreeMap::new();
+        let mut analysed_code_starts = HashSet::with_capacity(256);
+        for (idx, (start, block)) in chunks.iter().enumerate() {
+            match block {
+                BinaryBlock::Code(code) => {
+                    let Some(last_instruction) = code.instructions.last() else { continue };
+                    let addr_after_block = last_instruction.offset + last_instruction.opcode.instruction_size() as u32;
+                    analysed_chunks.insert(addr_after_block, (*start, idx));
+                    analysed_code_starts.insert(*start);
+                }
+                BinaryBlock::Data(data) => {
+                    let end = AddrPc((start.as_index() + data.slice.size).min(rom.0.len()) as u32);
+                    analysed_chunks.insert(end, (*start, idx));
+                }
+                BinaryBlock::Unknown | BinaryBlock::EndOfRom => {}
+            }
+        }
 
         Self {
             rom,
-            chunks: Default::default(),
-            analysed_chunks: Default::default(),
-            remaining_steps,
-            analysed_code_starts: HashSet::with_capacity(256),
+            chunks,
+            analysed_chunks,
+            remaining_steps: entry_points.into_iter().map(RomAssemblyWalkerStep::BasicBlock).collect(),
+            analysed_code_starts,
             subroutine_returns: HashMap::with_capacity(256),
             analysed_subroutines: HashMap::with_capacity(256),
+            rejected_jump_table_entries: Vec::new(),
+            warnings: Vec::new(),
+            defer_calls: false,
+            deferred_subroutines: Vec::new(),
         }
     }
 
+    /// Final processor states of the subroutines whose analysis has finished.
+    fn subroutine_states(&self) -> HashMap<AddrPc, Processor> {
+        self.analysed_subroutines
+            .iter()
+            .filter_map(|(&start, sub)| {
+                let sub = sub.lock().unwrap();
+                sub.is_complete().then(|| (start, sub.final_processor_state.clone()))
+            })
+            .collect()
+    }
+
+    /// The processor state the subroutine at `start` returns with, if its analysis has finished.
+    fn finished_subroutine_state(&self, start: AddrPc) -> Option<Processor> {
+        let sub = self.analysed_subroutines.get(&start)?.lock().unwrap();
+        sub.is_complete().then(|| sub.final_processor_state.clone())
+    }
+
+    fn warn(&mut self, warning: ParseWarning) {
+        log::warn!("{warning}");
+        self.warnings.push(warning);
+    }
+
+    /// Marks `data` as not being code: code blocks end where it begins and jumps into it aren't followed.
+    fn pre_mark_data(&mut self, data: DataBlock) {
+        let Ok(start) = AddrPc::try_from(data.slice.begin) else {
+            self.warn(ParseWarning::DataOutsideRom(data.slice.begin));
+            return;
+        };
+        let end = AddrPc((start.as_index() + data.slice.size).min(self.rom.0.len()) as u32);
+        self.chunks.push((start, BinaryBlock::Data(data)));
+        self.analysed_chunks.insert(end, (start, self.chunks.len() - 1));
+        self.chunks.push((end, BinaryBlock::Unknown));
+    }
+
     fn full_analysis(&mut self) -> Result<()> {
+        self.walk()?;
+        self.cleanup()
+    }
+
+    /// Analyses the ROM in rounds, each analysing the subroutines called by the code found so far in parallel.
+    ///
+    /// Each round walks the code queued so far without following calls to subroutines that haven't been analysed yet.
+    /// Those subroutines are then analysed on separate threads along with the subroutines they call, and the code
+    /// after the calls to them is walked in the next round. If analysing any of them fails, the whole ROM is analysed
+    /// serially to report the same error as [`full_analysis`](Self::full_analysis).
+    fn parallel_analysis(rom: Rom, entry_points: Vec<StepBasicBlock>) -> Result<Self> {
+        let serial_analysis = || {
+            let mut walker = Self::with_analysed_chunks(rom.clone(), Vec::new(), entry_points.clone());
+            walker.full_analysis().map(|()| walker)
+        };
+
+        let mut walker = Self::with_analysed_chunks(rom.clone(), Vec::new(), entry_points.clone());
+        walker.defer_calls = true;
+        let mut analysed_roots = HashSet::new();
+        loop {
+            if walker.walk().is_err() {
+                return serial_analysis();
+            }
+            let roots = std::mem::take(&mut walker.deferred_subroutines)
+                .into_iter()
+                .filter(|root| analysed_roots.insert(root.subroutine.code_start))
+                .collect_vec();
+            if roots.is_empty() {
+                break;
+            }
+            if walker.analyse_deferred_subroutines(&roots).is_err() {
+                return serial_analysis();
+            }
+            for root in roots {
+                let StepSubroutine { code_start, entrance, .. } = root.subroutine;
+                if let Some(processor) = walker.finished_subroutine_state(code_start) {
+                    walker.enqueue_subroutine_returns(code_start, entrance, &processor);
+                }
+            }
+        }
+        walker.cleanup()?;
+        Ok(walker)
+    }
+
+    /// Analyses deferred subroutines on separate threads, each together with the ones sharing code with it, and adds
+    /// the results. Subroutines running into code found by this walker are analysed serially by it instead.
+    fn analyse_deferred_subroutines(&mut self, subroutines: &[DeferredSubroutine]) -> Result<()> {
+        // Their blocks aren't walked again, so they don't count as shared code.
+        let finished_subroutines: HashMap<_, _> = self
+            .analysed_subroutines
+            .iter()
+            .filter(|(_, sub)| sub.lock().unwrap().is_complete())
+            .map(|(&start, sub)| (start, Arc::clone(sub)))
+            .collect();
+        let mut groups = (0..subroutines.len()).map(|idx| vec![idx]).collect_vec();
+        let walkers = loop {
+            let walkers = std::thread::scope(|scope| {
+                let threads = groups
+                    .iter()
+                    .map(|group| {
+                        let mut walker = Self::with_analysed_chunks(self.rom.clone(), Vec::new(), []);
+                        walker.analysed_subroutines = finished_subroutines.clone();
+                        for &idx in group {
+                            walker.enqueue_deferred_subroutine(subroutines[idx].clone());
+                        }
+                        scope.spawn(move || walker.walk().map(|()| walker))
+                    })
+                    .collect_vec();
+                threads
+                    .into_iter()
+                    .map(|thread| thread.join().expect("Analysis thread panicked"))
+                    .collect::<Result<Vec<_>>>()
+            })?;
+            let merged_groups = Self::merge_overlapping_groups(&walkers, &groups);
+            if merged_groups.len() == groups.len() {
+                break walkers;
+            }
+            groups = merged_groups;
+        };
+
+        let found_ranges = self.covered_ranges().sorted_by_key(|range| range.start).collect_vec();
+        let overlaps_found_code = |walker: &Self| {
+            walker.covered_ranges().any(|range| {
+                let next = found_ranges.partition_point(|found| found.start < range.end);
+                next > 0 && found_ranges[next - 1].end > range.start
+            })
+        };
+        let (overlapping, separate): (Vec<_>, Vec<_>) =
+            groups.into_iter().zip(walkers).partition(|(_, walker)| overlaps_found_code(walker));
+        for (_, walker) in separate {
+            self.absorb(walker);
+        }
+
+        self.defer_calls = false;
+        for idx in overlapping.into_iter().flat_map(|(group, _)| group) {
+            self.enqueue_deferred_subroutine(subroutines[idx].clone());
+        }
+        let result = self.walk();
+        self.defer_calls = true;
+        result
+    }
+
+    /// Queues the analysis of a deferred subroutine after the steps that are already queued.
+    fn enqueue_deferred_subroutine(&mut self, deferred: DeferredSubroutine) {
+        if !self.analysed_subroutines.contains_key(&deferred.subroutine.code_start) {
+            self.remaining_steps.push_back(RomAssemblyWalkerStep::Subroutine(deferred.subroutine));
+        }
+        if self.analysed_code_starts.insert(deferred.first_block.code_start) {
+            self.remaining_steps.push_back(RomAssemblyWalkerStep::BasicBlock(deferred.first_block));
+        }
+    }
+
+    /// Adds the chunks and subroutines found by `other`, none of which may overlap the ones found by this walker.
+    fn absorb(&mut self, other: Self) {
+        let offset = self.chunks.len();
+        self.chunks.extend(other.chunks);
+        self.analysed_chunks
+            .extend(other.analysed_chunks.into_iter().map(|(end, (start, idx))| (end, (start, idx + offset))));
+        self.analysed_code_starts.extend(other.analysed_code_starts);
+        for (start, returns) in other.subroutine_returns {
+            self.subroutine_returns.entry(start).or_default().extend(returns);
+        }
+        for (start, sub) in other.analysed_subroutines {
+            if let Entry::Vacant(entry) = self.analysed_subroutines.entry(start) {
+                sub.lock().unwrap().code_blocks.iter_mut().for_each(|idx| *idx += offset);
+                entry.insert(sub);
+            }
+        }
+        self.rejected_jump_table_entries.extend(other.rejected_jump_table_entries);
+        self.warnings.extend(other.warnings);
+    }
+
+    /// Joins the groups of subroutines whose walkers found overlapping code or data. Subroutines in each group stay in
+    /// their original order, so that they're analysed in the same order as in a serial analysis.
+    fn merge_overlapping_groups(walkers: &[Self], groups: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        fn root(parents: &mut [usize], mut idx: usize) -> usize {
+            while parents[idx] != idx {
+                idx = parents[idx];
+            }
+            idx
+        }
+
+        let mut parents = (0..groups.len()).collect_vec();
+        let ranges = walkers
+            .iter()
+            .enumerate()
+            .flat_map(|(group, walker)| walker.covered_ranges().map(move |range| (range, group)))
+            .sorted_by_key(|(range, _)| range.start);
+        // Every range overlapping the current one also overlaps the one ending last among those before it.
+        let mut furthest: Option<(AddrPc, usize)> = None;
+        for (range, group) in ranges {
+            match furthest {
+                Some((end, other_group)) if range.start < end => {
+                    let (a, b) = (root(&mut parents, group), root(&mut parents, other_group));
+                    parents[a.max(b)] = a.min(b);
+                    if range.end > end {
+                        furthest = Some((range.end, group));
+                    }
+                }
+                _ => furthest = Some((range.end, group)),
+            }
+        }
+
+        let mut merged: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (group, subroutines) in groups.iter().enumerate() {
+            merged.entry(root(&mut parents, group)).or_default().extend(subroutines);
+        }
+        merged.into_values().map(|group| group.into_iter().sorted().collect()).collect()
+    }
+
+    /// PC ranges of the code and data blocks found so far.
+    fn covered_ranges(&self) -> impl Iterator<Item = Range<AddrPc>> + '_ {
+        self.chunks.iter().filter_map(|(start, block)| {
+            let end = match block {
+                BinaryBlock::Code(code) => {
+                    code.instructions.last().map(|i| i.offset + i.opcode.instruction_size() as u32)?
+                }
+                BinaryBlock::Data(data) => *start + data.slice.size as u32,
+                BinaryBlock::Unknown | BinaryBlock::EndOfRom => return None,
+            };
+            Some(*start..end)
+        })
+    }
+
+    /// Follows the queued steps until there are none left, without merging the resulting chunks.
+    fn walk(&mut self) -> Result<()> {
         while let Some(step) = self.remaining_steps.pop_front() {
             match step {
                 RomAssemblyWalkerStep::BasicBlock(step) => self.analyse_basic_block(step)?,
                 RomAssemblyWalkerStep::Subroutine(step) => self.analyse_subroutine(step)?,
             }
         }
-        self.cleanup();
         Ok(())
     }
 
-    fn cleanup(&mut self) {
+    fn cleanup(&mut self) -> Result<()> {
         self.chunks.push((AddrPc(self.rom.0.len() as _), BinaryBlock::EndOfRom));
         self.chunks.sort_by_key(|(address, _)| address.0);
         let mut dedup_chunks = Vec::with_capacity(self.chunks.len());
@@ -419,32 +948,42 @@ impl RomAssemblyWalker {
                 } else if matches!(chunk.1, BinaryBlock::Unknown) {
                     continue;
                 } else {
-                    panic!("Multiple chunks generated at address {}", final_chunk.0);
+                    return Err(DisassemblyError::MultipleChunks(final_chunk.0));
                 }
             }
         }
         self.chunks = dedup_chunks;
+        Ok(())
     }
 
-    fn print_backtrace(
+    /// Lists the code leading to the block at `code_start`, following the first entrance of each block back to where
+    /// analysis started.
+    fn backtrace(
         &self, code_start: AddrPc, mut entrance: AddrSnes, processor: &Processor, code_block: &CodeBlock,
-    ) {
-        eprintln!("!!!! Code error backtrace start, block at {code_start}, M: {}", processor.p_reg.m_flag());
-        code_block.instructions.iter().for_each(|i| eprintln!(" {}", i.display_with_flags()));
+    ) -> String {
+        let mut backtrace =
+            format!("Code error backtrace start, block at {code_start}, M: {}\n", processor.p_reg.m_flag());
+        code_block.instructions.iter().for_each(|i| writeln!(backtrace, " {}", i.display_with_flags()).unwrap());
         while entrance != AddrSnes::MIN {
-            let entrance_pc: AddrPc = entrance.try_into().unwrap();
-            let (_, &(_, block_idx)) = self.analysed_chunks.range(entrance_pc..).next().unwrap();
-            let block = self.chunks[block_idx].1.code_block().unwrap();
-            eprintln!(
+            let Ok(entrance_pc) = AddrPc::try_from(entrance) else { break };
+            let Some((_, &(_, block_idx))) = self.analysed_chunks.range(entrance_pc..).next() else { break };
+            let Some(block) = self.chunks[block_idx].1.code_block() else { break };
+            let (Some(first), Some(&next_entrance)) = (block.instructions.first(), block.entrances.first()) else {
+                break;
+            };
+            writeln!(
+                backtrace,
                 "Next backtrace block at {:?}, M: {}, X: {}, entrance: {entrance:?}",
-                block.instructions[0].offset, block.instructions[0].m_flag, block.instructions[0].x_flag,
-            );
-            block.instructions.iter().for_each(|i| eprintln!(" {}", i.display_with_flags()));
-            if entrance == block.entrances[0] {
+                first.offset, first.m_flag, first.x_flag,
+            )
+            .unwrap();
+            block.instructions.iter().for_each(|i| writeln!(backtrace, " {}", i.display_with_flags()).unwrap());
+            if entrance == next_entrance {
                 break;
             }
-            entrance = block.entrances[0];
+            entrance = next_entrance;
         }
+        backtrace
     }
 
     fn analyse_subroutine(&mut self, step: StepSubroutine) -> Result<()> {
@@ -452,7 +991,7 @@ impl RomAssemblyWalker {
             .analysed_subroutines
             .entry(step.code_start)
             .or_insert_with(|| {
-                Rc::new(RefCell::new(SubroutineAnalysisState {
+                Arc::new(Mutex::new(SubroutineAnalysisState {
                     code_blocks:           Vec::with_capacity(32),
                     analysed_blocks:       HashSet::with_capacity(32),
                     remaining_blocks:      vec![step.code_start],
@@ -460,14 +999,14 @@ impl RomAssemblyWalker {
                 }))
             })
             .clone();
-        let mut sub = sub.deref().borrow_mut();
+        let mut sub = sub.lock().unwrap();
 
         while let Some(curr_code_start) = sub.remaining_blocks.pop() {
             match self.find_analysed_chunk_at(curr_code_start) {
                 BlockFindResult::Found { range_vec_idx, .. } => {
+                    let Some(block) = self.chunks[range_vec_idx].1.code_block() else { continue };
                     sub.code_blocks.push(range_vec_idx);
 
-                    let block = self.chunks[range_vec_idx].1.code_block().unwrap();
                     let last_instruction = *block.instructions.last().unwrap();
 
                     if last_instruction.uses_jump_table() {
@@ -480,7 +1019,9 @@ impl RomAssemblyWalker {
                     // The zero exits case happens when a JSR or JSL's destination is not in ROM but e.g. in RAM.
                     if exits.len() != 0 {
                         if last_instruction.is_subroutine_call() {
-                            let sub_location = AddrPc::try_from(exits.as_slice()[0]).unwrap();
+                            let sub_addr = exits.as_slice()[0];
+                            let sub_location = AddrPc::try_from(sub_addr)
+                                .map_err(|_| DisassemblyError::SubroutineOutsideRom(sub_addr))?;
                             self.subroutine_returns.entry(sub_location).or_default().push(addr_after_block);
                             if step.sub_exists_in_call_hierarchy(sub_location) {
                                 self.enqueue_basic_block(StepBasicBlock {
@@ -491,7 +1032,7 @@ impl RomAssemblyWalker {
                             } else {
                                 let next_step = StepSubroutine {
                                     code_start: sub_location,
-                                    entrance:   last_instruction.offset.try_into().unwrap(),
+                                    entrance:   snes_addr(last_instruction.offset)?,
                                     caller:     Some(Box::new(step.clone())),
                                 };
                                 if self.enqueue_subroutine(next_step) {
@@ -502,9 +1043,9 @@ impl RomAssemblyWalker {
                         } else if !last_instruction.is_subroutine_return() {
                             let pending_blocks = exits
                                 .clone()
-                                .map(|&a| AddrPc::try_from(a).unwrap())
-                                .filter(|&a| sub.analysed_blocks.insert(a))
-                                .collect_vec();
+                                .map(|&a| AddrPc::try_from(a).map_err(|_| DisassemblyError::SubroutineOutsideRom(a)))
+                                .filter_ok(|&a| sub.analysed_blocks.insert(a))
+                                .collect::<Result<Vec<_>>>()?;
                             sub.remaining_blocks.extend(pending_blocks);
                         }
                     }
@@ -529,35 +1070,44 @@ impl RomAssemblyWalker {
                 sub.final_processor_state =
                     self.chunks[returning_block_index].1.code_block().unwrap().final_processor_state.clone();
             }
-            None => return Err(DisassemblyError::SubroutineWithoutReturn(step.code_start.try_into().unwrap())),
+            None => return Err(DisassemblyError::SubroutineWithoutReturn(snes_addr(step.code_start)?)),
         };
 
         if let Some(caller) = step.caller {
             self.enqueue_subroutine(*caller);
         }
 
+        self.enqueue_subroutine_returns(step.code_start, step.entrance, &sub.final_processor_state);
+        Ok(())
+    }
+
+    /// Queues the code after each call to the subroutine at `sub_start`, which continues in the state it returns with.
+    fn enqueue_subroutine_returns(&mut self, sub_start: AddrPc, entrance: AddrSnes, final_processor_state: &Processor) {
         // Subroutines in jump tables don't have returns, or rather we don't need to analyse them.
-        if let Some(returns) = self.subroutine_returns.get(&step.code_start) {
+        if let Some(returns) = self.subroutine_returns.get(&sub_start) {
             for return_addr in returns.clone().into_iter() {
                 self.enqueue_basic_block(StepBasicBlock {
                     code_start: return_addr,
-                    processor:  sub.final_processor_state.clone(),
-                    entrance:   step.entrance,
+                    processor: final_processor_state.clone(),
+                    entrance,
                 });
             }
         }
-
-        Ok(())
     }
 
     fn analyse_basic_block(&mut self, step: StepBasicBlock) -> Result<()> {
         let StepBasicBlock { code_start, mut processor, entrance } = step;
+        if code_start.as_index() >= self.rom.0.len() {
+            return Err(DisassemblyError::CodeOutsideRom(code_start));
+        }
 
         let mut next_known_start = self.rom.0.len();
         match self.find_analysed_chunk_at(code_start) {
             BlockFindResult::Found { range_start, range_end, range_vec_idx } => {
-                if code_start != range_start {
-                    self.split_block_at(range_start, range_end, range_vec_idx, code_start, entrance);
+                if self.chunks[range_vec_idx].1.code_block().is_none() {
+                    self.warn(ParseWarning::CodeInsideData { code: code_start, data: range_start });
+                } else if code_start != range_start {
+                    self.split_block_at(range_start, range_end, range_vec_idx, code_start, entrance)?;
                 }
                 return Ok(());
             }
@@ -569,14 +1119,19 @@ impl RomAssemblyWalker {
             }
         }
 
-        let (mut code_block, addr_after_block) =
-            CodeBlock::from_bytes(code_start, &self.rom.0[code_start.as_index()..next_known_start], &mut processor);
+        // Blocks end at calls here, unlike with `CodeBlock::from_bytes`. The code after a call is a separate block
+        // that's only analysed once the processor state the subroutine returns with is known.
+        let (mut code_block, addr_after_block) = CodeBlock::from_bytes_until_call(
+            code_start,
+            &self.rom.0[code_start.as_index()..next_known_start],
+            &mut processor,
+        );
         code_block.entrances.push(entrance);
 
-        let last_instruction = code_block.instructions.last().unwrap_or_else(|| {
-            self.print_backtrace(code_start, entrance, &processor, &code_block);
-            panic!("Empty (invalid) code block at {code_start}")
-        });
+        let Some(last_instruction) = code_block.instructions.last() else {
+            let backtrace = self.backtrace(code_start, entrance, &processor, &code_block);
+            return Err(DisassemblyError::EmptyCodeBlock { addr: code_start, backtrace });
+        };
 
         let mut next_covered = false;
         if last_instruction.can_change_program_counter() {
@@ -588,13 +1143,35 @@ impl RomAssemblyWalker {
                 // The M and X flags are getting set in the `ExecutePtr` and `ExecutePtrLong` trampolines.
                 processor.p_reg.0 |= 0x30;
 
-                let jump_table_addr = AddrSnes::try_from_lorom(addr_after_block).unwrap();
-                match JUMP_TABLES.iter().find(|t| t.begin == jump_table_addr) {
-                    None => log::warn!("Could not find jump table at {jump_table_addr:?}"),
-                    Some(&jtv) => {
-                        let addresses = get_jump_table_from_rom(&self.rom, jtv).unwrap();
-                        for addr in addresses.into_iter().filter(|a| a.absolute() != 0) {
-                            if !NON_CODE_JUMP_ADDRESSES.contains(&addr) {
+                let jump_table_addr = snes_addr(addr_after_block)?;
+                let jump_table = JUMP_TABLES.iter().copied().find(|t| t.begin == jump_table_addr).or_else(|| {
+                    let long_ptrs = last_instruction.next_instructions().contains(&EXECUTE_PTR_LONG_TRAMPOLINE_ADDR);
+                    let ptr_size = if long_ptrs { 3 } else { 2 };
+                    let max_length = next_known_start.saturating_sub(addr_after_block.as_index()) / ptr_size;
+                    let detected = detect_jump_table(&self.rom, jump_table_addr, long_ptrs, max_length);
+                    if let Some(jtv) = detected {
+                        log::info!("Detected jump table at {jump_table_addr:?} with {} pointers", jtv.length);
+                    }
+                    detected
+                });
+                match jump_table {
+                    None => self.warn(ParseWarning::JumpTableNotFound(jump_table_addr)),
+                    Some(jtv) => {
+                        let ptr_size = if jtv.long_ptrs { 3 } else { 2 };
+                        let table_end = addr_after_block + (jtv.length * ptr_size) as u32;
+                        let addresses = get_jump_table_from_rom(&self.rom, jtv)
+                            .map_err(|_| DisassemblyError::TruncatedJumpTable(jtv.begin))?;
+                        for (i, addr) in addresses.into_iter().enumerate().filter(|(_, a)| a.absolute() != 0) {
+                            if NON_CODE_JUMP_ADDRESSES.contains(&addr) {
+                                continue;
+                            }
+                            let points_into_table =
+                                AddrPc::try_from(addr).is_ok_and(|pc| (addr_after_block..table_end).contains(&pc));
+                            if points_into_table || !self.is_code_target(addr) {
+                                let entry = jtv.begin + (i * ptr_size) as u32;
+                                log::info!("Not following jump table entry at {entry:?} pointing to {addr:?}");
+                                self.rejected_jump_table_entries.push(RejectedJumpTableEntry { entry, target: addr });
+                            } else {
                                 next_instructions.push(addr);
                             }
                         }
@@ -611,17 +1188,15 @@ impl RomAssemblyWalker {
                 let mut step_following_block = StepBasicBlock {
                     code_start: addr_after_block,
                     processor:  processor.clone(),
-                    entrance:   code_start.try_into().unwrap(),
+                    entrance:   snes_addr(code_start)?,
                 };
 
                 match AddrPc::try_from(next_instructions[0]) {
                     Ok(sub_start) => {
                         self.subroutine_returns.entry(sub_start).or_default().push(addr_after_block);
-                        if let Some(sub) = self.analysed_subroutines.get(&sub_start) {
-                            if sub.deref().borrow().is_complete() {
-                                step_following_block.processor = sub.deref().borrow().final_processor_state.clone();
-                                self.enqueue_basic_block(step_following_block);
-                            }
+                        if let Some(final_processor_state) = self.finished_subroutine_state(sub_start) {
+                            step_following_block.processor = final_processor_state;
+                            self.enqueue_basic_block(step_following_block);
                         }
                     }
                     Err(_) => {
@@ -632,9 +1207,13 @@ impl RomAssemblyWalker {
                 }
             }
 
+            let leaves_to_subroutine = is_jump_table || last_instruction.is_subroutine_call();
+            let mut deferred_blocks = Vec::new();
             for &next_target_snes in next_instructions.iter() {
                 match AddrPc::try_from(next_target_snes) {
-                    Err(_) => log::warn!("Wrong address of next target: {next_target_snes:06X}"),
+                    Err(_) => {
+                        self.warn(ParseWarning::InvalidNextTarget { block: code_start, target: next_target_snes })
+                    }
                     Ok(next_target_pc) if next_target_pc.as_index() >= self.rom.0.len() => {
                         let last_instruction = *last_instruction;
                         self.chunks.push((code_start, BinaryBlock::Code(code_block)));
@@ -651,23 +1230,37 @@ impl RomAssemblyWalker {
 
                         code_block.exits.push(next_target_snes);
 
-                        self.enqueue_basic_block(StepBasicBlock {
+                        let next_step = StepBasicBlock {
                             code_start: next_target_pc,
                             processor:  processor.clone(),
-                            entrance:   code_start.try_into().unwrap(),
-                        });
+                            entrance:   snes_addr(code_start)?,
+                        };
+                        if !leaves_to_subroutine {
+                            self.enqueue_basic_block(next_step);
+                        } else if self.finished_subroutine_state(next_target_pc).is_none() {
+                            // The blocks of a finished subroutine have already been analysed.
+                            if self.defer_calls {
+                                deferred_blocks.push(next_step);
+                            } else {
+                                self.enqueue_basic_block(next_step);
+                            }
+                        }
                     }
                 }
             }
 
-            if is_jump_table || last_instruction.is_subroutine_call() {
-                for sub_start in next_instructions.into_iter() {
-                    if let Ok(code_start) = AddrPc::try_from(sub_start) {
-                        self.enqueue_subroutine(StepSubroutine {
-                            code_start,
-                            entrance: last_instruction.offset.try_into().unwrap(),
-                            caller: None,
-                        });
+            if leaves_to_subroutine {
+                let entrance = snes_addr(last_instruction.offset)?;
+                if self.defer_calls {
+                    for first_block in deferred_blocks {
+                        let subroutine = StepSubroutine { code_start: first_block.code_start, entrance, caller: None };
+                        self.deferred_subroutines.push(DeferredSubroutine { subroutine, first_block });
+                    }
+                } else {
+                    for sub_start in next_instructions.into_iter() {
+                        if let Ok(code_start) = AddrPc::try_from(sub_start) {
+                            self.enqueue_subroutine(StepSubroutine { code_start, entrance, caller: None });
+                        }
                     }
                 }
             }
@@ -682,6 +1275,17 @@ impl RomAssemblyWalker {
         Ok(())
     }
 
+    /// Whether code can start at `target`, which needs to be in ROM and not inside a block known to be data.
+    fn is_code_target(&self, target: AddrSnes) -> bool {
+        match AddrPc::try_from(target) {
+            Ok(pc) if pc.as_index() < self.rom.0.len() => match self.find_analysed_chunk_at(pc) {
+                BlockFindResult::Found { range_vec_idx, .. } => self.chunks[range_vec_idx].1.code_block().is_some(),
+                _ => true,
+            },
+            _ => false,
+        }
+    }
+
     fn find_analysed_chunk_at(&self, instruction: AddrPc) -> BlockFindResult {
         match self.analysed_chunks.range(instruction + 1..).next() {
             Some((&range_end, &(range_start, range_vec_idx))) => {
@@ -717,22 +1321,25 @@ impl RomAssemblyWalker {
     fn split_block_at(
         &mut self, range_start: AddrPc, range_end: AddrPc, range_vec_idx: usize, middle_start: AddrPc,
         entrance: AddrSnes,
-    ) -> usize {
+    ) -> Result<usize> {
         // jump into the middle of a block, split it in two
-        let (original_pc, mut original_block) =
-            std::mem::replace(&mut self.chunks[range_vec_idx], (range_start, BinaryBlock::Unknown));
+        let middle_start_snes = snes_addr(middle_start)?;
+        let (original_pc, original_block) = &mut self.chunks[range_vec_idx];
+        assert_eq!(*original_pc, range_start);
+        let Some(original_block) = original_block.code_block_mut() else {
+            return Err(DisassemblyError::JumpIntoNonCode(middle_start));
+        };
         let CodeBlock {
             instructions: original_instructions,
             exits: original_exits,
             entrances: original_entrances,
             entry_processor_state,
             final_processor_state,
-        } = std::mem::take(original_block.code_block_mut().expect("Found jump into the middle of a non-code section"));
-        assert_eq!(original_pc, range_start);
+        } = std::mem::take(original_block);
 
         let mut first_block = CodeBlock {
             instructions: Vec::with_capacity(original_instructions.len() / 2),
-            exits: vec![middle_start.try_into().unwrap()],
+            exits: vec![middle_start_snes],
             entrances: original_entrances,
             entry_processor_state,
             final_processor_state: Default::default(),
@@ -747,7 +1354,9 @@ impl RomAssemblyWalker {
         for imeta in original_instructions.into_iter() {
             if imeta.offset < middle_start { &mut first_block } else { &mut second_block }.instructions.push(imeta);
         }
-        second_block.entrances.push(first_block.instructions.last().unwrap().offset.try_into().unwrap());
+        if let Some(last_instruction) = first_block.instructions.last() {
+            second_block.entrances.push(snes_addr(last_instruction.offset)?);
+        }
         first_block.recalculate_final_processor_state();
         second_block.entry_processor_state = first_block.final_processor_state.clone();
 
@@ -757,10 +1366,15 @@ impl RomAssemblyWalker {
         self.analysed_chunks.insert(range_end, (middle_start, range_vec_idx));
         self.analysed_chunks.insert(middle_start, (range_start, self.chunks.len() - 1));
         self.analysed_code_starts.insert(middle_start);
-        self.chunks.len() - 1
+        Ok(self.chunks.len() - 1)
     }
 }
 
+/// Converts the address of analysed code to LoROM.
+fn snes_addr(addr: AddrPc) -> Result<AddrSnes> {
+    AddrSnes::try_from_lorom(addr).map_err(|_| DisassemblyError::AddrOutsideLoRom(addr))
+}
+
 impl StepSubroutine {
     pub fn sub_exists_in_call_hierarchy(&self, sub_addr: AddrPc) -> bool {
         if self.code_start == sub_addr {
@@ -778,3 +1392,507 @@ impl SubroutineAnalysisState {
         self.remaining_blocks.is_empty()
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, sync::Once};
+
+    use itertools::Itertools;
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+
+    use super::{DisassemblyError, ParseWarning, RomAssemblyWalker, RomDisassembly, StepBasicBlock};
+    use crate::{
+        disassembler::{
+            binary_block::{BinaryBlock, DataBlock, DataKind},
+            instruction::Instruction,
+            jump_tables::RejectedJumpTableEntry,
+            opcodes::Mnemonic::*,
+            processor::Processor,
+        },
+        internal_header::{MapMode, RegionCode, RomInternalHeader, RomType},
+        snes_utils::{
+            addr::{AddrPc, AddrSnes},
+            rom_slice::SnesSlice,
+        },
+        Rom,
+    };
+
+    fn rom_with_code(code: &[u8]) -> Rom {
+        let mut bytes = vec![0; 0x8000];
+        bytes[..code.len()].copy_from_slice(code);
+        Rom::new(bytes).unwrap()
+    }
+
+    /// Returns a walker that starts analysing `code` at $8000.
+    fn walker_for(code: &[u8]) -> RomAssemblyWalker {
+        let entry =
+            StepBasicBlock { code_start: AddrPc(0), processor: Processor::new(), entrance: AddrSnes(0x008000) };
+        RomAssemblyWalker::with_analysed_chunks(rom_with_code(code), Vec::new(), [entry])
+    }
+
+    thread_local! {
+        static LOGGED: RefCell<Vec<(Level, String)>> = RefCell::new(Vec::new());
+    }
+
+    /// Records the messages logged by each thread. Applications that don't install a logger get no output at all
+    /// from the disassembler, since it reports everything through `log` rather than writing to stderr.
+    struct RecordingLogger;
+
+    impl Log for RecordingLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            LOGGED.with(|logged| logged.borrow_mut().push((record.level(), record.args().to_string())));
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Returns everything logged on this thread while running `f`, at all levels.
+    fn logged_during(f: impl FnOnce()) -> Vec<(Level, String)> {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&RecordingLogger).unwrap();
+            log::set_max_level(LevelFilter::Trace);
+        });
+        LOGGED.with(|logged| logged.borrow_mut().clear());
+        f();
+        LOGGED.with(|logged| logged.take())
+    }
+
+    fn chunk_summary(disasm: &RomDisassembly) -> Vec<(AddrPc, Option<Vec<Instruction>>)> {
+        disasm.chunks.iter().map(|(addr, block)| (*addr, block.code_block().map(|b| b.instructions.clone()))).collect()
+    }
+
+    #[test]
+    fn test_reanalyze_patched_block() {
+        #[rustfmt::skip]
+        let mut code = [
+            0xA9, 0x01,       // $8000: LDA #$01
+            0xF0, 0x03,       // $8002: BEQ $8007
+            0x20, 0x10, 0x80, // $8004: JSR $8010
+            0x60,             // $8007: RTS
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0xA9, 0x02,       // $8010: LDA #$02
+            0x60,             // $8012: RTS
+        ];
+        let mut walker = walker_for(&code);
+        walker.full_analysis().unwrap();
+        let mut disasm = RomDisassembly { chunks: walker.chunks, ..RomDisassembly::unanalysed(walker.rom) };
+        let before = chunk_summary(&disasm);
+
+        // $8000: LDX #$05
+        code[0] = 0xA2;
+        code[1] = 0x05;
+        disasm.rom = rom_with_code(&code);
+        disasm.reanalyze_from(AddrSnes(0x008000)).unwrap();
+        let after = chunk_summary(&disasm);
+
+        assert_eq!(before.len(), after.len());
+        for (before, after) in before.iter().zip(after.iter()) {
+            assert_eq!(before.0, after.0);
+            if after.0 == AddrPc(0x0000) {
+                let mnemonics = after.1.as_ref().unwrap().iter().map(|i| i.opcode.mnemonic).collect_vec();
+                assert_eq!(mnemonics, vec![LDX, BEQ]);
+            } else {
+                assert_eq!(before.1, after.1);
+            }
+        }
+        assert!(matches!(disasm.chunks.last(), Some((AddrPc(0x8000), BinaryBlock::EndOfRom))));
+    }
+
+    #[test]
+    fn test_reanalyze_across_data() {
+        #[rustfmt::skip]
+        let code = [
+            0xF0, 0x02,       // $8000: BEQ $8004
+            0xA9, 0x00, 0xEA, // $8002: data that would disassemble into LDA #$00, NOP
+            0x60,             // $8005: RTS
+        ];
+        let mut walker = walker_for(&code);
+        walker.pre_mark_data(DataBlock { slice: SnesSlice::new(AddrSnes(0x008002), 3), kind: DataKind::Empty });
+        walker.full_analysis().unwrap();
+        let warnings = walker.warnings.clone();
+        let mut disasm = RomDisassembly {
+            chunks: walker.chunks,
+            warnings: walker.warnings,
+            ..RomDisassembly::unanalysed(walker.rom)
+        };
+        let before = chunk_summary(&disasm);
+
+        disasm.reanalyze_from(AddrSnes(0x008000)).unwrap();
+        assert_eq!(chunk_summary(&disasm), before, "the data isn't disassembled");
+        assert!(matches!(disasm.chunks[1], (AddrPc(0x0002), BinaryBlock::Data(_))));
+        assert_eq!(disasm.warnings, warnings, "warnings aren't repeated");
+    }
+
+    #[test]
+    fn test_interrupt_handler_ends_at_rti() {
+        #[rustfmt::skip]
+        let code = [
+            0x78,       // $8000: SEI
+            0xC2, 0x30, // $8001: REP #$30
+            0x48,       // $8003: PHA
+            0x68,       // $8004: PLA
+            0x40,       // $8005: RTI
+            0xFF, 0xFF, 0xFF, 0xFF,
+        ];
+        let entry = StepBasicBlock {
+            code_start: AddrPc(0),
+            processor:  Processor::interrupt_handler(true),
+            entrance:   AddrSnes(0x008000),
+        };
+        let mut walker = RomAssemblyWalker::with_analysed_chunks(rom_with_code(&code), Vec::new(), [entry]);
+        walker.full_analysis().unwrap();
+
+        assert_eq!(walker.chunks.len(), 3);
+        let (handler_addr, handler) = &walker.chunks[0];
+        let handler = handler.code_block().unwrap();
+        assert_eq!(*handler_addr, AddrPc(0));
+        assert_eq!(handler.instructions.len(), 5);
+        assert_eq!(handler.instructions.last().unwrap().opcode.mnemonic, RTI);
+        assert!(handler.exits.is_empty());
+        assert!(handler.entry_processor_state.p_reg.i_flag());
+        assert_eq!(handler.entry_processor_state.dbr, None);
+        assert!(!handler.final_processor_state.p_reg.m_flag());
+        assert!(matches!(walker.chunks[1], (AddrPc(0x0006), BinaryBlock::Unknown)));
+    }
+
+    #[test]
+    fn test_subroutine_returning_with_rti() {
+        let mut code = vec![0; 0x12];
+        code[0x00..0x04].copy_from_slice(&[0x20, 0x10, 0x80, 0x60]); // $8000: JSR $8010, RTS
+        code[0x10..0x12].copy_from_slice(&[0x40, 0xFF]); // $8010: RTI
+        let mut walker = walker_for(&code);
+        walker.full_analysis().unwrap();
+
+        let (_, sub) = walker.chunks.iter().find(|(addr, _)| *addr == AddrPc(0x0010)).unwrap();
+        assert_eq!(sub.code_block().unwrap().instructions.len(), 1);
+        assert!(walker.chunks.iter().any(|chunk| matches!(chunk, (AddrPc(0x0011), BinaryBlock::Unknown))));
+        assert!(walker.chunks.iter().any(|(addr, block)| *addr == AddrPc(0x0003) && block.code_block().is_some()));
+    }
+
+    #[test]
+    fn test_wdm_and_cop_end_blocks() {
+        #[rustfmt::skip]
+        let code = [
+            0x42, 0x01, // $8000: WDM #$01
+            0xA9, 0x02, // $8002: LDA #$02
+            0x02, 0x00, // $8004: COP #$00
+            0xA9, 0x03, // $8006: LDA #$03
+            0x60,       // $8008: RTS
+            0xFF,
+        ];
+        let mut walker = walker_for(&code);
+        walker.full_analysis().unwrap();
+
+        let mut blocks = walker
+            .chunks
+            .iter()
+            .filter_map(|(addr, block)| Some((*addr, block.code_block()?)))
+            .map(|(addr, block)| {
+                (addr, block.instructions.iter().map(|i| i.opcode.mnemonic).collect_vec(), block.exits.clone())
+            })
+            .collect_vec();
+        blocks.sort_by_key(|(addr, ..)| *addr);
+        assert_eq!(blocks, vec![
+            (AddrPc(0x0000), vec![WDM], vec![AddrSnes(0x008002)]),
+            (AddrPc(0x0002), vec![LDA, COP], vec![AddrSnes(0x008006)]),
+            (AddrPc(0x0006), vec![LDA, RTS], vec![]),
+        ]);
+    }
+
+    #[test]
+    fn test_instruction_boundary_at() {
+        #[rustfmt::skip]
+        let code = [
+            0xA9, 0x01,       // $8000: LDA #$01
+            0x8D, 0x00, 0x01, // $8002: STA $0100
+            0x60,             // $8005: RTS
+        ];
+        let mut walker = walker_for(&code);
+        walker.full_analysis().unwrap();
+        let disasm = RomDisassembly { chunks: walker.chunks, ..RomDisassembly::unanalysed(walker.rom) };
+
+        let snap = |addr| disasm.instruction_boundary_at(AddrPc(addr));
+        assert_eq!(snap(0x0000), AddrPc(0x0000));
+        assert_eq!(snap(0x0001), AddrPc(0x0000));
+        assert_eq!(snap(0x0003), AddrPc(0x0002));
+        assert_eq!(snap(0x0004), AddrPc(0x0002));
+        assert_eq!(snap(0x0005), AddrPc(0x0005));
+        assert_eq!(snap(0x0007), AddrPc(0x0007), "not in code");
+    }
+
+    #[test]
+    fn test_instructions_in() {
+        #[rustfmt::skip]
+        let code = [
+            0xF0, 0x02,       // $8000: BEQ $8004
+            0xA9, 0x00,       // $8002: data
+            0xEA,             // $8004: NOP
+            0x60,             // $8005: RTS
+        ];
+        let mut walker = walker_for(&code);
+        walker.pre_mark_data(DataBlock { slice: SnesSlice::new(AddrSnes(0x008002), 2), kind: DataKind::Empty });
+        walker.full_analysis().unwrap();
+        let disasm = RomDisassembly { chunks: walker.chunks, ..RomDisassembly::unanalysed(walker.rom) };
+
+        let offsets = |begin, size| {
+            disasm.instructions_in(SnesSlice::new(AddrSnes(begin), size)).map(|i| i.offset.0).collect_vec()
+        };
+        assert_eq!(offsets(0x008000, 6), [0x0000, 0x0004, 0x0005]);
+        assert_eq!(offsets(0x008001, 4), [0x0004], "instructions starting before the range are skipped");
+        assert_eq!(offsets(0x008002, 2), [], "data");
+        assert_eq!(offsets(0x7E0000, 6), [], "not in ROM");
+    }
+
+    pub(super) fn header_with_vectors(interrupt_vectors: Vec<AddrSnes>) -> RomInternalHeader {
+        RomInternalHeader {
+            internal_rom_name: String::from("TEST"),
+            map_mode: MapMode::SlowLoRom,
+            rom_type: RomType::Rom,
+            rom_size: 0,
+            sram_size: 0,
+            region_code: RegionCode::NorthAmerica,
+            developer_id: 0,
+            version_number: 0,
+            interrupt_vectors,
+        }
+    }
+
+    #[test]
+    fn test_reset_vector_is_seeded() {
+        let mut interrupt_vectors = vec![AddrSnes(0x8100); 12];
+        interrupt_vectors[10] = AddrSnes(0x9234);
+        interrupt_vectors[11] = AddrSnes(0x0123);
+        let rih = header_with_vectors(interrupt_vectors);
+        assert_eq!(rih.reset_vector(), AddrSnes(0x9234));
+
+        let entry_points = RomAssemblyWalker::entry_points(&rih);
+        let reset = &entry_points[0];
+        assert_eq!((reset.entrance, reset.code_start), (AddrSnes(0x9234), AddrPc(0x1234)));
+        assert!(reset.processor.p_reg.i_flag());
+        assert_eq!(reset.processor.dbr, Some(0));
+        assert!(entry_points.iter().all(|step| step.entrance != AddrSnes(0x8000)));
+        // Vectors pointing outside of ROM aren't seeded.
+        assert!(entry_points.iter().all(|step| step.entrance != AddrSnes(0x0123)));
+    }
+
+    #[test]
+    fn test_walk_from_custom_entry_point() {
+        #[rustfmt::skip]
+        let code = [
+            0xA9, 0x00,       // $8000: LDA #$00, only reachable from the interrupt vectors
+            0x60,             // $8002: RTS
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0xA9, 0x01,       // $8010: LDA #$01
+            0x80, 0x02,       // $8012: BRA $8016
+            0xFF, 0xFF,
+            0x60,             // $8016: RTS
+        ];
+        let rih = header_with_vectors(vec![AddrSnes(0x008000); 12]);
+        let disasm = RomDisassembly::with_entry_points(rom_with_code(&code), &rih, &[AddrSnes(0x008010)]).unwrap();
+
+        let blocks = disasm
+            .chunks
+            .iter()
+            .filter_map(|(addr, block)| Some((*addr, block.code_block()?)))
+            .map(|(addr, block)| (addr, block.instructions.iter().map(|i| i.opcode.mnemonic).collect_vec()))
+            .collect_vec();
+        assert_eq!(blocks, vec![(AddrPc(0x0010), vec![LDA, BRA]), (AddrPc(0x0016), vec![RTS])]);
+        assert!(matches!(disasm.chunks.last(), Some((AddrPc(0x8000), BinaryBlock::EndOfRom))));
+
+        assert!(RomDisassembly::with_entry_points(rom_with_code(&code), &rih, &[AddrSnes(0x7E0000)]).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_rom_is_an_error() {
+        // The reset vector points to an LDA instruction cut off by the end of the ROM.
+        let mut bytes = vec![0; 0x8000];
+        bytes[0x7FFE] = 0xAD;
+        let rih = header_with_vectors(vec![AddrSnes(0x00FFFE); 12]);
+
+        let result = RomDisassembly::try_new(Rom::new(bytes).unwrap(), &rih);
+        assert!(matches!(result, Err(DisassemblyError::EmptyCodeBlock { addr: AddrPc(0x7FFE), .. })));
+
+        // The reset vector points past the end of a 32 KiB ROM.
+        let rih = header_with_vectors(vec![AddrSnes(0x108000); 12]);
+        let result = RomDisassembly::try_new(Rom::new(vec![0; 0x8000]).unwrap(), &rih);
+        assert!(matches!(result, Err(DisassemblyError::CodeOutsideRom(AddrPc(0x80000)))));
+        let result = RomDisassembly::try_new_parallel(Rom::new(vec![0; 0x8000]).unwrap(), &rih);
+        assert!(matches!(result, Err(DisassemblyError::CodeOutsideRom(AddrPc(0x80000)))));
+
+        // The ROM ends in the middle of the known jump table at $0DA10F.
+        let mut bytes = vec![0; 0x6A400];
+        bytes[0x6A10B..0x6A10F].copy_from_slice(&[0x22, 0xDF, 0x86, 0x00]); // $0DA10B: JSL ExecutePtr
+        let rih = header_with_vectors(vec![AddrSnes(0x008000); 12]);
+        let result = RomDisassembly::with_entry_points(Rom::new(bytes).unwrap(), &rih, &[AddrSnes(0x0DA10B)]);
+        assert!(matches!(result, Err(DisassemblyError::TruncatedJumpTable(AddrSnes(0x0DA10F)))));
+    }
+
+    #[test]
+    fn test_pre_marked_data_is_not_analysed() {
+        #[rustfmt::skip]
+        let code = [
+            0xF0, 0x02,       // $8000: BEQ $8004
+            0xA9, 0x00, 0xEA, // $8002: data that would disassemble into LDA #$00, NOP
+            0x60,             // $8005: RTS
+        ];
+        let mut walker = walker_for(&code);
+        walker.pre_mark_data(DataBlock { slice: SnesSlice::new(AddrSnes(0x008002), 3), kind: DataKind::Empty });
+        walker.full_analysis().unwrap();
+
+        assert_eq!(walker.chunks.len(), 4);
+        let (_, branch) = &walker.chunks[0];
+        assert_eq!(branch.code_block().unwrap().instructions.len(), 1);
+        assert!(matches!(walker.chunks[1], (AddrPc(0x0002), BinaryBlock::Data(_))));
+        assert!(matches!(walker.chunks[2], (AddrPc(0x0005), BinaryBlock::Unknown)));
+        assert!(matches!(walker.chunks[3], (AddrPc(0x8000), BinaryBlock::EndOfRom)));
+    }
+
+    #[test]
+    fn test_jump_table_entries_to_data_are_rejected() {
+        let mut code = vec![0; 0x23];
+        #[rustfmt::skip]
+        code[..9].copy_from_slice(&[
+            0x20, 0xDF, 0x86, // $8000: JSR ExecutePtr
+            0x10, 0x80,       // $8003: $8010
+            0x04, 0x80,       // $8005: $8004, inside of the jump table
+            0x20, 0x80,       // $8007: $8020, known data
+        ]);
+        code[0x10] = 0x60; // $8010: RTS
+        code[0x20..0x23].copy_from_slice(&[0x60, 0x60, 0x60]);
+        let mut walker = walker_for(&code);
+        walker.pre_mark_data(DataBlock { slice: SnesSlice::new(AddrSnes(0x008020), 3), kind: DataKind::Empty });
+        walker.full_analysis().unwrap();
+
+        assert_eq!(walker.rejected_jump_table_entries, vec![
+            RejectedJumpTableEntry { entry: AddrSnes(0x008005), target: AddrSnes(0x008004) },
+            RejectedJumpTableEntry { entry: AddrSnes(0x008007), target: AddrSnes(0x008020) },
+        ]);
+        let (_, jump) = &walker.chunks[0];
+        assert_eq!(jump.code_block().unwrap().exits, vec![AddrSnes(0x008010)]);
+        assert!(walker.chunks.iter().any(|(addr, block)| *addr == AddrPc(0x0010) && block.code_block().is_some()));
+        assert!(matches!(
+            walker.chunks.iter().find(|(addr, _)| *addr == AddrPc(0x0020)),
+            Some((_, BinaryBlock::Data(_)))
+        ));
+    }
+
+    #[test]
+    fn test_unresolved_jump_table_is_warned_about() {
+        #[rustfmt::skip]
+        let code = [
+            0x20, 0xDF, 0x86, // $8000: JSR ExecutePtr
+            0x00, 0x00,       // $8003: $0000, not in ROM
+        ];
+        let mut walker = walker_for(&code);
+        walker.pre_mark_data(DataBlock { slice: SnesSlice::new(AddrSnes(0x7E0000), 1), kind: DataKind::Empty });
+        walker.full_analysis().unwrap();
+
+        assert_eq!(walker.warnings, vec![
+            ParseWarning::DataOutsideRom(AddrSnes(0x7E0000)),
+            ParseWarning::JumpTableNotFound(AddrSnes(0x008003)),
+        ]);
+    }
+
+    #[test]
+    fn test_parallel_analysis_matches_serial() {
+        #[rustfmt::skip]
+        let code: [(usize, &[u8]); 5] = [
+            (0x000, &[0x20, 0x00, 0x84, 0x80, 0xFB]), // $8000: JSR $8400, BRA $8000
+            (0x100, &[0x20, 0x00, 0x84, 0x40]),       // $8100: JSR $8400, RTI
+            (0x200, &[0xEA, 0x1A, 0x40]),             // $8200: NOP, INC A, RTI
+            (0x300, &[0x40]),                         // $8300: RTI
+            (0x400, &[0xEA, 0x60]),                   // $8400: NOP, RTS
+        ];
+        // The rest of the ROM, including the `ExecutePtr` trampolines, returns right away.
+        let mut bytes = vec![0x60; 0x8000];
+        for (pc, code) in code {
+            bytes[pc..pc + code.len()].copy_from_slice(code);
+        }
+        let rom = Rom::new(bytes).unwrap();
+        let mut interrupt_vectors = vec![AddrSnes(0x008300); 12];
+        interrupt_vectors[2] = AddrSnes(0x008100);
+        interrupt_vectors[3] = AddrSnes(0x008200);
+        interrupt_vectors[10] = AddrSnes(0x008000);
+        let rih = header_with_vectors(interrupt_vectors);
+
+        let serial = RomDisassembly::try_new(rom.clone(), &rih).unwrap();
+        assert!(serial.chunks.iter().any(|(addr, block)| *addr == AddrPc(0x400) && block.code_block().is_some()));
+        // Repeated, as the results of the threads must not depend on which finishes first.
+        for _ in 0..10 {
+            let parallel = RomDisassembly::try_new_parallel(rom.clone(), &rih).unwrap();
+            assert_eq!(chunk_summary(&parallel), chunk_summary(&serial));
+            assert_eq!(parallel.warnings.len(), serial.warnings.len());
+        }
+    }
+
+    #[test]
+    fn test_parallel_analysis_of_subroutines_sharing_code() {
+        #[rustfmt::skip]
+        let code: [(usize, &[u8]); 9] = [
+            (0x000, &[0x20, 0x00, 0x84, 0x80, 0xFB]),                   // $8000: JSR $8400, BRA $8000
+            (0x100, &[0x20, 0x00, 0x85, 0x40]),                         // $8100: JSR $8500, RTI
+            (0x200, &[0x20, 0x00, 0x88, 0x40]),                         // $8200: JSR $8800, RTI
+            (0x300, &[0x40]),                                           // $8300: RTI
+            (0x400, &[0xEA, 0x20, 0x00, 0x86, 0x60]),                   // $8400: NOP, JSR $8600, RTS
+            (0x500, &[0x1A, 0x20, 0x00, 0x86, 0x20, 0x00, 0x87, 0x60]), // $8500: INC A, JSR $8600, JSR $8700, RTS
+            (0x600, &[0xEA, 0x60]),                                     // $8600: NOP, RTS
+            (0x700, &[0xE2, 0x20, 0x60]),                               // $8700: SEP #$20, RTS
+            (0x800, &[0x3A, 0x60]),                                     // $8800: DEC A, RTS
+        ];
+        let mut bytes = vec![0x60; 0x8000];
+        for (pc, code) in code {
+            bytes[pc..pc + code.len()].copy_from_slice(code);
+        }
+        let rom = Rom::new(bytes).unwrap();
+        let mut interrupt_vectors = vec![AddrSnes(0x008300); 12];
+        interrupt_vectors[2] = AddrSnes(0x008100);
+        interrupt_vectors[3] = AddrSnes(0x008200);
+        interrupt_vectors[10] = AddrSnes(0x008000);
+        let rih = header_with_vectors(interrupt_vectors);
+
+        // $8400 and $8500 share $8600, so they're analysed together, while $8800 is analysed on its own.
+        let serial = RomDisassembly::try_new(rom.clone(), &rih).unwrap();
+        for _ in 0..10 {
+            let parallel = RomDisassembly::try_new_parallel(rom.clone(), &rih).unwrap();
+            assert_eq!(chunk_summary(&parallel), chunk_summary(&serial));
+            assert_eq!(
+                parallel.subroutine_states.keys().sorted().collect_vec(),
+                serial.subroutine_states.keys().sorted().collect_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn test_analysis_without_problems_logs_nothing() {
+        #[rustfmt::skip]
+        let code = [
+            0xA9, 0x01, // $8000: LDA #$01
+            0xF0, 0x01, // $8002: BEQ $8005
+            0xEA,       // $8004: NOP
+            0x60,       // $8005: RTS
+        ];
+        let logged = logged_during(|| walker_for(&code).full_analysis().unwrap());
+        assert_eq!(logged, vec![]);
+    }
+
+    #[test]
+    fn test_overlapping_data_block_is_logged_as_one_error() {
+        let mut disasm = RomDisassembly::unanalysed(rom_with_code(&[]));
+        let data = DataBlock { slice: SnesSlice::new(AddrSnes(0x008010), 0x10), kind: DataKind::Empty };
+        assert!(disasm.rom_slice_at_block(data, |e| e).is_ok());
+
+        let overlapping = DataBlock { slice: SnesSlice::new(AddrSnes(0x008000), 0x18), kind: DataKind::Empty };
+        let logged = logged_during(|| assert!(disasm.rom_slice_at_block(overlapping, |e| e).is_err()));
+        assert_eq!(logged, vec![(
+            Level::Error,
+            format!("Requested data block {overlapping:?} overlaps with the next data block {data:?}")
+        )]);
+    }
+}
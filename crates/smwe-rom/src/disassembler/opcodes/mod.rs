@@ -3,9 +3,10 @@ mod data;
 use std::fmt;
 
 pub use data::SNES_OPCODES;
+use serde::{Deserialize, Serialize};
 use AddressingMode::*;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 #[repr(u8)]
 pub enum AddressingMode {
     Accumulator,
@@ -38,7 +39,7 @@ pub enum AddressingMode {
     Relative16,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[repr(u8)]
 pub enum Mnemonic {
     /// Add with carry
@@ -227,7 +228,7 @@ pub enum Mnemonic {
     XCE,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize, Serialize)]
 pub struct Opcode {
     pub mnemonic: Mnemonic,
     pub mode:     AddressingMode,
@@ -237,33 +238,22 @@ pub struct Opcode {
 
 impl Mnemonic {
     pub fn can_change_program_counter(self) -> bool {
+        self.ends_basic_block() || self.is_subroutine_call()
+    }
+
+    /// Whether execution may not continue with the next instruction, which is the case for branches, jumps, returns
+    /// and interrupts. Subroutine calls don't end basic blocks, as they return to the next instruction.
+    pub fn ends_basic_block(self) -> bool {
         use Mnemonic::*;
         matches!(
             self,
-            BCC | BCS
-                | BEQ
-                | BMI
-                | BNE
-                | BRK
-                | BPL
-                | BRA
-                | BRL
-                | BVC
-                | BVS
-                | COP
-                | JMP
-                | JML
-                | JSR
-                | JSL
-                | RTI
-                | RTS
-                | RTL
+            BCC | BCS | BEQ | BMI | BNE | BRK | BPL | BRA | BRL | BVC | BVS | COP | JMP | JML | RTI | RTS | RTL | WDM
         )
     }
 
     pub fn is_single_path_leap(self) -> bool {
         use Mnemonic::*;
-        matches!(self, BRA | BRL | JMP | JML | RTS | RTL)
+        matches!(self, BRA | BRL | JMP | JML | RTS | RTL | RTI)
     }
 
     pub fn is_double_path(self) -> bool {
@@ -281,9 +271,10 @@ impl Mnemonic {
         matches!(self, JSR | JSL)
     }
 
+    /// Also true for returns from interrupt handlers.
     pub fn is_subroutine_return(self) -> bool {
         use Mnemonic::*;
-        matches!(self, RTS | RTL)
+        matches!(self, RTS | RTL | RTI)
     }
 }
 
@@ -320,4 +311,146 @@ impl Opcode {
     pub fn instruction_size(self) -> usize {
         1 + self.mode.operands_size()
     }
+
+    /// Number of CPU cycles taken by the instruction in native mode with 8-bit accumulator and index registers,
+    /// page-aligned direct page, no page crossing when indexing and with conditional branches not taken.
+    pub fn base_cycles(self) -> u32 {
+        use Mnemonic::*;
+        match self.mnemonic {
+            // Branches and jumps
+            BCC | BCS | BEQ | BMI | BNE | BPL | BVC | BVS => 2,
+            BRA => 3,
+            BRL => 4,
+            JMP => match self.mode {
+                Address => 3,
+                AddressIndirect => 5,
+                _ => 6,
+            },
+            JML => match self.mode {
+                Long => 4,
+                _ => 6,
+            },
+            JSR => match self.mode {
+                Address => 6,
+                _ => 8,
+            },
+            JSL => 8,
+            RTS | RTL => 6,
+            RTI => 7,
+            BRK | COP => 8,
+
+            // Stack
+            PHA | PHB | PHK | PHP | PHX | PHY => 3,
+            PHD | PLA | PLB | PLP | PLX | PLY => 4,
+            PLD | PEA => 5,
+            PEI | PER => 6,
+
+            // Read-modify-write
+            ASL | DEC | INC | LSR | ROL | ROR | TRB | TSB => match self.mode {
+                Accumulator => 2,
+                DirectPage => 5,
+                DirectPageXIndex | Address => 6,
+                _ => 7,
+            },
+
+            // Misc.
+            REP | SEP | STP | WAI | XBA => 3,
+            MVN | MVP => 7,
+
+            // Loads, stores, arithmetic and register operations
+            _ => {
+                let base = match self.mode {
+                    DirectPage => 3,
+                    DirectPageXIndex | DirectPageYIndex | DirectPageSIndex => 4,
+                    Address | AddressXIndex | AddressYIndex => 4,
+                    DirectPageIndirect | DirectPageIndirectYIndex | Long | LongXIndex => 5,
+                    DirectPageLongIndirect | DirectPageLongIndirectYIndex | DirectPageXIndexIndirect => 6,
+                    DirectPageSIndexIndirectYIndex => 7,
+                    _ => 2,
+                };
+                // Indexed writes always take the extra cycle, regardless of page crossing.
+                let indexed_write = matches!(self.mnemonic, STA | STZ)
+                    && matches!(self.mode, AddressXIndex | AddressYIndex | DirectPageIndirectYIndex);
+                base + indexed_write as u32
+            }
+        }
+    }
+
+    /// Estimates the number of CPU cycles taken by the instruction given the sizes of the accumulator (`m_flag`) and
+    /// index registers (`x_flag`), and the value of the direct page register (assumed page-aligned if unknown).
+    ///
+    /// Since page crossing cannot be determined statically, it's assumed to happen only with 16-bit index registers.
+    /// Conditional branches are assumed not to be taken.
+    pub fn estimate_cycles(self, m_flag: bool, x_flag: bool, dp: Option<u16>) -> u32 {
+        use Mnemonic::*;
+        let mut cycles = self.base_cycles();
+
+        let is_memory_mode = !matches!(self.mode, Implied | Accumulator);
+        let wide_accumulator = !m_flag as u32;
+        let wide_index = !x_flag as u32;
+        cycles += match self.mnemonic {
+            ADC | AND | BIT | CMP | EOR | LDA | ORA | SBC | STA | STZ => wide_accumulator,
+            ASL | DEC | INC | LSR | ROL | ROR | TRB | TSB if is_memory_mode => 2 * wide_accumulator,
+            PHA | PLA => wide_accumulator,
+            CPX | CPY | LDX | LDY | STX | STY | PHX | PHY | PLX | PLY => wide_index,
+            _ => 0,
+        };
+
+        let is_indexed_read = !matches!(self.mnemonic, STA | STZ)
+            && matches!(self.mode, AddressXIndex | AddressYIndex | DirectPageIndirectYIndex);
+        if is_indexed_read {
+            cycles += wide_index;
+        }
+
+        let is_direct_page_mode = (DirectPage..=DirectPageYIndex).contains(&self.mode);
+        if is_direct_page_mode && dp.is_some_and(|dp| dp & 0xFF != 0) {
+            cycles += 1;
+        }
+
+        cycles
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::SNES_OPCODES as OP;
+
+    fn assert_cycles(opcode: u8, m8_x8: u32, m16_x16: u32) {
+        let opcode = OP[opcode as usize];
+        assert_eq!(opcode.estimate_cycles(true, true, Some(0)), m8_x8, "{opcode:?} with 8-bit registers");
+        assert_eq!(opcode.estimate_cycles(false, false, Some(0)), m16_x16, "{opcode:?} with 16-bit registers");
+    }
+
+    #[test]
+    fn test_accumulator_width() {
+        assert_cycles(0xA9, 2, 3); // LDA #imm
+        assert_cycles(0xA5, 3, 4); // LDA dp
+        assert_cycles(0x8D, 4, 5); // STA abs
+        assert_cycles(0xAF, 5, 6); // LDA long
+        assert_cycles(0x06, 5, 7); // ASL dp
+        assert_cycles(0x0A, 2, 2); // ASL A
+        assert_cycles(0x48, 3, 4); // PHA
+    }
+
+    #[test]
+    fn test_index_width() {
+        assert_cycles(0xA2, 2, 3); // LDX #imm
+        assert_cycles(0xAE, 4, 5); // LDX abs
+        assert_cycles(0xBD, 4, 6); // LDA abs, X
+        assert_cycles(0x9D, 5, 6); // STA abs, X
+        assert_cycles(0xDA, 3, 4); // PHX
+        assert_cycles(0xE8, 2, 2); // INX
+    }
+
+    #[test]
+    fn test_direct_page_penalty() {
+        let lda_dp = OP[0xA5];
+        assert_eq!(lda_dp.estimate_cycles(true, true, Some(0x4300)), 3);
+        assert_eq!(lda_dp.estimate_cycles(true, true, Some(0x0001)), 4);
+        assert_eq!(lda_dp.estimate_cycles(true, true, None), 3);
+        let lda_abs = OP[0xAD];
+        assert_eq!(lda_abs.estimate_cycles(true, true, Some(0x0001)), 4);
+    }
 }
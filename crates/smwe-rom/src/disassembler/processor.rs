@@ -1,28 +1,126 @@
-use crate::disassembler::{instruction::Instruction, opcodes::Mnemonic::*, registers::*};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+use crate::{
+    disassembler::{
+        instruction::Instruction,
+        opcodes::{AddressingMode, Mnemonic::*},
+        registers::*,
+    },
+    snes_utils::addr::AddrSnes,
+};
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Processor {
     pub p_reg: PRegister,
-    pub stack: Vec<u8>,
+    /// Data Bank Register, `None` if its value cannot be determined statically.
+    pub dbr:   Option<u8>,
+    /// Direct Page Register, `None` if its value cannot be determined statically.
+    pub dp:    Option<u16>,
+    /// Accumulator bytes (low, high), only tracked as far as needed to follow values into DBR and DP.
+    pub a_reg: [Option<u8>; 2],
+    pub stack: Vec<Option<u8>>,
 }
 
 impl Processor {
     pub fn new() -> Self {
-        Self { p_reg: PRegister(0b00110000), stack: Vec::with_capacity(256) }
+        Self {
+            p_reg: PRegister(0b00110000),
+            dbr:   Some(0),
+            dp:    Some(0),
+            a_reg: [None; 2],
+            stack: Vec::with_capacity(256),
+        }
+    }
+
+    /// State at the start of an interrupt handler: IRQs are disabled and decimal mode is off. In native mode, the
+    /// accumulator and index sizes, DBR and DP are those of the interrupted code, so only their reset values are
+    /// assumed for the sizes and the registers are unknown. In emulation mode, everything is in its reset state.
+    pub fn interrupt_handler(native_mode: bool) -> Self {
+        let mut processor = Self::new();
+        processor.p_reg.0 = (processor.p_reg.0 | 0b00000100) & !0b00001000;
+        if native_mode {
+            processor.dbr = None;
+            processor.dp = None;
+        }
+        processor
     }
 
     pub fn execute(&mut self, instr: Instruction) {
+        let a_size = if self.p_reg.m_flag() { 1 } else { 2 };
+        let xy_size = if self.p_reg.x_flag() { 1 } else { 2 };
         match instr.opcode.mnemonic {
             SEP => self.p_reg.0 |= instr.operands()[0],
             REP => self.p_reg.0 &= !instr.operands()[0],
-            PHP => self.stack.push(self.p_reg.0),
+            PHP => self.stack.push(Some(self.p_reg.0)),
             PLP => match self.stack.pop() {
-                Some(p) => self.p_reg.0 = p,
+                Some(Some(p)) => self.p_reg.0 = p,
+                Some(None) => log::warn!("Pulling unknown flags at {} ({:?})", instr.offset, instr.display()),
                 None => log::error!("Stack underflow at {} ({:?})", instr.offset, instr.display()),
             },
+            PHB => self.stack.push(self.dbr),
+            PLB => self.dbr = self.pull(instr),
+            PHK => {
+                let bank = AddrSnes::try_from(instr.offset).map(AddrSnes::bank).ok();
+                self.stack.push(bank);
+            }
+            PHD => self.push_word(self.dp),
+            PLD => self.dp = self.pull_word(instr),
+            TCD => self.dp = self.a_word(),
+            TDC => self.a_reg = self.dp.map_or([None; 2], |dp| dp.to_le_bytes().map(Some)),
+            PHA => self.push_bytes(self.a_reg, a_size),
+            PLA => {
+                self.a_reg[0] = self.pull(instr);
+                if a_size == 2 {
+                    self.a_reg[1] = self.pull(instr);
+                }
+            }
+            PHX | PHY => self.push_bytes([None; 2], xy_size),
+            PLX | PLY => {
+                for _ in 0..xy_size {
+                    self.pull(instr);
+                }
+            }
+            PEA => self.push_word(Some(u16::from_le_bytes([instr.operands()[0], instr.operands()[1]]))),
+            PEI | PER => self.push_word(None),
+            LDA if instr.opcode.mode == AddressingMode::Immediate8 => self.a_reg[0] = Some(instr.operands()[0]),
+            LDA if instr.opcode.mode == AddressingMode::Immediate16 => {
+                self.a_reg = [Some(instr.operands()[0]), Some(instr.operands()[1])]
+            }
+            ASL | DEC | INC | LSR | ROL | ROR if instr.opcode.mode != AddressingMode::Accumulator => {}
+            ADC | AND | ASL | DEC | EOR | INC | LDA | LSR | ORA | ROL | ROR | SBC | TXA | TYA => {
+                self.a_reg[..a_size].fill(None)
+            }
+            TSC => self.a_reg = [None; 2],
+            XBA => self.a_reg.swap(0, 1),
             _ => {}
         }
     }
+
+    fn a_word(&self) -> Option<u16> {
+        Some(u16::from_le_bytes([self.a_reg[0]?, self.a_reg[1]?]))
+    }
+
+    fn push_bytes(&mut self, bytes: [Option<u8>; 2], size: usize) {
+        // The high byte is pushed first, so that the low byte is pulled first.
+        self.stack.extend(bytes[..size].iter().rev());
+    }
+
+    fn push_word(&mut self, word: Option<u16>) {
+        self.push_bytes(word.map_or([None; 2], |w| w.to_le_bytes().map(Some)), 2);
+    }
+
+    fn pull(&mut self, instr: Instruction) -> Option<u8> {
+        self.stack.pop().unwrap_or_else(|| {
+            log::error!("Stack underflow at {} ({:?})", instr.offset, instr.display());
+            None
+        })
+    }
+
+    fn pull_word(&mut self, instr: Instruction) -> Option<u16> {
+        let low = self.pull(instr);
+        let high = self.pull(instr);
+        Some(u16::from_le_bytes([low?, high?]))
+    }
 }
 
 impl Default for Processor {
@@ -30,3 +128,87 @@ impl Default for Processor {
         Self::new()
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::Processor;
+    use crate::{
+        disassembler::binary_block::CodeBlock,
+        snes_utils::addr::{AddrPc, AddrSnes},
+    };
+
+    fn resolved_addresses(bytes: &[u8]) -> Vec<Option<AddrSnes>> {
+        // PC $010000 is SNES $028000
+        let (block, _) = CodeBlock::from_bytes(AddrPc(0x010000), bytes, &mut Processor::new());
+        block.instructions.iter().map(|i| i.resolved_address()).collect()
+    }
+
+    #[test]
+    fn test_dbr_from_program_bank() {
+        let bytes = [
+            0x4B, // PHK
+            0xAB, // PLB
+            0xAD, 0x34, 0x12, // LDA $1234
+            0x60, // RTS
+        ];
+        assert_eq!(resolved_addresses(&bytes)[2], Some(AddrSnes(0x021234)));
+    }
+
+    #[test]
+    fn test_dbr_from_accumulator() {
+        let bytes = [
+            0xA9, 0x7F, // LDA #$7F
+            0x48, // PHA
+            0xAB, // PLB
+            0xBD, 0x00, 0x20, // LDA $2000, X
+            0x8B, // PHB
+            0xAB, // PLB
+            0x8D, 0x10, 0x00, // STA $0010
+            0x60, // RTS
+        ];
+        let addresses = resolved_addresses(&bytes);
+        assert_eq!(addresses[3], Some(AddrSnes(0x7F2000)));
+        assert_eq!(addresses[6], Some(AddrSnes(0x7F0010)));
+    }
+
+    #[test]
+    fn test_unknown_dbr() {
+        let bytes = [
+            0xAD, 0x00, 0x80, // LDA $8000
+            0x48, // PHA
+            0xAB, // PLB
+            0xAD, 0x34, 0x12, // LDA $1234
+            0x60, // RTS
+        ];
+        assert_eq!(resolved_addresses(&bytes)[3], None);
+    }
+
+    #[test]
+    fn test_direct_page_from_tcd() {
+        let bytes = [
+            0xA5, 0x05, // LDA $05
+            0xC2, 0x20, // REP #$20
+            0xA9, 0x00, 0x43, // LDA #$4300
+            0x5B, // TCD
+            0xE2, 0x20, // SEP #$20
+            0xA5, 0x05, // LDA $05
+            0x60, // RTS
+        ];
+        let addresses = resolved_addresses(&bytes);
+        assert_eq!(addresses[0], Some(AddrSnes(0x000005)));
+        assert_eq!(addresses[5], Some(AddrSnes(0x004305)));
+    }
+
+    #[test]
+    fn test_direct_page_from_pld() {
+        let bytes = [
+            0xF4, 0x00, 0x21, // PEA $2100
+            0x2B, // PLD
+            0x85, 0x15, // STA $15
+            0x60, // RTS
+        ];
+        assert_eq!(resolved_addresses(&bytes)[2], Some(AddrSnes(0x002115)));
+    }
+}
@@ -1,8 +1,10 @@
 // This module will not define every register of the 65816,
 // only those that are needed for disassembly.
 
+use serde::{Deserialize, Serialize};
+
 /// Processor Status
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
 pub struct PRegister(pub u8);
 
 impl PRegister {
@@ -0,0 +1,141 @@
+use itertools::Itertools;
+
+use crate::{
+    disassembler::{
+        binary_block::{BinaryBlock, DataBlock, DataKind},
+        jump_tables::points_into_rom,
+        RomDisassembly,
+    },
+    snes_utils::{
+        addr::{AddrPc, AddrSnes},
+        rom_slice::SnesSlice,
+    },
+    Rom,
+};
+
+/// Number of bytes in a LoROM bank. Chunks spanning more than one bank are left unclassified.
+const LOROM_BANK_SIZE: usize = 0x8000;
+/// Fewest pointers in a chunk for it to be taken for a pointer table.
+const MIN_POINTER_TABLE_LENGTH: usize = 2;
+/// Fewest bytes in each string of a text chunk, including the last one.
+const MIN_STRING_LENGTH: usize = 3;
+/// Fewest strings in a chunk for it to be taken for text.
+const MIN_STRING_COUNT: usize = 2;
+
+// -------------------------------------------------------------------------------------------------
+
+impl RomDisassembly {
+    /// Marks unknown chunks that look like pointer tables or text as data of that kind, and returns how many were
+    /// marked. A chunk is a pointer table if all of it reads as pointers into ROM, either 16-bit ones into the chunk's
+    /// own bank or 24-bit ones, that aren't all the same and don't look like $00 or $FF padding.
+    ///
+    /// The marked blocks are guesses and can't be requested as data afterwards, so this is best done once everything
+    /// else has been parsed.
+    pub fn classify_unknowns(&mut self) -> usize {
+        let mut classified = 0;
+        for idx in 0..self.chunks.len().saturating_sub(1) {
+            let (start, BinaryBlock::Unknown) = self.chunks[idx] else { continue };
+            let end = self.chunks[idx + 1].0;
+            if end <= start || start.as_index() / LOROM_BANK_SIZE != (end.as_index() - 1) / LOROM_BANK_SIZE {
+                continue;
+            }
+            let Ok(begin) = AddrSnes::try_from_lorom(start) else { continue };
+            let Some(bytes) = self.rom.0.get(start.as_index()..end.as_index()) else { continue };
+            let Some(kind) = classify_bytes(&self.rom, begin, bytes) else { continue };
+            self.chunks[idx].1 = BinaryBlock::Data(DataBlock { slice: SnesSlice::new(begin, bytes.len()), kind });
+            classified += 1;
+        }
+        classified
+    }
+}
+
+/// Guesses the kind of data in `bytes`, which are located at `begin`.
+fn classify_bytes(rom: &Rom, begin: AddrSnes, bytes: &[u8]) -> Option<DataKind> {
+    // 16-bit address implies the same bank number as the pointer table's address.
+    let short_ptr = |ptr: &[u8]| AddrSnes(u16::from_le_bytes([ptr[0], ptr[1]]) as _) | (begin & 0xFF0000);
+    let long_ptr = |ptr: &[u8]| AddrSnes(u32::from_le_bytes([ptr[0], ptr[1], ptr[2], 0]));
+    if is_pointer_table(rom, bytes, 2, short_ptr) {
+        Some(DataKind::PointerTableShort)
+    } else if is_pointer_table(rom, bytes, 3, long_ptr) {
+        Some(DataKind::PointerTableLong)
+    } else if is_text(bytes) {
+        Some(DataKind::Text)
+    } else {
+        None
+    }
+}
+
+fn is_pointer_table(rom: &Rom, bytes: &[u8], ptr_size: usize, read_ptr: impl Fn(&[u8]) -> AddrSnes) -> bool {
+    let ptrs = bytes.chunks_exact(ptr_size);
+    let is_padding = |ptr: &[u8]| ptr.iter().all(|&byte| byte == 0x00) || ptr.iter().all(|&byte| byte == 0xFF);
+    ptrs.len() >= MIN_POINTER_TABLE_LENGTH
+        && ptrs.remainder().is_empty()
+        && ptrs.clone().all(|ptr| !is_padding(ptr) && points_into_rom(rom, read_ptr(ptr)))
+        && !ptrs.map(read_ptr).all_equal()
+}
+
+/// Text is encoded the way level names are: as tile numbers below $80, with bit 7 set in the last byte of a string.
+/// A chunk is only taken for text if it has several strings, none of which repeat a single tile.
+fn is_text(bytes: &[u8]) -> bool {
+    let is_last = |&byte: &u8| byte & 0x80 != 0;
+    let mut strings = bytes.split_inclusive(is_last);
+    bytes.last().is_some_and(is_last)
+        && strings.clone().count() >= MIN_STRING_COUNT
+        && strings
+            .all(|string| string.len() >= MIN_STRING_LENGTH && !string.iter().map(|&byte| byte & 0x7F).all_equal())
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_unknowns() {
+        #[rustfmt::skip]
+        let regions: [&[u8]; 4] = [
+            &[0x00, 0x81, 0x00, 0x00, 0xA0, 0x01, 0x00, 0xC0, 0x05], // $008000: $008100, $01A000, $05C000
+            &[0x00, 0x82, 0x10, 0x83],                               // $008009: $8200, $8310
+            &[0x12, 0x9A, 0x34, 0xFF, 0x56, 0x07],                   // $00800D: random bytes
+            &[0x07, 0x04, 0x0B, 0x0B, 0x8E, 0x0E, 0x0D, 0x84],       // $008013: two strings
+        ];
+        let mut bytes = vec![0; 0x40000];
+        let mut chunks = Vec::new();
+        let mut pc = 0;
+        for region in regions {
+            bytes[pc..pc + region.len()].copy_from_slice(region);
+            chunks.push((AddrPc(pc as _), BinaryBlock::Unknown));
+            pc += region.len();
+        }
+        chunks.push((AddrPc(pc as _), BinaryBlock::Unknown));
+        chunks.push((AddrPc(bytes.len() as _), BinaryBlock::EndOfRom));
+
+        let mut disasm = RomDisassembly { chunks, ..RomDisassembly::unanalysed(Rom::new(bytes).unwrap()) };
+        assert_eq!(disasm.classify_unknowns(), 3);
+
+        let kinds = disasm.chunks.iter().map(|(_, block)| block.data_block().map(|data| data.kind)).collect::<Vec<_>>();
+        assert_eq!(kinds, [
+            Some(DataKind::PointerTableLong),
+            Some(DataKind::PointerTableShort),
+            None,
+            Some(DataKind::Text),
+            None,
+            None,
+        ]);
+        assert_eq!(disasm.chunks[0].1.data_block().unwrap().slice, SnesSlice::new(AddrSnes(0x008000), 9));
+        assert!(matches!(disasm.chunks[2].1, BinaryBlock::Unknown));
+    }
+
+    #[test]
+    fn test_padding_is_not_classified() {
+        let rom = Rom::new(vec![0; 0x40000]).unwrap();
+        let begin = AddrSnes(0x008000);
+        assert_eq!(classify_bytes(&rom, begin, &[0xFF; 12]), None, "0xFF fill");
+        assert_eq!(classify_bytes(&rom, begin, &[0x00; 12]), None, "0x00 fill");
+        assert_eq!(classify_bytes(&rom, begin, &[0x00, 0x81, 0xFF, 0xFF]), None, "padding entry");
+        assert_eq!(classify_bytes(&rom, begin, &[0x00, 0x81, 0x00, 0x81, 0x00, 0x81]), None, "all equal entries");
+        assert_eq!(classify_bytes(&rom, begin, &[0x12, 0x34, 0x9A]), None, "a single string");
+        assert_eq!(classify_bytes(&rom, begin, &[0x01, 0x01, 0x81, 0x02, 0x02, 0x82]), None, "repeated tiles");
+    }
+}
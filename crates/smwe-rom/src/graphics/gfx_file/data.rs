@@ -3,8 +3,9 @@ use crate::{
     snes_utils::{addr::AddrSnes, rom_slice::SnesSlice},
 };
 
+/// Format and location of each stock GFX file, indexed by file number.
 #[rustfmt::skip]
-pub(crate) static GFX_FILES_META: [(TileFormat, SnesSlice); 0x34] = [
+pub static GFX_FILES_META: [(TileFormat, SnesSlice); 0x34] = [
     /* 00 */ (TileFormat::Tile3bpp,  SnesSlice::new(AddrSnes(0x08D9F9), 2104)),
     /* 01 */ (TileFormat::Tile3bpp,  SnesSlice::new(AddrSnes(0x08E231), 2698)),
     /* 02 */ (TileFormat::Tile3bpp,  SnesSlice::new(AddrSnes(0x08ECBB), 2199)),
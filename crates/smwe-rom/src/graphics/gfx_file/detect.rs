@@ -0,0 +1,134 @@
+use nom::IResult;
+
+use crate::graphics::gfx_file::{Tile, TileFormat};
+
+/// Doubling the bits per pixel of graphics that aren't stored with that many is expected to roughly double the number
+/// of edges, while doubling them up to the actual bit depth only adds a few.
+const EDGE_GROWTH_THRESHOLD: f32 = 1.6;
+
+/// Number of pairs of horizontally or vertically adjacent pixels in a tile.
+const N_NEIGHBOUR_PAIRS_IN_TILE: usize = 2 * 8 * 7;
+
+// -------------------------------------------------------------------------------------------------
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileFormatGuess {
+    pub format:     TileFormat,
+    /// Between 0 (no better than a random pick) and 1 (no other format is plausible).
+    pub confidence: f32,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Guesses whether decompressed graphics are in 2BPP, 4BPP or 8BPP format.
+///
+/// Only formats yielding a whole number of tiles are considered. Decoding graphics with more bits per pixel than they
+/// were stored with overlays bitplanes of unrelated tiles, so the edges of both tiles' shapes end up in one tile.
+/// Decoding them with fewer bits only drops some bitplanes, which can merge areas but never adds edges. Starting from
+/// 2BPP, the bit depth is therefore doubled for as long as that doesn't make the edge density grow too much.
+pub fn detect_tile_format(decompressed: &[u8]) -> TileFormatGuess {
+    type ParserFn = fn(&[u8]) -> IResult<&[u8], Tile>;
+    const CANDIDATES: [(TileFormat, ParserFn); 3] = [
+        (TileFormat::Tile2bpp, Tile::from_2bpp),
+        (TileFormat::Tile4bpp, Tile::from_4bpp),
+        (TileFormat::Tile8bpp, Tile::from_8bpp),
+    ];
+
+    let densities: Vec<(TileFormat, f32)> = CANDIDATES
+        .into_iter()
+        .filter(|(format, _)| !decompressed.is_empty() && decompressed.len() % format.tile_size() == 0)
+        .map(|(format, parser)| (format, edge_density(decompressed, format.tile_size(), parser)))
+        .collect();
+
+    match densities.as_slice() {
+        [] => TileFormatGuess { format: TileFormat::Tile4bpp, confidence: 0.0 },
+        [(format, _)] => TileFormatGuess { format: *format, confidence: 1.0 },
+        [(format, density), ..] if *density == 0.0 => TileFormatGuess { format: *format, confidence: 0.0 },
+        _ => {
+            let mut chosen = 0;
+            let mut confidence: f32 = 1.0;
+            for pair in densities.windows(2) {
+                let growth = pair[1].1 / pair[0].1;
+                // Distance from the threshold, where both no growth and doubling count as fully certain.
+                let margin = (growth - EDGE_GROWTH_THRESHOLD).abs() / (EDGE_GROWTH_THRESHOLD - 1.0);
+                confidence = confidence.min(margin.min(1.0));
+                if growth >= EDGE_GROWTH_THRESHOLD {
+                    break;
+                }
+                chosen += 1;
+            }
+            TileFormatGuess { format: densities[chosen].0, confidence }
+        }
+    }
+}
+
+/// Fraction of pairs of adjacent pixels within each tile that have different colour indices.
+fn edge_density(data: &[u8], tile_size: usize, parser: fn(&[u8]) -> IResult<&[u8], Tile>) -> f32 {
+    let mut edges = 0;
+    let mut n_tiles = 0;
+    for tile in data.chunks_exact(tile_size).filter_map(|bytes| parser(bytes).ok().map(|(_, tile)| tile)) {
+        let idx = &tile.color_indices;
+        for a in 0..8 {
+            for b in 0..7 {
+                edges += (idx[8 * a + b] != idx[8 * a + b + 1]) as usize;
+                edges += (idx[8 * b + a] != idx[8 * (b + 1) + a]) as usize;
+            }
+        }
+        n_tiles += 1;
+    }
+    if n_tiles == 0 {
+        0.0
+    } else {
+        edges as f32 / (n_tiles * N_NEIGHBOUR_PAIRS_IN_TILE) as f32
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tiles with a pseudo-random rectangle on a pseudo-random background, roughly resembling pixel art.
+    fn rectangle_graphics(n_tiles: usize, bpp: usize) -> Vec<u8> {
+        let mut seed = 0x1234_5678u32;
+        let mut next = move |max: usize| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as usize % max
+        };
+        let n_colors = 1 << bpp;
+        (0..n_tiles)
+            .flat_map(|_| {
+                let (background, foreground) = (next(n_colors), next(n_colors));
+                let (x0, y0) = (next(6), next(6));
+                let (x1, y1) = (x0 + 2 + next(6 - x0), y0 + 2 + next(6 - y0));
+                let color_indices = (0..64)
+                    .map(|i| {
+                        let (x, y) = (i % 8, i / 8);
+                        let inside = (x0..x1).contains(&x) && (y0..y1).contains(&y);
+                        (if inside { foreground } else { background }) as u8
+                    })
+                    .collect();
+                Tile { color_indices }.to_xbpp(bpp)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_known_formats() {
+        for (format, bpp) in [(TileFormat::Tile2bpp, 2), (TileFormat::Tile4bpp, 4), (TileFormat::Tile8bpp, 8)] {
+            let guess = detect_tile_format(&rectangle_graphics(64, bpp));
+            assert_eq!(guess.format, format, "{guess:?}");
+            assert!(guess.confidence > 0.3, "{guess:?}");
+        }
+    }
+
+    #[test]
+    fn test_detect_partial_tile_count() {
+        // 3 tiles of 2BPP can't be evenly split into 4BPP or 8BPP tiles.
+        let guess = detect_tile_format(&rectangle_graphics(3, 2));
+        assert_eq!(guess, TileFormatGuess { format: TileFormat::Tile2bpp, confidence: 1.0 });
+        assert_eq!(detect_tile_format(&[0; 5]).confidence, 0.0);
+        assert_eq!(detect_tile_format(&[0; 128]).confidence, 0.0);
+    }
+}
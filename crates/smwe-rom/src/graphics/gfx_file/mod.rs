@@ -1,8 +1,13 @@
 mod data;
+mod detect;
 
-use std::fmt::{self, Display, Formatter};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
 
-pub(crate) use data::GFX_FILES_META;
+pub use data::GFX_FILES_META;
+pub use detect::{detect_tile_format, TileFormatGuess};
 use epaint::Rgba;
 use nom::{bytes::complete::take, combinator::map_parser, multi::many1, IResult};
 use smwe_render::color::Abgr1555;
@@ -28,10 +33,25 @@ pub enum GfxFileParseError {
     ParsingTile,
 }
 
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum GfxTileError {
+    #[error("Color index {index} of pixel {pixel} doesn't fit in {format}")]
+    ColorIndexOutOfRange { pixel: usize, index: u8, format: TileFormat },
+    #[error("More than {} unique tiles don't fit in remap entries", REMAP_TILE_INDEX as usize + 1)]
+    TooManyUniqueTiles,
+}
+
 // -------------------------------------------------------------------------------------------------
 
 pub const N_PIXELS_IN_TILE: usize = 8 * 8;
 
+/// Set in a [`GfxFile::deduplicate`] remap entry if the new tile has to be flipped horizontally.
+pub const REMAP_FLIP_X: u16 = 0x4000;
+/// Set in a [`GfxFile::deduplicate`] remap entry if the new tile has to be flipped vertically.
+pub const REMAP_FLIP_Y: u16 = 0x8000;
+/// Mask of the new tile index in a [`GfxFile::deduplicate`] remap entry.
+pub const REMAP_TILE_INDEX: u16 = 0x3FFF;
+
 // -------------------------------------------------------------------------------------------------
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -43,7 +63,7 @@ pub enum TileFormat {
     Tile3bppMode7,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Tile {
     pub color_indices: Box<[u8]>,
 }
@@ -80,6 +100,32 @@ impl TileFormat {
             Tile3bppMode7 => 3 * 8,
         }
     }
+
+    pub fn bits_per_pixel(self) -> u32 {
+        use TileFormat::*;
+        match self {
+            Tile2bpp => 2,
+            Tile3bpp | Tile3bppMode7 => 3,
+            Tile4bpp => 4,
+            Tile8bpp => 8,
+        }
+    }
+
+    /// Number of colors that tiles in this format can use.
+    pub fn n_colors(self) -> usize {
+        1 << self.bits_per_pixel()
+    }
+
+    fn tile_parser(self) -> fn(&[u8]) -> IResult<&[u8], Tile> {
+        use TileFormat::*;
+        match self {
+            Tile2bpp => Tile::from_2bpp,
+            Tile3bpp => Tile::from_3bpp,
+            Tile4bpp => Tile::from_4bpp,
+            Tile8bpp => Tile::from_8bpp,
+            Tile3bppMode7 => Tile::from_3bpp_mode7,
+        }
+    }
 }
 
 impl Tile {
@@ -129,6 +175,36 @@ impl Tile {
         Ok((input, tile))
     }
 
+    /// Returns the tile encoded as 2BPP, the inverse of [`Tile::from_2bpp`].
+    pub fn to_2bpp(&self) -> Vec<u8> {
+        self.to_xbpp(2)
+    }
+
+    /// Returns the tile encoded as 4BPP, the inverse of [`Tile::from_4bpp`].
+    pub fn to_4bpp(&self) -> Vec<u8> {
+        self.to_xbpp(4)
+    }
+
+    /// Returns the tile encoded as 8BPP, the inverse of [`Tile::from_8bpp`].
+    pub fn to_8bpp(&self) -> Vec<u8> {
+        self.to_xbpp(8)
+    }
+
+    fn to_xbpp(&self, x: usize) -> Vec<u8> {
+        debug_assert!([2, 4, 8].contains(&x));
+        let mut bytes = vec![0; x * 8];
+
+        for (i, &color_idx) in self.color_indices.iter().enumerate().take(N_PIXELS_IN_TILE) {
+            let (row, col) = (i / 8, 7 - (i % 8));
+            for bit_idx in 0..x {
+                let byte_idx = (2 * row) + (16 * (bit_idx / 2)) + (bit_idx % 2);
+                bytes[byte_idx] |= ((color_idx >> bit_idx) & 1) << col;
+            }
+        }
+
+        bytes
+    }
+
     pub fn from_3bpp_mode7(input: &[u8]) -> IResult<&[u8], Self> {
         let (input, bytes) = take(24usize)(input)?;
         let mut color_indices = [0u8; 64];
@@ -146,6 +222,29 @@ impl Tile {
         Ok((input, tile))
     }
 
+    /// Returns a copy of the tile to be stored in `target` format, or an error if any of its color indices doesn't
+    /// fit in that format.
+    pub fn to_format(&self, target: TileFormat) -> Result<Tile, GfxTileError> {
+        match self.color_indices.iter().position(|&index| index as usize >= target.n_colors()) {
+            Some(pixel) => {
+                Err(GfxTileError::ColorIndexOutOfRange { pixel, index: self.color_indices[pixel], format: target })
+            }
+            None => Ok(self.clone()),
+        }
+    }
+
+    #[must_use]
+    pub fn flipped_x(&self) -> Self {
+        let color_indices = self.color_indices.chunks(8).flat_map(|row| row.iter().rev().copied()).collect();
+        Self { color_indices }
+    }
+
+    #[must_use]
+    pub fn flipped_y(&self) -> Self {
+        let color_indices = self.color_indices.chunks(8).rev().flatten().copied().collect();
+        Self { color_indices }
+    }
+
     pub fn to_bgr555(&self, palette: &[Abgr1555]) -> Box<[Abgr1555]> {
         self.color_indices
             .iter()
@@ -191,17 +290,7 @@ impl GfxFile {
     pub fn new(disasm: &mut RomDisassembly, file_num: usize, revised_gfx: bool) -> Result<Self, GfxFileParseError> {
         debug_assert!(file_num < GFX_FILES_META.len());
 
-        use TileFormat::*;
-        type ParserFn = fn(&[u8]) -> IResult<&[u8], Tile>;
-
         let (tile_format, slice) = GFX_FILES_META[file_num];
-        let (tile_parser, tile_size_bytes): (ParserFn, usize) = match tile_format {
-            Tile2bpp => (Tile::from_2bpp, 2 * 8),
-            Tile3bpp => (Tile::from_3bpp, 3 * 8),
-            Tile4bpp => (Tile::from_4bpp, 4 * 8),
-            Tile8bpp => (Tile::from_8bpp, 8 * 8),
-            Tile3bppMode7 => (Tile::from_3bpp_mode7, 3 * 8),
-        };
 
         let tiles = disasm
             .rom_slice_at_block(DataBlock { slice, kind: DataKind::GfxFile }, |e| match e {
@@ -212,12 +301,156 @@ impl GfxFile {
             })?
             .decompress(move |slice| lc_lz2::decompress(slice, revised_gfx))?
             .view()
-            .parse(many1(map_parser(take(tile_size_bytes), tile_parser)))?;
+            .parse(many1(map_parser(take(tile_format.tile_size()), tile_format.tile_parser())))?;
 
         Ok(Self { tile_format, tiles })
     }
 
+    /// Parses graphics that have already been decompressed, such as ExGFX files which aren't at fixed locations.
+    pub fn from_decompressed(tile_format: TileFormat, bytes: &[u8]) -> Result<Self, GfxFileParseError> {
+        let (_, tiles) = many1(map_parser(take(tile_format.tile_size()), tile_format.tile_parser()))(bytes)
+            .map_err(|_: nom::Err<nom::error::Error<&[u8]>>| GfxFileParseError::ParsingTile)?;
+        Ok(Self { tile_format, tiles })
+    }
+
     pub fn n_pixels(&self) -> usize {
         self.tiles.len() * N_PIXELS_IN_TILE
     }
+
+    /// Removes duplicate tiles, including ones that are flipped copies of another tile.
+    ///
+    /// Returns the GFX file with unique tiles and a table mapping each old tile index to the index of its new tile,
+    /// with [`REMAP_FLIP_X`] and [`REMAP_FLIP_Y`] set if the new tile needs to be flipped to look like the old one.
+    /// Fails if there are more unique tiles than [`REMAP_TILE_INDEX`] can address.
+    pub fn deduplicate(&self) -> Result<(GfxFile, Vec<u16>), GfxTileError> {
+        let mut unique_tiles: Vec<Tile> = Vec::with_capacity(self.tiles.len());
+        let mut unique_indices: HashMap<&Tile, u16> = HashMap::with_capacity(self.tiles.len());
+        let mut remap = Vec::with_capacity(self.tiles.len());
+
+        for tile in self.tiles.iter() {
+            let flipped_x = tile.flipped_x();
+            let flipped_y = tile.flipped_y();
+            let flipped_xy = flipped_x.flipped_y();
+            let existing = [
+                (tile, 0),
+                (&flipped_x, REMAP_FLIP_X),
+                (&flipped_y, REMAP_FLIP_Y),
+                (&flipped_xy, REMAP_FLIP_X | REMAP_FLIP_Y),
+            ]
+            .into_iter()
+            .find_map(|(variant, flip)| unique_indices.get(variant).map(|&idx| idx | flip));
+            match existing {
+                Some(entry) => remap.push(entry),
+                None => {
+                    let idx = u16::try_from(unique_tiles.len())
+                        .ok()
+                        .filter(|&idx| idx <= REMAP_TILE_INDEX)
+                        .ok_or(GfxTileError::TooManyUniqueTiles)?;
+                    remap.push(idx);
+                    unique_tiles.push(tile.clone());
+                    unique_indices.insert(tile, idx);
+                }
+            }
+        }
+
+        Ok((GfxFile { tile_format: self.tile_format, tiles: unique_tiles }, remap))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile_from_fn(f: impl Fn(usize, usize) -> u8) -> Tile {
+        Tile { color_indices: (0..N_PIXELS_IN_TILE).map(|i| f(i % 8, i / 8)).collect() }
+    }
+
+    #[test]
+    fn test_decode_3bpp_tile() {
+        let mut bytes = [0; 3 * 8 + 1];
+        // Bitplanes 0 and 1 are interleaved by row, followed by bitplane 2 with one byte per row.
+        bytes[0..2].copy_from_slice(&[0x80, 0xC0]);
+        bytes[16] = 0xE0;
+        bytes[14..16].copy_from_slice(&[0xFF, 0x00]);
+        bytes[23] = 0x01;
+        bytes[24] = 0xAA;
+
+        let (rest, tile) = Tile::from_3bpp(&bytes).unwrap();
+        assert_eq!(rest, [0xAA]);
+        assert_eq!(tile.color_indices[..8], [7, 6, 4, 0, 0, 0, 0, 0]);
+        assert!(tile.color_indices[8..56].iter().all(|&i| i == 0));
+        assert_eq!(tile.color_indices[56..], [1, 1, 1, 1, 1, 1, 1, 5]);
+        assert_eq!(TileFormat::Tile3bpp.tile_size(), 24);
+    }
+
+    #[test]
+    fn test_tile_format_conversion() {
+        let two_bpp = tile_from_fn(|x, y| ((x + y) % 4) as u8);
+        let widened = two_bpp.to_format(TileFormat::Tile4bpp).unwrap();
+        assert_eq!(widened, two_bpp);
+        assert_eq!(Tile::from_4bpp(&widened.to_4bpp()).unwrap().1, two_bpp);
+        assert_eq!(widened.to_format(TileFormat::Tile2bpp), Ok(two_bpp));
+
+        let four_bpp = tile_from_fn(|x, y| if (x, y) == (5, 1) { 7 } else { 3 });
+        assert_eq!(
+            four_bpp.to_format(TileFormat::Tile2bpp),
+            Err(GfxTileError::ColorIndexOutOfRange { pixel: 13, index: 7, format: TileFormat::Tile2bpp })
+        );
+        assert!(four_bpp.to_format(TileFormat::Tile3bppMode7).is_ok());
+    }
+
+    #[test]
+    fn test_deduplicate_tiles() {
+        let gradient = tile_from_fn(|x, y| (x + 2 * y) as u8);
+        let diagonal = tile_from_fn(|x, y| (x == y) as u8);
+        let gfx = GfxFile {
+            tile_format: TileFormat::Tile4bpp,
+            tiles:       vec![
+                gradient.clone(),
+                diagonal.clone(),
+                gradient.clone(),
+                gradient.flipped_x(),
+                gradient.flipped_y(),
+                gradient.flipped_x().flipped_y(),
+                diagonal.flipped_x(),
+            ],
+        };
+
+        let (deduplicated, remap) = gfx.deduplicate().unwrap();
+        assert_eq!(deduplicated.tile_format, TileFormat::Tile4bpp);
+        assert_eq!(deduplicated.tiles, vec![gradient, diagonal]);
+        assert_eq!(remap, vec![0, 1, 0, REMAP_FLIP_X, REMAP_FLIP_Y, REMAP_FLIP_X | REMAP_FLIP_Y, 1 | REMAP_FLIP_X]);
+    }
+
+    #[test]
+    fn test_deduplicate_symmetric_tile() {
+        let filled = tile_from_fn(|_, _| 3);
+        let gfx = GfxFile { tile_format: TileFormat::Tile2bpp, tiles: vec![filled.clone(), filled.flipped_x()] };
+
+        let (deduplicated, remap) = gfx.deduplicate().unwrap();
+        assert_eq!(deduplicated.tiles, vec![filled]);
+        assert_eq!(remap, vec![0, 0]);
+    }
+
+    #[test]
+    fn test_deduplicate_too_many_tiles() {
+        // Each tile has a marker in its top left corner, so that no tile is a flipped copy of another.
+        let tile_count = REMAP_TILE_INDEX as usize + 2;
+        let tiles = (0..tile_count)
+            .map(|n| {
+                tile_from_fn(|x, y| match y * 8 + x {
+                    0 => 2,
+                    bit @ 1..=15 => (n >> (bit - 1) & 1) as u8,
+                    _ => 0,
+                })
+            })
+            .collect();
+        let gfx = GfxFile { tile_format: TileFormat::Tile2bpp, tiles };
+        assert!(matches!(gfx.deduplicate(), Err(GfxTileError::TooManyUniqueTiles)));
+
+        let gfx = GfxFile { tiles: gfx.tiles[..tile_count - 1].to_vec(), ..gfx };
+        assert_eq!(gfx.deduplicate().unwrap().0.tiles.len(), tile_count - 1);
+    }
 }
@@ -1,25 +1,45 @@
+use std::collections::BTreeMap;
+
+use nom::{combinator::map, number::complete::le_u24};
 use thiserror::Error;
 
 use crate::{
+    compression::lc_lz2,
     disassembler::RomDisassembly,
     graphics::{
-        gfx_file::{GfxFile, Tile, GFX_FILES_META},
+        gfx_file::{detect_tile_format, GfxFile, GfxFileParseError, Tile, TileFormat, GFX_FILES_META},
         palette::ColorPalettes,
     },
     level::Level,
     objects::{
-        animated_tile_data::AnimatedTileData,
+        animated_tile_data::{AnimatedTileData, AnimationState, BLANK_ANIMATION_FRAMES},
         map16::Block,
         object_gfx_list::ObjectGfxList,
         tilesets::TILESETS_COUNT,
     },
-    snes_utils::addr::AddrSnes,
+    snes_utils::{
+        addr::{AddrPc, AddrSnes},
+        rom_slice::SnesSlice,
+    },
     RegionCode,
     RomInternalHeader,
 };
 
 pub mod gfx_file;
 pub mod palette;
+pub mod sheet;
+pub mod tilemap;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Slots of ExGFX files inserted by Lunar Magic. Files 0x80-0xFF are in a fixed table, later ones in a table that
+/// [`SUPER_EXGFX_POINTERS_PTR`] points to.
+pub const EXGFX_SLOTS: std::ops::Range<usize> = 0x80..0x1000;
+pub const EXGFX_POINTERS: AddrSnes = AddrSnes(0x0FF600);
+pub const SUPER_EXGFX_POINTERS_PTR: AddrSnes = AddrSnes(0x0FF937);
+
+/// Size of decompressed 3BPP GFX files, which are told apart from 4BPP ones by their size like in the game.
+const GFX_FILE_3BPP_SIZE: usize = 0xC00;
 
 // -------------------------------------------------------------------------------------------------
 
@@ -35,9 +55,20 @@ pub enum BlockGfx<'t> {
     Static([&'t Tile; 4]),
 }
 
+/// State of the game that affects how levels look, shared by everything that previews level graphics.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct LevelPreviewState {
+    pub blue_pswitch:   bool,
+    pub silver_pswitch: bool,
+    pub on_off:         bool,
+    pub animation:      AnimationState,
+}
+
 #[derive(Debug)]
 pub struct Gfx {
     pub files:              Vec<GfxFile>,
+    /// ExGFX files by slot number, only those present in the ROM.
+    pub exgfx_files:        BTreeMap<usize, GfxFile>,
     pub color_palettes:     ColorPalettes,
     pub object_gfx_list:    ObjectGfxList,
     pub animated_tile_data: AnimatedTileData,
@@ -58,33 +89,73 @@ impl Gfx {
             files.push(file);
         }
 
+        let mut exgfx_files = BTreeMap::new();
+        for slot in EXGFX_SLOTS {
+            match Self::parse_exgfx(disasm, slot, revised_gfx) {
+                Ok(Some(file)) => {
+                    exgfx_files.insert(slot, file);
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("Skipping ExGFX file {slot:X}: {e}"),
+            }
+        }
+        log::info!("Parsed {} ExGFX files", exgfx_files.len());
+
         Ok(Self {
             files,
+            exgfx_files,
             color_palettes: ColorPalettes::parse(disasm, levels)?,
             object_gfx_list: ObjectGfxList::parse(disasm)?,
             animated_tile_data: AnimatedTileData::parse(disasm)?,
         })
     }
 
+    /// Decompresses the ExGFX file in the given slot using the pointer tables inserted by Lunar Magic.
+    ///
+    /// Returns `None` if the ROM doesn't have the pointer table, or the slot is empty.
+    pub fn parse_exgfx(
+        disasm: &RomDisassembly, slot: usize, revised_gfx: bool,
+    ) -> Result<Option<GfxFile>, GfxFileParseError> {
+        if !EXGFX_SLOTS.contains(&slot) {
+            return Ok(None);
+        }
+        let (table, idx) = if slot < 0x100 {
+            (EXGFX_POINTERS, slot - 0x80)
+        } else {
+            match read_pointer(disasm, SUPER_EXGFX_POINTERS_PTR) {
+                Some(table) => (table, slot - 0x100),
+                None => return Ok(None),
+            }
+        };
+        let Some(addr) = read_pointer(disasm, table + 3 * idx) else {
+            return Ok(None);
+        };
+
+        let compressed = disasm
+            .rom
+            .with_error_mapper(GfxFileParseError::IsolatingData)
+            .slice_lorom(SnesSlice::new(addr, 0).infinite())?
+            .as_bytes()?;
+        let bytes = lc_lz2::decompress(compressed, revised_gfx).map_err(GfxFileParseError::DecompressingData)?;
+
+        let tile_format = match bytes.len() {
+            GFX_FILE_3BPP_SIZE => TileFormat::Tile3bpp,
+            _ => detect_tile_format(&bytes).format,
+        };
+        GfxFile::from_decompressed(tile_format, &bytes).map(Some)
+    }
+
+    /// Returns the stock GFX file or ExGFX file with the given number.
+    pub fn file(&self, file_num: usize) -> Option<&GfxFile> {
+        self.files.get(file_num).or_else(|| self.exgfx_files.get(&file_num))
+    }
+
     #[allow(clippy::erasing_op)]
-    pub fn tiles_from_block(
-        &self, block: &Block, tileset: usize, blue_pswitch: bool, silver_pswitch: bool, on_off_switch: bool,
-        offset: u16,
-    ) -> BlockGfx {
+    pub fn tiles_from_block(&self, block: &Block, tileset: usize, state: &LevelPreviewState, offset: u16) -> BlockGfx {
         assert!(tileset < TILESETS_COUNT);
 
-        const BLANK_ANIM: [AddrSnes; 4] =
-            [AddrSnes(0x7EAC20), AddrSnes(0x7EAC20), AddrSnes(0x7EAC20), AddrSnes(0x7EAC20)];
-
-        match self.animated_tile_data.get_animation_frames_for_block(
-            block,
-            tileset,
-            blue_pswitch,
-            silver_pswitch,
-            on_off_switch,
-            offset,
-        ) {
-            Some(BLANK_ANIM) | None => {
+        match self.animated_tile_data.get_animation_frames_for_block(block, tileset, state, offset) {
+            Some(BLANK_ANIMATION_FRAMES) | None => {
                 let ref_gfx = |tile| {
                     let file_num = self.object_gfx_list.gfx_file_for_object_tile(tile, tileset);
                     let tile_num = tile.tile_number() as usize % 0x80;
@@ -119,15 +190,70 @@ impl Gfx {
     }
 
     pub fn tile_from_wram(&self, wram_addr: AddrSnes) -> Result<&Tile, TileFromWramError> {
-        let (file, offset) = match wram_addr {
-            // Mario graphics & berry animation
-            AddrSnes(addr @ 0x7E2000..=0x7E7CFF) => (&self.files[0x32], addr - 0x7E2000),
-            // Yoshi graphics & animated tiles
-            AddrSnes(addr @ 0x7E7D00..=0x7EACFF) => (&self.files[0x33], addr - 0x7E7D00),
-            // Unknown
-            AddrSnes(_) => return Err(TileFromWramError(wram_addr)),
-        };
-        let index = offset as usize / (4 * 8);
-        Ok(&file.tiles[index])
+        tile_from_wram(&self.files, wram_addr)
+    }
+
+    /// Copies the graphics of the current frame of all animated tiles to `vram`, see
+    /// [`AnimatedTileData::apply_animation_frame`].
+    pub fn apply_animation_frame(&self, vram: &mut [u8], tileset: usize, state: &LevelPreviewState) {
+        self.animated_tile_data.apply_animation_frame(vram, &self.files, tileset, state);
+    }
+}
+
+/// Reads a 24-bit pointer, treating null pointers and ones outside of the ROM as absent.
+fn read_pointer(disasm: &RomDisassembly, addr: AddrSnes) -> Option<AddrSnes> {
+    let ptr = disasm
+        .rom
+        .with_error_mapper(|_| ())
+        .slice_lorom(SnesSlice::new(addr, 3))
+        .and_then(|view| view.parse(map(le_u24, AddrSnes)))
+        .ok()?;
+    match ptr {
+        AddrSnes(0) | AddrSnes(0xFFFFFF) => None,
+        _ => AddrPc::try_from_lorom(ptr).ok().filter(|pc| pc.as_index() < disasm.rom.0.len()).map(|_| ptr),
+    }
+}
+
+/// Returns the tile of the GFX file the game loads to the given WRAM address.
+pub fn tile_from_wram(files: &[GfxFile], wram_addr: AddrSnes) -> Result<&Tile, TileFromWramError> {
+    let (file, offset) = match wram_addr {
+        // Mario graphics & berry animation
+        AddrSnes(addr @ 0x7E2000..=0x7E7CFF) => (&files[0x32], addr - 0x7E2000),
+        // Yoshi graphics & animated tiles
+        AddrSnes(addr @ 0x7E7D00..=0x7EACFF) => (&files[0x33], addr - 0x7E7D00),
+        // Unknown
+        AddrSnes(_) => return Err(TileFromWramError(wram_addr)),
+    };
+    let index = offset as usize / (4 * 8);
+    file.tiles.get(index).ok_or(TileFromWramError(wram_addr))
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snes_utils::rom::Rom;
+
+    #[test]
+    fn test_parse_exgfx() {
+        let mut bytes = vec![0; 0x80000];
+        let table = AddrPc::try_from_lorom(EXGFX_POINTERS).unwrap().as_index();
+        bytes[table + 3..table + 6].copy_from_slice(&[0x00, 0x81, 0x0E]);
+        // Three long byte fills of 0x400 bytes each, giving a 3BPP file.
+        let data = AddrPc::try_from_lorom(AddrSnes(0x0E8100)).unwrap().as_index();
+        let compressed = [0xE7, 0xFF, 0x55, 0xE7, 0xFF, 0x55, 0xE7, 0xFF, 0x55, 0xFF];
+        bytes[data..data + compressed.len()].copy_from_slice(&compressed);
+
+        let disasm = RomDisassembly::unanalysed(Rom::new(bytes).unwrap());
+
+        let file = Gfx::parse_exgfx(&disasm, 0x81, false).unwrap().expect("slot 0x81 is filled");
+        assert_eq!(file.tile_format, TileFormat::Tile3bpp);
+        assert_eq!(file.tiles.len(), 0x80);
+        assert_eq!(file.tiles[0].color_indices[..8], [0, 7, 0, 7, 0, 7, 0, 7]);
+
+        assert!(Gfx::parse_exgfx(&disasm, 0x80, false).unwrap().is_none(), "empty slot");
+        assert!(Gfx::parse_exgfx(&disasm, 0x100, false).unwrap().is_none(), "no super ExGFX table");
+        assert!(Gfx::parse_exgfx(&disasm, 0x10, false).unwrap().is_none(), "stock GFX slot");
     }
 }
@@ -1,4 +1,7 @@
-use std::ops::RangeInclusive;
+use std::{
+    io::{self, Read, Write},
+    ops::RangeInclusive,
+};
 
 use duplicate::duplicate;
 use nom::{combinator::map, multi::many1, number::complete::le_u16};
@@ -67,6 +70,23 @@ pub enum ColorPaletteParseError {
     LevelSpritePalette(usize),
 }
 
+#[derive(Debug, Error)]
+pub enum ColorPaletteFileError {
+    #[error("Could not read palette file:\n- {0}")]
+    Io(#[from] io::Error),
+    #[error("Not a SNES TPL palette file")]
+    TplHeader,
+    #[error("Invalid palette file size: {0} bytes, expected {1}")]
+    Size(usize, usize),
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Header of Tile Layer Pro palette files, where the last byte marks colors as being in SNES format.
+pub const TPL_HEADER: [u8; 4] = *b"TPL\x02";
+/// Size of a full palette stored as little-endian BGR555 colors, as used by raw palette files.
+pub const RAW_PALETTE_SIZE: usize = 16 * 16 * ABGR1555_SIZE;
+
 // -------------------------------------------------------------------------------------------------
 
 pub trait ColorPalette {
@@ -97,6 +117,47 @@ pub trait ColorPalette {
         row
     }
 
+    /// Writes all 256 colors as little-endian BGR555, row by row. Transparent cells are written as black.
+    fn export_raw(&self, mut writer: impl Write) -> io::Result<()> {
+        for r in 0..16 {
+            for color in self.get_row(r) {
+                writer.write_all(&(color.0 & 0x7FFF).to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the palette as a Tile Layer Pro palette file, which holds the same data as a raw palette after the
+    /// [`TPL_HEADER`].
+    fn export_tpl(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(&TPL_HEADER)?;
+        self.export_raw(writer)
+    }
+
+    /// Reads colors written by [`export_raw`](Self::export_raw). Colors of cells that the palette doesn't store
+    /// are ignored.
+    fn import_raw(&mut self, mut reader: impl Read) -> Result<(), ColorPaletteFileError> {
+        let mut bytes = Vec::with_capacity(RAW_PALETTE_SIZE);
+        reader.read_to_end(&mut bytes)?;
+        if bytes.len() != RAW_PALETTE_SIZE {
+            return Err(ColorPaletteFileError::Size(bytes.len(), RAW_PALETTE_SIZE));
+        }
+        for (idx, color) in bytes.chunks_exact(ABGR1555_SIZE).enumerate() {
+            self.set_color_at(idx / 16, idx % 16, Abgr1555(u16::from_le_bytes([color[0], color[1]])));
+        }
+        Ok(())
+    }
+
+    /// Reads colors written by [`export_tpl`](Self::export_tpl).
+    fn import_tpl(&mut self, mut reader: impl Read) -> Result<(), ColorPaletteFileError> {
+        let mut header = [0; TPL_HEADER.len()];
+        reader.read_exact(&mut header)?;
+        if header != TPL_HEADER {
+            return Err(ColorPaletteFileError::TplHeader);
+        }
+        self.import_raw(reader)
+    }
+
     fn set_color_at(&mut self, row: usize, col: usize, color: Abgr1555);
     fn get_color_at(&self, row: usize, col: usize) -> Option<Abgr1555>;
 }
@@ -498,3 +559,58 @@ impl_color_palette!(SpecificOverworldColorPalette {
     [0x8..=0x8, 0x6..=0xF] => players,
     [0x8..=0x8, 0x1..=0x5] => wtf,
 });
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_palette(seed: u16) -> SpecificOverworldColorPalette {
+        let colors = |len: usize, offset: u16| (0..len as u16).map(|i| Abgr1555(seed + offset + i)).collect();
+        SpecificOverworldColorPalette {
+            layer1:  colors(6 * 7, 0x000),
+            layer2:  colors(4 * 7, 0x100),
+            layer3:  colors(2 * 8, 0x200),
+            sprite:  colors(7 * 7, 0x300),
+            players: colors(10, 0x400),
+            wtf:     colors(5, 0x500),
+        }
+    }
+
+    fn rows(palette: &impl ColorPalette) -> Vec<[u16; 16]> {
+        (0..16).map(|r| palette.get_row(r).map(|color| color.0)).collect()
+    }
+
+    #[test]
+    fn test_raw_round_trip() {
+        let palette = test_palette(0x1000);
+        let mut raw = Vec::new();
+        palette.export_raw(&mut raw).unwrap();
+        assert_eq!(raw.len(), RAW_PALETTE_SIZE);
+        // Row 2, column 9 is the first color of layer 1.
+        assert_eq!(raw[2 * 32 + 9 * 2..][..2], [0x00, 0x10]);
+
+        let mut imported = test_palette(0x2000);
+        imported.import_raw(raw.as_slice()).unwrap();
+        assert_eq!(rows(&imported), rows(&palette));
+
+        assert!(matches!(imported.import_raw(&raw[1..]), Err(ColorPaletteFileError::Size(0x1FF, RAW_PALETTE_SIZE))));
+    }
+
+    #[test]
+    fn test_tpl_round_trip() {
+        let palette = test_palette(0x1000);
+        let mut tpl = Vec::new();
+        palette.export_tpl(&mut tpl).unwrap();
+        assert_eq!(tpl[..4], *b"TPL\x02");
+        assert_eq!(tpl.len(), 4 + RAW_PALETTE_SIZE);
+
+        let mut imported = test_palette(0x2000);
+        imported.import_tpl(tpl.as_slice()).unwrap();
+        assert_eq!(rows(&imported), rows(&palette));
+
+        tpl[3] = 0x00;
+        assert!(matches!(imported.import_tpl(tpl.as_slice()), Err(ColorPaletteFileError::TplHeader)));
+    }
+}
@@ -0,0 +1,286 @@
+use std::io::Write;
+
+use epaint::Color32;
+use smwe_render::color::Abgr1555;
+use thiserror::Error;
+
+use crate::graphics::{
+    gfx_file::{GfxFile, Tile, TileFormat, N_PIXELS_IN_TILE},
+    Gfx,
+};
+
+/// Width of a GFX sheet in tiles.
+pub const SHEET_WIDTH_TILES: usize = 16;
+/// Width of a GFX sheet in pixels.
+pub const SHEET_WIDTH_PX: usize = SHEET_WIDTH_TILES * 8;
+
+// -------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Error)]
+pub enum GfxImportError {
+    #[error("Decoding PNG:\n- {0}")]
+    Decoding(#[from] png::DecodingError),
+    #[error("Image size {0}x{1} is not a multiple of 8")]
+    InvalidSize(u32, u32),
+    #[error("Color ${:04X} at ({x}, {y}) is not one of the first {n_colors} palette colors", .color.0)]
+    ColorNotInPalette { x: usize, y: usize, color: Abgr1555, n_colors: usize },
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Rows of a GFX sheet occupied by a single GFX file.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SheetRegion {
+    pub file_num:    usize,
+    pub tile_format: TileFormat,
+    /// Top of the region in pixels.
+    pub y:           usize,
+    /// Height of the region in pixels.
+    pub height:      usize,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Lays out `files` one below another, each starting on a new row of [`SHEET_WIDTH_TILES`] tiles.
+///
+/// Returns the region of each file and the height of the whole sheet in pixels.
+pub fn sheet_layout(files: &[GfxFile]) -> (Vec<SheetRegion>, usize) {
+    let mut y = 0;
+    let regions = files
+        .iter()
+        .enumerate()
+        .map(|(file_num, file)| {
+            let height = (file.tiles.len() + SHEET_WIDTH_TILES - 1) / SHEET_WIDTH_TILES * 8;
+            let region = SheetRegion { file_num, tile_format: file.tile_format, y, height };
+            y += height;
+            region
+        })
+        .collect();
+    (regions, y)
+}
+
+/// Exports the tiles of `files` as a single PNG image laid out by [`sheet_layout`], coloured with `palette`. Each
+/// file's region is labelled with a `tEXt` chunk named after the file, e.g. `GFX0A`, holding its format and rows in
+/// the image.
+///
+/// Color indices outside of `palette` are drawn in magenta.
+pub fn export_sheet(files: &[GfxFile], palette: &[Abgr1555]) -> Result<Vec<u8>, png::EncodingError> {
+    let (regions, height) = sheet_layout(files);
+    let mut pixels = vec![0u8; SHEET_WIDTH_PX * height * 4];
+    for (region, file) in regions.iter().zip(files.iter()) {
+        for (tile_num, tile) in file.tiles.iter().enumerate() {
+            let tile_x = (tile_num % SHEET_WIDTH_TILES) * 8;
+            let tile_y = region.y + (tile_num / SHEET_WIDTH_TILES) * 8;
+            draw_tile(&mut pixels, SHEET_WIDTH_PX, tile_x, tile_y, tile, palette);
+        }
+    }
+
+    let mut png_bytes = Vec::new();
+    let mut encoder = rgba_encoder(&mut png_bytes, SHEET_WIDTH_PX, height);
+    for region in regions.iter() {
+        let label = format!("{}, rows {}..{}", region.tile_format, region.y, region.y + region.height);
+        encoder.add_text_chunk(format!("GFX{:02X}", region.file_num), label)?;
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+    Ok(png_bytes)
+}
+
+/// Draws an 8x8 tile into RGBA `pixels` of an image `width` pixels wide, with its top left corner at `x` and `y`.
+fn draw_tile(pixels: &mut [u8], width: usize, x: usize, y: usize, tile: &Tile, palette: &[Abgr1555]) {
+    for (i, &color_index) in tile.color_indices.iter().enumerate() {
+        let color = palette.get(color_index as usize).copied().unwrap_or(Abgr1555::MAGENTA);
+        let pixel = ((y + i / 8) * width + x + i % 8) * 4;
+        pixels[pixel..pixel + 4].copy_from_slice(&Color32::from(color).to_srgba_unmultiplied());
+    }
+}
+
+fn rgba_encoder<W: Write>(writer: W, width: usize, height: usize) -> png::Encoder<'static, W> {
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+}
+
+impl GfxFile {
+    /// Writes the file's tiles as a PNG image with `tiles_per_row` tiles in each row, coloured with `palette`.
+    /// Tiles of every format, including Mode 7 ones, are coloured by their color indices.
+    ///
+    /// Color indices outside of `palette` are drawn in magenta. Panics if `tiles_per_row` is zero.
+    pub fn to_png(
+        &self, palette: &[Abgr1555], tiles_per_row: usize, writer: impl Write,
+    ) -> Result<(), png::EncodingError> {
+        assert_ne!(tiles_per_row, 0, "Cannot lay out tiles in rows of zero tiles");
+        let width = tiles_per_row * 8;
+        let height = (self.tiles.len() + tiles_per_row - 1) / tiles_per_row * 8;
+        let mut pixels = vec![0u8; width * height * 4];
+        for (tile_num, tile) in self.tiles.iter().enumerate() {
+            let tile_x = (tile_num % tiles_per_row) * 8;
+            let tile_y = (tile_num / tiles_per_row) * 8;
+            draw_tile(&mut pixels, width, tile_x, tile_y, tile, palette);
+        }
+
+        let mut writer = rgba_encoder(writer, width, height).write_header()?;
+        writer.write_image_data(&pixels)?;
+        writer.finish()
+    }
+}
+
+/// Converts a PNG pixel to the nearest SNES color. Pixels that are less than half opaque are transparent.
+fn snes_color(rgba: [u8; 4]) -> Abgr1555 {
+    let channel = |value: u8| (value as f32 / 255. * 31.).round() as u16;
+    let transparent = (rgba[3] < 0x80) as u16;
+    Abgr1555((transparent << 0xF) | (channel(rgba[2]) << 0xA) | (channel(rgba[1]) << 0x5) | channel(rgba[0]))
+}
+
+/// Decodes a PNG image into RGBA pixels, returning them with the image's width and height.
+fn decode_rgba(png_bytes: &[u8]) -> Result<(Vec<[u8; 4]>, usize, usize), png::DecodingError> {
+    let mut decoder = png::Decoder::new(png_bytes);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let bytes = &buffer[..info.buffer_size()];
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => bytes.chunks_exact(4).map(|p| [p[0], p[1], p[2], p[3]]).collect(),
+        png::ColorType::Rgb => bytes.chunks_exact(3).map(|p| [p[0], p[1], p[2], 0xFF]).collect(),
+        png::ColorType::GrayscaleAlpha => bytes.chunks_exact(2).map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale | png::ColorType::Indexed => bytes.iter().map(|&v| [v, v, v, 0xFF]).collect(),
+    };
+    Ok((pixels, info.width as usize, info.height as usize))
+}
+
+impl GfxFile {
+    /// Reads tiles from a PNG image, the inverse of [`to_png`](Self::to_png). Tiles are read row by row, and each
+    /// pixel is mapped to the index of its color in `palette`, compared at SNES color precision.
+    ///
+    /// Only as many palette colors as fit in `tile_format` are used. If the palette has the same color more than once,
+    /// the first index is chosen.
+    pub fn from_png(png_bytes: &[u8], tile_format: TileFormat, palette: &[Abgr1555]) -> Result<Self, GfxImportError> {
+        let (pixels, width, height) = decode_rgba(png_bytes)?;
+        if width % 8 != 0 || height % 8 != 0 {
+            return Err(GfxImportError::InvalidSize(width as u32, height as u32));
+        }
+
+        let n_colors = palette.len().min(tile_format.n_colors());
+        let palette = &palette[..n_colors];
+        let tiles_per_row = width / 8;
+        let tiles = (0..tiles_per_row * height / 8)
+            .map(|tile_num| {
+                let (tile_x, tile_y) = ((tile_num % tiles_per_row) * 8, (tile_num / tiles_per_row) * 8);
+                let color_indices = (0..N_PIXELS_IN_TILE)
+                    .map(|i| {
+                        let (x, y) = (tile_x + i % 8, tile_y + i / 8);
+                        let color = snes_color(pixels[y * width + x]);
+                        palette
+                            .iter()
+                            .position(|c| c.0 == color.0)
+                            .map(|index| index as u8)
+                            .ok_or(GfxImportError::ColorNotInPalette { x, y, color, n_colors })
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Tile { color_indices })
+            })
+            .collect::<Result<_, GfxImportError>>()?;
+        Ok(Self { tile_format, tiles })
+    }
+}
+
+impl Gfx {
+    /// Exports all GFX files as a single PNG image, see [`export_sheet`].
+    pub fn export_all_sheet(&self, palette: &[Abgr1555]) -> Result<Vec<u8>, png::EncodingError> {
+        export_sheet(&self.files, palette)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use smwe_render::color::Abgr1555;
+
+    use super::*;
+    use crate::graphics::gfx_file::{Tile, N_PIXELS_IN_TILE};
+
+    fn files_with_tile_counts(counts: &[usize]) -> Vec<GfxFile> {
+        counts
+            .iter()
+            .map(|&count| GfxFile {
+                tile_format: TileFormat::Tile4bpp,
+                tiles:       vec![Tile { color_indices: vec![1; N_PIXELS_IN_TILE].into() }; count],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sheet_dimensions() {
+        let files = files_with_tile_counts(&[0x80, 0x11, 0x01]);
+        let (regions, height) = sheet_layout(&files);
+        assert_eq!(height, (8 + 2 + 1) * 8);
+        assert_eq!(regions[1], SheetRegion {
+            file_num:    1,
+            tile_format: TileFormat::Tile4bpp,
+            y:           64,
+            height:      16,
+        });
+
+        let png_bytes = export_sheet(&files, &[Abgr1555::TRANSPARENT, Abgr1555::RED]).unwrap();
+        let decoder = png::Decoder::new(png_bytes.as_slice());
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (SHEET_WIDTH_PX as u32, 88));
+        let labels: Vec<_> = info.uncompressed_latin1_text.iter().map(|t| (&*t.keyword, &*t.text)).collect();
+        assert_eq!(labels[1], ("GFX01", "4BPP, rows 64..80"));
+    }
+
+    #[test]
+    fn test_file_to_png() {
+        let blank = Tile { color_indices: vec![0; N_PIXELS_IN_TILE].into() };
+        let column = Tile { color_indices: (0..N_PIXELS_IN_TILE).map(|i| (i % 8 == 3) as u8).collect() };
+        let file = GfxFile { tile_format: TileFormat::Tile3bppMode7, tiles: vec![blank, column] };
+
+        let mut png_bytes = Vec::new();
+        file.to_png(&[Abgr1555::BLACK, Abgr1555::RED], 1, &mut png_bytes).unwrap();
+        let mut reader = png::Decoder::new(png_bytes.as_slice()).read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (8, 16));
+
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        let pixel = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..][..4];
+        assert_eq!(pixel(3, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(3, 9), [255, 0, 0, 255]);
+        assert_eq!(pixel(4, 9), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_file_png_round_trip() {
+        let palette = [Abgr1555::TRANSPARENT, Abgr1555::RED, Abgr1555(0x1234), Abgr1555::WHITE];
+        let file = GfxFile {
+            tile_format: TileFormat::Tile2bpp,
+            tiles:       (0..6)
+                .map(|n| Tile { color_indices: (0..N_PIXELS_IN_TILE).map(|i| ((i + n) % 4) as u8).collect() })
+                .collect(),
+        };
+
+        let mut png_bytes = Vec::new();
+        file.to_png(&palette, 3, &mut png_bytes).unwrap();
+        let imported = GfxFile::from_png(&png_bytes, TileFormat::Tile2bpp, &palette).unwrap();
+        assert_eq!(imported.tile_format, TileFormat::Tile2bpp);
+        assert_eq!(imported.tiles, file.tiles);
+
+        let result = GfxFile::from_png(&png_bytes, TileFormat::Tile2bpp, &palette[..3]);
+        assert!(matches!(result, Err(GfxImportError::ColorNotInPalette { x: 3, y: 0, n_colors: 3, .. })));
+    }
+
+    #[test]
+    fn test_png_size_must_be_multiple_of_8() {
+        let mut png_bytes = Vec::new();
+        let mut writer = rgba_encoder(&mut png_bytes, 12, 8).write_header().unwrap();
+        writer.write_image_data(&[0; 12 * 8 * 4]).unwrap();
+        writer.finish().unwrap();
+
+        let result = GfxFile::from_png(&png_bytes, TileFormat::Tile4bpp, &[Abgr1555::TRANSPARENT]);
+        assert!(matches!(result, Err(GfxImportError::InvalidSize(12, 8))));
+    }
+}
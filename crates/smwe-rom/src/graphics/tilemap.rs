@@ -0,0 +1,142 @@
+use epaint::Rgba;
+use smwe_render::color::Abgr1555;
+use thiserror::Error;
+
+use crate::{
+    graphics::gfx_file::{GfxFile, GfxTileError, Tile, TileFormat, N_PIXELS_IN_TILE},
+    objects::map16::Tile8x8,
+};
+
+// -------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Error)]
+pub enum TilemapError {
+    #[error("Image size {0}x{1} is not a multiple of the tile size")]
+    UnalignedImageSize(usize, usize),
+    #[error("Image has {0} unique tiles, more than can be addressed in a tilemap")]
+    TooManyUniqueTiles(usize),
+    #[error(transparent)]
+    Deduplication(#[from] GfxTileError),
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Largest number of tiles that a tilemap entry can refer to.
+pub const MAX_TILEMAP_TILES: usize = 0x400;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tilemap {
+    /// Width in tiles.
+    pub width:  usize,
+    /// Height in tiles.
+    pub height: usize,
+    /// Row-major tilemap entries, with palette and priority bits left clear.
+    pub tiles:  Vec<Tile8x8>,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl Tilemap {
+    /// Converts a row-major image into a GFX file with unique tiles and a tilemap that reconstructs the image from
+    /// them. Each pixel is mapped to the nearest color of `palette`, with transparent pixels mapped to color 0.
+    pub fn from_image(
+        pixels: &[Rgba], width_px: usize, palette: &[Abgr1555], tile_format: TileFormat,
+    ) -> Result<(Self, GfxFile), TilemapError> {
+        let height_px = pixels.len().checked_div(width_px).unwrap_or(0);
+        if width_px % 8 != 0 || height_px % 8 != 0 || width_px * height_px != pixels.len() {
+            return Err(TilemapError::UnalignedImageSize(width_px, height_px));
+        }
+        let (width, height) = (width_px / 8, height_px / 8);
+
+        let colors_per_palette = 1 << (tile_format.tile_size() / 8);
+        let palette = &palette[..palette.len().min(colors_per_palette)];
+
+        let mut tiles = Vec::with_capacity(width * height);
+        for tile_y in 0..height {
+            for tile_x in 0..width {
+                let color_indices = (0..N_PIXELS_IN_TILE)
+                    .map(|i| {
+                        let (x, y) = (tile_x * 8 + i % 8, tile_y * 8 + i / 8);
+                        nearest_color_index(pixels[y * width_px + x], palette)
+                    })
+                    .collect();
+                tiles.push(Tile { color_indices });
+            }
+        }
+
+        let (gfx, remap) = GfxFile { tile_format, tiles }.deduplicate()?;
+        if gfx.tiles.len() > MAX_TILEMAP_TILES {
+            return Err(TilemapError::TooManyUniqueTiles(gfx.tiles.len()));
+        }
+
+        // Remap entries use the same bits for tile numbers and flips as tilemap entries.
+        let tiles = remap.into_iter().map(Tile8x8).collect();
+        Ok((Self { width, height, tiles }, gfx))
+    }
+}
+
+/// Index of the palette color closest to `color`, or 0 if `color` is transparent.
+pub fn nearest_color_index(color: Rgba, palette: &[Abgr1555]) -> u8 {
+    if color.a() < 0.5 {
+        return 0;
+    }
+    palette
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(index, &palette_color)| {
+            let palette_color = Rgba::from(palette_color);
+            let distance = (color.r() - palette_color.r()).powi(2)
+                + (color.g() - palette_color.g()).powi(2)
+                + (color.b() - palette_color.b()).powi(2);
+            (index, distance)
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(0, |(index, _)| index as u8)
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use epaint::Rgba;
+    use smwe_render::color::Abgr1555;
+
+    use super::Tilemap;
+    use crate::{graphics::gfx_file::TileFormat, objects::map16::Tile8x8};
+
+    #[test]
+    fn test_tilemap_from_image() {
+        let palette = [Abgr1555::TRANSPARENT, Abgr1555::RED, Abgr1555::GREEN, Abgr1555::BLUE];
+        let diagonal = |x: usize, y: usize| if x == y { Rgba::from_rgb(0.9, 0.1, 0.) } else { Rgba::TRANSPARENT };
+        let split = |x: usize, _| if x < 4 { Rgba::from_rgb(0., 1., 0.2) } else { Rgba::from_rgb(0., 0., 1.) };
+        let filled = |_, _| Rgba::from_rgb(0.1, 0., 0.8);
+
+        // Top row: diagonal, split; bottom row: diagonal, filled.
+        let pixels = (0..16 * 16)
+            .map(|i| {
+                let (x, y) = (i % 16, i / 16);
+                match (x / 8, y / 8) {
+                    (0, _) => diagonal(x % 8, y % 8),
+                    (1, 0) => split(x % 8, y % 8),
+                    _ => filled(x % 8, y % 8),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let (tilemap, gfx) = Tilemap::from_image(&pixels, 16, &palette, TileFormat::Tile4bpp).unwrap();
+        assert_eq!((tilemap.width, tilemap.height), (2, 2));
+        assert_eq!(tilemap.tiles, vec![Tile8x8(0), Tile8x8(1), Tile8x8(0), Tile8x8(2)]);
+        assert_eq!(gfx.tiles.len(), 3);
+        assert_eq!(gfx.tiles[0].color_indices[9], 1);
+        assert_eq!(gfx.tiles[0].color_indices[1], 0);
+        assert_eq!(&gfx.tiles[1].color_indices[..8], &[2, 2, 2, 2, 3, 3, 3, 3]);
+        assert!(gfx.tiles[2].color_indices.iter().all(|&i| i == 3));
+    }
+
+    #[test]
+    fn test_unaligned_image() {
+        let pixels = vec![Rgba::TRANSPARENT; 12 * 8];
+        assert!(Tilemap::from_image(&pixels, 12, &[Abgr1555::BLACK], TileFormat::Tile2bpp).is_err());
+    }
+}
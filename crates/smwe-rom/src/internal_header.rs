@@ -1,4 +1,4 @@
-use std::{clone::Clone, convert::TryFrom, fmt};
+use std::{clone::Clone, fmt};
 
 use nom::{
     combinator::{map, map_res},
@@ -6,7 +6,7 @@ use nom::{
     number::complete::{le_u16, le_u8},
     sequence::pair,
 };
-use num_enum::{IntoPrimitive, TryFromPrimitive};
+use num_enum::{FromPrimitive, IntoPrimitive};
 use thiserror::Error;
 
 use crate::{
@@ -57,6 +57,7 @@ pub enum InternalHeaderParseError {
 
 #[rustfmt::skip]
 pub mod offsets {
+    pub const ROM_SIZE:         usize = 0x17;
     pub const COMPLEMENT_CHECK: usize = 0x1C;
     pub const CHECKSUM:         usize = 0x1E;
 }
@@ -65,8 +66,21 @@ pub mod offsets {
 pub mod sizes {
     pub const INTERNAL_HEADER:   usize = 64;
     pub const INTERNAL_ROM_NAME: usize = 21;
+    /// Fields from the ROM name up to the version number, followed by the complement and checksum.
+    pub const HEADER_FIELDS:     usize = 0x1C;
 }
 
+/// Locations of the internal ROM header in LoROM and HiROM images without a copier header.
+const HEADER_LOROM: PcSlice = PcSlice::new(AddrPc(0x007FC0), sizes::INTERNAL_HEADER);
+const HEADER_HIROM: PcSlice = PcSlice::new(AddrPc(0x00FFC0), sizes::INTERNAL_HEADER);
+
+/// Sum of the bytes of any complement and checksum pair that are each other's complement.
+const VALID_COMPLEMENT_AND_CHECKSUM_SUM: u32 = 2 * 0xFF;
+
+/// Index of the emulation mode RESET vector ($FFFC) in [`RomInternalHeader::interrupt_vectors`], which holds
+/// the six native mode vectors ($FFE4-$FFEF) followed by the six emulation mode ones ($FFF4-$FFFF).
+const RESET_VECTOR_INDEX: usize = 6 + 4;
+
 // -------------------------------------------------------------------------------------------------
 
 #[derive(Debug)]
@@ -82,7 +96,8 @@ pub struct RomInternalHeader {
     pub interrupt_vectors: Vec<AddrSnes>,
 }
 
-#[derive(Copy, Clone, Debug, IntoPrimitive, TryFromPrimitive)]
+/// Memory mapping and speed of the ROM. Unexpected bytes are kept in [`Unknown`](MapMode::Unknown).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, IntoPrimitive, FromPrimitive)]
 #[repr(u8)]
 pub enum MapMode {
     SlowLoRom   = 0b100000,
@@ -93,9 +108,12 @@ pub enum MapMode {
     FastHiRom   = 0b110001,
     FastExLoRom = 0b110010,
     FastExHiRom = 0b110100,
+    #[num_enum(catch_all)]
+    Unknown(u8),
 }
 
-#[derive(Copy, Clone, Debug, IntoPrimitive, TryFromPrimitive)]
+/// Chips on the cartridge. Unexpected bytes are kept in [`Unknown`](RomType::Unknown).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, IntoPrimitive, FromPrimitive)]
 #[repr(u8)]
 pub enum RomType {
     Rom               = 0x00,
@@ -137,9 +155,13 @@ pub enum RomType {
     RomSRtcSram       = 0x56,
     RomOtherSram      = 0xE6,
     RomCustomSram     = 0xF6,
+    #[num_enum(catch_all)]
+    Unknown(u8),
 }
 
-#[derive(Debug, TryFromPrimitive)]
+/// Destination code of the ROM. Codes outside of the known range, as found in some hacks, are kept as they are in
+/// [`Unknown`](RegionCode::Unknown) instead of failing the header parsing.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, IntoPrimitive, FromPrimitive)]
 #[repr(u8)]
 pub enum RegionCode {
     Japan        = 0x00,
@@ -163,6 +185,8 @@ pub enum RegionCode {
     Other1       = 0x12,
     Other2       = 0x13,
     Other3       = 0x14,
+    #[num_enum(catch_all)]
+    Unknown(u8),
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -181,11 +205,11 @@ impl RomInternalHeader {
             map_mode:          rom
                 .with_error_mapper(InternalHeaderParseError::ReadMapMode)
                 .slice_pc(byte_slice)
-                .parse(map_res(le_u8, MapMode::try_from))?,
+                .parse(map(le_u8, MapMode::from))?,
             rom_type:          rom
                 .with_error_mapper(InternalHeaderParseError::ReadRomType)
                 .slice_pc(byte_slice.skip_forward(1))
-                .parse(map_res(le_u8, RomType::try_from))?,
+                .parse(map(le_u8, RomType::from))?,
             rom_size:          rom
                 .with_error_mapper(InternalHeaderParseError::ReadRomSize)
                 .slice_pc(byte_slice.skip_forward(2))
@@ -197,7 +221,7 @@ impl RomInternalHeader {
             region_code:       rom
                 .with_error_mapper(InternalHeaderParseError::ReadRegionCode)
                 .slice_pc(byte_slice.skip_forward(4))
-                .parse(map_res(le_u8, RegionCode::try_from))?,
+                .parse(map(le_u8, RegionCode::from))?,
             developer_id:      rom
                 .with_error_mapper(InternalHeaderParseError::ReadDeveloperId)
                 .slice_pc(byte_slice.skip_forward(5))
@@ -222,10 +246,55 @@ impl RomInternalHeader {
         })
     }
 
-    fn find(rom: &Rom) -> Result<PcSlice, InternalHeaderParseError> {
-        const HEADER_LOROM: PcSlice = PcSlice::new(AddrPc(0x007FC0), sizes::INTERNAL_HEADER);
-        const HEADER_HIROM: PcSlice = PcSlice::new(AddrPc(0x00FFC0), sizes::INTERNAL_HEADER);
+    /// Sets the internal ROM name, truncated or padded with spaces to [`sizes::INTERNAL_ROM_NAME`] characters.
+    ///
+    /// The name is stored as single-byte characters, so anything other than printable ASCII, including every
+    /// non-ASCII character, is replaced with `?`.
+    pub fn set_rom_name(&mut self, name: &str) {
+        let name = name
+            .chars()
+            .map(|c| if c == ' ' || c.is_ascii_graphic() { c } else { '?' })
+            .chain(std::iter::repeat(' '))
+            .take(sizes::INTERNAL_ROM_NAME);
+        self.internal_rom_name = name.collect();
+    }
 
+    /// Returns the header fields from the ROM name up to the version number, as stored in the ROM.
+    pub fn serialize(&self) -> [u8; sizes::HEADER_FIELDS] {
+        let mut bytes = [0; sizes::HEADER_FIELDS];
+        let name = self.internal_rom_name.as_bytes().iter().copied().chain(std::iter::repeat(b' '));
+        for (dst, src) in bytes[..sizes::INTERNAL_ROM_NAME].iter_mut().zip(name) {
+            *dst = src;
+        }
+        bytes[sizes::INTERNAL_ROM_NAME..].copy_from_slice(&[
+            self.map_mode.into(),
+            self.rom_type.into(),
+            self.rom_size,
+            self.sram_size,
+            self.region_code.into(),
+            self.developer_id,
+            self.version_number,
+        ]);
+        bytes
+    }
+
+    /// Writes the [serialized](Self::serialize) header fields to the LoROM or HiROM header location of a ROM image
+    /// without a copier header, chosen as in [`fix_checksum`](Self::fix_checksum), then fixes the complement and
+    /// checksum so that the whole 32-byte header is up to date.
+    pub fn write_to(&self, rom_bytes: &mut [u8]) {
+        let start = Self::header_offset(rom_bytes);
+        if let Some(dst) = rom_bytes.get_mut(start..start + sizes::HEADER_FIELDS) {
+            dst.copy_from_slice(&self.serialize());
+        }
+        Self::fix_checksum(rom_bytes);
+    }
+
+    /// Address at which the CPU starts executing after power-on or reset.
+    pub fn reset_vector(&self) -> AddrSnes {
+        self.interrupt_vectors[RESET_VECTOR_INDEX]
+    }
+
+    fn find(rom: &Rom) -> Result<PcSlice, InternalHeaderParseError> {
         let lo_cpl_csm = HEADER_LOROM.offset_forward(offsets::COMPLEMENT_CHECK).resize(4);
         let hi_cpl_csm = HEADER_HIROM.offset_forward(offsets::COMPLEMENT_CHECK).resize(4);
 
@@ -251,6 +320,74 @@ impl RomInternalHeader {
         }
     }
 
+    /// Computes the complement and checksum of a ROM image without a copier header, as stored in its internal header.
+    ///
+    /// The checksum is the sum of all bytes. If the ROM size isn't a power of two, the part past the largest power
+    /// of two is repeated until it's as big, the same way recursively. The complement and checksum bytes count as
+    /// any valid pair, so their current values don't affect the result.
+    pub fn compute_checksum(rom_bytes: &[u8]) -> (u16, u16) {
+        let mut sum = Self::mirrored_sum(rom_bytes).0;
+        let fields_start = Self::header_offset(rom_bytes) + offsets::COMPLEMENT_CHECK;
+        if let Some(fields) = rom_bytes.get(fields_start..fields_start + 4) {
+            let fields_sum: u32 = fields.iter().map(|&b| b as u32).sum();
+            sum = sum.wrapping_sub(fields_sum).wrapping_add(VALID_COMPLEMENT_AND_CHECKSUM_SUM);
+        }
+        let checksum = sum as u16;
+        (!checksum, checksum)
+    }
+
+    /// Reads the complement and checksum stored in the internal header of a ROM image without a copier header, found
+    /// as in [`fix_checksum`](Self::fix_checksum). Returns `None` if the ROM is too small to hold them.
+    pub fn read_checksum(rom_bytes: &[u8]) -> Option<(u16, u16)> {
+        let start = Self::header_offset(rom_bytes) + offsets::COMPLEMENT_CHECK;
+        let b = rom_bytes.get(start..start + 4)?;
+        Some((u16::from_le_bytes([b[0], b[1]]), u16::from_le_bytes([b[2], b[3]])))
+    }
+
+    /// Writes the complement and checksum computed by [`compute_checksum`](Self::compute_checksum) to the ROM's
+    /// internal header. ROMs without a valid HiROM header are assumed to be LoROM.
+    pub fn fix_checksum(rom_bytes: &mut [u8]) {
+        let (complement, checksum) = Self::compute_checksum(rom_bytes);
+        let header_offset = Self::header_offset(rom_bytes);
+        for (offset, value) in [(offsets::COMPLEMENT_CHECK, complement), (offsets::CHECKSUM, checksum)] {
+            let start = header_offset + offset;
+            if let Some(field) = rom_bytes.get_mut(start..start + 2) {
+                field.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+
+    /// Returns the wrapping sum of the bytes mirrored up to the nearest power of two, and that power of two.
+    fn mirrored_sum(bytes: &[u8]) -> (u32, usize) {
+        let sum = |bytes: &[u8]| bytes.iter().fold(0u32, |sum, &b| sum.wrapping_add(b as u32));
+        if bytes.len().is_power_of_two() || bytes.is_empty() {
+            return (sum(bytes), bytes.len());
+        }
+        let base_size = 1 << (usize::BITS - 1 - bytes.len().leading_zeros());
+        let (base, rest) = bytes.split_at(base_size);
+        let (rest_sum, rest_size) = Self::mirrored_sum(rest);
+        let repeats = (base_size / rest_size) as u32;
+        (sum(base).wrapping_add(rest_sum.wrapping_mul(repeats)), 2 * base_size)
+    }
+
+    /// Returns the offset of the internal header, which is at the HiROM location if that one holds a valid complement
+    /// and checksum pair while the LoROM one doesn't.
+    pub(crate) fn header_offset(rom_bytes: &[u8]) -> usize {
+        let has_valid_pair = |header: PcSlice| {
+            let start = header.begin.as_index() + offsets::COMPLEMENT_CHECK;
+            rom_bytes.get(start..start + 4).is_some_and(|b| {
+                let complement = u16::from_le_bytes([b[0], b[1]]);
+                let checksum = u16::from_le_bytes([b[2], b[3]]);
+                (complement ^ checksum) == 0xFFFF
+            })
+        };
+        if !has_valid_pair(HEADER_LOROM) && has_valid_pair(HEADER_HIROM) {
+            HEADER_HIROM.begin.as_index()
+        } else {
+            HEADER_LOROM.begin.as_index()
+        }
+    }
+
     pub fn rom_size_in_kb(&self) -> u32 {
         let exponent = self.rom_size as u32;
         2u32.pow(exponent)
@@ -276,6 +413,7 @@ impl fmt::Display for MapMode {
             FastHiRom => "Fast HiROM",
             FastExLoRom => "Fast ExLoROM",
             FastExHiRom => "Fast ExHiROM",
+            Unknown(mode) => return write!(f, "Unknown ({mode:#04X})"),
         })
     }
 }
@@ -299,6 +437,7 @@ impl fmt::Display for RomType {
             Rom => String::from("ROM"),
             RomRam => String::from("ROM + RAM"),
             RomRamSram => String::from("ROM + RAM + SRAM"),
+            Unknown(rom_type) => format!("Unknown ({rom_type:#04X})"),
             _ => format!(
                 "ROM + {}{}",
                 match self_as_byte & 0xF0 {
@@ -349,6 +488,149 @@ impl fmt::Display for RegionCode {
             Other1 => "Other (1)",
             Other2 => "Other (2)",
             Other3 => "Other (3)",
+            Unknown(code) => return write!(f, "Unknown ({code:#04X})"),
         })
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOROM_COMPLEMENT: usize = 0x7FC0 + offsets::COMPLEMENT_CHECK;
+    const LOROM_CHECKSUM: usize = 0x7FC0 + offsets::CHECKSUM;
+
+    fn test_header() -> RomInternalHeader {
+        RomInternalHeader {
+            internal_rom_name: String::from("SUPER MARIOWORLD     "),
+            map_mode:          MapMode::SlowLoRom,
+            rom_type:          RomType::RomRamSram,
+            rom_size:          0x09,
+            sram_size:         0x01,
+            region_code:       RegionCode::NorthAmerica,
+            developer_id:      0x01,
+            version_number:    0x00,
+            interrupt_vectors: Vec::new(),
+        }
+    }
+
+    fn stored_checksum(rom_bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([rom_bytes[at], rom_bytes[at + 1]])
+    }
+
+    /// Returns a ROM with a valid checksum whose header byte at `offset` from the map mode is set to `value`.
+    fn rom_with_header_byte(offset: usize, value: u8) -> Rom {
+        let mut rom_bytes = vec![0; 0x80000];
+        test_header().write_to(&mut rom_bytes);
+        rom_bytes[0x7FC0 + sizes::INTERNAL_ROM_NAME + offset] = value;
+        RomInternalHeader::fix_checksum(&mut rom_bytes);
+        Rom::new(rom_bytes).unwrap()
+    }
+
+    #[test]
+    fn test_checksum_power_of_two() {
+        let mut rom_bytes = vec![0; 0x80000];
+        rom_bytes[0x00000] = 0x12;
+        rom_bytes[0x40000] = 0x34;
+        rom_bytes[0x7FFFF] = 0x56;
+        rom_bytes[LOROM_CHECKSUM] = 0xAB; // Invalid values are ignored.
+
+        let expected = 0x12 + 0x34 + 0x56 + 0x1FE;
+        assert_eq!(RomInternalHeader::compute_checksum(&rom_bytes), (!expected, expected));
+
+        assert_eq!(RomInternalHeader::read_checksum(&rom_bytes), Some((0, 0xAB)));
+        RomInternalHeader::fix_checksum(&mut rom_bytes);
+        assert_eq!(RomInternalHeader::read_checksum(&rom_bytes), Some((!expected, expected)));
+        assert_eq!(stored_checksum(&rom_bytes, LOROM_COMPLEMENT), !expected);
+        assert_eq!(stored_checksum(&rom_bytes, LOROM_CHECKSUM), expected);
+        assert_eq!(RomInternalHeader::compute_checksum(&rom_bytes), (!expected, expected));
+    }
+
+    #[test]
+    fn test_checksum_mirrored() {
+        // 1.5 MB: the last 0.5 MB is counted twice to fill up to 2 MB.
+        let mut rom_bytes = vec![0; 0x180000];
+        rom_bytes[0x0FFFFF] = 0x10;
+        rom_bytes[0x100000] = 0x20;
+        let expected = 0x10 + 2 * 0x20 + 0x1FE;
+        assert_eq!(RomInternalHeader::compute_checksum(&rom_bytes), (!expected, expected));
+
+        // 1.25 MB: the last 0.25 MB is mirrored to 0.5 MB, which is then counted twice.
+        let mut rom_bytes = vec![0; 0x140000];
+        rom_bytes[0x100000] = 0x20;
+        let expected = 4 * 0x20 + 0x1FE;
+        RomInternalHeader::fix_checksum(&mut rom_bytes);
+        assert_eq!(stored_checksum(&rom_bytes, LOROM_CHECKSUM), expected);
+    }
+
+    #[test]
+    fn test_checksum_hirom() {
+        let mut rom_bytes = vec![0; 0x10000];
+        rom_bytes[0xFFC0 + offsets::COMPLEMENT_CHECK..0xFFC0 + offsets::CHECKSUM + 2]
+            .copy_from_slice(&[0xFF, 0xFF, 0x00, 0x00]);
+        rom_bytes[0x1234] = 0x42;
+        RomInternalHeader::fix_checksum(&mut rom_bytes);
+        assert_eq!(stored_checksum(&rom_bytes, 0xFFC0 + offsets::CHECKSUM), 0x42 + 0x1FE);
+        assert_eq!(stored_checksum(&rom_bytes, LOROM_CHECKSUM), 0);
+    }
+
+    #[test]
+    fn test_set_rom_name() {
+        let mut header = test_header();
+
+        header.set_rom_name("MY HACK");
+        assert_eq!(header.internal_rom_name, "MY HACK              ");
+        assert_eq!(&header.serialize()[..sizes::INTERNAL_ROM_NAME], b"MY HACK              ");
+
+        header.set_rom_name("A VERY LONG NAME FOR A ROM HACK");
+        assert_eq!(header.internal_rom_name, "A VERY LONG NAME FOR ");
+
+        header.set_rom_name("Mário\tWorld");
+        assert_eq!(header.internal_rom_name, "M?rio?World          ");
+    }
+
+    #[test]
+    fn test_write_header() {
+        let mut header = test_header();
+        header.set_rom_name("MY HACK");
+
+        let mut rom_bytes = vec![0; 0x80000];
+        header.write_to(&mut rom_bytes);
+        #[rustfmt::skip]
+        assert_eq!(&rom_bytes[0x7FD5..0x7FDC], &[0x20, 0x02, 0x09, 0x01, 0x01, 0x01, 0x00]);
+        assert_eq!(RomInternalHeader::compute_checksum(&rom_bytes).1, stored_checksum(&rom_bytes, LOROM_CHECKSUM));
+
+        let parsed = RomInternalHeader::parse(&Rom::new(rom_bytes).unwrap()).unwrap();
+        assert_eq!(parsed.internal_rom_name, "MY HACK              ");
+        assert_eq!(parsed.serialize(), header.serialize());
+    }
+
+    #[test]
+    fn test_unknown_region_code() {
+        let parsed = RomInternalHeader::parse(&rom_with_header_byte(4, 0xFF)).unwrap();
+        assert_eq!(parsed.region_code, RegionCode::Unknown(0xFF));
+        assert_eq!(parsed.region_code.to_string(), "Unknown (0xFF)");
+        assert_eq!(parsed.serialize()[sizes::INTERNAL_ROM_NAME + 4], 0xFF);
+        assert_eq!(RegionCode::from(0x01), RegionCode::NorthAmerica);
+    }
+
+    #[test]
+    fn test_unknown_map_mode() {
+        let parsed = RomInternalHeader::parse(&rom_with_header_byte(0, 0x2F)).unwrap();
+        assert_eq!(parsed.map_mode, MapMode::Unknown(0x2F));
+        assert_eq!(parsed.map_mode.to_string(), "Unknown (0x2F)");
+        assert_eq!(parsed.serialize()[sizes::INTERNAL_ROM_NAME], 0x2F);
+        assert_eq!(parsed.rom_type, RomType::RomRamSram, "other fields are still parsed");
+    }
+
+    #[test]
+    fn test_unknown_rom_type() {
+        let parsed = RomInternalHeader::parse(&rom_with_header_byte(1, 0x99)).unwrap();
+        assert_eq!(parsed.rom_type, RomType::Unknown(0x99));
+        assert_eq!(parsed.rom_type.to_string(), "Unknown (0x99)");
+        assert_eq!(parsed.serialize()[sizes::INTERNAL_ROM_NAME + 1], 0x99);
+        assert_eq!(parsed.map_mode, MapMode::SlowLoRom);
+    }
+}
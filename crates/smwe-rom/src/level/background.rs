@@ -4,9 +4,20 @@ use crate::compression::{lc_rle1, DecompressionError};
 
 pub type BackgroundTileID = u8;
 
-#[derive(Debug, Clone)]
+/// Width of a background screen in 16x16 tiles.
+pub const BACKGROUND_SCREEN_WIDTH: usize = 16;
+/// Height of a background screen in 16x16 tiles.
+pub const BACKGROUND_SCREEN_HEIGHT: usize = 27;
+/// Backgrounds consist of this many screens placed side by side, repeating horizontally in levels.
+pub const BACKGROUND_SCREEN_COUNT: usize = 2;
+/// Width of the whole background in 16x16 tiles.
+pub const BACKGROUND_WIDTH: usize = BACKGROUND_SCREEN_WIDTH * BACKGROUND_SCREEN_COUNT;
+/// Height of the whole background in 16x16 tiles.
+pub const BACKGROUND_HEIGHT: usize = BACKGROUND_SCREEN_HEIGHT;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BackgroundData {
-    _tile_ids: Vec<BackgroundTileID>,
+    tile_ids: Vec<BackgroundTileID>,
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -15,6 +26,75 @@ impl BackgroundData {
     /// Returns self and the number of bytes consumed by parsing.
     pub fn read_from(input: &[u8]) -> Result<(Self, usize), DecompressionError> {
         let (tile_ids, bytes_consumed) = lc_rle1::decompress(input)?;
-        Ok((Self { _tile_ids: tile_ids }, bytes_consumed))
+        Ok((Self { tile_ids }, bytes_consumed))
+    }
+
+    /// Returns the tile IDs compressed with LC-RLE1.
+    pub fn serialize(&self) -> Vec<u8> {
+        lc_rle1::compress(&self.tile_ids)
+    }
+
+    /// Returns the tile ID at the given position within a screen, where `x` and `y` are in 16x16 tiles.
+    ///
+    /// The decompressed data stores screens one after another, each of them in row-major order.
+    pub fn tile_at(&self, screen: usize, x: usize, y: usize) -> Option<BackgroundTileID> {
+        if screen >= BACKGROUND_SCREEN_COUNT || x >= BACKGROUND_SCREEN_WIDTH || y >= BACKGROUND_SCREEN_HEIGHT {
+            return None;
+        }
+        let screen_offset = screen * BACKGROUND_SCREEN_WIDTH * BACKGROUND_SCREEN_HEIGHT;
+        self.tile_ids.get(screen_offset + y * BACKGROUND_SCREEN_WIDTH + x).copied()
+    }
+
+    /// Returns the whole background as [`BACKGROUND_HEIGHT`] rows of [`BACKGROUND_WIDTH`] tile IDs, with the screens
+    /// side by side. Tiles missing from the decompressed data are 0.
+    pub fn to_tilemap(&self) -> Vec<BackgroundTileID> {
+        (0..BACKGROUND_HEIGHT)
+            .flat_map(|y| (0..BACKGROUND_WIDTH).map(move |x| (x, y)))
+            .map(|(x, y)| self.tile_at(x / BACKGROUND_SCREEN_WIDTH, x % BACKGROUND_SCREEN_WIDTH, y).unwrap_or_default())
+            .collect()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_tilemap() {
+        // Screen 0 is filled with 0x25 apart from its top row, screen 1 with 0x80 apart from its top-left tile.
+        #[rustfmt::skip]
+        let compressed = [
+            0x8F, 0x10,       // 16 tiles of 0x10
+            0xFF, 0x25,       // 128 tiles of 0x25
+            0xFF, 0x25,       // 128 tiles of 0x25
+            0xFF, 0x25,       // 128 tiles of 0x25
+            0x9F, 0x25,       // 32 tiles of 0x25
+            0x00, 0x42,       // 1 tile: 0x42
+            0xFF, 0x80,       // 128 tiles of 0x80
+            0xFF, 0x80,       // 128 tiles of 0x80
+            0xFF, 0x80,       // 128 tiles of 0x80
+            0xAE, 0x80,       // 47 tiles of 0x80
+            0xFF, 0xFF,
+        ];
+        let (background, bytes_consumed) = BackgroundData::read_from(&compressed).unwrap();
+        assert_eq!(bytes_consumed, compressed.len() - 2);
+
+        assert_eq!(background.tile_at(0, 15, 0), Some(0x10));
+        assert_eq!(background.tile_at(0, 0, 1), Some(0x25));
+        assert_eq!(background.tile_at(0, 15, 26), Some(0x25));
+        assert_eq!(background.tile_at(1, 0, 0), Some(0x42));
+        assert_eq!(background.tile_at(1, 15, 26), Some(0x80));
+        assert_eq!(background.tile_at(1, 16, 0), None);
+        assert_eq!(background.tile_at(2, 0, 0), None);
+
+        let tilemap = background.to_tilemap();
+        assert_eq!(tilemap.len(), BACKGROUND_WIDTH * BACKGROUND_HEIGHT);
+        assert_eq!(tilemap[15], 0x10);
+        assert_eq!(tilemap[16], 0x42);
+        assert_eq!(tilemap[17], 0x80);
+        assert_eq!(tilemap[BACKGROUND_WIDTH], 0x25);
+        assert_eq!(tilemap[BACKGROUND_WIDTH + 16], 0x80);
     }
 }
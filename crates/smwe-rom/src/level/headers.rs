@@ -4,7 +4,8 @@ use nom::{bytes::complete::take, IResult};
 
 use crate::{
     disassembler::binary_block::{DataBlock, DataKind},
-    snes_utils::{addr::AddrSnes, rom::noop_error_mapper, rom_slice::SnesSlice},
+    level::LevelTables,
+    snes_utils::{rom::noop_error_mapper, rom_slice::SnesSlice},
     RomDisassembly,
     RomError,
 };
@@ -13,13 +14,15 @@ pub const PRIMARY_HEADER_SIZE: usize = 5;
 pub const SECONDARY_HEADER_SIZE: usize = 4;
 pub const SPRITE_HEADER_SIZE: usize = 1;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PrimaryHeader(pub [u8; PRIMARY_HEADER_SIZE]);
 
-#[derive(Debug, Clone)]
+/// Level settings stored in four byte tables at $05F000, $05F200, $05F400 and $05F600, indexed by level number.
+/// Level mode, Layer3 priority and vertical scroll settings are stored in the [`PrimaryHeader`] instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SecondaryHeader(pub [u8; SECONDARY_HEADER_SIZE]);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SpriteHeader(pub u8);
 
 impl PrimaryHeader {
@@ -27,6 +30,10 @@ impl PrimaryHeader {
         Self(bytes.try_into().unwrap())
     }
 
+    pub fn serialize(&self) -> [u8; PRIMARY_HEADER_SIZE] {
+        self.0
+    }
+
     pub fn palette_bg(&self) -> u8 {
         // BBB----- -------- -------- -------- --------
         // palette_bg = BBB
@@ -107,12 +114,11 @@ impl PrimaryHeader {
 }
 
 impl SecondaryHeader {
-    pub fn read_from_rom(disasm: &mut RomDisassembly, level_num: u32) -> Result<Self, RomError> {
-        let mut bytes = [0; 4];
-        let byte_table_addrs = [0x05F000, 0x05F200, 0x05F400, 0x05F600];
-        for (byte, addr) in bytes.iter_mut().zip(byte_table_addrs) {
+    pub fn read_from_rom(disasm: &mut RomDisassembly, tables: &LevelTables, level_num: u32) -> Result<Self, RomError> {
+        let mut bytes = [0; SECONDARY_HEADER_SIZE];
+        for (i, byte) in bytes.iter_mut().enumerate() {
             let data_block = DataBlock {
-                slice: SnesSlice::new(AddrSnes(addr), 0x200),
+                slice: SnesSlice::new(tables.secondary_header_table(i), tables.level_count),
                 kind:  DataKind::LevelHeaderSecondaryByteTable,
             };
             let byte_table = disasm.rom_slice_at_block(data_block, noop_error_mapper)?.as_bytes()?;
@@ -121,6 +127,11 @@ impl SecondaryHeader {
         Ok(Self(bytes))
     }
 
+    /// Returns the bytes stored at the level's index in each of the four secondary header byte tables.
+    pub fn serialize(&self) -> [u8; SECONDARY_HEADER_SIZE] {
+        self.0
+    }
+
     pub fn layer2_scroll(&self) -> u8 {
         // SSSS---- -------- -------- --------
         // layer2_scroll = SSSS
@@ -196,6 +207,10 @@ impl SpriteHeader {
         Ok((input, Self(bytes[0])))
     }
 
+    pub fn serialize(&self) -> [u8; SPRITE_HEADER_SIZE] {
+        [self.0]
+    }
+
     pub fn sprite_buoyancy(&self) -> bool {
         // B-------
         // sprite_buoyancy = B
@@ -214,3 +229,45 @@ impl SpriteHeader {
         self.0 & 0b00111111
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+#[allow(clippy::unusual_byte_groupings)] // Digits are grouped by header fields.
+mod tests {
+    use super::{PrimaryHeader, SecondaryHeader};
+
+    #[test]
+    fn test_primary_header_fields() {
+        let header = PrimaryHeader::new(&[0b101_10011, 0b110_01110, 0b1_011_0101, 0b10_100_011, 0b01_10_1001]);
+        assert_eq!(header.palette_bg(), 0b101);
+        assert_eq!(header.level_length(), 0b10011);
+        assert_eq!(header.back_area_color(), 0b110);
+        assert_eq!(header.level_mode(), 0b01110);
+        assert!(header.layer3_priority());
+        assert_eq!(header.music(), 0b011);
+        assert_eq!(header.sprite_gfx(), 0b0101);
+        assert_eq!(header.timer(), 0b10);
+        assert_eq!(header.palette_sprite(), 0b100);
+        assert_eq!(header.palette_fg(), 0b011);
+        assert_eq!(header.item_memory(), 0b01);
+        assert_eq!(header.vertical_scroll(), 0b10);
+        assert_eq!(header.fg_bg_gfx(), 0b1001);
+    }
+
+    #[test]
+    fn test_secondary_header_fields() {
+        let header = SecondaryHeader([0b1010_0110, 0b10_011_101, 0b0111_10_01, 0b1_0_1_10110]);
+        assert_eq!(header.layer2_scroll(), 0b1010);
+        assert_eq!(header.main_entrance_xy_pos(), (0b101, 0b0110));
+        assert_eq!(header.layer3(), 0b10);
+        assert_eq!(header.main_entrance_mario_action(), 0b011);
+        assert_eq!(header.midway_entrance_screen(), 0b0111);
+        assert_eq!(header.fg_initial_pos(), 0b10);
+        assert_eq!(header.bg_initial_pos(), 0b01);
+        assert!(header.no_yoshi_level());
+        assert!(!header.unknown_vertical_pos_level());
+        assert!(header.vertical_level());
+        assert_eq!(header.main_entrance_screen(), 0b10110);
+    }
+}
@@ -1,20 +1,24 @@
-use nom::{
-    combinator::map,
-    multi::count,
-    number::complete::{le_u16, le_u24},
-};
 use thiserror::Error;
 
 pub use self::{
     background::{BackgroundData, BackgroundTileID},
-    headers::{PrimaryHeader, SecondaryHeader, SpriteHeader, PRIMARY_HEADER_SIZE, SPRITE_HEADER_SIZE},
+    headers::{
+        PrimaryHeader,
+        SecondaryHeader,
+        SpriteHeader,
+        PRIMARY_HEADER_SIZE,
+        SECONDARY_HEADER_SIZE,
+        SPRITE_HEADER_SIZE,
+    },
     object_layer::ObjectLayer,
+    screen_exits::{ScreenExit, ScreenExits},
     sprite_layer::SpriteLayer,
+    tables::LevelTables,
 };
 use crate::{
     compression::DecompressionError,
     disassembler::binary_block::{DataBlock, DataKind},
-    snes_utils::{addr::AddrSnes, rom_slice::SnesSlice},
+    snes_utils::rom_slice::SnesSlice,
     RomDisassembly,
     RomError,
 };
@@ -22,8 +26,10 @@ use crate::{
 pub mod background;
 pub mod headers;
 pub mod object_layer;
+pub mod screen_exits;
 pub mod secondary_entrance;
 pub mod sprite_layer;
+pub mod tables;
 
 // -------------------------------------------------------------------------------------------------
 
@@ -58,17 +64,22 @@ pub enum LevelParseError {
 
 // -------------------------------------------------------------------------------------------------
 
+/// Number of levels in the original game.
 pub const LEVEL_COUNT: usize = 0x200;
 
 // -------------------------------------------------------------------------------------------------
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Layer2Data {
     Background(BackgroundData),
-    Objects(ObjectLayer),
+    /// Layer2 objects, preceded by a header that the game doesn't use, in the same format as the primary header.
+    Objects {
+        header:  PrimaryHeader,
+        objects: ObjectLayer,
+    },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Level {
     pub primary_header:   PrimaryHeader,
     pub secondary_header: SecondaryHeader,
@@ -78,27 +89,64 @@ pub struct Level {
     pub sprite_layer:     SpriteLayer,
 }
 
+/// Level data as stored in ROM. Except for the secondary header, each field holds the bytes that the level's entry
+/// in the corresponding pointer table points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelBytes {
+    /// Bytes at the level's index in each of the four secondary header byte tables.
+    pub secondary_header: [u8; SECONDARY_HEADER_SIZE],
+    /// Primary header followed by Layer1 objects.
+    pub layer1:           Vec<u8>,
+    /// Either LC-RLE1 compressed background tiles, or Layer2 objects preceded by their header.
+    pub layer2:           Vec<u8>,
+    /// Sprite header followed by sprites.
+    pub sprites:          Vec<u8>,
+}
+
 // -------------------------------------------------------------------------------------------------
 
 impl Level {
-    pub fn parse(disasm: &mut RomDisassembly, level_num: u32) -> Result<Self, LevelParseError> {
-        let (primary_header, layer1) = Self::parse_ph_and_l1(disasm, level_num)?;
-        let layer2 = Self::parse_l2(disasm, level_num)?;
-        let (sprite_header, sprite_layer) = Self::parse_sh_and_sl(disasm, level_num)?;
+    pub fn parse(disasm: &mut RomDisassembly, tables: &LevelTables, level_num: u32) -> Result<Self, LevelParseError> {
+        let (primary_header, layer1) = Self::parse_ph_and_l1(disasm, tables, level_num)?;
+        let layer2 = Self::parse_l2(disasm, tables, level_num)?;
+        let (sprite_header, sprite_layer) = Self::parse_sh_and_sl(disasm, tables, level_num)?;
         let secondary_header =
-            SecondaryHeader::read_from_rom(disasm, level_num).map_err(LevelParseError::SecondaryHeaderRead)?;
+            SecondaryHeader::read_from_rom(disasm, tables, level_num).map_err(LevelParseError::SecondaryHeaderRead)?;
 
         Ok(Level { primary_header, secondary_header, sprite_header, layer1, layer2, sprite_layer })
     }
 
+    pub fn screen_exits(&self) -> ScreenExits {
+        ScreenExits::from_object_layer(&self.layer1)
+    }
+
+    /// Converts the level back into the bytes it is parsed from, such that parsing them again yields an equal level.
+    pub fn serialize(&self) -> LevelBytes {
+        let mut layer1 = self.primary_header.serialize().to_vec();
+        layer1.extend(self.layer1.serialize());
+
+        let layer2 = match &self.layer2 {
+            Layer2Data::Background(background) => background.serialize(),
+            Layer2Data::Objects { header, objects } => {
+                let mut layer2 = header.serialize().to_vec();
+                layer2.extend(objects.serialize());
+                layer2
+            }
+        };
+
+        let mut sprites = self.sprite_header.serialize().to_vec();
+        sprites.extend(self.sprite_layer.serialize());
+
+        LevelBytes { secondary_header: self.secondary_header.serialize(), layer1, layer2, sprites }
+    }
+
     fn parse_ph_and_l1(
-        disasm: &mut RomDisassembly, level_num: u32,
+        disasm: &mut RomDisassembly, tables: &LevelTables, level_num: u32,
     ) -> Result<(PrimaryHeader, ObjectLayer), LevelParseError> {
-        let l1_ptr_block =
-            DataBlock { slice: SnesSlice::new(AddrSnes(0x05E000), 0x200 * 3), kind: DataKind::LevelPointersLayer1 };
-        let ph_addr = disasm
-            .rom_slice_at_block(l1_ptr_block, LevelParseError::Layer1AddressRead)?
-            .parse(count(map(le_u24, AddrSnes), 0x200))?[level_num as usize];
+        let l1_pointers = tables.layer1_pointer_table();
+        let l1_ptr_block = DataBlock { slice: l1_pointers.slice(), kind: DataKind::LevelPointersLayer1 };
+        disasm.rom_slice_at_block(l1_ptr_block, LevelParseError::Layer1AddressRead)?;
+        let ph_addr = l1_pointers.read(&disasm.rom, level_num as usize).map_err(LevelParseError::Layer1AddressRead)?;
 
         let ph_block =
             DataBlock { slice: SnesSlice::new(ph_addr, PRIMARY_HEADER_SIZE), kind: DataKind::LevelHeaderPrimary };
@@ -117,14 +165,16 @@ impl Level {
         Ok((primary_header, layer1))
     }
 
-    fn parse_l2(disasm: &mut RomDisassembly, level_num: u32) -> Result<Layer2Data, LevelParseError> {
-        const LAYER2_DATA: AddrSnes = AddrSnes(0x05E600);
-
-        let l2_addr_block =
-            DataBlock { slice: SnesSlice::new(LAYER2_DATA + (3 * level_num), 3), kind: DataKind::LevelPointersLayer2 };
-        let l2_ptr = disasm
-            .rom_slice_at_block(l2_addr_block, LevelParseError::Layer2AddressRead)?
-            .parse(map(le_u24, AddrSnes))?;
+    fn parse_l2(
+        disasm: &mut RomDisassembly, tables: &LevelTables, level_num: u32,
+    ) -> Result<Layer2Data, LevelParseError> {
+        let l2_pointers = tables.layer2_pointer_table();
+        let l2_addr_block = DataBlock {
+            slice: l2_pointers.entry(level_num as usize).map_err(LevelParseError::Layer2AddressRead)?,
+            kind:  DataKind::LevelPointersLayer2,
+        };
+        disasm.rom_slice_at_block(l2_addr_block, LevelParseError::Layer2AddressRead)?;
+        let l2_ptr = l2_pointers.read(&disasm.rom, level_num as usize).map_err(LevelParseError::Layer2AddressRead)?;
 
         if l2_ptr.bank() == 0xFF {
             let background = disasm.parse_and_mark_data(
@@ -138,25 +188,34 @@ impl Level {
             )?;
             Ok(Layer2Data::Background(background))
         } else {
+            let header = disasm
+                .rom
+                .view()
+                .slice_lorom(SnesSlice::new(l2_ptr, PRIMARY_HEADER_SIZE))
+                .and_then(|view| view.as_bytes())
+                .map(PrimaryHeader::new)
+                .map_err(LevelParseError::Layer2Read)?;
             let objects = disasm.parse_and_mark_data(
                 l2_ptr + PRIMARY_HEADER_SIZE as u32,
                 DataKind::LevelLayer2Objects,
                 LevelParseError::Layer2Read,
                 |rom_view| rom_view.parse(ObjectLayer::parse),
             )?;
-            Ok(Layer2Data::Objects(objects))
+            Ok(Layer2Data::Objects { header, objects })
         }
     }
 
     fn parse_sh_and_sl(
-        disasm: &mut RomDisassembly, level_num: u32,
+        disasm: &mut RomDisassembly, tables: &LevelTables, level_num: u32,
     ) -> Result<(SpriteHeader, SpriteLayer), LevelParseError> {
-        const SPRITE_DATA: AddrSnes = AddrSnes(0x05EC00);
-
-        let sprite_ptr_block =
-            DataBlock { slice: SnesSlice::new(SPRITE_DATA + (2 * level_num), 2), kind: DataKind::LevelPointersSprite };
-        let sh_addr = disasm.rom_slice_at_block(sprite_ptr_block, LevelParseError::SpriteAddressRead)?.parse(le_u16)?;
-        let sh_addr = AddrSnes(sh_addr as _).with_bank(0x07);
+        let sprite_pointers = tables.sprite_pointer_table();
+        let sprite_ptr_block = DataBlock {
+            slice: sprite_pointers.entry(level_num as usize).map_err(LevelParseError::SpriteAddressRead)?,
+            kind:  DataKind::LevelPointersSprite,
+        };
+        disasm.rom_slice_at_block(sprite_ptr_block, LevelParseError::SpriteAddressRead)?;
+        let sh_addr =
+            sprite_pointers.read(&disasm.rom, level_num as usize).map_err(LevelParseError::SpriteAddressRead)?;
 
         let sh_block =
             DataBlock { slice: SnesSlice::new(sh_addr, SPRITE_HEADER_SIZE), kind: DataKind::LevelHeaderSprites };
@@ -173,3 +232,161 @@ impl Level {
         Ok((sprite_header, sprite_layer))
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{Layer2Data, Level, LevelBytes, LevelParseError, LevelTables};
+    use crate::{
+        disassembler::RomDisassembly,
+        snes_utils::addr::{AddrPc, AddrSnes},
+        Rom,
+    };
+
+    const ROUND_TRIP_ROM_SIZE: usize = 0x68000;
+
+    fn disassembly_of(bytes: Vec<u8>) -> RomDisassembly {
+        RomDisassembly::unanalysed(Rom::new(bytes).unwrap())
+    }
+
+    /// Writes the level's data at the given addresses and points the level's pointer table entries to them. Layer1
+    /// and sprite data are placed in banks $06 and $07, and backgrounds in bank $0C.
+    fn write_level(
+        rom: &mut [u8], tables: &LevelTables, level_num: usize, level: &LevelBytes, layer2_is_background: bool,
+        offset: u32,
+    ) {
+        let mut write = |addr: AddrSnes, bytes: &[u8]| {
+            let pc = AddrPc::try_from(addr).unwrap().as_index();
+            rom[pc..pc + bytes.len()].copy_from_slice(bytes);
+        };
+
+        let layer1_addr = AddrSnes(0x068000 + offset);
+        write(layer1_addr, &level.layer1);
+
+        let (layer2_addr, layer2_ptr) = if layer2_is_background {
+            let addr = AddrSnes(0x0C8000 + offset);
+            (addr, addr.with_bank(0xFF))
+        } else {
+            let addr = AddrSnes(0x06C000 + offset);
+            (addr, addr)
+        };
+        write(layer2_addr, &level.layer2);
+
+        let sprites_addr = AddrSnes(0x07C000 + offset);
+        write(sprites_addr, &level.sprites);
+
+        for (i, &byte) in level.secondary_header.iter().enumerate() {
+            write(tables.secondary_header_table(i) + level_num as u32, &[byte]);
+        }
+
+        tables.layer1_pointer_table().write(rom, level_num, layer1_addr).unwrap();
+        tables.layer2_pointer_table().write(rom, level_num, layer2_ptr).unwrap();
+        tables.sprite_pointer_table().write(rom, level_num, sprites_addr).unwrap();
+    }
+
+    #[test]
+    fn test_level_round_trip() {
+        #[rustfmt::skip]
+        let layer1 = vec![
+            0x12, 0x34, 0x56, 0x78, 0x9A, // Primary header
+            0x85, 0x13, 0x04,             // Standard object
+            0x02, 0x01, 0x00, 0x25,       // Exit
+            0x03, 0x00, 0x01,             // Screen jump
+            0x00, 0x00, 0x10,             // Other extended object
+            0xFF,
+        ];
+        let objects = LevelBytes {
+            secondary_header: [0x11, 0x22, 0x33, 0x44],
+            layer1,
+            layer2: vec![0x21, 0x43, 0x65, 0x87, 0xA9, 0x20, 0x40, 0x07, 0xFF],
+            sprites: vec![0x80, 0x50, 0x32, 0x74, 0xAB, 0xF1, 0x0D, 0xFF],
+        };
+        let background = LevelBytes {
+            secondary_header: [0x55, 0x66, 0x77, 0x88],
+            layer1:           vec![0, 0, 0, 0, 0, 0xFF],
+            layer2:           vec![0x02, 0x01, 0x02, 0x03, 0xC0, 0x09, 0xFF, 0xFF],
+            sprites:          vec![0x00, 0xFF],
+        };
+
+        let tables = LevelTables::default();
+        let mut bytes = vec![0; ROUND_TRIP_ROM_SIZE];
+        write_level(&mut bytes, &tables, 0, &objects, false, 0);
+        write_level(&mut bytes, &tables, 1, &background, true, 0x100);
+        let mut disasm = disassembly_of(bytes);
+        let levels = [Level::parse(&mut disasm, &tables, 0).unwrap(), Level::parse(&mut disasm, &tables, 1).unwrap()];
+        assert!(matches!(levels[0].layer2, Layer2Data::Objects { .. }));
+        assert!(matches!(levels[1].layer2, Layer2Data::Background(_)));
+
+        let serialized = levels.clone().map(|level| level.serialize());
+        assert_eq!(serialized[0], objects);
+        assert_eq!(serialized[1].layer1, background.layer1);
+        assert_eq!(serialized[1].sprites, background.sprites);
+
+        // Write the levels somewhere else to make sure nothing is read from where they were originally.
+        let mut bytes = vec![0; ROUND_TRIP_ROM_SIZE];
+        write_level(&mut bytes, &tables, 0, &serialized[0], false, 0x1000);
+        write_level(&mut bytes, &tables, 1, &serialized[1], true, 0x1100);
+        let mut disasm = disassembly_of(bytes);
+        assert_eq!(Level::parse(&mut disasm, &tables, 0).unwrap(), levels[0]);
+        assert_eq!(Level::parse(&mut disasm, &tables, 1).unwrap(), levels[1]);
+    }
+
+    #[test]
+    fn test_parse_level_from_moved_tables() {
+        let mut bytes = vec![0; 0x80000];
+        let moved = LevelTables {
+            level_count:       0x300,
+            layer1_pointers:   AddrSnes(0x088000),
+            layer2_pointers:   AddrSnes(0x089000),
+            sprite_pointers:   AddrSnes(0x08A000),
+            secondary_headers: AddrSnes(0x08B000),
+        };
+        let level = LevelBytes {
+            secondary_header: [0x11, 0x22, 0x33, 0x44],
+            layer1:           vec![0x12, 0x34, 0x56, 0x78, 0x9A, 0x85, 0x13, 0x04, 0xFF],
+            layer2:           vec![0, 0, 0, 0, 0, 0xFF],
+            sprites:          vec![0x80, 0x50, 0x32, 0x74, 0xFF],
+        };
+        write_level(&mut bytes, &moved, 0x2A5, &level, false, 0);
+
+        let mut disasm = disassembly_of(bytes);
+        assert_eq!(Level::parse(&mut disasm, &moved, 0x2A5).unwrap().serialize(), level);
+        assert!(Level::parse(&mut disasm, &LevelTables::default(), 0x2A5).is_err());
+    }
+
+    #[test]
+    fn test_parse_sprite_layer() {
+        let mut bytes = vec![0; 0x40000];
+        // Sprite pointer of level 105 at $05EC00 + 2 * $105, pointing to $07C000.
+        bytes[0x2EC00 + 2 * 0x105..][..2].copy_from_slice(&[0x00, 0xC0]);
+        bytes[0x3C000..][..8].copy_from_slice(&[0x00, 0x50, 0x32, 0x74, 0xAB, 0xF1, 0x0D, 0xFF]);
+
+        let mut disasm = disassembly_of(bytes);
+        let (_, sprite_layer) = Level::parse_sh_and_sl(&mut disasm, &LevelTables::default(), 0x105).unwrap();
+
+        let sprites = sprite_layer.sprites();
+        assert_eq!(sprites.len(), 2);
+        assert_eq!(sprites[0].sprite_id(), 0x74);
+        assert_eq!(sprites[0].xy_pos(), (0x3, 0x05));
+        assert_eq!(sprites[0].screen_number(), 0x02);
+        assert_eq!(sprites[0].extra_bits(), 0);
+        assert_eq!(sprites[1].sprite_id(), 0x0D);
+        assert_eq!(sprites[1].xy_pos(), (0xF, 0x1A));
+        assert_eq!(sprites[1].screen_number(), 0x11);
+        assert_eq!(sprites[1].extra_bits(), 0b10);
+    }
+
+    #[test]
+    fn test_truncated_layer2_background() {
+        let mut bytes = vec![0; 0x60400];
+        // Layer2 pointer of level 0 at $05E600, pointing to a background at $0C83FC.
+        bytes[0x2E600..][..3].copy_from_slice(&[0xFC, 0x83, 0xFF]);
+        // Direct copy of 128 bytes, cut off by the end of the ROM.
+        bytes[0x603FC..].copy_from_slice(&[0x7F, 0x01, 0x02, 0x03]);
+
+        let mut disasm = disassembly_of(bytes);
+        let result = Level::parse_l2(&mut disasm, &LevelTables::default(), 0);
+        assert!(matches!(result, Err(LevelParseError::Layer2BackgroundRead(_))));
+    }
+}
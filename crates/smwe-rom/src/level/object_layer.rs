@@ -14,32 +14,32 @@ pub const EXIT_INSTANCE_SIZE: usize = 4;
 pub type StandardObjectID = u8;
 pub type ExtendedObjectID = u8;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StandardObject([u8; NON_EXIT_INSTANCE_SIZE]);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExitObject([u8; EXIT_INSTANCE_SIZE]);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ScreenJumpObject([u8; NON_EXIT_INSTANCE_SIZE]);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExtendedOtherObject([u8; NON_EXIT_INSTANCE_SIZE]);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ExtendedInstance {
     Exit(ExitObject),
     ScreenJump(ScreenJumpObject),
     Other(ExtendedOtherObject),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ObjectInstance {
     Standard(StandardObject),
     Extended(ExtendedInstance),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ObjectLayer {
     _objects: Vec<ObjectInstance>,
 }
@@ -57,6 +57,12 @@ impl ExitObject {
         (self.0[1] & 0b10) != 0
     }
 
+    pub fn water(&self) -> bool {
+        // -------- ----w--- -------- --------
+        // water = w
+        (self.0[1] & 0b1000) != 0
+    }
+
     pub fn destination_level(&self) -> u16 {
         // -------- -------D -------- dddddddd
         // destination_level = Ddddddddd
@@ -121,9 +127,22 @@ impl ObjectInstance {
             ObjectInstance::Extended(_) => true,
         }
     }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            ObjectInstance::Standard(StandardObject(bytes)) => bytes,
+            ObjectInstance::Extended(ExtendedInstance::Exit(ExitObject(bytes))) => bytes,
+            ObjectInstance::Extended(ExtendedInstance::ScreenJump(ScreenJumpObject(bytes))) => bytes,
+            ObjectInstance::Extended(ExtendedInstance::Other(ExtendedOtherObject(bytes))) => bytes,
+        }
+    }
 }
 
 impl ObjectLayer {
+    pub fn objects(&self) -> &[ObjectInstance] {
+        &self._objects
+    }
+
     fn parse_object(input: &[u8]) -> IResult<&[u8], ObjectInstance> {
         let (input, first_three) = take(3usize)(input)?;
         if first_three[0] & 0b01100000 == 0 && first_three[1] & 0b11110000 == 0 {
@@ -156,4 +175,14 @@ impl ObjectLayer {
         let bytes_consumed = input.len() - rest.len();
         Ok((rest, (Self { _objects: objects }, bytes_consumed)))
     }
+
+    /// Returns the object data as stored in ROM, terminated with 0xFF.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self._objects.len() * NON_EXIT_INSTANCE_SIZE + 1);
+        for object in self._objects.iter() {
+            bytes.extend_from_slice(object.as_bytes());
+        }
+        bytes.push(0xFF);
+        bytes
+    }
 }
@@ -0,0 +1,78 @@
+use crate::level::object_layer::{ExtendedInstance, ObjectInstance, ObjectLayer};
+
+// -------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ScreenExit {
+    pub screen:      u8,
+    /// Destination level number, or secondary entrance number if `secondary` is set.
+    pub destination: u16,
+    pub secondary:   bool,
+    /// Whether the destination of a secondary exit is a water level.
+    pub water:       bool,
+}
+
+/// Exits taken by pipes, doors and similar when entered on a given screen of a level. At most one exit is used per
+/// screen: if there are several, the last one in object data takes precedence.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScreenExits(pub Vec<ScreenExit>);
+
+// -------------------------------------------------------------------------------------------------
+
+impl ScreenExits {
+    /// Collects the screen exit objects from a level's Layer1 data.
+    pub fn from_object_layer(layer: &ObjectLayer) -> Self {
+        let exits = layer
+            .objects()
+            .iter()
+            .filter_map(|object| match object {
+                ObjectInstance::Extended(ExtendedInstance::Exit(exit)) => Some(ScreenExit {
+                    screen:      exit.screen_number(),
+                    destination: exit.destination_level(),
+                    secondary:   exit.secondary_exit(),
+                    water:       exit.water(),
+                }),
+                _ => None,
+            })
+            .collect();
+        Self(exits)
+    }
+
+    /// Returns the exit used on `screen`, if any.
+    pub fn get(&self, screen: u8) -> Option<&ScreenExit> {
+        self.0.iter().rev().find(|exit| exit.screen == screen)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{ScreenExit, ScreenExits};
+    use crate::level::object_layer::ObjectLayer;
+
+    #[test]
+    fn test_screen_exits() {
+        #[rustfmt::skip]
+        let layer1 = [
+            0x02, 0x00, 0x00, 0x25,       // Exit on screen 2 to level 25
+            0x85, 0x13, 0x04,             // Standard object
+            0x05, 0x0B, 0x00, 0x10,       // Secondary exit on screen 5 to water entrance 110
+            0x02, 0x01, 0x00, 0x04,       // Exit on screen 2 to level 104, overriding the first one
+            0xFF,
+        ];
+        let (_, (layer, _)) = ObjectLayer::parse(&layer1).unwrap();
+        let exits = ScreenExits::from_object_layer(&layer);
+
+        assert_eq!(exits.0.len(), 3);
+        assert_eq!(exits.0[0], ScreenExit {
+            screen:      2,
+            destination: 0x025,
+            secondary:   false,
+            water:       false,
+        });
+        assert_eq!(exits.get(5), Some(&ScreenExit { screen: 5, destination: 0x110, secondary: true, water: true }));
+        assert_eq!(exits.get(2), Some(&ScreenExit { screen: 2, destination: 0x104, secondary: false, water: false }));
+        assert_eq!(exits.get(3), None);
+    }
+}
@@ -10,12 +10,12 @@ pub const SPRITE_INSTANCE_SIZE: usize = 3;
 
 pub type SpriteID = u8;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SpriteInstance([u8; SPRITE_INSTANCE_SIZE]);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SpriteLayer {
-    _sprites: Vec<SpriteInstance>,
+    sprites: Vec<SpriteInstance>,
 }
 
 impl SpriteInstance {
@@ -53,12 +53,26 @@ impl SpriteInstance {
 }
 
 impl SpriteLayer {
+    pub fn sprites(&self) -> &[SpriteInstance] {
+        &self.sprites
+    }
+
     /// Returns self and the number of bytes consumed by parsing.
     pub fn parse(input: &[u8]) -> IResult<&[u8], (Self, usize)> {
         let mut read_sprite_layer = many_till(take(SPRITE_INSTANCE_SIZE), tag(&[0xFFu8]));
         let (rest, (sprites_raw, _)) = read_sprite_layer(input)?;
         let sprites = sprites_raw.into_iter().map(|spr| SpriteInstance(spr.try_into().unwrap())).collect();
         let bytes_consumed = input.len() - rest.len();
-        Ok((rest, (Self { _sprites: sprites }, bytes_consumed)))
+        Ok((rest, (Self { sprites }, bytes_consumed)))
+    }
+
+    /// Returns the sprite data as stored in ROM, terminated with 0xFF.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.sprites.len() * SPRITE_INSTANCE_SIZE + 1);
+        for sprite in self.sprites.iter() {
+            bytes.extend_from_slice(&sprite.0);
+        }
+        bytes.push(0xFF);
+        bytes
     }
 }
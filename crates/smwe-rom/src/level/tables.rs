@@ -0,0 +1,53 @@
+use crate::{
+    level::LEVEL_COUNT,
+    snes_utils::{addr::AddrSnes, pointer_table::PointerTable},
+};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Locations of the tables indexed by level number, which hacks may move to make room for more levels.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LevelTables {
+    pub level_count:       usize,
+    /// 24-bit pointers to each level's primary header, which is followed by Layer1 data.
+    pub layer1_pointers:   AddrSnes,
+    /// 24-bit pointers to each level's Layer2 data.
+    pub layer2_pointers:   AddrSnes,
+    /// 16-bit pointers to each level's sprite data in bank $07.
+    pub sprite_pointers:   AddrSnes,
+    /// Start of four consecutive tables of `level_count` bytes, holding one byte of each level's secondary header.
+    pub secondary_headers: AddrSnes,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl Default for LevelTables {
+    fn default() -> Self {
+        Self {
+            level_count:       LEVEL_COUNT,
+            layer1_pointers:   AddrSnes(0x05E000),
+            layer2_pointers:   AddrSnes(0x05E600),
+            sprite_pointers:   AddrSnes(0x05EC00),
+            secondary_headers: AddrSnes(0x05F000),
+        }
+    }
+}
+
+impl LevelTables {
+    pub fn layer1_pointer_table(&self) -> PointerTable {
+        PointerTable::long(self.layer1_pointers, self.level_count)
+    }
+
+    pub fn layer2_pointer_table(&self) -> PointerTable {
+        PointerTable::long(self.layer2_pointers, self.level_count)
+    }
+
+    pub fn sprite_pointer_table(&self) -> PointerTable {
+        PointerTable::short(self.sprite_pointers, self.level_count, 0x07)
+    }
+
+    /// Address of the `index`-th secondary header byte table.
+    pub fn secondary_header_table(&self, index: usize) -> AddrSnes {
+        self.secondary_headers + (index * self.level_count) as u32
+    }
+}
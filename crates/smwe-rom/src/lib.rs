@@ -1,11 +1,13 @@
 #![allow(clippy::identity_op)]
 
+pub mod assembler;
 pub mod compression;
 pub mod disassembler;
 pub mod graphics;
 pub mod internal_header;
 pub mod level;
 pub mod objects;
+pub mod prelude;
 pub mod snes_utils;
 
 use std::{fs, path::Path};
@@ -13,6 +15,7 @@ use std::{fs, path::Path};
 use crate::{
     disassembler::{
         binary_block::{DataBlock, DataKind},
+        ParseWarning,
         RomDisassembly,
     },
     graphics::Gfx,
@@ -20,7 +23,7 @@ use crate::{
     level::{
         secondary_entrance::{SecondaryEntrance, SECONDARY_ENTRANCE_TABLE},
         Level,
-        LEVEL_COUNT,
+        LevelTables,
     },
     objects::tilesets::Tilesets,
     snes_utils::{
@@ -36,10 +39,13 @@ use crate::{
 pub struct SmwRom {
     pub disassembly:         RomDisassembly,
     pub internal_header:     RomInternalHeader,
+    pub level_tables:        LevelTables,
     pub levels:              Vec<Level>,
     pub secondary_entrances: Vec<SecondaryEntrance>,
     pub gfx:                 Gfx,
     pub map16_tilesets:      Tilesets,
+    /// Problems found while parsing that didn't stop it, in the order they were found.
+    pub warnings:            Vec<ParseWarning>,
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -60,11 +66,17 @@ impl SmwRom {
     }
 
     pub fn from_rom(rom: Rom) -> anyhow::Result<Self> {
+        Self::from_rom_with_level_tables(rom, LevelTables::default())
+    }
+
+    /// Parses the ROM like [`from_rom`](Self::from_rom), but reads levels from tables at the given locations, e.g.
+    /// ones moved by a hack to make room for more levels.
+    pub fn from_rom_with_level_tables(rom: Rom, level_tables: LevelTables) -> anyhow::Result<Self> {
         log::info!("Parsing internal ROM header");
         let internal_header = RomInternalHeader::parse(&rom)?;
 
         log::info!("Creating disassembly map");
-        let mut disassembly = RomDisassembly::new(rom, &internal_header);
+        let mut disassembly = RomDisassembly::try_new(rom, &internal_header)?;
 
         // Mark IRH
         disassembly.rom_slice_at_block(
@@ -76,7 +88,7 @@ impl SmwRom {
         )?;
 
         log::info!("Parsing level data");
-        let levels = Self::parse_levels(&mut disassembly)?;
+        let levels = Self::parse_levels(&mut disassembly, &level_tables)?;
 
         log::info!("Parsing secondary entrances");
         let secondary_entrances = Self::parse_secondary_entrances(&mut disassembly)?;
@@ -87,13 +99,27 @@ impl SmwRom {
         log::info!("Parsing Map16 tilesets");
         let map16_tilesets = Tilesets::parse(&mut disassembly)?;
 
-        Ok(Self { disassembly, internal_header, levels, secondary_entrances, gfx, map16_tilesets })
+        let warnings = disassembly.warnings.clone();
+        if !warnings.is_empty() {
+            log::info!("{} warnings during load", warnings.len());
+        }
+
+        Ok(Self {
+            disassembly,
+            internal_header,
+            level_tables,
+            levels,
+            secondary_entrances,
+            gfx,
+            map16_tilesets,
+            warnings,
+        })
     }
 
-    fn parse_levels(disasm: &mut RomDisassembly) -> anyhow::Result<Vec<Level>> {
-        let mut levels = Vec::with_capacity(LEVEL_COUNT);
-        for level_num in 0..LEVEL_COUNT as u32 {
-            let level = Level::parse(disasm, level_num)?;
+    fn parse_levels(disasm: &mut RomDisassembly, tables: &LevelTables) -> anyhow::Result<Vec<Level>> {
+        let mut levels = Vec::with_capacity(tables.level_count);
+        for level_num in 0..tables.level_count as u32 {
+            let level = Level::parse(disasm, tables, level_num)?;
             levels.push(level);
         }
         Ok(levels)
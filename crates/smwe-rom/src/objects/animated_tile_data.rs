@@ -6,6 +6,7 @@ use crate::{
         binary_block::{DataBlock, DataKind},
         RomDisassembly,
     },
+    graphics::{gfx_file::GfxFile, tile_from_wram, LevelPreviewState},
     objects::map16::{Block, Tile8x8},
     snes_utils::{
         addr::{AddrSnes, AddrVram},
@@ -25,8 +26,20 @@ const ANIM_SRC_ADDRESSES_TABLE: SnesSlice = SnesSlice::new(AddrSnes(0x05B999), 4
 const ANIM_DST_ADDRESSES_TABLE: SnesSlice = SnesSlice::new(AddrSnes(0x05B93B), 48);
 const ANIM_BEHAVIOUR_TABLE: SnesSlice = SnesSlice::new(AddrSnes(0x05B96B), 46);
 
+/// Source of all frames of animations that show a blank tile.
+pub const BLANK_ANIMATION_FRAMES: [AddrSnes; 4] =
+    [AddrSnes(0x7EAC20), AddrSnes(0x7EAC20), AddrSnes(0x7EAC20), AddrSnes(0x7EAC20)];
+/// Number of 8x8 tiles uploaded to VRAM for each animation destination.
+pub const TILES_PER_ANIMATION: usize = 4;
+
 // -------------------------------------------------------------------------------------------------
 
+/// Frame of tile animations, which the game advances every 8 frames.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct AnimationState {
+    pub frame: u8,
+}
+
 #[derive(Debug)]
 pub struct AnimatedTileData {
     pub src_addresses: Vec<AddrSnes>,
@@ -36,6 +49,16 @@ pub struct AnimatedTileData {
     pub tilesets:      Vec<u8>,
 }
 
+impl AnimationState {
+    /// Number of frames of each animation, after which they start over.
+    pub const N_FRAMES: u8 = 4;
+
+    /// Advances to the next frame, wrapping around after the last one.
+    pub fn step(&mut self) {
+        self.frame = (self.frame + 1) % Self::N_FRAMES;
+    }
+}
+
 impl AnimatedTileData {
     pub fn parse(disasm: &mut RomDisassembly) -> anyhow::Result<Self> {
         let src_addresses = {
@@ -63,37 +86,171 @@ impl AnimatedTileData {
     }
 
     pub fn get_animation_frames_for_block(
-        &self, block: &Block, tileset: usize, blue_pswitch: bool, silver_pswitch: bool, on_off_switch: bool,
-        offset: u16,
+        &self, block: &Block, tileset: usize, state: &LevelPreviewState, offset: u16,
+    ) -> Option<[AddrSnes; 4]> {
+        let vram_addr = block.upper_left.tile_vram_addr(offset);
+        let dst_index = self.dst_addresses.iter().position(|&addr| addr == vram_addr)?;
+        self.get_animation_frames_for_destination(dst_index, tileset, state)
+    }
+
+    /// Returns the WRAM addresses of the graphics uploaded to `dst_addresses[dst_index]` in each animation frame.
+    pub fn get_animation_frames_for_destination(
+        &self, dst_index: usize, tileset: usize, state: &LevelPreviewState,
     ) -> Option<[AddrSnes; 4]> {
-        self.is_tile_animated(block.upper_left, offset).then(|| {
-            let vram_addr = block.upper_left.tile_vram_addr(offset);
-            let dst_index = self.dst_addresses.iter().position(|&addr| addr == vram_addr).unwrap();
-            let gfx_tile_offset = match self.behaviours[dst_index] {
-                0 => dst_index,
-                1 => {
-                    let switch_state = match self.switches[dst_index] {
-                        0 => blue_pswitch,
-                        1 => silver_pswitch,
-                        2 => on_off_switch,
-                        _ => unreachable!(),
-                    };
-                    if switch_state {
-                        dst_index + 0x26
-                    } else {
-                        dst_index
-                    }
+        let gfx_tile_offset = match self.behaviours.get(dst_index)? {
+            0 => dst_index,
+            1 => {
+                let switch_state = match self.switches.get(dst_index)? {
+                    0 => state.blue_pswitch,
+                    1 => state.silver_pswitch,
+                    2 => state.on_off,
+                    _ => return None,
+                };
+                if switch_state {
+                    dst_index + 0x26
+                } else {
+                    dst_index
                 }
-                2 => dst_index + self.tilesets[tileset] as usize,
-                _ => unreachable!(),
-            };
-            let src_index = ((gfx_tile_offset & 0xFF) << 3) / 2;
-            [
-                self.src_addresses[src_index + 0],
-                self.src_addresses[src_index + 1],
-                self.src_addresses[src_index + 2],
-                self.src_addresses[src_index + 3],
-            ]
-        })
+            }
+            2 => dst_index + *self.tilesets.get(tileset)? as usize,
+            _ => return None,
+        };
+        let src_index = ((gfx_tile_offset & 0xFF) << 3) / 2;
+        let frames = self.src_addresses.get(src_index..src_index + 4)?;
+        Some([frames[0], frames[1], frames[2], frames[3]])
+    }
+
+    /// Returns the VRAM destination of each animation together with the WRAM address of the graphics copied there in
+    /// the current frame. Animations whose source can't be determined are left out.
+    pub fn current_frame_sources(&self, tileset: usize, state: &LevelPreviewState) -> Vec<(AddrVram, AddrSnes)> {
+        self.dst_addresses
+            .iter()
+            .enumerate()
+            .filter_map(|(dst_index, &dst_addr)| {
+                let frames = self.get_animation_frames_for_destination(dst_index, tileset, state)?;
+                Some((dst_addr, frames[state.animation.frame as usize % frames.len()]))
+            })
+            .collect()
+    }
+
+    /// Copies the graphics of each animation's current frame to its destination in `vram`, a buffer of raw VRAM
+    /// bytes, as the game does when animating tiles.
+    ///
+    /// Animations showing a blank tile, ones whose graphics aren't loaded from GFX files, and ones whose destination
+    /// lies outside of `vram` are skipped.
+    pub fn apply_animation_frame(
+        &self, vram: &mut [u8], gfx_files: &[GfxFile], tileset: usize, state: &LevelPreviewState,
+    ) {
+        let blank_src = BLANK_ANIMATION_FRAMES[0];
+        for (dst_addr, src_addr) in self.current_frame_sources(tileset, state) {
+            if src_addr == blank_src {
+                continue;
+            }
+
+            let vram_start = dst_addr.0 as usize * 2;
+            for tile_num in 0..TILES_PER_ANIMATION as u32 {
+                let Ok(tile) = tile_from_wram(gfx_files, src_addr + tile_num * 32) else { break };
+                let bytes = tile.to_4bpp();
+                let tile_start = vram_start + tile_num as usize * bytes.len();
+                if let Some(dst) = vram.get_mut(tile_start..tile_start + bytes.len()) {
+                    dst.copy_from_slice(&bytes);
+                }
+            }
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::gfx_file::{Tile, TileFormat, GFX_FILES_META, N_PIXELS_IN_TILE};
+
+    #[test]
+    fn test_apply_animation_frame() {
+        // GFX33 is loaded to $7E7D00, with one tile every 32 bytes.
+        let tiles = (0..16).map(|i| Tile { color_indices: [i as u8; N_PIXELS_IN_TILE].into() }).collect();
+        let mut gfx_files: Vec<GfxFile> = (0..GFX_FILES_META.len())
+            .map(|_| GfxFile { tile_format: TileFormat::Tile4bpp, tiles: Vec::new() })
+            .collect();
+        gfx_files[0x33].tiles = tiles;
+
+        let frame_addr = |tile_num: u32| AddrSnes(0x7E7D00 + tile_num * 32);
+        let anim = AnimatedTileData {
+            src_addresses: vec![frame_addr(0), frame_addr(4), frame_addr(8), frame_addr(12)],
+            dst_addresses: vec![AddrVram(0x0100)],
+            behaviours:    vec![0],
+            switches:      vec![0],
+            tilesets:      vec![0],
+        };
+
+        let mut vram = vec![0xAA; 0x400];
+        let state = LevelPreviewState { animation: AnimationState { frame: 2 }, ..LevelPreviewState::default() };
+        anim.apply_animation_frame(&mut vram, &gfx_files, 0, &state);
+
+        // Frame 2 shows tiles 8 to 11, with every pixel using the tile's number as its colour index.
+        let written = &vram[0x200..0x280];
+        for (tile_num, tile_bytes) in (8..12).zip(written.chunks(32)) {
+            let expected = gfx_files[0x33].tiles[tile_num].to_4bpp();
+            assert_eq!(tile_bytes, &expected[..]);
+        }
+        // Colour 10 is %1010, so only the odd bitplanes of tile 10 are set.
+        assert_eq!(&written[0x40..0x60], &[0x00, 0xFF].repeat(16)[..]);
+        assert!(vram[..0x200].iter().chain(&vram[0x280..]).all(|&b| b == 0xAA));
+    }
+
+    #[test]
+    fn test_switch_state_selects_frames() {
+        let anim = AnimatedTileData {
+            src_addresses: (0..0xB0).map(|i| AddrSnes(0x7E7D00 + i * 0x80)).collect(),
+            dst_addresses: vec![AddrVram(0x0100), AddrVram(0x0140), AddrVram(0x0180)],
+            behaviours:    vec![0, 1, 1],
+            switches:      vec![0, 0, 2],
+            tilesets:      vec![0],
+        };
+        let frames = |dst_index, state| anim.get_animation_frames_for_destination(dst_index, 0, &state).unwrap();
+        let src = |first: u32| [0, 1, 2, 3].map(|i| AddrSnes(0x7E7D00 + (first + i) * 0x80));
+
+        let released = LevelPreviewState::default();
+        let blue_pswitch = LevelPreviewState { blue_pswitch: true, ..released };
+        let on_off = LevelPreviewState { on_off: true, ..released };
+
+        assert_eq!(frames(0, released), src(0));
+        assert_eq!(frames(0, blue_pswitch), src(0), "not affected by switches");
+        assert_eq!(frames(1, released), src(4));
+        assert_eq!(frames(1, blue_pswitch), src((1 + 0x26) * 4));
+        assert_eq!(frames(1, on_off), src(4), "affected by a different switch");
+        assert_eq!(frames(2, released), src(8));
+        assert_eq!(frames(2, on_off), src((2 + 0x26) * 4));
+    }
+
+    #[test]
+    fn test_step_animation_frames() {
+        let anim = AnimatedTileData {
+            src_addresses: (0..0xB0).map(|i| AddrSnes(0x7E7D00 + i * 0x80)).collect(),
+            dst_addresses: vec![AddrVram(0x0100), AddrVram(0x0140)],
+            behaviours:    vec![0, 1],
+            switches:      vec![0, 0],
+            tilesets:      vec![0],
+        };
+        let src = |first: u32| AddrSnes(0x7E7D00 + first * 0x80);
+
+        let mut state = LevelPreviewState::default();
+        let mut sources = Vec::new();
+        for _ in 0..6 {
+            sources.push(anim.current_frame_sources(0, &state));
+            state.animation.step();
+        }
+        assert_eq!(state.animation.frame, 2, "wraps around after 4 frames");
+        assert_eq!(sources[0], [(AddrVram(0x0100), src(0)), (AddrVram(0x0140), src(4))]);
+        assert_eq!(sources[3], [(AddrVram(0x0100), src(3)), (AddrVram(0x0140), src(7))]);
+        assert_eq!(sources[4], sources[0]);
+        assert_eq!(sources[5], sources[1]);
+
+        state.blue_pswitch = true;
+        state.animation = AnimationState { frame: 1 };
+        let switched = anim.current_frame_sources(0, &state);
+        assert_eq!(switched, [(AddrVram(0x0100), src(1)), (AddrVram(0x0140), src((1 + 0x26) * 4 + 1))]);
     }
 }
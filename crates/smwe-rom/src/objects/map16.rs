@@ -1,4 +1,20 @@
-use crate::{graphics::gfx_file::TileFormat, snes_utils::addr::AddrVram};
+use epaint::Rgba;
+use smwe_render::color::Abgr1555;
+
+use crate::{
+    graphics::{
+        gfx_file::{Tile, TileFormat, N_PIXELS_IN_TILE},
+        BlockGfx,
+        Gfx,
+        LevelPreviewState,
+    },
+    snes_utils::addr::AddrVram,
+};
+
+/// Number of colours in a palette row selected by the palette bits of a [`Tile8x8`].
+pub const COLORS_IN_PALETTE_ROW: usize = 16;
+/// Number of pixels in a rendered [`Block`].
+pub const N_PIXELS_IN_BLOCK: usize = 4 * N_PIXELS_IN_TILE;
 
 // Format: YXPCCCTT TTTTTTTT
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -47,6 +63,30 @@ impl Tile8x8 {
         ((self.0 >> 10) & 0b111) as u8
     }
 
+    pub fn set_tile_number(&mut self, tile_number: u16) {
+        self.0 = (self.0 & !0x3FF) | (tile_number & 0x3FF);
+    }
+
+    pub fn set_flip_y(&mut self, flip_y: bool) {
+        self.set_flag(15, flip_y);
+    }
+
+    pub fn set_flip_x(&mut self, flip_x: bool) {
+        self.set_flag(14, flip_x);
+    }
+
+    pub fn set_priority(&mut self, priority: bool) {
+        self.set_flag(13, priority);
+    }
+
+    pub fn set_palette(&mut self, palette: u8) {
+        self.0 = (self.0 & !(0b111 << 10)) | (((palette & 0b111) as u16) << 10);
+    }
+
+    fn set_flag(&mut self, bit: u16, value: bool) {
+        self.0 = (self.0 & !(1 << bit)) | ((value as u16) << bit);
+    }
+
     pub fn layer(&self) -> TileLayer {
         (self.tile_number() / 0x80) as TileLayer
     }
@@ -66,4 +106,131 @@ impl Block {
     ) -> Self {
         Self { upper_left, lower_left, upper_right, lower_right }
     }
+
+    /// Returns the block's 8x8 tiles in the same order as its fields.
+    pub fn tiles_mut(&mut self) -> [&mut Tile8x8; 4] {
+        [&mut self.upper_left, &mut self.lower_left, &mut self.upper_right, &mut self.lower_right]
+    }
+
+    /// Renders the block as a 16x16 image in row-major order, showing animated tiles as they look in the given state.
+    ///
+    /// `palette` holds all palette rows, of which each 8x8 tile uses the one selected by its palette bits.
+    pub fn render(
+        &self, gfx: &Gfx, palette: &[Abgr1555], tileset: usize, state: &LevelPreviewState, offset: u16,
+    ) -> [Rgba; N_PIXELS_IN_BLOCK] {
+        let tiles = match gfx.tiles_from_block(self, tileset, state, offset) {
+            BlockGfx::Static(tiles) => tiles,
+            BlockGfx::Animated(frames) => frames[state.animation.frame as usize % frames.len()],
+        };
+        self.render_tiles(tiles, palette)
+    }
+
+    /// Renders the block as a 16x16 image in row-major order from the graphics of its 8x8 tiles, given in the same
+    /// order as the block's fields, honouring each tile's flip and palette bits.
+    pub fn render_tiles(&self, tiles: [&Tile; 4], palette: &[Abgr1555]) -> [Rgba; N_PIXELS_IN_BLOCK] {
+        let mut pixels = [Rgba::TRANSPARENT; N_PIXELS_IN_BLOCK];
+        let placements =
+            [(self.upper_left, 0, 0), (self.lower_left, 0, 8), (self.upper_right, 8, 0), (self.lower_right, 8, 8)];
+
+        for ((tile8x8, left, top), tile) in placements.into_iter().zip(tiles) {
+            let mut tile = tile.clone();
+            if tile8x8.flip_x() {
+                tile = tile.flipped_x();
+            }
+            if tile8x8.flip_y() {
+                tile = tile.flipped_y();
+            }
+            let row_start = tile8x8.palette() as usize * COLORS_IN_PALETTE_ROW;
+            let palette_row = palette.get(row_start..).unwrap_or_default();
+            for (i, color) in tile.to_rgba(palette_row).iter().enumerate() {
+                let (x, y) = (left + i % 8, top + i / 8);
+                pixels[y * 16 + x] = *color;
+            }
+        }
+
+        pixels
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+#[allow(clippy::unusual_byte_groupings)] // Digits are grouped by tile fields.
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile8x8_fields() {
+        let tile = Tile8x8(0b0_0_0_000_10_1010_1010);
+        assert_eq!(tile.tile_number(), 0x2AA);
+        assert_eq!(tile.palette(), 0);
+        assert!(!tile.priority());
+        assert!(!tile.flip_x());
+        assert!(!tile.flip_y());
+
+        let tile = Tile8x8(0b0_0_0_101_00_0000_0000);
+        assert_eq!(tile.tile_number(), 0);
+        assert_eq!(tile.palette(), 5);
+
+        let tile = Tile8x8(0b0_0_1_000_00_0000_0000);
+        assert!(tile.priority());
+        assert!(!tile.flip_x());
+        assert!(!tile.flip_y());
+
+        let tile = Tile8x8(0b0_1_0_000_00_0000_0000);
+        assert!(!tile.priority());
+        assert!(tile.flip_x());
+        assert!(!tile.flip_y());
+
+        let tile = Tile8x8(0b1_0_0_000_00_0000_0000);
+        assert!(!tile.flip_x());
+        assert!(tile.flip_y());
+
+        let tile = Tile8x8(0xFFFF);
+        assert_eq!(tile.tile_number(), 0x3FF);
+        assert_eq!(tile.palette(), 7);
+        assert!(tile.priority() && tile.flip_x() && tile.flip_y());
+    }
+
+    #[test]
+    fn test_tile8x8_setters() {
+        let mut tile = Tile8x8(0);
+        tile.set_tile_number(0x2AA);
+        tile.set_palette(5);
+        tile.set_flip_x(true);
+        assert_eq!(tile, Tile8x8(0b0_1_0_101_10_1010_1010));
+
+        tile.set_tile_number(0xFFFF);
+        tile.set_palette(0xFF);
+        tile.set_priority(true);
+        tile.set_flip_y(true);
+        tile.set_flip_x(false);
+        assert_eq!(tile, Tile8x8(0b1_0_1_111_11_1111_1111), "out of range values are truncated");
+    }
+
+    #[test]
+    fn test_render_block() {
+        // Colour index 1 in the top-left corner, 2 elsewhere.
+        let corner = Tile { color_indices: (0..N_PIXELS_IN_TILE).map(|i| if i == 0 { 1 } else { 2 }).collect() };
+        let palette: Vec<Abgr1555> = (0..8 * COLORS_IN_PALETTE_ROW as u16).map(Abgr1555).collect();
+        let color = |row: u16, index: u16| Rgba::from(Abgr1555(row * COLORS_IN_PALETTE_ROW as u16 + index));
+
+        let block = Block {
+            upper_left:  Tile8x8(0x0000),
+            lower_left:  Tile8x8(0x8000 | (3 << 10)),
+            upper_right: Tile8x8(0x4000 | (5 << 10)),
+            lower_right: Tile8x8(0xC000 | (7 << 10)),
+        };
+        let pixels = block.render_tiles([&corner; 4], &palette);
+
+        let pixel = |x: usize, y: usize| pixels[y * 16 + x];
+        assert_eq!(pixel(0, 0), color(0, 1));
+        assert_eq!(pixel(7, 7), color(0, 2));
+        assert_eq!(pixel(0, 15), color(3, 1), "vertically flipped");
+        assert_eq!(pixel(0, 8), color(3, 2));
+        assert_eq!(pixel(15, 0), color(5, 1), "horizontally flipped");
+        assert_eq!(pixel(8, 0), color(5, 2));
+        assert_eq!(pixel(15, 15), color(7, 1), "flipped both ways");
+        assert_eq!(pixel(8, 8), color(7, 2));
+    }
 }
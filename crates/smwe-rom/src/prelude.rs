@@ -0,0 +1,40 @@
+//! Commonly used types, re-exported for a single import.
+//!
+//! ```
+//! use smwe_rom::prelude::*;
+//!
+//! fn level_count(rom: &SmwRom) -> usize {
+//!     rom.levels.len()
+//! }
+//!
+//! fn code_chunk_count(disasm: &RomDisassembly) -> usize {
+//!     disasm.chunks.iter().filter(|(_, block)| matches!(block, BinaryBlock::Code(_))).count()
+//! }
+//!
+//! fn gfx_pixel_count(gfx: &GfxFile) -> usize {
+//!     gfx.n_pixels()
+//! }
+//!
+//! let addr = AddrSnes(0x018000);
+//! assert_eq!(AddrPc::try_from(addr).unwrap(), AddrPc(0x008000));
+//! assert_eq!(<AddrSnes as Addr>::MIN, AddrSnes(0x008000));
+//! assert_ne!(DataKind::GfxFile, DataKind::Tileset);
+//! assert_eq!(Rgba::from(Abgr1555::WHITE), Rgba::WHITE);
+//! assert!(Rom::new(vec![0; 0x8000]).is_ok());
+//! ```
+
+pub use epaint::Rgba;
+pub use smwe_render::color::Abgr1555;
+
+pub use crate::{
+    disassembler::{
+        binary_block::{BinaryBlock, DataKind},
+        RomDisassembly,
+    },
+    graphics::gfx_file::GfxFile,
+    snes_utils::{
+        addr::{Addr, AddrPc, AddrSnes},
+        rom::Rom,
+    },
+    SmwRom,
+};
@@ -3,6 +3,7 @@ use std::{convert::TryFrom, fmt, num::ParseIntError, ops::*};
 use duplicate::*;
 use num_traits::{cast::cast, *};
 use paste::*;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 // -------------------------------------------------------------------------------------------------
@@ -11,12 +12,20 @@ use thiserror::Error;
 pub enum AddressError {
     #[error("Invalid PC LoROM address {0:#x}")]
     InvalidPcLoRom(AddrPc),
-    #[error("Invalid PC LoROM address {0:#x}")]
+    #[error("Invalid PC HiROM address {0:#x}")]
     InvalidPcHiRom(AddrPc),
+    #[error("Invalid PC ExLoROM address {0:#x}")]
+    InvalidPcExLoRom(AddrPc),
+    #[error("Invalid PC ExHiROM address {0:#x}")]
+    InvalidPcExHiRom(AddrPc),
     #[error("Invalid SNES LoROM address {0:#x}")]
     InvalidSnesLoRom(AddrSnes),
-    #[error("Invalid SNES LoROM address {0:#x}")]
+    #[error("Invalid SNES HiROM address {0:#x}")]
     InvalidSnesHiRom(AddrSnes),
+    #[error("Invalid SNES ExLoROM address {0:#x}")]
+    InvalidSnesExLoRom(AddrSnes),
+    #[error("Invalid SNES ExHiROM address {0:#x}")]
+    InvalidSnesExHiRom(AddrSnes),
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -48,7 +57,7 @@ duplicate! {
         [AddrVram]  [u16]   ["VRAM ${:x}"]  ["VRAM ${:X}"];
     ]
 
-    #[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+    #[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Deserialize, Serialize)]
     pub struct addr_type(pub inner);
 
     impl addr_type {
@@ -374,6 +383,26 @@ impl AddrPc {
         }
     }
 
+    /// The first 4 MiB are mapped to banks $80-$FF, and the rest to banks $00-$7D.
+    pub fn try_from_exlorom(addr: AddrSnes) -> Result<Self, AddressError> {
+        if addr.is_valid_lorom() {
+            let offset = ((addr.0 & 0x7F0000) >> 1) | (addr.0 & 0x7FFF);
+            Ok(Self(if addr.0 & 0x800000 != 0 { offset } else { offset | 0x400000 }))
+        } else {
+            Err(AddressError::InvalidSnesExLoRom(addr))
+        }
+    }
+
+    /// The first 4 MiB are mapped to banks $C0-$FF, and the rest to banks $40-$7D.
+    pub fn try_from_exhirom(addr: AddrSnes) -> Result<Self, AddressError> {
+        if addr.is_valid_hirom() {
+            let offset = addr.0 & 0x3FFFFF;
+            Ok(Self(if addr.0 & 0x800000 != 0 { offset } else { offset | 0x400000 }))
+        } else {
+            Err(AddressError::InvalidSnesExHiRom(addr))
+        }
+    }
+
     pub fn is_valid_lorom(&self) -> bool {
         self.0 < 0x400000
     }
@@ -404,6 +433,24 @@ impl AddrSnes {
         }
     }
 
+    pub fn try_from_exlorom(addr: AddrPc) -> Result<Self, AddressError> {
+        let err = || AddressError::InvalidPcExLoRom(addr);
+        let snes = match addr.0 {
+            0x000000..=0x3FFFFF => Self::try_from_lorom(addr).map_err(|_| err())?.0 | 0x800000,
+            0x400000..=0x7FFFFF => Self::try_from_lorom(AddrPc(addr.0 - 0x400000)).map_err(|_| err())?.0,
+            _ => return Err(err()),
+        };
+        Some(Self(snes)).filter(Self::is_valid_lorom).ok_or_else(err)
+    }
+
+    pub fn try_from_exhirom(addr: AddrPc) -> Result<Self, AddressError> {
+        match addr.0 {
+            0x000000..=0x3FFFFF => Ok(Self(addr.0 | 0xC00000)),
+            0x400000..=0x7DFFFF => Ok(Self(addr.0)),
+            _ => Err(AddressError::InvalidPcExHiRom(addr)),
+        }
+    }
+
     pub fn is_valid_lorom(&self) -> bool {
         let wram = (self.0 & 0xFE0000) == 0x7E0000;
         let junk = (self.0 & 0x408000) == 0x000000;
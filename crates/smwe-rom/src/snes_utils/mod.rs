@@ -1,3 +1,4 @@
 pub mod addr;
+pub mod pointer_table;
 pub mod rom;
 pub mod rom_slice;
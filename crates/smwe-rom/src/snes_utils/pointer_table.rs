@@ -0,0 +1,146 @@
+use nom::{
+    combinator::map,
+    number::complete::{le_u16, le_u24},
+};
+
+use crate::snes_utils::{
+    addr::{AddrPc, AddrSnes},
+    rom::{Rom, RomError},
+    rom_slice::SnesSlice,
+};
+
+/// Table of 16-bit or 24-bit pointers, such as the ones indexed by level number.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PointerTable {
+    pub base:   AddrSnes,
+    /// Distance in bytes between the starts of consecutive pointers.
+    pub stride: usize,
+    pub count:  usize,
+    /// Whether the pointers are 24-bit rather than 16-bit.
+    pub long:   bool,
+    /// Bank of the addresses that 16-bit pointers point to.
+    pub bank:   u8,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl PointerTable {
+    /// Table of `count` consecutive 16-bit pointers to addresses in `bank`.
+    pub fn short(base: AddrSnes, count: usize, bank: u8) -> Self {
+        Self { base, stride: 2, count, long: false, bank }
+    }
+
+    /// Table of `count` consecutive 24-bit pointers.
+    pub fn long(base: AddrSnes, count: usize) -> Self {
+        Self { base, stride: 3, count, long: true, bank: 0 }
+    }
+
+    pub fn pointer_size(&self) -> usize {
+        if self.long {
+            3
+        } else {
+            2
+        }
+    }
+
+    /// Bytes taken by the whole table.
+    pub fn slice(&self) -> SnesSlice {
+        let size = match self.count {
+            0 => 0,
+            count => (count - 1) * self.stride + self.pointer_size(),
+        };
+        SnesSlice::new(self.base, size)
+    }
+
+    /// Bytes of the `index`-th pointer, or an error if the table doesn't have that many.
+    pub fn entry(&self, index: usize) -> Result<SnesSlice, RomError> {
+        let slice = SnesSlice::new(self.base + index * self.stride, self.pointer_size());
+        if index < self.count {
+            Ok(slice)
+        } else {
+            Err(RomError::SliceSnes(slice))
+        }
+    }
+
+    /// Reads the `index`-th pointer from the LoROM `rom`.
+    pub fn read(&self, rom: &Rom, index: usize) -> Result<AddrSnes, RomError> {
+        let view = rom.view().slice_lorom(self.entry(index)?)?;
+        if self.long {
+            view.parse(map(le_u24, AddrSnes))
+        } else {
+            view.parse(map(le_u16, |ptr| AddrSnes(ptr as u32).with_bank(self.bank)))
+        }
+    }
+
+    /// Writes `addr` as the `index`-th pointer to the LoROM image `rom_bytes`. If the pointers are 16-bit, `addr` needs
+    /// to be in the table's bank.
+    pub fn write(&self, rom_bytes: &mut [u8], index: usize, addr: AddrSnes) -> Result<(), RomError> {
+        if !self.long && addr.bank() != self.bank {
+            return Err(RomError::PointerOutsideBank(addr, self.bank));
+        }
+        let slice = self.entry(index)?;
+        let begin = AddrPc::try_from_lorom(slice.begin).map_err(|_| RomError::SliceSnes(slice))?.as_index();
+        let bytes = rom_bytes.get_mut(begin..begin + slice.size).ok_or(RomError::SliceSnes(slice))?;
+        bytes.copy_from_slice(&addr.0.to_le_bytes()[..slice.size]);
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom_with_table(table: &[u8]) -> Rom {
+        let mut bytes = vec![0; 0x10000];
+        bytes[0x100..0x100 + table.len()].copy_from_slice(table);
+        Rom::new(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_read_short_pointers() {
+        let rom = rom_with_table(&[0x00, 0x90, 0x34, 0xA2, 0x78, 0xB5]);
+        let table = PointerTable::short(AddrSnes(0x008100), 3, 0x07);
+        assert_eq!(table.read(&rom, 1).unwrap(), AddrSnes(0x07A234));
+        assert_eq!(table.read(&rom, 2).unwrap(), AddrSnes(0x07B578));
+        assert!(table.read(&rom, 3).is_err());
+        assert_eq!(table.slice(), SnesSlice::new(AddrSnes(0x008100), 6));
+    }
+
+    #[test]
+    fn test_read_long_pointers() {
+        let rom = rom_with_table(&[0x00, 0x90, 0x05, 0x34, 0xA2, 0x0C, 0x78, 0xB5, 0x7E]);
+        let table = PointerTable::long(AddrSnes(0x008100), 3);
+        assert_eq!(table.read(&rom, 0).unwrap(), AddrSnes(0x059000));
+        assert_eq!(table.read(&rom, 1).unwrap(), AddrSnes(0x0CA234));
+        assert!(table.read(&rom, 3).is_err());
+
+        // Every other pointer, e.g. when pointers are interleaved with other data.
+        let table = PointerTable { stride: 6, count: 2, ..table };
+        assert_eq!(table.read(&rom, 1).unwrap(), AddrSnes(0x7EB578));
+        assert_eq!(table.slice(), SnesSlice::new(AddrSnes(0x008100), 9));
+    }
+
+    #[test]
+    fn test_write_pointers() {
+        let mut bytes = vec![0; 0x10000];
+        let short = PointerTable::short(AddrSnes(0x018000), 2, 0x07);
+        short.write(&mut bytes, 1, AddrSnes(0x07ABCD)).unwrap();
+        assert_eq!(bytes[0x8002..0x8004], [0xCD, 0xAB]);
+        assert!(short.write(&mut bytes, 2, AddrSnes(0x07ABCD)).is_err());
+        assert!(matches!(
+            short.write(&mut bytes, 0, AddrSnes(0x08ABCD)),
+            Err(RomError::PointerOutsideBank(AddrSnes(0x08ABCD), 0x07))
+        ));
+        assert_eq!(bytes[0x8000..0x8002], [0x00, 0x00]);
+
+        let long = PointerTable::long(AddrSnes(0x018010), 2);
+        long.write(&mut bytes, 1, AddrSnes(0x0CA234)).unwrap();
+        assert_eq!(bytes[0x8013..0x8016], [0x34, 0xA2, 0x0C]);
+
+        let rom = Rom::new(bytes).unwrap();
+        assert_eq!(short.read(&rom, 1).unwrap(), AddrSnes(0x07ABCD));
+        assert_eq!(long.read(&rom, 1).unwrap(), AddrSnes(0x0CA234));
+    }
+}
@@ -5,7 +5,11 @@ use thiserror::Error;
 use crate::{
     compression::DecompressionError,
     disassembler::binary_block::DataBlock,
-    snes_utils::{addr::AddrPc, rom_slice::*},
+    internal_header::{offsets, RomInternalHeader},
+    snes_utils::{
+        addr::{AddrPc, AddrSnes},
+        rom_slice::*,
+    },
 };
 
 // -------------------------------------------------------------------------------------------------
@@ -16,7 +20,7 @@ type ParseErr<'a> = nom::Err<nom::error::Error<&'a [u8]>>;
 pub enum RomError {
     #[error("Empty ROM file")]
     Empty,
-    #[error("Invalid ROM size (not a multiple of 512 bytes): {0} ({0:#x})")]
+    #[error("Invalid ROM size (not a multiple of 1024 bytes, plus 512 if headered): {0} ({0:#x})")]
     Size(usize),
     #[error("Could not PC slice ROM: {0}")]
     SlicePc(PcSlice),
@@ -28,11 +32,17 @@ pub enum RomError {
     Parse,
     #[error("Data block not found: {0:?}")]
     DataBlockNotFound(DataBlock),
+    #[error("Cannot expand ROM to {0} KB (must be a power of two between the current size and {MAX_ROM_SIZE_KB} KB)")]
+    ExpansionSize(u32),
+    #[error("Cannot write a 16-bit pointer to {0:?} into a table of pointers to bank ${1:02X}")]
+    PointerOutsideBank(AddrSnes, u8),
 }
 
 // -------------------------------------------------------------------------------------------------
 
 pub const SMC_HEADER_SIZE: usize = 0x200;
+/// Largest ROM size in kilobytes that fits in the SNES address space, used by ExHiROM.
+pub const MAX_ROM_SIZE_KB: u32 = 0x2000;
 
 // -------------------------------------------------------------------------------------------------
 
@@ -74,8 +84,9 @@ pub trait IsDecompressed {
     fn as_decompressed(&self) -> &Decompressed;
 }
 
+/// ROM data without a copier header, and the copier header if the ROM file had one.
 #[derive(Clone)]
-pub struct Rom(pub Arc<[u8]>);
+pub struct Rom(pub Arc<[u8]>, Option<Arc<[u8]>>);
 
 pub struct RomWithErrorMapper<'r, EM, ET>
 where
@@ -120,21 +131,67 @@ pub fn noop_error_mapper<ET>(e: ET) -> ET {
 }
 
 impl Rom {
+    /// Creates a ROM from the contents of a ROM file, detecting and stripping a copier header so that it doesn't offset
+    /// any addresses. The header is kept for [`to_file_bytes`](Self::to_file_bytes).
     pub fn new(mut data: Vec<u8>) -> Result<Self, RomError> {
         if !data.is_empty() {
-            match data.len() % 0x400 {
-                SMC_HEADER_SIZE => {
-                    data.drain(..SMC_HEADER_SIZE);
-                    Ok(Self(Arc::from(data)))
-                }
-                0 => Ok(Self(Arc::from(data))),
-                _ => Err(RomError::Size(data.len())),
+            if data.len() % 0x400 == SMC_HEADER_SIZE {
+                log::info!("Stripping {SMC_HEADER_SIZE}-byte copier header from ROM");
+                let smc_header: Vec<u8> = data.drain(..SMC_HEADER_SIZE).collect();
+                Ok(Self(Arc::from(data), Some(Arc::from(smc_header))))
+            } else if data.len() % 0x400 == 0 {
+                Ok(Self(Arc::from(data), None))
+            } else {
+                Err(RomError::Size(data.len()))
             }
         } else {
             Err(RomError::Empty)
         }
     }
 
+    pub fn had_smc_header(&self) -> bool {
+        self.1.is_some()
+    }
+
+    pub fn smc_header(&self) -> Option<&[u8]> {
+        self.1.as_deref()
+    }
+
+    /// Returns the contents of a ROM file, including the copier header if the ROM was loaded with one.
+    pub fn to_file_bytes(&self) -> Vec<u8> {
+        self.smc_header().unwrap_or_default().iter().chain(self.0.iter()).copied().collect()
+    }
+
+    /// Returns the part of `slice` that is in the LoROM image, and whether the rest of it was cut off because it
+    /// extends past the end of the ROM or doesn't start in it. Unlike
+    /// [`slice_lorom`](RomWithErrorMapper::slice_lorom), this never fails. Infinite slices extend to the end of the
+    /// ROM without being considered cut off.
+    pub fn slice_lorom_clamped(&self, slice: SnesSlice) -> (&[u8], bool) {
+        let Ok(begin) = AddrPc::try_from_lorom(slice.begin) else { return (&[], slice.size != 0) };
+        let begin = begin.as_index().min(self.0.len());
+        let end = begin.saturating_add(slice.size).min(self.0.len());
+        let bytes = &self.0[begin..end];
+        (bytes, !slice.is_infinite() && bytes.len() < slice.size)
+    }
+
+    /// Grows the ROM to `size_kb` kilobytes, filling the new space with zeros, then updates the ROM size in the
+    /// internal header and fixes the checksum.
+    pub fn expand_to(&mut self, size_kb: u32) -> Result<(), RomError> {
+        let size = size_kb as usize * 0x400;
+        if !size_kb.is_power_of_two() || size_kb > MAX_ROM_SIZE_KB || size < self.0.len() {
+            return Err(RomError::ExpansionSize(size_kb));
+        }
+
+        let mut bytes = self.0.to_vec();
+        bytes.resize(size, 0x00);
+        let rom_size_offset = RomInternalHeader::header_offset(&bytes) + offsets::ROM_SIZE;
+        bytes[rom_size_offset] = size_kb.trailing_zeros() as u8;
+        RomInternalHeader::fix_checksum(&mut bytes);
+        log::info!("Expanded ROM from {} KB to {size_kb} KB", self.0.len() / 0x400);
+        self.0 = Arc::from(bytes);
+        Ok(())
+    }
+
     pub fn view(&self) -> RomWithErrorMapper<'_, impl Fn(RomError) -> RomError, RomError> {
         self.with_error_mapper(noop_error_mapper)
     }
@@ -260,3 +317,73 @@ impl Decompressed {
         DecompressedView { decompressed: self }
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smc_header_detection() {
+        let data: Vec<u8> = (0..0x10000).map(|i| i as u8).collect();
+        let header = vec![0xAA; SMC_HEADER_SIZE];
+        let headered: Vec<u8> = header.iter().chain(data.iter()).copied().collect();
+
+        let unheadered_rom = Rom::new(data.clone()).unwrap();
+        assert!(!unheadered_rom.had_smc_header());
+        assert_eq!(unheadered_rom.smc_header(), None);
+        assert_eq!(unheadered_rom.to_file_bytes(), data);
+
+        let headered_rom = Rom::new(headered.clone()).unwrap();
+        assert!(headered_rom.had_smc_header());
+        assert_eq!(headered_rom.smc_header(), Some(&header[..]));
+        assert_eq!(headered_rom.0, unheadered_rom.0);
+        assert_eq!(headered_rom.to_file_bytes(), headered);
+    }
+
+    #[test]
+    fn test_invalid_rom_size() {
+        assert!(matches!(Rom::new(Vec::new()), Err(RomError::Empty)));
+        assert!(matches!(Rom::new(vec![0; 0x8100]), Err(RomError::Size(0x8100))));
+        assert!(matches!(Rom::new(vec![0; 0x8300]), Err(RomError::Size(0x8300))));
+        let rom = Rom::new(vec![0; 0x8600]).unwrap();
+        assert!(rom.had_smc_header(), "sizes only need to be a multiple of 1 KiB");
+        assert_eq!(rom.0.len(), 0x8400);
+    }
+
+    #[test]
+    fn test_slice_lorom_clamped() {
+        let rom = Rom::new((0..0x8000).map(|i| i as u8).collect()).unwrap();
+        assert_eq!(rom.slice_lorom_clamped(SnesSlice::new(AddrSnes(0x008010), 3)), (&[0x10, 0x11, 0x12][..], false));
+        assert_eq!(rom.slice_lorom_clamped(SnesSlice::new(AddrSnes(0x00FFFE), 4)), (&[0xFE, 0xFF][..], true));
+        assert_eq!(
+            rom.slice_lorom_clamped(SnesSlice::new(AddrSnes(0x00FFFE), 0).infinite()),
+            (&[0xFE, 0xFF][..], false)
+        );
+        assert_eq!(rom.slice_lorom_clamped(SnesSlice::new(AddrSnes(0x018000), 4)), (&[][..], true));
+        assert_eq!(rom.slice_lorom_clamped(SnesSlice::new(AddrSnes(0x7E0000), 4)), (&[][..], true), "not in ROM");
+    }
+
+    #[test]
+    fn test_expand_rom() {
+        let mut bytes = vec![0x11; 0x40000];
+        bytes[0x7FC0 + offsets::ROM_SIZE] = 8;
+        RomInternalHeader::fix_checksum(&mut bytes);
+        let mut rom = Rom::new(bytes).unwrap();
+
+        rom.expand_to(1024).unwrap();
+        assert_eq!(rom.0.len(), 0x100000);
+        assert_eq!(rom.0[0x3FFFF], 0x11);
+        assert_eq!(rom.0[0x40000], 0x00);
+        assert_eq!(rom.0[0x7FC0 + offsets::ROM_SIZE], 10);
+        let checksum = u16::from_le_bytes([rom.0[0x7FC0 + offsets::CHECKSUM], rom.0[0x7FC0 + offsets::CHECKSUM + 1]]);
+        assert_eq!(RomInternalHeader::compute_checksum(&rom.0), (!checksum, checksum));
+        assert_eq!(RomInternalHeader::parse(&rom).unwrap().rom_size_in_kb(), 1024);
+
+        assert!(matches!(rom.expand_to(1536), Err(RomError::ExpansionSize(1536))));
+        assert!(matches!(rom.expand_to(512), Err(RomError::ExpansionSize(512))));
+        assert!(matches!(rom.expand_to(16384), Err(RomError::ExpansionSize(16384))));
+        assert_eq!(rom.0.len(), 0x100000);
+    }
+}
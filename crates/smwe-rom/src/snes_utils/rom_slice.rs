@@ -1,8 +1,11 @@
 use std::{fmt, fmt::Formatter};
 
+use duplicate::duplicate;
+use serde::{Deserialize, Serialize};
+
 use crate::snes_utils::addr::{Addr, AddrPc, AddrSnes};
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
 pub struct RomSlice<A: Addr> {
     pub begin: A,
     pub size:  usize,
@@ -101,8 +104,35 @@ impl<A: Addr> RomSlice<A> {
     }
 }
 
-impl<A: Addr> fmt::Display for RomSlice<A> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "RomSlice {{ begin: {:X}, size: {} }}", self.begin, self.size)
+duplicate! {
+    [
+        slice_type  addr_format;
+        [PcSlice]   ["{:#08X}"];
+        [SnesSlice] ["${:06X}"];
+    ]
+    /// Formats the slice as `begin..end (size bytes)`, or `begin.. (unknown size)` if it's infinite.
+    impl fmt::Display for slice_type {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            match self.end() {
+                Some(end) => {
+                    write!(f, concat!(addr_format, "..", addr_format, " ({} bytes)"), self.begin.0, end.0, self.size)
+                }
+                None => write!(f, concat!(addr_format, ".. (unknown size)"), self.begin.0),
+            }
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_slices() {
+        assert_eq!(SnesSlice::new(AddrSnes(0x0E0000), 0x200).to_string(), "$0E0000..$0E0200 (512 bytes)");
+        assert_eq!(PcSlice::new(AddrPc(0x070000), 0x10).to_string(), "0x070000..0x070010 (16 bytes)");
+        assert_eq!(SnesSlice::new(AddrSnes(0x05D000), 0).infinite().to_string(), "$05D000.. (unknown size)");
     }
 }
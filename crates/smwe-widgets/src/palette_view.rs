@@ -59,40 +59,76 @@ impl Widget for PaletteView<'_> {
         });
 
         let cell_width = self.size.x / 16.;
-        let row_count = match self.viewed_palettes {
-            ViewedPalettes::All => 16,
-            _ => 8,
+        let cell_rect = |row: f32, col: f32, width: f32| {
+            Rect::from_min_size(view_rect.min + vec2(col, row) * cell_width, vec2(width, cell_width))
         };
+        let hovered_cell = response.hover_pos().map(|hover_pos| palette_index_at(hover_pos, view_rect));
+        let selection_stroke = Stroke::new(2., Color32::from_rgba_premultiplied(200, 100, 30, 100));
         match self.selection {
-            Some(SelectionType::Cell((_x, _y))) => {
-                unimplemented!()
+            Some(SelectionType::Cell(cell)) => {
+                if let Some((row, col)) = hovered_cell {
+                    let highlight_rect = cell_rect(row as f32, col as f32, cell_width);
+                    ui.painter().rect_filled(highlight_rect, Rounding::ZERO, Color32::from_white_alpha(tweak!(100)));
+
+                    let hovered_cell = (col as u32, row as u32);
+                    if response.clicked_by(PointerButton::Primary) && *cell != hovered_cell {
+                        response.mark_changed();
+                        *cell = hovered_cell;
+                    }
+                }
+
+                let selection_rect = cell_rect(cell.1 as f32, cell.0 as f32, cell_width);
+                ui.painter().rect_stroke(selection_rect, Rounding::same(2.), selection_stroke);
             }
             Some(SelectionType::Row(row)) => {
-                let row_size = vec2(self.size.x, self.size.x / 16.);
-
-                if let Some(hover_pos) = response.hover_pos() {
-                    let relative_pos = hover_pos - view_rect.left_top();
-                    let hovered_row = (relative_pos.y / cell_width).floor().clamp(0., (row_count - 1) as f32);
-                    let highlight_rect =
-                        Rect::from_min_size(view_rect.min + vec2(0., hovered_row * cell_width), row_size);
+                if let Some((hovered_row, _)) = hovered_cell {
+                    let highlight_rect = cell_rect(hovered_row as f32, 0., self.size.x);
                     ui.painter().rect_filled(highlight_rect, Rounding::ZERO, Color32::from_white_alpha(tweak!(100)));
 
-                    if response.clicked_by(PointerButton::Primary) && *row != hovered_row as _ {
+                    if response.clicked_by(PointerButton::Primary) && *row != hovered_row as u32 {
                         response.mark_changed();
                         *row = hovered_row as _;
                     }
                 }
 
-                let selection_rect = Rect::from_min_size(view_rect.min + vec2(0., *row as f32 * cell_width), row_size);
-                ui.painter().rect_stroke(
-                    selection_rect,
-                    Rounding::same(2.),
-                    Stroke::new(2., Color32::from_rgba_premultiplied(200, 100, 30, 100)),
-                );
+                let selection_rect = cell_rect(*row as f32, 0., self.size.x);
+                ui.painter().rect_stroke(selection_rect, Rounding::same(2.), selection_stroke);
             }
-            _ => {}
+            None => {}
         }
 
         response
     }
 }
+
+/// Returns the row and column of the palette cell at `pos` in a view occupying `rect`, clamped to the view's cells.
+/// Cells are square and there are 16 of them in each row, so the number of rows follows from the view's size.
+pub fn palette_index_at(pos: Pos2, rect: Rect) -> (u8, u8) {
+    let cell_width = rect.width() / 16.;
+    let row_count = (rect.height() / cell_width).round().max(1.);
+    let cell = ((pos - rect.min) / cell_width).floor();
+    (cell.y.clamp(0., row_count - 1.) as u8, cell.x.clamp(0., 15.) as u8)
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_index_at_corners() {
+        let rect = Rect::from_min_size(pos2(100., 50.), vec2(160., 160.));
+        assert_eq!(palette_index_at(pos2(100., 50.), rect), (0, 0));
+        assert_eq!(palette_index_at(pos2(259.9, 50.), rect), (0, 15));
+        assert_eq!(palette_index_at(pos2(100., 209.9), rect), (15, 0));
+        assert_eq!(palette_index_at(pos2(259.9, 209.9), rect), (15, 15));
+        assert_eq!(palette_index_at(pos2(125., 75.), rect), (2, 2));
+        assert_eq!(palette_index_at(pos2(400., 400.), rect), (15, 15), "clamped to the view");
+        assert_eq!(palette_index_at(pos2(0., 0.), rect), (0, 0), "clamped to the view");
+
+        let half_rect = Rect::from_min_size(pos2(0., 0.), vec2(230., 115.));
+        assert_eq!(palette_index_at(pos2(229., 114.), half_rect), (7, 15));
+        assert_eq!(palette_index_at(pos2(229., 200.), half_rect), (7, 15), "only 8 rows are shown");
+    }
+}
@@ -1,7 +1,25 @@
 use std::sync::{Arc, Mutex};
 
-use egui::{vec2, Color32, PaintCallback, PointerButton, Rect, Response, Rounding, Sense, Stroke, Ui, Vec2, Widget};
-use egui_glow::{glow::Context, CallbackFn};
+use egui::{
+    vec2,
+    Color32,
+    Context,
+    EventFilter,
+    Id,
+    Key,
+    Modifiers,
+    PaintCallback,
+    PointerButton,
+    Rect,
+    Response,
+    Rounding,
+    Sense,
+    Stroke,
+    Ui,
+    Vec2,
+    Widget,
+};
+use egui_glow::{glow, CallbackFn};
 use inline_tweak::tweak;
 use itertools::Itertools;
 use smwe_render::{
@@ -9,6 +27,9 @@ use smwe_render::{
     tile_renderer::{Tile, TileRenderer, TileUniforms},
 };
 
+/// Number of 8x8 tiles in VRAM shown by the view.
+const N_TILES: u32 = 16 * 64;
+
 #[derive(Copy, Clone, Debug)]
 pub enum ViewedVramTiles {
     All,
@@ -29,11 +50,21 @@ pub struct VramView<'a> {
     viewed_tiles: ViewedVramTiles,
     selection:    Option<(VramSelectionMode, &'a mut (u32, u32))>,
     zoom:         f32,
+    columns:      u32,
 }
 
 impl<'a> VramView<'a> {
+    pub const DEFAULT_COLUMNS: u32 = 16;
+
     pub fn new(renderer: Arc<Mutex<TileRenderer>>, gfx_bufs: GfxBuffers) -> Self {
-        Self { renderer, gfx_bufs, viewed_tiles: ViewedVramTiles::All, selection: None, zoom: 1. }
+        Self {
+            renderer,
+            gfx_bufs,
+            viewed_tiles: ViewedVramTiles::All,
+            selection: None,
+            zoom: 1.,
+            columns: Self::DEFAULT_COLUMNS,
+        }
     }
 
     pub fn viewed_tiles(mut self, viewed_tiles: ViewedVramTiles) -> Self {
@@ -51,13 +82,44 @@ impl<'a> VramView<'a> {
         self
     }
 
-    pub fn new_renderer(gl: &Context) -> (TileRenderer, Vec<Tile>) {
-        let tiles = (0..16 * 64)
+    /// Sets how many tiles are shown in each row, which must match the number of columns the renderer was
+    /// created with in [`new_renderer_with_columns`](Self::new_renderer_with_columns). The selection is counted
+    /// in this many columns.
+    ///
+    /// # Panics
+    /// If `columns` is less than 2 or doesn't evenly divide the 512 tiles in each half of VRAM.
+    pub fn columns(mut self, columns: u32) -> Self {
+        assert_valid_columns(columns);
+        self.columns = columns;
+        self
+    }
+
+    /// Returns the tile hovered over in the view that returned `response`, in the same form as the selection. With
+    /// 2x2 tile selection, this is the top left tile that would be selected by clicking.
+    pub fn hovered_tile(response: &Response) -> Option<(u32, u32)> {
+        response.ctx.data_mut(|data| data.get_temp(hovered_tile_id(response.id)))
+    }
+
+    pub fn new_renderer(gl: &glow::Context) -> (TileRenderer, Vec<Tile>) {
+        Self::new_renderer_with_columns(gl, Self::DEFAULT_COLUMNS)
+    }
+
+    pub fn new_renderer_with_columns(gl: &glow::Context, columns: u32) -> (TileRenderer, Vec<Tile>) {
+        let tiles = Self::vram_tiles(columns);
+        let mut renderer = TileRenderer::new(gl);
+        renderer.set_tiles(gl, tiles.clone());
+        (renderer, tiles)
+    }
+
+    /// Returns all VRAM tiles laid out in rows of `columns` tiles, background tiles first.
+    pub fn vram_tiles(columns: u32) -> Vec<Tile> {
+        assert_valid_columns(columns);
+        (0..N_TILES)
             .map(|t| {
                 let scale = 8;
-                let pos_x = (t % 16) * scale;
-                let pos_y = (t / 16) * scale;
-                let (tile, pal) = if t < 16 * 32 {
+                let pos_x = (t % columns) * scale;
+                let pos_y = (t / columns) * scale;
+                let (tile, pal) = if t < N_TILES / 2 {
                     // background tiles
                     (t & 0x3FF, 0)
                 } else {
@@ -67,74 +129,279 @@ impl<'a> VramView<'a> {
                 let params = scale | (pal << 8) | (t & 0xC000);
                 Tile([pos_x, pos_y, tile, params])
             })
-            .collect_vec();
-        let mut renderer = TileRenderer::new(gl);
-        renderer.set_tiles(gl, tiles.clone());
-        (renderer, tiles)
+            .collect_vec()
     }
 }
 
 impl Widget for VramView<'_> {
     fn ui(self, ui: &mut Ui) -> Response {
-        let Self { renderer, gfx_bufs, viewed_tiles, selection, zoom } = self;
-        let scale = tweak!(8.);
-        let (height, offset) = match viewed_tiles {
-            ViewedVramTiles::All => (64., Vec2::ZERO),
-            ViewedVramTiles::BackgroundOnly => (32., Vec2::ZERO),
-            ViewedVramTiles::SpritesOnly => (32., vec2(0., -32. * scale)),
-        };
-        let px = ui.ctx().pixels_per_point();
-        let scale = scale / px;
-
-        let rect_size = vec2(16., height) * scale * zoom;
-        let (rect, response) =
-            ui.allocate_exact_size(rect_size, if selection.is_some() { Sense::click() } else { Sense::hover() });
-
-        // VRAM image
-        let screen_size = rect.size() * px;
-        ui.painter().add(PaintCallback {
-            rect,
-            callback: Arc::new(CallbackFn::new(move |_info, painter| {
-                renderer.lock().expect("Cannot lock mutex on VRAM renderer").paint(painter.gl(), &TileUniforms {
-                    gfx_bufs,
-                    screen_size,
-                    offset,
-                    zoom,
-                });
-            })),
-        });
+        let Self { renderer, gfx_bufs, viewed_tiles, selection, zoom, columns } = self;
+        show_tiles(ui, viewed_tiles, selection, zoom, columns, |ui, rect, offset| {
+            let screen_size = rect.size() * ui.ctx().pixels_per_point();
+            ui.painter().add(PaintCallback {
+                rect,
+                callback: Arc::new(CallbackFn::new(move |_info, painter| {
+                    renderer.lock().expect("Cannot lock mutex on VRAM renderer").paint(painter.gl(), &TileUniforms {
+                        gfx_bufs,
+                        screen_size,
+                        offset,
+                        zoom,
+                    });
+                })),
+            });
+        })
+    }
+}
 
-        // Hover/select tile
-        if let Some((mode, selection)) = selection {
-            let (selection_size, max_selected_tile) = match mode {
-                VramSelectionMode::SingleTile => (scale, vec2(15., 31.)),
-                VramSelectionMode::TwoByTwoTiles => (2. * scale, vec2(14., 30.)),
-            };
+/// Lays out the VRAM view and handles hovering over and selecting its tiles. The VRAM image itself is left to
+/// `paint_vram`, which is given the view's rect and the offset of the viewed tiles.
+fn show_tiles(
+    ui: &mut Ui, viewed_tiles: ViewedVramTiles, selection: Option<(VramSelectionMode, &mut (u32, u32))>, zoom: f32,
+    columns: u32, paint_vram: impl FnOnce(&Ui, Rect, Vec2),
+) -> Response {
+    let scale = tweak!(8.);
+    let width = columns as f32;
+    let half_height = (N_TILES / 2 / columns) as f32;
+    let (height, offset) = match viewed_tiles {
+        ViewedVramTiles::All => (2. * half_height, Vec2::ZERO),
+        ViewedVramTiles::BackgroundOnly => (half_height, Vec2::ZERO),
+        ViewedVramTiles::SpritesOnly => (half_height, vec2(0., -half_height * scale)),
+    };
+    let px = ui.ctx().pixels_per_point();
+    let scale = scale / px;
 
-            let selection_rect = Rect::from_min_size(rect.left_top(), Vec2::splat(selection_size * zoom));
+    let rect_size = vec2(width, height) * scale * zoom;
+    let (rect, response) =
+        ui.allocate_exact_size(rect_size, if selection.is_some() { Sense::click() } else { Sense::hover() });
 
-            if let Some(hover_pos) = response.hover_pos() {
-                let relative_pos = hover_pos - rect.left_top();
-                let hovered_tile = (relative_pos / scale / zoom).floor().clamp(vec2(0., 0.), max_selected_tile);
+    // VRAM image
+    paint_vram(ui, rect, offset);
 
-                ui.painter().rect_filled(
-                    selection_rect.translate(hovered_tile * scale * zoom),
-                    Rounding::same(tweak!(3.)),
-                    Color32::from_white_alpha(tweak!(100)),
-                );
+    // Hover/select tile
+    let (selection_size, max_selected_tile) = match selection.as_ref().map(|(mode, _)| *mode) {
+        None | Some(VramSelectionMode::SingleTile) => (scale, vec2(width - 1., height - 1.)),
+        Some(VramSelectionMode::TwoByTwoTiles) => (2. * scale, vec2(width - 2., height - 2.)),
+    };
+    // A 2x2 selection may not fit in a single row of tiles.
+    let max_selected_tile = max_selected_tile.max(Vec2::ZERO);
+    let hovered_tile =
+        response.hover_pos().map(|hover_pos| tile_at(hover_pos - rect.left_top(), scale * zoom, max_selected_tile));
+    set_hovered_tile(ui.ctx(), response.id, hovered_tile.map(|tile| (tile.x as u32, tile.y as u32)));
 
-                if response.clicked_by(PointerButton::Primary) {
-                    *selection = (hovered_tile.x as _, hovered_tile.y as _);
-                }
-            }
+    if let Some((_, selection)) = selection {
+        let selection_rect = Rect::from_min_size(rect.left_top(), Vec2::splat(selection_size * zoom));
 
-            ui.painter().rect_stroke(
-                selection_rect.translate(vec2(selection.0 as f32, selection.1 as f32) * scale * zoom),
+        if let Some(hovered_tile) = hovered_tile {
+            ui.painter().rect_filled(
+                selection_rect.translate(hovered_tile * scale * zoom),
                 Rounding::same(tweak!(3.)),
-                Stroke::new(tweak!(2.), Color32::from_rgba_premultiplied(200, 100, 30, 100)),
+                Color32::from_white_alpha(tweak!(100)),
             );
+
+            if response.clicked_by(PointerButton::Primary) {
+                *selection = (hovered_tile.x as _, hovered_tile.y as _);
+                response.request_focus();
+            }
+        }
+
+        if response.has_focus() {
+            let arrows = EventFilter { horizontal_arrows: true, vertical_arrows: true, ..Default::default() };
+            ui.memory_mut(|memory| memory.set_focus_lock_filter(response.id, arrows));
+            for key in [Key::ArrowLeft, Key::ArrowRight, Key::ArrowUp, Key::ArrowDown] {
+                if ui.input_mut(|input| input.consume_key(Modifiers::NONE, key)) {
+                    *selection = move_selection(*selection, key, max_selected_tile);
+                }
+            }
+        }
+
+        ui.painter().rect_stroke(
+            selection_rect.translate(vec2(selection.0 as f32, selection.1 as f32) * scale * zoom),
+            Rounding::same(tweak!(3.)),
+            Stroke::new(tweak!(2.), Color32::from_rgba_premultiplied(200, 100, 30, 100)),
+        );
+    }
+
+    response
+}
+
+fn assert_valid_columns(columns: u32) {
+    assert!(
+        columns >= 2 && (N_TILES / 2) % columns == 0,
+        "VRAM view columns must be at least 2 and divide {}, got {columns}",
+        N_TILES / 2
+    );
+}
+
+fn hovered_tile_id(response_id: Id) -> Id {
+    response_id.with("hovered_tile")
+}
+
+fn set_hovered_tile(ctx: &Context, response_id: Id, tile: Option<(u32, u32)>) {
+    ctx.data_mut(|data| match tile {
+        Some(tile) => data.insert_temp(hovered_tile_id(response_id), tile),
+        None => data.remove::<(u32, u32)>(hovered_tile_id(response_id)),
+    });
+}
+
+/// Returns the position of the tile at `relative_pos` from the view's top left corner, in tiles.
+fn tile_at(relative_pos: Vec2, tile_size: f32, max_tile: Vec2) -> Vec2 {
+    (relative_pos / tile_size).floor().clamp(Vec2::ZERO, max_tile)
+}
+
+/// Moves the selection one tile in the direction of the arrow key, keeping it within `max_selected_tile`.
+fn move_selection(selection: (u32, u32), key: Key, max_selected_tile: Vec2) -> (u32, u32) {
+    let (x, y) = selection;
+    let (max_x, max_y) = (max_selected_tile.x as u32, max_selected_tile.y as u32);
+    match key {
+        Key::ArrowLeft => (x.saturating_sub(1), y),
+        Key::ArrowRight => ((x + 1).min(max_x), y),
+        Key::ArrowUp => (x, y.saturating_sub(1)),
+        Key::ArrowDown => (x, (y + 1).min(max_y)),
+        _ => selection,
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use egui::{Event, Pos2, RawInput};
+
+    use super::*;
+
+    /// Shows a VRAM view with 16px tiles for one frame of `events` and returns its response.
+    fn show_view(ctx: &Context, events: Vec<Event>, selection: &mut (u32, u32)) -> Response {
+        let mut response = None;
+        let _ = ctx.run(RawInput { events, ..Default::default() }, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let selection = Some((VramSelectionMode::SingleTile, &mut *selection));
+                let columns = VramView::DEFAULT_COLUMNS;
+                response = Some(show_tiles(ui, ViewedVramTiles::All, selection, 2., columns, |_, _, _| {}));
+            });
+        });
+        response.expect("VRAM view wasn't shown")
+    }
+
+    fn tile_center(view_rect: Rect, tile: (u32, u32)) -> Pos2 {
+        view_rect.left_top() + (vec2(tile.0 as f32, tile.1 as f32) + Vec2::splat(0.5)) * 16.
+    }
+
+    fn primary_button(pos: Pos2, pressed: bool) -> Event {
+        Event::PointerButton { pos, button: PointerButton::Primary, pressed, modifiers: Modifiers::NONE }
+    }
+
+    fn key_press(key: Key) -> Event {
+        Event::Key { key, physical_key: None, pressed: true, repeat: false, modifiers: Modifiers::NONE }
+    }
+
+    #[test]
+    fn test_tile_at() {
+        let max_tile = vec2(15., 31.);
+        assert_eq!(tile_at(vec2(0., 0.), 16., max_tile), vec2(0., 0.));
+        assert_eq!(tile_at(vec2(40., 17.), 16., max_tile), vec2(2., 1.));
+        assert_eq!(tile_at(vec2(-3., 600.), 16., max_tile), vec2(0., 31.), "clamped to the view");
+        assert_eq!(tile_at(vec2(40., 17.), 16., vec2(1., 30.)), vec2(1., 1.), "clamped for 2x2 selection");
+    }
+
+    #[test]
+    fn test_vram_tiles_in_columns() {
+        let tiles = VramView::vram_tiles(32);
+        assert_eq!(tiles.len(), N_TILES as usize);
+        assert_eq!(tiles[0x25].0[..2], [5 * 8, 8]);
+        assert_eq!(tiles[0x200].0[..2], [0, 16 * 8], "sprite tiles start halfway down");
+
+        let tiles = VramView::vram_tiles(VramView::DEFAULT_COLUMNS);
+        assert_eq!(tiles[0x25].0[..2], [5 * 8, 2 * 8]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_columns_are_rejected() {
+        VramView::vram_tiles(0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_columns_not_dividing_vram_half_are_rejected() {
+        VramView::vram_tiles(24);
+    }
+
+    #[test]
+    fn test_move_selection_with_arrows() {
+        let max_tile = vec2(15., 31.);
+        assert_eq!(move_selection((3, 4), Key::ArrowRight, max_tile), (4, 4));
+        assert_eq!(move_selection((3, 4), Key::ArrowLeft, max_tile), (2, 4));
+        assert_eq!(move_selection((3, 4), Key::ArrowUp, max_tile), (3, 3));
+        assert_eq!(move_selection((3, 4), Key::ArrowDown, max_tile), (3, 5));
+        assert_eq!(move_selection((15, 31), Key::ArrowRight, max_tile), (15, 31));
+        assert_eq!(move_selection((15, 31), Key::ArrowDown, max_tile), (15, 31));
+        assert_eq!(move_selection((0, 0), Key::ArrowLeft, max_tile), (0, 0));
+        assert_eq!(move_selection((0, 0), Key::ArrowUp, max_tile), (0, 0));
+        assert_eq!(move_selection((14, 30), Key::ArrowRight, vec2(14., 30.)), (14, 30), "clamped for 2x2 selection");
+        assert_eq!(move_selection((3, 4), Key::A, max_tile), (3, 4));
+        assert_eq!(move_selection((0, 0), Key::ArrowDown, Vec2::ZERO), (0, 0), "2x2 selection in a single row");
+    }
+
+    #[test]
+    fn test_hovered_tile_is_stored_per_response() {
+        let ctx = Context::default();
+        let mut hovered = None;
+        let _ = ctx.run(Default::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let response = ui.allocate_response(vec2(128., 256.), Sense::hover());
+                let other = ui.allocate_response(vec2(128., 256.), Sense::hover());
+                set_hovered_tile(ctx, response.id, Some((3, 5)));
+                hovered = Some((VramView::hovered_tile(&response), VramView::hovered_tile(&other)));
+                set_hovered_tile(ctx, response.id, None);
+                assert_eq!(VramView::hovered_tile(&response), None);
+            });
+        });
+        assert_eq!(hovered, Some((Some((3, 5)), None)));
+    }
+
+    #[test]
+    fn test_view_hovers_and_selects_tiles() {
+        let ctx = Context::default();
+        let mut selection = (0, 0);
+        let rect = show_view(&ctx, Vec::new(), &mut selection).rect;
+
+        let pos = tile_center(rect, (3, 5));
+        let response = show_view(&ctx, vec![Event::PointerMoved(pos)], &mut selection);
+        assert_eq!(VramView::hovered_tile(&response), Some((3, 5)));
+        assert_eq!(selection, (0, 0), "hovering doesn't select");
+
+        show_view(&ctx, vec![primary_button(pos, true)], &mut selection);
+        let response = show_view(&ctx, vec![primary_button(pos, false)], &mut selection);
+        assert_eq!(selection, (3, 5));
+        assert!(response.has_focus(), "clicking focuses the view");
+
+        let response = show_view(&ctx, vec![Event::PointerGone], &mut selection);
+        assert_eq!(VramView::hovered_tile(&response), None);
+    }
+
+    #[test]
+    fn test_view_moves_selection_with_arrow_keys_when_focused() {
+        let ctx = Context::default();
+        let mut selection = (3, 5);
+        let rect = show_view(&ctx, Vec::new(), &mut selection).rect;
+
+        show_view(&ctx, vec![key_press(Key::ArrowRight)], &mut selection);
+        assert_eq!(selection, (3, 5), "arrow keys are ignored while unfocused");
+
+        let pos = tile_center(rect, (15, 0));
+        show_view(&ctx, vec![Event::PointerMoved(pos)], &mut selection);
+        show_view(&ctx, vec![primary_button(pos, true)], &mut selection);
+        show_view(&ctx, vec![primary_button(pos, false)], &mut selection);
+        assert_eq!(selection, (15, 0));
+
+        for key in [Key::ArrowDown, Key::ArrowDown, Key::ArrowLeft] {
+            show_view(&ctx, vec![key_press(key)], &mut selection);
         }
+        assert_eq!(selection, (14, 2));
 
-        response
+        for key in [Key::ArrowRight, Key::ArrowRight, Key::ArrowUp, Key::ArrowUp, Key::ArrowUp] {
+            show_view(&ctx, vec![key_press(key)], &mut selection);
+        }
+        assert_eq!(selection, (15, 0), "clamped to the view");
     }
 }
@@ -63,6 +63,23 @@ pub struct Cpu<M: Mem> {
     pub mem:   M,
 }
 
+/// Register state of a [`Cpu`], without its memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuRegs {
+    pub a:         u16,
+    pub x:         u16,
+    pub y:         u16,
+    pub s:         u16,
+    pub dbr:       u8,
+    pub pbr:       u8,
+    pub d:         u16,
+    pub pc:        u16,
+    /// Processor status register
+    pub p:         u8,
+    pub emulation: bool,
+    pub wai:       bool,
+}
+
 impl<M: Mem> Cpu<M> {
     /// Creates a new CPU and executes a reset. This will fetch the RESET vector from memory and
     /// put the CPU in emulation mode.
@@ -95,6 +112,37 @@ impl<M: Mem> Cpu<M> {
         }
     }
 
+    /// Returns a copy of the registers, which can be restored with [`set_regs`](Self::set_regs).
+    pub fn regs(&self) -> CpuRegs {
+        CpuRegs {
+            a:         self.a,
+            x:         self.x,
+            y:         self.y,
+            s:         self.s,
+            dbr:       self.dbr,
+            pbr:       self.pbr,
+            d:         self.d,
+            pc:        self.pc,
+            p:         self.p.0,
+            emulation: self.emulation,
+            wai:       self.wai,
+        }
+    }
+
+    pub fn set_regs(&mut self, regs: &CpuRegs) {
+        self.a = regs.a;
+        self.x = regs.x;
+        self.y = regs.y;
+        self.s = regs.s;
+        self.dbr = regs.dbr;
+        self.pbr = regs.pbr;
+        self.d = regs.d;
+        self.pc = regs.pc;
+        self.p = StatusReg(regs.p);
+        self.emulation = regs.emulation;
+        self.wai = regs.wai;
+    }
+
     /// Load a byte from memory.
     fn loadb(&mut self, bank: u8, addr: u16) -> u8 {
         // FIXME Remove?
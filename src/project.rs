@@ -1,30 +1,362 @@
-use std::{cell::RefCell, path::Path, rc::Rc, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
 
-use egui::Id;
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
 use smwe_emu::rom::Rom;
+use smwe_render::{color::Abgr1555, tile_renderer::TileJson};
+use smwe_rom::{graphics::palette::ColorPalette, SmwRom};
+
+/// An edit of the ROM's bytes that hasn't been saved yet.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RomPatch {
+    pub offset: usize,
+    pub bytes:  Vec<u8>,
+}
 
 #[derive(Debug)]
 pub struct Project {
     pub title: String,
+    /// The loaded ROM with all staged patches applied.
     pub rom:   Arc<Rom>,
+
+    /// Where the base ROM was loaded from, if it was loaded from a file.
+    rom_path: Option<PathBuf>,
+
+    /// Notes attached to SNES addresses. They aren't part of the ROM, so they're kept when ROM edits are discarded.
+    annotations:      BTreeMap<u32, String>,
+    base_rom:         Vec<u8>,
+    /// Copier header of the loaded ROM file, written back when saving.
+    smc_header:       Option<Vec<u8>>,
+    patches:          Vec<RomPatch>,
+    /// Tiles of the map made in the sprite map editor.
+    sprite_map:       Vec<TileJson>,
+    /// Whether staged patches have changed since the project or the ROM was last saved.
+    patches_modified: bool,
+    /// Whether annotations or the sprite map have changed since the project was loaded or last saved.
+    file_modified:    bool,
+    /// [`rom`](Self::rom) parsed by the tools that need its structure, dropped when the ROM changes.
+    smw_rom:          Option<Arc<SmwRom>>,
+}
+
+/// Contents of a `.smwe` project file. Staged patches are stored in it, so unsaved ROM edits survive reopening the
+/// project.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProjectFile {
+    pub version:       u32,
+    pub title:         String,
+    pub base_rom_path: PathBuf,
+    pub annotations:   BTreeMap<u32, String>,
+    pub patches:       Vec<RomPatch>,
+    /// Added in version 2.
+    #[serde(default)]
+    pub sprite_map:    Vec<TileJson>,
 }
 
 pub type ProjectRef = Rc<RefCell<Project>>;
 
+impl ProjectFile {
+    pub const EXTENSION: &'static str = "smwe";
+    pub const VERSION: u32 = 2;
+}
+
 impl Project {
     pub fn new(rom_path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        // TODO: check smc/sfc
-        let mut rom = Rom::new(std::fs::read(&rom_path)?[0x200..].to_vec());
+        let rom = smwe_rom::snes_utils::rom::Rom::new(std::fs::read(&rom_path)?)?;
+        let mut project = Self::from_rom_bytes(String::from("Test Project"), rom.0.to_vec());
+        project.rom_path = Some(rom_path.as_ref().canonicalize()?);
+        project.smc_header = rom.smc_header().map(<[u8]>::to_vec);
+        Ok(project)
+    }
+
+    /// Opens a project file, loading its base ROM and applying the patches staged when it was saved. A relative
+    /// base ROM path is resolved against the project file's directory.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).with_context(|| format!("Cannot read '{}'", path.display()))?;
+        let file: ProjectFile = serde_json::from_str(&json)?;
+        if file.version > ProjectFile::VERSION {
+            bail!("Project file version {} is newer than the supported version {}", file.version, ProjectFile::VERSION);
+        }
+
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        let mut project = Self::new(base_dir.join(&file.base_rom_path))
+            .with_context(|| format!("Cannot open base ROM '{}'", file.base_rom_path.display()))?;
+        project.title = file.title;
+        project.annotations = file.annotations;
+        project.patches = file.patches;
+        project.sprite_map = file.sprite_map;
+        project.rebuild_rom();
+        log::info!("Loaded project '{}' from '{}'", project.title, path.display());
+        Ok(project)
+    }
+
+    /// Writes the project file to `path`. Staged patches are kept in it rather than written to the ROM.
+    pub fn save(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let Some(base_rom_path) = self.rom_path.clone() else {
+            bail!("The project's ROM wasn't loaded from a file");
+        };
+        let file = ProjectFile {
+            version: ProjectFile::VERSION,
+            title: self.title.clone(),
+            base_rom_path,
+            annotations: self.annotations.clone(),
+            patches: self.patches.clone(),
+            sprite_map: self.sprite_map.clone(),
+        };
+        std::fs::write(&path, serde_json::to_string_pretty(&file)?)?;
+        self.patches_modified = false;
+        self.file_modified = false;
+        log::info!("Saved project '{}' to '{}'", self.title, path.as_ref().display());
+        Ok(())
+    }
+
+    /// Creates a project from the bytes of a ROM without a copier header.
+    pub fn from_rom_bytes(title: String, rom_bytes: Vec<u8>) -> Self {
+        let rom = Arc::new(Self::build_rom(rom_bytes.clone()));
+        Self {
+            title,
+            rom,
+            rom_path: None,
+            annotations: BTreeMap::new(),
+            base_rom: rom_bytes,
+            smc_header: None,
+            patches: Vec::new(),
+            sprite_map: Vec::new(),
+            patches_modified: false,
+            file_modified: false,
+            smw_rom: None,
+        }
+    }
+
+    /// Whether the ROM file had a copier header when it was loaded.
+    pub fn had_smc_header(&self) -> bool {
+        self.smc_header.is_some()
+    }
+
+    pub fn rom_path(&self) -> Option<&Path> {
+        self.rom_path.as_deref()
+    }
+
+    /// Whether the project has been edited since it was loaded or last saved.
+    pub fn is_dirty(&self) -> bool {
+        self.patches_modified || self.file_modified
+    }
+
+    pub fn annotations(&self) -> &BTreeMap<u32, String> {
+        &self.annotations
+    }
+
+    pub fn set_annotation(&mut self, addr: u32, text: String) {
+        self.annotations.insert(addr, text);
+        self.file_modified = true;
+    }
+
+    pub fn remove_annotation(&mut self, addr: u32) -> Option<String> {
+        let removed = self.annotations.remove(&addr);
+        self.file_modified |= removed.is_some();
+        removed
+    }
+
+    pub fn sprite_map(&self) -> &[TileJson] {
+        &self.sprite_map
+    }
+
+    pub fn set_sprite_map(&mut self, tiles: Vec<TileJson>) {
+        if tiles != self.sprite_map {
+            self.sprite_map = tiles;
+            self.file_modified = true;
+        }
+    }
+
+    /// Patches staged since the ROM was loaded or saved, in the order they were applied.
+    pub fn patches(&self) -> &[RomPatch] {
+        &self.patches
+    }
+
+    /// Applies the patch to [`rom`](Self::rom) and keeps it until the changes are discarded. Bytes past the end of
+    /// the ROM are ignored.
+    pub fn stage_patch(&mut self, patch: RomPatch) {
+        self.patches.push(patch);
+        self.patches_modified = true;
+        self.rebuild_rom();
+    }
+
+    /// Writes the ROM with all staged patches applied to `path`, after which it becomes the state [`reload`]
+    /// returns to. If the ROM was loaded with a copier header, it's written back too.
+    ///
+    /// [`reload`]: Self::reload
+    pub fn save_rom(&mut self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let smc_header = self.smc_header.as_deref().unwrap_or_default();
+        std::fs::write(path, [smc_header, self.rom.as_slice()].concat())?;
+        self.base_rom = self.rom.as_slice().to_vec();
+        self.patches.clear();
+        self.patches_modified = false;
+        Ok(())
+    }
+
+    /// Parses the ROM with all staged patches applied, or returns the result of parsing it earlier.
+    pub fn smw_rom(&mut self) -> anyhow::Result<Arc<SmwRom>> {
+        if let Some(smw_rom) = &self.smw_rom {
+            return Ok(Arc::clone(smw_rom));
+        }
+        log::info!("Parsing ROM of project '{}'", self.title);
+        let rom = smwe_rom::snes_utils::rom::Rom::new(self.rom.as_slice().to_vec())?;
+        let smw_rom = Arc::new(SmwRom::from_rom(rom)?);
+        self.smw_rom = Some(Arc::clone(&smw_rom));
+        Ok(smw_rom)
+    }
+
+    /// Discards all staged patches, restoring the ROM to the state it was loaded or saved in. Annotations are kept.
+    pub fn reload(&mut self) {
+        log::info!("Discarding {} staged ROM patches", self.patches.len());
+        self.patches_modified |= !self.patches.is_empty();
+        self.patches.clear();
+        self.rebuild_rom();
+    }
+
+    fn rebuild_rom(&mut self) {
+        let mut rom_bytes = self.base_rom.clone();
+        for patch in self.patches.iter() {
+            let end = (patch.offset + patch.bytes.len()).min(rom_bytes.len());
+            if let Some(dst) = rom_bytes.get_mut(patch.offset..end) {
+                dst.copy_from_slice(&patch.bytes[..dst.len()]);
+            }
+        }
+        self.rom = Arc::new(Self::build_rom(rom_bytes));
+        self.smw_rom = None;
+    }
+
+    fn build_rom(rom_bytes: Vec<u8>) -> Rom {
+        let mut rom = Rom::new(rom_bytes);
         rom.load_symbols(include_str!("../symbols/SMW_U.sym"));
+        rom
+    }
+}
 
-        Ok(Self { title: String::from("Test Project"), rom: Arc::new(rom) })
+/// All 256 CGRAM colors of a level using the foreground palette `fg_palette`, or none if it doesn't exist.
+pub fn level_cgram(smw_rom: &SmwRom, fg_palette: usize) -> Vec<Abgr1555> {
+    let palettes = &smw_rom.gfx.color_palettes;
+    match palettes.lv_specific_set.palette_from_indices(0, 0, fg_palette, 0, palettes) {
+        Ok(palette) => (0..16).flat_map(|row| palette.get_row(row)).collect(),
+        Err(e) => {
+            log::error!("Cannot build level palette with foreground palette {fg_palette}: {e}");
+            Vec::new()
+        }
     }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use smwe_render::tile_renderer::Tile;
 
-    pub fn rom_id() -> Id {
-        Id::new("rom")
+    use super::*;
+
+    #[test]
+    fn test_reload_discards_patches_and_keeps_annotations() {
+        let mut project = Project::from_rom_bytes(String::from("Test"), vec![0; 0x10000]);
+        project.stage_patch(RomPatch { offset: 0x10, bytes: vec![0xEA, 0xEA] });
+        project.stage_patch(RomPatch { offset: 0xFFFF, bytes: vec![0x12, 0x34] });
+        project.set_annotation(0x008010, String::from("NOPs"));
+        assert_eq!(&project.rom.as_slice()[0x10..0x12], &[0xEA, 0xEA]);
+        assert_eq!(project.rom.as_slice()[0xFFFF], 0x12);
+        assert_eq!(project.rom.as_slice().len(), 0x10000);
+
+        project.reload();
+        assert!(project.patches().is_empty());
+        assert!(project.is_dirty(), "annotations are still unsaved");
+        assert!(project.rom.as_slice().iter().all(|&b| b == 0));
+        assert_eq!(project.annotations().get(&0x008010).map(String::as_str), Some("NOPs"));
+    }
+
+    #[test]
+    fn test_project_file_round_trip() {
+        let dir = std::env::temp_dir().join(format!("smwe-test-project-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let rom_path = dir.join("base.sfc");
+        std::fs::write(&rom_path, vec![0; 0x8000]).unwrap();
+
+        let mut project = Project::new(&rom_path).unwrap();
+        project.title = String::from("My hack");
+        project.set_annotation(0x008000, String::from("Reset"));
+        project.stage_patch(RomPatch { offset: 2, bytes: vec![0xEA] });
+        project.set_sprite_map(vec![TileJson::from(Tile([8, 16, 0x620, 0x0A08]))]);
+        let project_path = dir.join(format!("hack.{}", ProjectFile::EXTENSION));
+        project.save(&project_path).unwrap();
+        assert!(!project.is_dirty(), "patches are kept in the project file");
+
+        let file: ProjectFile = serde_json::from_str(&std::fs::read_to_string(&project_path).unwrap()).unwrap();
+        assert_eq!(file.version, ProjectFile::VERSION);
+        assert_eq!(file.base_rom_path, rom_path.canonicalize().unwrap());
+
+        let loaded = Project::load(&project_path).unwrap();
+        assert_eq!(loaded.title, "My hack");
+        assert_eq!(loaded.annotations(), project.annotations());
+        assert_eq!(loaded.patches(), project.patches());
+        assert_eq!(loaded.sprite_map(), project.sprite_map());
+        assert_eq!(loaded.rom.as_slice()[2], 0xEA);
+        assert!(!loaded.is_dirty());
+
+        let newer = ProjectFile { version: ProjectFile::VERSION + 1, base_rom_path: PathBuf::from("base.sfc"), ..file };
+        std::fs::write(&project_path, serde_json::to_string(&newer).unwrap()).unwrap();
+        assert!(Project::load(&project_path).is_err());
+        std::fs::write(&project_path, serde_json::to_string(&ProjectFile { version: 1, ..newer }).unwrap()).unwrap();
+        assert_eq!(Project::load(&project_path).unwrap().rom.as_slice()[2], 0xEA, "relative to the project file");
+
+        let mut loaded = Project::load(&project_path).unwrap();
+        loaded.reload();
+        assert!(loaded.is_dirty(), "patches from the project file were discarded");
+        loaded.stage_patch(RomPatch { offset: 3, bytes: vec![0x42] });
+        assert!(loaded.is_dirty(), "as many patches as in the project file, but different ones");
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    pub fn project_title_id() -> Id {
-        Id::new("project_title")
+    #[test]
+    fn test_dirty_flag() {
+        let mut project = Project::from_rom_bytes(String::from("Test"), vec![0; 0x8000]);
+        assert!(!project.is_dirty());
+
+        project.stage_patch(RomPatch { offset: 0, bytes: vec![0x42] });
+        assert!(project.is_dirty());
+
+        let path = std::env::temp_dir().join(format!("smwe-test-dirty-flag-{}.sfc", std::process::id()));
+        project.save_rom(&path).unwrap();
+        let saved = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!project.is_dirty());
+        assert_eq!(saved[0], 0x42);
+        assert!(project.patches().is_empty());
+
+        project.reload();
+        assert!(!project.is_dirty());
+        assert_eq!(project.rom.as_slice()[0], 0x42, "reloads the saved ROM");
+
+        assert_eq!(project.remove_annotation(0x008000), None);
+        assert!(!project.is_dirty());
+        project.set_annotation(0x008000, String::from("Reset"));
+        assert!(project.is_dirty());
+
+        let mut other = Project::from_rom_bytes(String::from("Test"), vec![0; 0x8000]);
+        other.set_sprite_map(Vec::new());
+        assert!(!other.is_dirty(), "the sprite map didn't change");
+        other.set_sprite_map(vec![TileJson::from(Tile([0, 0, 0x620, 0x0A08]))]);
+        assert!(other.is_dirty());
+
+        project.stage_patch(RomPatch { offset: 1, bytes: vec![0x43] });
+        project.save_rom(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(project.is_dirty(), "annotations are only saved with the project file");
+        project.stage_patch(RomPatch { offset: 1, bytes: vec![0x44] });
+        project.reload();
+        assert!(project.patches().is_empty());
+        assert!(project.is_dirty(), "the annotation is still unsaved");
     }
 }
@@ -0,0 +1,214 @@
+use egui::{Align2, Context, Key, KeyboardShortcut, Modifiers, TextEdit, Window};
+
+use crate::ui::workspace::Workspace;
+
+pub const SHORTCUT_COMMAND_PALETTE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::P);
+
+/// Actions and tools available from the main window.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    NewProject,
+    OpenProject,
+    SaveProject,
+    SaveRomAs,
+    ReloadFromRom,
+    Exit,
+    OpenAddressConverter,
+    OpenBlockEditor,
+    OpenCodeEditor,
+    OpenDisassembler,
+    OpenGfxViewer,
+    OpenLevelEditor,
+    OpenRomInfo,
+    OpenSpriteMapEditor,
+    OpenTiles16x16,
+    ResetLayout,
+    ApplyWorkspace(Workspace),
+}
+
+#[derive(Debug, Default)]
+pub struct UiCommandPalette {
+    query:        String,
+    selected_idx: usize,
+
+    chosen_command: Option<Command>,
+}
+
+impl Command {
+    pub const ALL: [Command; 19] = [
+        Command::NewProject,
+        Command::OpenProject,
+        Command::SaveProject,
+        Command::SaveRomAs,
+        Command::ReloadFromRom,
+        Command::Exit,
+        Command::OpenAddressConverter,
+        Command::OpenBlockEditor,
+        Command::OpenCodeEditor,
+        Command::OpenDisassembler,
+        Command::OpenGfxViewer,
+        Command::OpenLevelEditor,
+        Command::OpenRomInfo,
+        Command::OpenSpriteMapEditor,
+        Command::OpenTiles16x16,
+        Command::ResetLayout,
+        Command::ApplyWorkspace(Workspace::Disassembly),
+        Command::ApplyWorkspace(Workspace::Graphics),
+        Command::ApplyWorkspace(Workspace::LevelEditing),
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Command::NewProject => "New project",
+            Command::OpenProject => "Open project...",
+            Command::SaveProject => "Save project as...",
+            Command::SaveRomAs => "Save ROM as...",
+            Command::ReloadFromRom => "Reload from ROM",
+            Command::Exit => "Exit",
+            Command::OpenAddressConverter => "Open address converter",
+            Command::OpenBlockEditor => "Open block editor",
+            Command::OpenCodeEditor => "Open code editor",
+            Command::OpenDisassembler => "Open disassembler",
+            Command::OpenGfxViewer => "Open GFX viewer",
+            Command::OpenLevelEditor => "Open level editor",
+            Command::OpenRomInfo => "Open ROM info",
+            Command::OpenSpriteMapEditor => "Open sprite map editor",
+            Command::OpenTiles16x16 => "Open 16x16 tiles viewer",
+            Command::ResetLayout => "Reset layout",
+            Command::ApplyWorkspace(Workspace::Default) => "Default workspace",
+            Command::ApplyWorkspace(Workspace::Disassembly) => "Disassembly workspace",
+            Command::ApplyWorkspace(Workspace::Graphics) => "Graphics workspace",
+            Command::ApplyWorkspace(Workspace::LevelEditing) => "Level editing workspace",
+        }
+    }
+
+    /// Whether the command can only be run with a project open.
+    pub fn requires_project(self) -> bool {
+        use Command::*;
+        matches!(
+            self,
+            SaveProject
+                | SaveRomAs
+                | ReloadFromRom
+                | OpenCodeEditor
+                | OpenDisassembler
+                | OpenGfxViewer
+                | OpenLevelEditor
+                | OpenRomInfo
+                | OpenSpriteMapEditor
+                | OpenTiles16x16
+        )
+    }
+}
+
+/// Returns the commands whose titles contain the query's characters in order, ignoring case. Commands where the
+/// matched characters are closer together come first.
+pub fn filter_commands(commands: &[Command], query: &str) -> Vec<Command> {
+    let mut matches: Vec<_> =
+        commands.iter().filter_map(|&command| Some((fuzzy_match_gaps(command.title(), query)?, command))).collect();
+    matches.sort_by_key(|&(gaps, _)| gaps);
+    matches.into_iter().map(|(_, command)| command).collect()
+}
+
+/// Returns the number of characters skipped between the first and last matched character, or `None` if `text`
+/// doesn't contain all of the query's non-whitespace characters in order.
+fn fuzzy_match_gaps(text: &str, query: &str) -> Option<usize> {
+    let mut text = text.chars().flat_map(char::to_lowercase).enumerate();
+    let mut last_match = None;
+    let mut gaps = 0;
+    for q in query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase) {
+        let (idx, _) = text.find(|&(_, c)| c == q)?;
+        if let Some(last_idx) = last_match {
+            gaps += idx - last_idx - 1;
+        }
+        last_match = Some(idx);
+    }
+    Some(gaps)
+}
+
+impl UiCommandPalette {
+    /// Shows the palette, listing the commands matching the query. Commands that need a project are only listed if
+    /// `has_project` is set. Returns whether the palette is still open.
+    pub fn update(&mut self, ctx: &Context, has_project: bool) -> bool {
+        let available: Vec<_> =
+            Command::ALL.into_iter().filter(|command| has_project || !command.requires_project()).collect();
+        let commands = filter_commands(&available, &self.query);
+        self.selected_idx = self.selected_idx.min(commands.len().saturating_sub(1));
+
+        let mut running = true;
+        ctx.input_mut(|input| {
+            if input.consume_key(Modifiers::NONE, Key::ArrowDown) && self.selected_idx + 1 < commands.len() {
+                self.selected_idx += 1;
+            }
+            if input.consume_key(Modifiers::NONE, Key::ArrowUp) {
+                self.selected_idx = self.selected_idx.saturating_sub(1);
+            }
+            if input.consume_key(Modifiers::NONE, Key::Enter) {
+                self.chosen_command = commands.get(self.selected_idx).copied();
+                running = false;
+            }
+            if input.consume_key(Modifiers::NONE, Key::Escape) {
+                running = false;
+            }
+        });
+
+        Window::new("Command palette").title_bar(false).resizable(false).anchor(Align2::CENTER_TOP, [0., 40.]).show(
+            ctx,
+            |ui| {
+                let query = ui.add(TextEdit::singleline(&mut self.query).hint_text("Type a command..."));
+                query.request_focus();
+                if query.changed() {
+                    self.selected_idx = 0;
+                }
+                ui.separator();
+                if commands.is_empty() {
+                    ui.weak("No matching commands");
+                }
+                for (idx, &command) in commands.iter().enumerate() {
+                    if ui.selectable_label(idx == self.selected_idx, command.title()).clicked() {
+                        self.chosen_command = Some(command);
+                        running = false;
+                    }
+                }
+            },
+        );
+
+        running
+    }
+
+    /// Returns the command if one has been chosen since the last call.
+    pub fn take_chosen_command(&mut self) -> Option<Command> {
+        self.chosen_command.take()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_commands() {
+        assert_eq!(filter_commands(&Command::ALL, ""), Command::ALL);
+        assert_eq!(filter_commands(&Command::ALL, "EXIT"), [Command::Exit]);
+        assert_eq!(filter_commands(&Command::ALL, "lvl ed"), [
+            Command::OpenLevelEditor,
+            Command::ApplyWorkspace(Workspace::LevelEditing),
+        ]);
+        assert_eq!(filter_commands(&Command::ALL, "editor"), [
+            Command::OpenBlockEditor,
+            Command::OpenCodeEditor,
+            Command::OpenLevelEditor,
+            Command::OpenSpriteMapEditor,
+            Command::ApplyWorkspace(Workspace::LevelEditing),
+        ]);
+        assert_eq!(filter_commands(&Command::ALL, "graphics"), [Command::ApplyWorkspace(Workspace::Graphics)]);
+        assert_eq!(filter_commands(&Command::ALL, "rom"), [
+            Command::SaveRomAs,
+            Command::OpenRomInfo,
+            Command::ReloadFromRom,
+        ]);
+        assert!(filter_commands(&Command::ALL, "xyz").is_empty());
+    }
+}
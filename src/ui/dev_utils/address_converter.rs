@@ -1,15 +1,14 @@
 use std::fmt::Write;
 
-use egui::{TextEdit, Ui, WidgetText};
-use smwe_rom::snes_utils::addr::{AddrPc, AddrSnes};
+use egui::{Grid, TextEdit, Ui, WidgetText};
 
 use crate::ui::{
     dev_utils::address_converter::{
-        helpers::adjust_to_header,
+        helpers::{adjust_to_header, convert, convert_lines, format_lines},
         modes::{ConvDir, ConversionMode},
     },
     style::{EditorStyle, ErrorStyle},
-    tool::DockableEditorTool,
+    tool::{DockableEditorTool, DockableEditorToolEnum},
 };
 
 #[derive(Debug)]
@@ -20,6 +19,10 @@ pub struct UiAddressConverter {
     text_pc:    String,
     text_snes:  String,
     text_error: String,
+
+    batch_mode:      bool,
+    batch_direction: ConvDir,
+    text_batch:      String,
 }
 
 impl Default for UiAddressConverter {
@@ -30,6 +33,9 @@ impl Default for UiAddressConverter {
             text_pc:         String::from("0"),
             text_snes:       String::from("8000"),
             text_error:      String::new(),
+            batch_mode:      false,
+            batch_direction: ConvDir::PcToSnes,
+            text_batch:      String::new(),
         }
     }
 }
@@ -37,19 +43,33 @@ impl Default for UiAddressConverter {
 impl DockableEditorTool for UiAddressConverter {
     fn update(&mut self, ui: &mut Ui) {
         self.mode_selection(ui);
-        self.conversions(ui);
+        ui.checkbox(&mut self.batch_mode, "Convert a list of addresses");
+        ui.separator();
+        if self.batch_mode {
+            self.batch_conversion(ui);
+        } else {
+            self.conversions(ui);
+        }
     }
 
     fn title(&self) -> WidgetText {
         "Address converter".into()
     }
+
+    fn kind(&self) -> DockableEditorToolEnum {
+        DockableEditorToolEnum::AddressConverter
+    }
 }
 
 impl UiAddressConverter {
     fn mode_selection(&mut self, ui: &mut Ui) {
         let lorom_changed = ui.radio_value(&mut self.conversion_mode, ConversionMode::LoRom, "PC and LoROM").clicked();
         let hirom_changed = ui.radio_value(&mut self.conversion_mode, ConversionMode::HiRom, "PC and HiROM").clicked();
-        if lorom_changed || hirom_changed {
+        let exlorom_changed =
+            ui.radio_value(&mut self.conversion_mode, ConversionMode::ExLoRom, "PC and ExLoROM").clicked();
+        let exhirom_changed =
+            ui.radio_value(&mut self.conversion_mode, ConversionMode::ExHiRom, "PC and ExHiROM").clicked();
+        if lorom_changed || hirom_changed || exlorom_changed || exhirom_changed {
             log::info!("Conversion mode changed to {}", self.conversion_mode);
             self.update_addresses(ConvDir::PcToSnes);
         }
@@ -85,6 +105,33 @@ impl UiAddressConverter {
                 self.update_addresses(direction);
             }
             ui.label(label);
+            if ui.small_button("Copy").on_hover_text(format!("Copy the {label} address")).clicked() {
+                ui.output_mut(|output| output.copied_text = buf.clone());
+            }
+        });
+    }
+
+    fn batch_conversion(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.batch_direction, ConvDir::PcToSnes, ConvDir::PcToSnes.to_string());
+            ui.radio_value(&mut self.batch_direction, ConvDir::SnesToPc, ConvDir::SnesToPc.to_string());
+        });
+        ui.add(TextEdit::multiline(&mut self.text_batch).hint_text("One address per line").desired_rows(8));
+
+        let results = convert_lines(&self.text_batch, self.conversion_mode, self.batch_direction, self.include_header);
+        if ui.add_enabled(!results.is_empty(), egui::Button::new("Copy results")).clicked() {
+            ui.output_mut(|output| output.copied_text = format_lines(&results));
+        }
+        let error_color = ErrorStyle::get_from_egui(ui.ctx(), |style| style.text_color);
+        Grid::new("address_converter.batch_results").num_columns(2).striped(true).show(ui, |ui| {
+            for (src, dst) in results.iter() {
+                ui.monospace(src);
+                match dst {
+                    Ok(addr) => ui.monospace(format!("{addr:x}")),
+                    Err(e) => ui.colored_label(error_color, e),
+                };
+                ui.end_row();
+            }
         });
     }
 
@@ -106,18 +153,7 @@ impl UiAddressConverter {
             }
         };
 
-        let addr_dst = match direction {
-            ConvDir::PcToSnes => match self.conversion_mode {
-                ConversionMode::LoRom => AddrSnes::try_from_lorom(AddrPc(addr_src)),
-                ConversionMode::HiRom => AddrSnes::try_from_hirom(AddrPc(addr_src)),
-            }
-            .map(|addr| addr.0),
-            ConvDir::SnesToPc => match self.conversion_mode {
-                ConversionMode::LoRom => AddrPc::try_from_lorom(AddrSnes(addr_src)),
-                ConversionMode::HiRom => AddrPc::try_from_hirom(AddrSnes(addr_src)),
-            }
-            .map(|addr| addr.0),
-        };
+        let addr_dst = convert(self.conversion_mode, direction, addr_src);
 
         match addr_dst {
             Ok(addr_dst) => {
@@ -137,8 +173,11 @@ mod modes {
     pub enum ConversionMode {
         LoRom,
         HiRom,
+        ExLoRom,
+        ExHiRom,
     }
 
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
     pub enum ConvDir {
         PcToSnes,
         SnesToPc,
@@ -149,6 +188,8 @@ mod modes {
             f.write_str(match self {
                 ConversionMode::LoRom => "PC ↔ LoRom",
                 ConversionMode::HiRom => "PC ↔ HiRom",
+                ConversionMode::ExLoRom => "PC ↔ ExLoRom",
+                ConversionMode::ExHiRom => "PC ↔ ExHiRom",
             })
         }
     }
@@ -164,7 +205,12 @@ mod modes {
 }
 
 mod helpers {
-    use smwe_rom::snes_utils::rom::SMC_HEADER_SIZE;
+    use smwe_rom::snes_utils::{
+        addr::{AddrPc, AddrSnes, AddressError},
+        rom::SMC_HEADER_SIZE,
+    };
+
+    use super::modes::{ConvDir, ConversionMode};
 
     pub fn adjust_to_header(addr: u32, include_header: bool) -> u32 {
         if include_header {
@@ -175,4 +221,130 @@ mod helpers {
             0
         }
     }
+
+    pub fn convert(mode: ConversionMode, direction: ConvDir, addr: u32) -> Result<u32, AddressError> {
+        match direction {
+            ConvDir::PcToSnes => match mode {
+                ConversionMode::LoRom => AddrSnes::try_from_lorom(AddrPc(addr)),
+                ConversionMode::HiRom => AddrSnes::try_from_hirom(AddrPc(addr)),
+                ConversionMode::ExLoRom => AddrSnes::try_from_exlorom(AddrPc(addr)),
+                ConversionMode::ExHiRom => AddrSnes::try_from_exhirom(AddrPc(addr)),
+            }
+            .map(|addr| addr.0),
+            ConvDir::SnesToPc => match mode {
+                ConversionMode::LoRom => AddrPc::try_from_lorom(AddrSnes(addr)),
+                ConversionMode::HiRom => AddrPc::try_from_hirom(AddrSnes(addr)),
+                ConversionMode::ExLoRom => AddrPc::try_from_exlorom(AddrSnes(addr)),
+                ConversionMode::ExHiRom => AddrPc::try_from_exhirom(AddrSnes(addr)),
+            }
+            .map(|addr| addr.0),
+        }
+    }
+
+    /// Converts each non-empty line of `text`, which may start with `$` or `0x`. Lines that can't be converted get
+    /// an error message instead, without stopping the rest. With `include_header`, PC addresses include the SMC header.
+    pub fn convert_lines(
+        text: &str, mode: ConversionMode, direction: ConvDir, include_header: bool,
+    ) -> Vec<(String, Result<u32, String>)> {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let hex = line.strip_prefix('$').or_else(|| line.strip_prefix("0x")).unwrap_or(line);
+                let result = match u32::from_str_radix(hex, 16) {
+                    Err(_) => Err(String::from("Not a hexadecimal address")),
+                    Ok(addr) => match direction {
+                        ConvDir::PcToSnes if include_header && addr < SMC_HEADER_SIZE as u32 => {
+                            Err(String::from("Address is inside the SMC header"))
+                        }
+                        ConvDir::PcToSnes => {
+                            let addr = if include_header { adjust_to_header(addr, false) } else { addr };
+                            convert(mode, direction, addr).map_err(|e| e.to_string())
+                        }
+                        ConvDir::SnesToPc => convert(mode, direction, addr)
+                            .map(|addr| if include_header { adjust_to_header(addr, true) } else { addr })
+                            .map_err(|e| e.to_string()),
+                    },
+                };
+                (String::from(line), result)
+            })
+            .collect()
+    }
+
+    /// Formats the results of [`convert_lines`] as tab-separated lines, with errors as comments.
+    pub fn format_lines(results: &[(String, Result<u32, String>)]) -> String {
+        results
+            .iter()
+            .map(|(src, dst)| match dst {
+                Ok(addr) => format!("{src}\t{addr:x}\n"),
+                Err(e) => format!("{src}\t; {e}\n"),
+            })
+            .collect()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(conversion_mode: ConversionMode, direction: ConvDir, addr: &str) -> (String, String, String) {
+        let mut converter = UiAddressConverter { conversion_mode, ..UiAddressConverter::default() };
+        match direction {
+            ConvDir::PcToSnes => converter.text_pc = String::from(addr),
+            ConvDir::SnesToPc => converter.text_snes = String::from(addr),
+        }
+        converter.update_addresses(direction);
+        (converter.text_pc, converter.text_snes, converter.text_error)
+    }
+
+    #[test]
+    fn test_exlorom_conversion() {
+        use ConversionMode::ExLoRom;
+
+        assert_eq!(convert(ExLoRom, ConvDir::PcToSnes, "0"), ("0".into(), "808000".into(), String::new()));
+        assert_eq!(convert(ExLoRom, ConvDir::PcToSnes, "400000"), ("400000".into(), "8000".into(), String::new()));
+        assert_eq!(convert(ExLoRom, ConvDir::SnesToPc, "7d8000").0, "7e8000");
+        assert_eq!(convert(ExLoRom, ConvDir::SnesToPc, "ffffff").0, "3fffff");
+
+        let (_, _, error) = convert(ExLoRom, ConvDir::PcToSnes, "7f0000");
+        assert!(error.contains("ExLoROM"), "{error}");
+        let (_, _, error) = convert(ExLoRom, ConvDir::SnesToPc, "7e0000");
+        assert!(error.contains("ExLoROM"), "{error}");
+    }
+
+    #[test]
+    fn test_convert_lines() {
+        let text = "0\n\n  $8000 \nxyz\n0x400000\n";
+        let results = convert_lines(text, ConversionMode::LoRom, ConvDir::PcToSnes, false);
+        assert_eq!(results.len(), 4, "empty lines are skipped");
+        assert_eq!(results[0], (String::from("0"), Ok(0x008000)));
+        assert_eq!(results[1], (String::from("$8000"), Ok(0x018000)));
+        assert_eq!(results[2].0, "xyz");
+        assert!(results[2].1.is_err(), "invalid lines don't stop the batch");
+        assert!(results[3].1.as_ref().unwrap_err().contains("LoROM"));
+        assert_eq!(format_lines(&results[..2]), "0\t8000\n$8000\t18000\n");
+
+        let results = convert_lines("008000\n7E0000", ConversionMode::LoRom, ConvDir::SnesToPc, true);
+        assert_eq!(results[0].1, Ok(0x200), "PC addresses include the header");
+        assert!(results[1].1.is_err());
+        let results = convert_lines("008000", ConversionMode::LoRom, ConvDir::SnesToPc, false);
+        assert_eq!(results[0].1, Ok(0), "addresses are unchanged without the header");
+
+        let results = convert_lines("100\n200", ConversionMode::LoRom, ConvDir::PcToSnes, true);
+        assert!(results[0].1.is_err());
+        assert_eq!(results[1].1, Ok(0x008000));
+    }
+
+    #[test]
+    fn test_exhirom_conversion() {
+        use ConversionMode::ExHiRom;
+
+        assert_eq!(convert(ExHiRom, ConvDir::PcToSnes, "0").1, "c00000");
+        assert_eq!(convert(ExHiRom, ConvDir::PcToSnes, "412345").1, "412345");
+        assert_eq!(convert(ExHiRom, ConvDir::SnesToPc, "c08000").0, "8000");
+        assert_eq!(convert(ExHiRom, ConvDir::SnesToPc, "408000").0, "408000");
+        assert!(convert(ExHiRom, ConvDir::PcToSnes, "7e0000").2.contains("ExHiROM"));
+    }
 }
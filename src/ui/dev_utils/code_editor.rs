@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use egui::{DragValue, Grid, TextEdit, TextStyle, Ui, WidgetText};
+use smwe_emu::{
+    emu::{CheckedMem, RunConfig, StopReason},
+    rom::Rom,
+    Cpu,
+};
+use smwe_rom::assembler::{assemble, AssemblyError};
+use wdc65816::CpuRegs;
+
+use crate::ui::{
+    style::{EditorStyle, ErrorStyle},
+    tool::{DockableEditorTool, DockableEditorToolEnum},
+};
+
+/// Number of cycles after which a snippet is stopped, so that infinite loops don't freeze the editor.
+const MAX_CYCLES: u64 = 1_000_000;
+
+/// Assembles a snippet of 65816 code and runs it in the emulator.
+pub struct UiCodeEditor {
+    rom:    Arc<Rom>,
+    source: String,
+    origin: u32,
+
+    errors:        Vec<AssemblyError>,
+    address_error: Option<String>,
+    result:        Option<RunResult>,
+}
+
+struct RunResult {
+    reason: StopReason,
+    cycles: u64,
+    regs:   CpuRegs,
+}
+
+impl UiCodeEditor {
+    pub fn new(rom: Arc<Rom>) -> Self {
+        Self {
+            rom,
+            source: String::from("REP #$20\nLDA #$1234\nSEP #$20\n"),
+            origin: 0x7F8000,
+            errors: Vec::new(),
+            address_error: None,
+            result: None,
+        }
+    }
+}
+
+impl DockableEditorTool for UiCodeEditor {
+    fn update(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Address");
+            ui.add(DragValue::new(&mut self.origin).clamp_range(0..=0xFFFFFF).hexadecimal(6, false, true).prefix("$"));
+            if ui.button("Run").clicked() {
+                self.run();
+            }
+        });
+        ui.add(
+            TextEdit::multiline(&mut self.source)
+                .font(TextStyle::Monospace)
+                .code_editor()
+                .desired_rows(12)
+                .desired_width(f32::INFINITY),
+        );
+
+        let error_color = ErrorStyle::get_from_egui(ui.ctx(), |style| style.text_color);
+        for error in self.errors.iter() {
+            ui.colored_label(error_color, error.to_string());
+        }
+        if let Some(error) = &self.address_error {
+            ui.colored_label(error_color, error);
+        }
+        if let Some(result) = &self.result {
+            ui.separator();
+            ui.label(format!("{:?} after {} cycles", result.reason, result.cycles));
+            registers(ui, &result.regs);
+        }
+    }
+
+    fn title(&self) -> WidgetText {
+        "Code editor".into()
+    }
+
+    fn kind(&self) -> DockableEditorToolEnum {
+        DockableEditorToolEnum::CodeEditor
+    }
+}
+
+impl UiCodeEditor {
+    /// Assembles the code, then runs it from the start with a fresh CPU until it reaches the end of the code.
+    fn run(&mut self) {
+        self.result = None;
+        self.address_error = None;
+        let code = match assemble(&self.source, self.origin) {
+            Ok(code) => code,
+            Err(errors) => {
+                self.errors = errors;
+                return;
+            }
+        };
+        self.errors.clear();
+        if !is_in_wram(self.origin, code.len() as u32) {
+            self.address_error = Some(format!(
+                "${:06X}-${:06X} is not in a single bank of WRAM at $7E0000-$7FFFFF or $0000-$1FFF",
+                self.origin,
+                self.origin + code.len().max(1) as u32 - 1,
+            ));
+            return;
+        }
+
+        let mut cpu = Cpu::new(CheckedMem::new(Arc::clone(&self.rom)));
+        for (offset, &byte) in code.iter().enumerate() {
+            cpu.mem.store_u8(self.origin + offset as u32, byte);
+        }
+        cpu.emulation = false;
+        cpu.s = 0x1FF;
+        cpu.pbr = (self.origin >> 16) as u8;
+        cpu.pc = self.origin as u16;
+        cpu.dbr = 0;
+
+        let end = self.origin + code.len() as u32;
+        let config = RunConfig {
+            max_cycles: Some(MAX_CYCLES),
+            stop_on_illegal: true,
+            stop_at: Some(end),
+            ..RunConfig::default()
+        };
+        let (reason, cycles) = smwe_emu::emu::run(&mut cpu, &config);
+        log::info!("Ran {} bytes of code at ${:06X}: {reason:?} after {cycles} cycles", code.len(), self.origin);
+        self.result = Some(RunResult { reason, cycles, regs: cpu.regs() });
+    }
+}
+
+/// Whether `len` bytes at `start` are in WRAM without crossing a bank boundary, either in banks $7E-$7F or in the
+/// mirror of its first $2000 bytes in banks $00-$3F and $80-$BF.
+fn is_in_wram(start: u32, len: u32) -> bool {
+    let bank = start >> 16;
+    let last = start + len.max(1) - 1;
+    last >> 16 == bank && (bank & 0xFE == 0x7E || (bank & 0x40 == 0 && last & 0xFFFF < 0x2000))
+}
+
+fn registers(ui: &mut Ui, regs: &CpuRegs) {
+    Grid::new("code_editor.registers").num_columns(2).striped(true).show(ui, |ui| {
+        let rows = [
+            ("A", format!("{:04X}", regs.a)),
+            ("X", format!("{:04X}", regs.x)),
+            ("Y", format!("{:04X}", regs.y)),
+            ("S", format!("{:04X}", regs.s)),
+            ("D", format!("{:04X}", regs.d)),
+            ("DBR", format!("{:02X}", regs.dbr)),
+            ("PC", format!("{:02X}:{:04X}", regs.pbr, regs.pc)),
+            ("P", format!("{:08b}", regs.p)),
+        ];
+        for (name, value) in rows {
+            ui.label(name);
+            ui.monospace(value);
+            ui.end_row();
+        }
+    });
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_in_wram() {
+        assert!(is_in_wram(0x7E0000, 0x10));
+        assert!(is_in_wram(0x7FFFF0, 0x10));
+        assert!(is_in_wram(0x001000, 0x10));
+        assert!(is_in_wram(0x801FF0, 0x10));
+        assert!(!is_in_wram(0x7EFFF0, 0x11), "crosses into bank $7F");
+        assert!(!is_in_wram(0x001FF0, 0x11), "crosses into I/O registers");
+        assert!(!is_in_wram(0x008000, 0x10), "ROM");
+        assert!(!is_in_wram(0x401000, 0x10), "no mirror in bank $40");
+    }
+}
@@ -0,0 +1,371 @@
+use std::{collections::HashSet, ops::RangeInclusive, sync::Arc};
+
+use egui::{Align, Button, DragValue, Layout, RichText, TextEdit, TextStyle, Ui, WidgetText};
+use egui_extras::{Column, TableBuilder};
+use itertools::Itertools;
+use smwe_emu::rom::Rom;
+use smwe_rom::{
+    disassembler::{binary_block::BinaryBlock, RomDisassembly},
+    snes_utils::addr::{AddrPc, AddrSnes},
+    SmwRom,
+};
+
+use crate::ui::{
+    dev_utils::disassembler::search::BytePattern,
+    style::{EditorStyle, ErrorStyle},
+    tool::{DockableEditorTool, DockableEditorToolEnum},
+};
+
+/// Lists the code and data blocks found in the ROM by the disassembler.
+pub struct UiDisassembler {
+    rom:  Arc<Rom>,
+    rows: Vec<DisassemblyRow>,
+
+    current_address_scroll: u32,
+    scroll_to_current:      bool,
+    goto_text:              String,
+    text_error:             String,
+
+    search_text:    String,
+    search_matches: Vec<AddrSnes>,
+    matched_rows:   HashSet<usize>,
+    current_match:  usize,
+}
+
+/// A line of the disassembly: an instruction, or a whole block that isn't code.
+struct DisassemblyRow {
+    addr:   AddrSnes,
+    bytes:  String,
+    code:   String,
+    /// Where a branch, jump or call goes to, shown as a link.
+    target: Option<AddrSnes>,
+}
+
+impl UiDisassembler {
+    /// Lists the disassembly of `smw_rom`, which is shared with the other tools instead of disassembling `rom` again.
+    pub fn new(rom: Arc<Rom>, smw_rom: anyhow::Result<Arc<SmwRom>>) -> Self {
+        let (rows, text_error) = match smw_rom {
+            Ok(smw_rom) => (disassembly_rows(&smw_rom.disassembly), String::new()),
+            Err(e) => (Vec::new(), format!("Cannot parse ROM: {e}")),
+        };
+        Self {
+            rom,
+            rows,
+            current_address_scroll: 0x008000,
+            scroll_to_current: false,
+            goto_text: String::new(),
+            text_error,
+
+            search_text: String::new(),
+            search_matches: Vec::new(),
+            matched_rows: HashSet::new(),
+            current_match: 0,
+        }
+    }
+}
+
+impl DockableEditorTool for UiDisassembler {
+    fn update(&mut self, ui: &mut Ui) {
+        self.navigation(ui);
+        self.search(ui);
+        if !self.text_error.is_empty() {
+            ui.colored_label(ErrorStyle::get_from_egui(ui.ctx(), |style| style.text_color), &self.text_error);
+        }
+        ui.separator();
+        self.display_code(ui);
+    }
+
+    fn title(&self) -> WidgetText {
+        "Disassembler".into()
+    }
+
+    fn kind(&self) -> DockableEditorToolEnum {
+        DockableEditorToolEnum::Disassembler
+    }
+}
+
+impl UiDisassembler {
+    fn navigation(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Address");
+            let address_range = snes_address_range(self.rom.as_slice().len());
+            let drag_value = DragValue::new(&mut self.current_address_scroll)
+                .clamp_range(address_range.clone())
+                .hexadecimal(6, false, true)
+                .prefix("$");
+            if ui.add(drag_value).changed() {
+                self.scroll_to_current = true;
+            }
+
+            ui.separator();
+            let goto =
+                ui.add(TextEdit::singleline(&mut self.goto_text).hint_text("Address or label").desired_width(160.));
+            let submitted = goto.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            if ui.button("Go to").clicked() || submitted {
+                self.go_to(parse_goto_target(&self.goto_text, &self.rom), address_range);
+            }
+        });
+    }
+
+    fn search(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Search");
+            let search = ui.add(
+                TextEdit::singleline(&mut self.search_text).hint_text("Bytes, e.g. 22 ?? ?? 0F").desired_width(160.),
+            );
+            let submitted = search.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            if ui.button("Find").clicked() || submitted {
+                self.find_matches();
+            }
+
+            let n_matches = self.search_matches.len();
+            if ui.add_enabled(n_matches > 0, Button::new("Previous")).clicked() {
+                self.go_to_match((self.current_match + n_matches - 1) % n_matches);
+            }
+            if ui.add_enabled(n_matches > 0, Button::new("Next")).clicked() {
+                self.go_to_match((self.current_match + 1) % n_matches);
+            }
+            if n_matches > 0 {
+                ui.label(format!("{} of {n_matches}", self.current_match + 1));
+            }
+        });
+    }
+
+    fn find_matches(&mut self) {
+        self.search_matches.clear();
+        self.matched_rows.clear();
+        let Some(pattern) = BytePattern::parse(&self.search_text) else {
+            self.text_error = format!("'{}' is not a valid byte pattern.", self.search_text.trim());
+            return;
+        };
+        self.search_matches = pattern
+            .find_all(self.rom.as_slice())
+            .into_iter()
+            .filter_map(|offset| AddrSnes::try_from_lorom(AddrPc(offset as u32)).ok())
+            .collect();
+        self.matched_rows = self.search_matches.iter().map(|&addr| row_at(&self.rows, addr)).collect();
+        log::info!("Found {} matches of {}", self.search_matches.len(), self.search_text.trim());
+
+        if self.search_matches.is_empty() {
+            self.text_error = String::from("No matches found.");
+        } else {
+            self.text_error.clear();
+            self.go_to_match(0);
+        }
+    }
+
+    fn go_to_match(&mut self, idx: usize) {
+        self.current_match = idx;
+        self.current_address_scroll = self.search_matches[idx].0;
+        self.scroll_to_current = true;
+    }
+
+    fn go_to(&mut self, target: Option<u32>, address_range: RangeInclusive<u32>) {
+        match target {
+            Some(addr) if address_range.contains(&addr) => {
+                self.current_address_scroll = addr;
+                self.scroll_to_current = true;
+                self.text_error.clear();
+            }
+            Some(addr) => self.text_error = format!("Address ${addr:06X} is outside of ROM."),
+            None => self.text_error = format!("'{}' is neither an address nor a known label.", self.goto_text.trim()),
+        }
+    }
+
+    fn display_code(&mut self, ui: &mut Ui) {
+        let row_height = ui.text_style_height(&TextStyle::Monospace);
+        let mut table = TableBuilder::new(ui)
+            .striped(true)
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::remainder());
+        if std::mem::take(&mut self.scroll_to_current) {
+            let row = row_at(&self.rows, AddrSnes(self.current_address_scroll));
+            table = table.scroll_to_row(row, Some(Align::TOP));
+        }
+        let mut clicked_target = None;
+        table.body(|body| {
+            body.rows(row_height, self.rows.len(), |mut tr| {
+                let row = &self.rows[tr.index()];
+                tr.set_selected(self.matched_rows.contains(&tr.index()));
+                tr.col(|ui| {
+                    ui.monospace(format!("{:06X}", row.addr.0));
+                });
+                tr.col(|ui| {
+                    ui.monospace(&row.bytes);
+                });
+                tr.col(|ui| match row.target {
+                    Some(target) => {
+                        let link = ui.link(RichText::new(&row.code).monospace());
+                        if link.on_hover_text(format!("Go to ${:06X}", target.0)).clicked() {
+                            clicked_target = Some(target);
+                        }
+                    }
+                    None => {
+                        ui.monospace(&row.code);
+                    }
+                });
+            });
+        });
+        if let Some(target) = clicked_target {
+            self.current_address_scroll = target.0;
+            self.scroll_to_current = true;
+        }
+    }
+}
+
+fn disassembly_rows(disasm: &RomDisassembly) -> Vec<DisassemblyRow> {
+    let rom_bytes = disasm.rom_bytes();
+    let mut rows = Vec::new();
+    for (idx, (start, block)) in disasm.chunks.iter().enumerate() {
+        let end = disasm.chunks.get(idx + 1).map_or(AddrPc(rom_bytes.len() as u32), |&(next, _)| next);
+        let Ok(addr) = AddrSnes::try_from(*start) else { continue };
+        match block {
+            BinaryBlock::Code(code) => rows.extend(code.instructions.iter().map(|ins| {
+                let bytes = &rom_bytes[ins.offset.as_index()..ins.offset.as_index() + ins.opcode.instruction_size()];
+                DisassemblyRow {
+                    addr:   AddrSnes::try_from(ins.offset).unwrap_or(addr),
+                    bytes:  bytes.iter().map(|b| format!("{b:02X}")).join(" "),
+                    code:   ins.display().to_string(),
+                    target: ins.static_target(),
+                }
+            })),
+            BinaryBlock::Data(data) => rows.push(DisassemblyRow {
+                addr,
+                bytes: String::new(),
+                code: format!("; {:?}, {} bytes", data.kind, end.0 - start.0),
+                target: None,
+            }),
+            BinaryBlock::Unknown => rows.push(DisassemblyRow {
+                addr,
+                bytes: String::new(),
+                code: format!("; Unknown, {} bytes", end.0 - start.0),
+                target: None,
+            }),
+            BinaryBlock::EndOfRom => break,
+        }
+    }
+    rows
+}
+
+/// Index of the last row starting at or before `addr`.
+fn row_at(rows: &[DisassemblyRow], addr: AddrSnes) -> usize {
+    rows.partition_point(|row| row.addr <= addr).saturating_sub(1)
+}
+
+/// SNES addresses of the first and last byte of a LoROM image of the given size.
+fn snes_address_range(rom_size: usize) -> RangeInclusive<u32> {
+    let last = AddrSnes::try_from_lorom(AddrPc(rom_size.max(1) as u32 - 1)).map_or(0x008000, |addr| addr.0);
+    0x008000..=last
+}
+
+/// Parses a label from the ROM's symbols, or a hex SNES address optionally prefixed with `$` or `0x`.
+pub fn parse_goto_target(text: &str, rom: &Rom) -> Option<u32> {
+    let text = text.trim();
+    rom.resolve(text).or_else(|| {
+        let hex = text.strip_prefix('$').or_else(|| text.strip_prefix("0x")).unwrap_or(text);
+        u32::from_str_radix(hex, 16).ok()
+    })
+}
+
+mod search {
+    /// Bytes to search for, where `None` matches any byte.
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub struct BytePattern(Vec<Option<u8>>);
+
+    impl BytePattern {
+        /// Parses pairs of hex digits, optionally separated by whitespace, where `??` or a lone `?` matches any byte.
+        /// Returns `None` if the pattern is empty or invalid.
+        pub fn parse(text: &str) -> Option<Self> {
+            let mut bytes = Vec::new();
+            for token in text.split_whitespace() {
+                if token == "?" {
+                    bytes.push(None);
+                    continue;
+                }
+                if token.len() % 2 != 0 {
+                    return None;
+                }
+                for pair in token.as_bytes().chunks(2) {
+                    bytes.push(match *pair {
+                        [b'?', b'?'] => None,
+                        [high, low] => Some(((high as char).to_digit(16)? << 4 | (low as char).to_digit(16)?) as u8),
+                        _ => unreachable!(),
+                    });
+                }
+            }
+            (!bytes.is_empty()).then_some(Self(bytes))
+        }
+
+        /// Returns the offsets of all, possibly overlapping, matches in `haystack`.
+        pub fn find_all(&self, haystack: &[u8]) -> Vec<usize> {
+            haystack
+                .windows(self.0.len())
+                .enumerate()
+                .filter(|(_, window)| self.0.iter().zip(window.iter()).all(|(p, b)| p.map_or(true, |p| p == *b)))
+                .map(|(offset, _)| offset)
+                .collect()
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_goto_target() {
+        let mut rom = Rom::new(vec![0; 0x80000]);
+        rom.load_symbols("00008123 MyRoutine\n0000ABCD ADD\n");
+
+        assert_eq!(parse_goto_target("MyRoutine", &rom), Some(0x008123));
+        assert_eq!(parse_goto_target(" MyRoutine ", &rom), Some(0x008123));
+        assert_eq!(parse_goto_target("ADD", &rom), Some(0x00ABCD), "labels take precedence over hex");
+        assert_eq!(parse_goto_target("$0FF600", &rom), Some(0x0FF600));
+        assert_eq!(parse_goto_target("0x8000", &rom), Some(0x008000));
+        assert_eq!(parse_goto_target("c0ffee", &rom), Some(0xC0FFEE));
+        assert_eq!(parse_goto_target("NotALabel", &rom), None);
+
+        let range = snes_address_range(rom.as_slice().len());
+        assert_eq!(range, 0x008000..=0x0FFFFF);
+        assert!(!range.contains(&0xC0FFEE));
+    }
+
+    #[test]
+    fn test_byte_pattern() {
+        let rom = [0x22, 0x00, 0xF6, 0x0F, 0xA9, 0x22, 0x34, 0x12, 0x0F, 0x22];
+
+        let jsl = BytePattern::parse("22 ?? ?? 0F").unwrap();
+        assert_eq!(jsl.find_all(&rom), [0, 5]);
+        assert_eq!(BytePattern::parse("22????0f"), Some(jsl));
+        assert_eq!(BytePattern::parse("22 ? ? 0F").unwrap().find_all(&rom), [0, 5]);
+        assert_eq!(BytePattern::parse("?? 22").unwrap().find_all(&rom), [4, 8], "wildcards match at the start");
+        assert_eq!(BytePattern::parse("22").unwrap().find_all(&rom), [0, 5, 9]);
+
+        assert!(BytePattern::parse("A9 23").unwrap().find_all(&rom).is_empty());
+        assert!(BytePattern::parse("0F 22 ?? ??").unwrap().find_all(&rom).is_empty(), "matches end within the data");
+
+        assert_eq!(BytePattern::parse(""), None);
+        assert_eq!(BytePattern::parse("2"), None);
+        assert_eq!(BytePattern::parse("GG"), None);
+        assert_eq!(BytePattern::parse("+1"), None);
+    }
+
+    #[test]
+    fn test_row_at() {
+        let row = |addr: u32| DisassemblyRow {
+            addr:   AddrSnes(addr),
+            bytes:  String::new(),
+            code:   String::new(),
+            target: None,
+        };
+        let rows = [row(0x008000), row(0x008002), row(0x008010)];
+        assert_eq!(row_at(&rows, AddrSnes(0x008000)), 0);
+        assert_eq!(row_at(&rows, AddrSnes(0x008005)), 1, "scrolls to the nearest line before the address");
+        assert_eq!(row_at(&rows, AddrSnes(0x00FFFF)), 2);
+        assert_eq!(row_at(&rows, AddrSnes(0x000000)), 0);
+    }
+}
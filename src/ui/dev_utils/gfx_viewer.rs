@@ -0,0 +1,187 @@
+use std::sync::Arc;
+
+use egui::{Color32, ColorImage, DragValue, Image, ScrollArea, TextureHandle, TextureOptions, Ui, WidgetText};
+use smwe_render::color::Abgr1555;
+use smwe_rom::{
+    graphics::gfx_file::{GfxFile, TileFormat, GFX_FILES_META},
+    SmwRom,
+};
+
+use crate::{
+    project::level_cgram,
+    ui::{
+        style::{EditorStyle, ErrorStyle},
+        tool::{DockableEditorTool, DockableEditorToolEnum},
+    },
+};
+
+const TILES_PER_ROW: usize = 16;
+const TILE_ZOOM: f32 = 3.;
+
+/// Shows the tiles of a stock GFX file drawn with a row of the level's CGRAM.
+pub struct UiGfxViewer {
+    smw_rom:    Option<Arc<SmwRom>>,
+    text_error: String,
+
+    file_num:    usize,
+    palette_row: usize,
+    /// All 256 colors of the level palette.
+    cgram:       Vec<Abgr1555>,
+    /// Tiles of the selected file, cleared when the file or palette row changes.
+    texture:     Option<TextureHandle>,
+}
+
+impl UiGfxViewer {
+    pub fn new(smw_rom: anyhow::Result<Arc<SmwRom>>) -> Self {
+        let (smw_rom, text_error) = match smw_rom {
+            Ok(smw_rom) => (Some(smw_rom), String::new()),
+            Err(e) => (None, format!("Cannot parse ROM: {e}")),
+        };
+        let cgram = smw_rom.as_deref().map_or_else(Vec::new, |smw_rom| level_cgram(smw_rom, 0));
+        Self { smw_rom, text_error, file_num: 0, palette_row: 0, cgram, texture: None }
+    }
+}
+
+impl DockableEditorTool for UiGfxViewer {
+    fn update(&mut self, ui: &mut Ui) {
+        if !self.text_error.is_empty() {
+            ui.colored_label(ErrorStyle::get_from_egui(ui.ctx(), |style| style.text_color), &self.text_error);
+            return;
+        }
+
+        self.selectors(ui);
+        ui.separator();
+        self.tiles(ui);
+    }
+
+    fn title(&self) -> WidgetText {
+        "GFX viewer".into()
+    }
+
+    fn kind(&self) -> DockableEditorToolEnum {
+        DockableEditorToolEnum::GfxViewer
+    }
+}
+
+impl UiGfxViewer {
+    fn files(&self) -> &[GfxFile] {
+        match &self.smw_rom {
+            Some(smw_rom) => &smw_rom.gfx.files,
+            None => &[],
+        }
+    }
+
+    fn select_file(&mut self, file_num: usize) {
+        self.file_num = file_num.min(GFX_FILES_META.len() - 1);
+        self.texture = None;
+    }
+
+    fn selectors(&mut self, ui: &mut Ui) {
+        let info = file_info(self.files(), self.file_num);
+        ui.horizontal(|ui| {
+            ui.label("File");
+            let mut file_num = self.file_num;
+            let file =
+                DragValue::new(&mut file_num).clamp_range(0..=GFX_FILES_META.len() - 1).hexadecimal(2, false, true);
+            if ui.add(file).changed() {
+                self.select_file(file_num);
+            }
+            ui.separator();
+            ui.label("Palette row");
+            let uses_whole_cgram = info.is_some_and(|(format, _)| uses_whole_cgram(format));
+            let row = DragValue::new(&mut self.palette_row).clamp_range(0..=0xF).hexadecimal(1, false, true);
+            if ui.add_enabled(!uses_whole_cgram, row).changed() {
+                self.texture = None;
+            }
+            if let Some((format, tile_count)) = info {
+                ui.separator();
+                match format {
+                    TileFormat::Tile3bppMode7 => ui.label(format!("Format: {format}, shown as 8BPP")),
+                    _ => ui.label(format!("Format: {format}")),
+                };
+                ui.label(format!("{tile_count} tiles"));
+            }
+        });
+    }
+
+    fn tiles(&mut self, ui: &mut Ui) {
+        let Some(smw_rom) = &self.smw_rom else { return };
+        let Some(file) = smw_rom.gfx.files.get(self.file_num) else { return };
+        let palette = file_palette(&self.cgram, file.tile_format, self.palette_row);
+        let texture = self.texture.get_or_insert_with(|| {
+            let image = render_file(file, palette);
+            ui.ctx().load_texture(format!("gfx_file_{:02X}", self.file_num), image, TextureOptions::NEAREST)
+        });
+        ScrollArea::vertical().show(ui, |ui| {
+            ui.add(Image::new((texture.id(), texture.size_vec2() * TILE_ZOOM)));
+        });
+    }
+}
+
+/// Format and number of tiles of file `file_num`, or `None` if it isn't loaded.
+fn file_info(files: &[GfxFile], file_num: usize) -> Option<(TileFormat, usize)> {
+    files.get(file_num).map(|file| (file.tile_format, file.tiles.len()))
+}
+
+/// Whether color indices of tiles in `format` refer to the whole CGRAM rather than a palette row. Mode 7 graphics
+/// are drawn like 8BPP ones.
+fn uses_whole_cgram(format: TileFormat) -> bool {
+    matches!(format, TileFormat::Tile8bpp | TileFormat::Tile3bppMode7)
+}
+
+/// Returns the colors that color indices of tiles in `format` refer to.
+fn file_palette(cgram: &[Abgr1555], format: TileFormat, row: usize) -> &[Abgr1555] {
+    if uses_whole_cgram(format) {
+        cgram
+    } else {
+        cgram.get(row * 16..).unwrap_or_default()
+    }
+}
+
+/// Draws the tiles of a GFX file in rows of [`TILES_PER_ROW`].
+fn render_file(file: &GfxFile, palette: &[Abgr1555]) -> ColorImage {
+    let rows = (file.tiles.len() + TILES_PER_ROW - 1) / TILES_PER_ROW;
+    let mut image = ColorImage::new([TILES_PER_ROW * 8, rows * 8], Color32::TRANSPARENT);
+    for (i, tile) in file.tiles.iter().enumerate() {
+        let (tile_x, tile_y) = (i % TILES_PER_ROW * 8, i / TILES_PER_ROW * 8);
+        for (pixel, &color) in tile.to_bgr555(palette).iter().enumerate() {
+            image[(tile_x + pixel % 8, tile_y + pixel / 8)] = Color32::from(color);
+        }
+    }
+    image
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_2bpp_file() {
+        let mut viewer = UiGfxViewer::new(Err(anyhow::anyhow!("No ROM")));
+        assert!(viewer.files().is_empty());
+        // File N has N + 1 blank tiles.
+        let files: Vec<_> = GFX_FILES_META
+            .iter()
+            .enumerate()
+            .map(|(i, &(format, _))| GfxFile::from_decompressed(format, &vec![0; format.tile_size() * (i + 1)]))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        viewer.select_file(0x28);
+        assert_eq!(file_info(&files, viewer.file_num), Some((TileFormat::Tile2bpp, 0x29)));
+        viewer.select_file(0x27);
+        assert_eq!(file_info(&files, viewer.file_num), Some((TileFormat::Tile3bppMode7, 0x28)));
+        viewer.select_file(0x100);
+        assert_eq!(viewer.file_num, 0x33);
+    }
+
+    #[test]
+    fn test_file_palette() {
+        let cgram: Vec<Abgr1555> = (0..0x100).map(Abgr1555).collect();
+        assert_eq!(file_palette(&cgram, TileFormat::Tile2bpp, 2)[0].0, 0x20);
+        assert_eq!(file_palette(&cgram, TileFormat::Tile3bppMode7, 2).len(), 0x100, "Mode 7 is drawn as 8BPP");
+        assert!(file_palette(&[], TileFormat::Tile4bpp, 1).is_empty());
+    }
+}
@@ -1 +1,6 @@
 pub mod address_converter;
+pub mod code_editor;
+pub mod disassembler;
+pub mod gfx_viewer;
+pub mod rom_info;
+pub mod tiles16x16;
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use egui::{Grid, RichText, Ui, WidgetText};
+use smwe_emu::rom::Rom;
+use smwe_rom::internal_header::RomInternalHeader;
+
+use crate::ui::{
+    style::{EditorStyle, ErrorStyle},
+    tool::{DockableEditorTool, DockableEditorToolEnum},
+};
+
+/// Shows the ROM's internal header and whether its checksum is valid.
+pub struct UiRomInfo {
+    summary: Result<RomSummary, String>,
+}
+
+/// Header fields formatted for display, computed once when the tool is opened.
+struct RomSummary {
+    fields:            Vec<(&'static str, String)>,
+    stored_checksum:   u16,
+    computed_checksum: u16,
+}
+
+impl UiRomInfo {
+    pub fn new(rom: Arc<Rom>, had_smc_header: bool) -> Self {
+        let summary = smwe_rom::snes_utils::rom::Rom::new(rom.as_slice().to_vec())
+            .map_err(|e| e.to_string())
+            .and_then(|rom| RomInternalHeader::parse(&rom).map_err(|e| e.to_string()))
+            .map(|header| RomSummary::new(&header, rom.as_slice(), had_smc_header));
+        if let Err(e) = &summary {
+            log::error!("Cannot read ROM info: {e}");
+        }
+        Self { summary }
+    }
+}
+
+impl RomSummary {
+    fn new(header: &RomInternalHeader, rom_bytes: &[u8], had_smc_header: bool) -> Self {
+        let fields = vec![
+            ("Internal name", header.internal_rom_name.trim_end().to_string()),
+            ("Map mode", header.map_mode.to_string()),
+            ("ROM type", header.rom_type.to_string()),
+            ("Region", header.region_code.to_string()),
+            ("Version", format!("1.{}", header.version_number)),
+            ("ROM size", format!("{} KB", header.rom_size_in_kb())),
+            ("SRAM size", format!("{} KB", header.sram_size_in_kb())),
+            ("SMC header", String::from(if had_smc_header { "Yes" } else { "No" })),
+        ];
+        let (_, stored_checksum) = RomInternalHeader::read_checksum(rom_bytes).unwrap_or_default();
+        let (_, computed_checksum) = RomInternalHeader::compute_checksum(rom_bytes);
+        Self { fields, stored_checksum, computed_checksum }
+    }
+
+    fn checksum_valid(&self) -> bool {
+        self.stored_checksum == self.computed_checksum
+    }
+}
+
+impl DockableEditorTool for UiRomInfo {
+    fn update(&mut self, ui: &mut Ui) {
+        let error_color = ErrorStyle::get_from_egui(ui.ctx(), |style| style.text_color);
+        let summary = match &self.summary {
+            Ok(summary) => summary,
+            Err(e) => {
+                ui.colored_label(error_color, e);
+                return;
+            }
+        };
+
+        Grid::new("rom_info.fields").num_columns(2).striped(true).show(ui, |ui| {
+            for (name, value) in summary.fields.iter() {
+                ui.label(*name);
+                ui.monospace(value);
+                ui.end_row();
+            }
+
+            ui.label("Checksum");
+            let text = format!("${:04X} (computed ${:04X})", summary.stored_checksum, summary.computed_checksum);
+            if summary.checksum_valid() {
+                ui.monospace(text);
+            } else {
+                ui.label(RichText::new(text).monospace().color(error_color))
+                    .on_hover_text("The checksum stored in the header doesn't match the ROM's contents.");
+            }
+            ui.end_row();
+        });
+    }
+
+    fn title(&self) -> WidgetText {
+        "ROM info".into()
+    }
+
+    fn kind(&self) -> DockableEditorToolEnum {
+        DockableEditorToolEnum::RomInfo
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use smwe_rom::internal_header::{MapMode, RegionCode, RomType};
+
+    use super::*;
+
+    #[test]
+    fn test_rom_summary() {
+        let header = RomInternalHeader {
+            internal_rom_name: String::from("SUPER MARIOWORLD     "),
+            map_mode:          MapMode::SlowLoRom,
+            rom_type:          RomType::RomRamSram,
+            rom_size:          0x09,
+            sram_size:         0x01,
+            region_code:       RegionCode::NorthAmerica,
+            developer_id:      0x01,
+            version_number:    0x00,
+            interrupt_vectors: Vec::new(),
+        };
+        let mut rom_bytes = vec![0; 0x80000];
+        header.write_to(&mut rom_bytes);
+
+        let summary = RomSummary::new(&header, &rom_bytes, true);
+        assert_eq!(summary.fields, [
+            ("Internal name", String::from("SUPER MARIOWORLD")),
+            ("Map mode", String::from("LoROM")),
+            ("ROM type", String::from("ROM + RAM + SRAM")),
+            ("Region", String::from("North America")),
+            ("Version", String::from("1.0")),
+            ("ROM size", String::from("512 KB")),
+            ("SRAM size", String::from("2 KB")),
+            ("SMC header", String::from("Yes")),
+        ]);
+        assert!(summary.checksum_valid());
+
+        rom_bytes[0x1234] = 0x42;
+        let summary = RomSummary::new(&header, &rom_bytes, false);
+        assert!(!summary.checksum_valid());
+        assert_eq!(summary.computed_checksum, summary.stored_checksum.wrapping_add(0x42));
+        assert_eq!(summary.fields[7].1, "No");
+    }
+}
@@ -0,0 +1,141 @@
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
+use egui::{Color32, ColorImage, DragValue, Image, ScrollArea, TextureHandle, TextureOptions, Ui, Vec2, WidgetText};
+use smwe_render::color::Abgr1555;
+use smwe_rom::{graphics::LevelPreviewState, objects::tilesets::TILESETS_COUNT, SmwRom};
+
+use crate::{
+    project::level_cgram,
+    ui::{
+        style::{EditorStyle, ErrorStyle},
+        tool::{DockableEditorTool, DockableEditorToolEnum},
+    },
+};
+
+const BLOCKS_PER_ROW: usize = 16;
+const BLOCK_COUNT: usize = 0x200;
+const BLOCK_DISPLAY_SIZE: f32 = 32.;
+
+/// Shows the Map16 blocks of a tileset drawn with one of the level foreground palettes.
+pub struct UiTiles16x16 {
+    smw_rom:    Option<Arc<SmwRom>>,
+    text_error: String,
+
+    tileset:     usize,
+    palette_idx: usize,
+    palette:     Vec<Abgr1555>,
+    /// Rendered blocks by tile number, cleared when the tileset or palette changes.
+    textures:    HashMap<usize, TextureHandle>,
+}
+
+impl UiTiles16x16 {
+    pub fn new(smw_rom: anyhow::Result<Arc<SmwRom>>) -> Self {
+        let (smw_rom, text_error) = match smw_rom {
+            Ok(smw_rom) => (Some(smw_rom), String::new()),
+            Err(e) => (None, format!("Cannot parse ROM: {e}")),
+        };
+        let mut tiles =
+            Self { smw_rom, text_error, tileset: 0, palette_idx: 0, palette: Vec::new(), textures: HashMap::new() };
+        tiles.update_palette();
+        tiles
+    }
+}
+
+impl DockableEditorTool for UiTiles16x16 {
+    fn update(&mut self, ui: &mut Ui) {
+        if !self.text_error.is_empty() {
+            ui.colored_label(ErrorStyle::get_from_egui(ui.ctx(), |style| style.text_color), &self.text_error);
+            return;
+        }
+
+        self.selectors(ui);
+        ui.separator();
+        self.blocks(ui);
+    }
+
+    fn title(&self) -> WidgetText {
+        "16x16 tiles".into()
+    }
+
+    fn kind(&self) -> DockableEditorToolEnum {
+        DockableEditorToolEnum::Tiles16x16
+    }
+}
+
+impl UiTiles16x16 {
+    fn update_palette(&mut self) {
+        let Some(smw_rom) = &self.smw_rom else { return };
+        self.palette = level_cgram(smw_rom, self.palette_idx);
+        self.textures.clear();
+    }
+
+    fn selectors(&mut self, ui: &mut Ui) {
+        let palette_count =
+            self.smw_rom.as_ref().map_or(1, |rom| rom.gfx.color_palettes.lv_specific_set.fg_palettes.len());
+        ui.horizontal(|ui| {
+            ui.label("Tileset");
+            let tileset = DragValue::new(&mut self.tileset).clamp_range(0..=TILESETS_COUNT - 1);
+            if ui.add(tileset).changed() {
+                self.textures.clear();
+            }
+            ui.separator();
+            ui.label("Palette");
+            let palette = DragValue::new(&mut self.palette_idx).clamp_range(0..=palette_count.saturating_sub(1));
+            if ui.add(palette).changed() {
+                self.update_palette();
+            }
+        });
+    }
+
+    fn blocks(&mut self, ui: &mut Ui) {
+        let Some(smw_rom) = &self.smw_rom else { return };
+        let (palette, textures) = (&self.palette, &mut self.textures);
+        let row_count = BLOCK_COUNT / BLOCKS_PER_ROW;
+        ui.spacing_mut().item_spacing = Vec2::ZERO;
+        ScrollArea::vertical().show_rows(ui, BLOCK_DISPLAY_SIZE, row_count, |ui, rows| {
+            for row in visible_blocks(rows, self.tileset).chunks(BLOCKS_PER_ROW) {
+                ui.horizontal(|ui| {
+                    for &(tile_num, tileset) in row {
+                        let texture = textures.entry(tile_num).or_insert_with(|| {
+                            let image = render_block(smw_rom, palette, tile_num, tileset);
+                            ui.ctx().load_texture(format!("map16_block_{tile_num:03X}"), image, TextureOptions::NEAREST)
+                        });
+                        let size = Vec2::splat(BLOCK_DISPLAY_SIZE);
+                        ui.add(Image::new((texture.id(), size))).on_hover_text(format!("{tile_num:03X}"));
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// Returns the tile numbers of the blocks in the given rows of the grid, each paired with the tileset to use.
+fn visible_blocks(rows: Range<usize>, tileset: usize) -> Vec<(usize, usize)> {
+    let start = (rows.start * BLOCKS_PER_ROW).min(BLOCK_COUNT);
+    let end = (rows.end * BLOCKS_PER_ROW).min(BLOCK_COUNT);
+    (start..end).map(|tile_num| (tile_num, tileset)).collect()
+}
+
+/// Renders a block as a 16x16 image. Blocks that can't be found are drawn in magenta.
+fn render_block(smw_rom: &SmwRom, palette: &[Abgr1555], tile_num: usize, tileset: usize) -> ColorImage {
+    let Some(block) = smw_rom.map16_tilesets.get_map16_tile(tile_num, tileset) else {
+        return ColorImage::new([16, 16], Color32::from(Abgr1555::MAGENTA));
+    };
+    let pixels = block.render(&smw_rom.gfx, palette, tileset, &LevelPreviewState::default(), 0);
+    ColorImage { size: [16, 16], pixels: pixels.into_iter().map(Color32::from).collect() }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_blocks() {
+        assert_eq!(visible_blocks(0..1, 0), (0..0x10).map(|tile_num| (tile_num, 0)).collect::<Vec<_>>());
+        assert_eq!(visible_blocks(2..4, 3), (0x20..0x40).map(|tile_num| (tile_num, 3)).collect::<Vec<_>>());
+        assert_eq!(visible_blocks(30..40, 1).last(), Some(&(0x1FF, 1)), "rows past the last block are empty");
+        assert!(visible_blocks(32..33, 0).is_empty());
+    }
+}
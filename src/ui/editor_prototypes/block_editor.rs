@@ -1,17 +1,46 @@
+use std::sync::Arc;
+
 use egui::*;
 use egui_extras::{Column, TableBuilder};
 use inline_tweak::tweak;
+use smwe_render::color::Abgr1555;
+use smwe_rom::{
+    graphics::LevelPreviewState,
+    objects::{
+        map16::{Block, Tile8x8, N_PIXELS_IN_BLOCK},
+        tilesets::TILESETS_COUNT,
+    },
+    SmwRom,
+};
+
+use crate::{
+    project::level_cgram,
+    ui::{
+        style::{EditorStyle, ErrorStyle},
+        tool::{DockableEditorTool, DockableEditorToolEnum},
+    },
+};
 
-use crate::ui::tool::DockableEditorTool;
+const MAX_BLOCK_NUM: usize = 0x1FF;
+const MAX_TILE_NUM: u16 = 0x3FF;
+const PREVIEW_SIZE: f32 = 64.;
+const SUBTILE_NAMES: [&str; 4] = ["Upper left", "Lower left", "Upper right", "Lower right"];
+const EMPTY_BLOCK: Block =
+    Block { upper_left: Tile8x8(0), lower_left: Tile8x8(0), upper_right: Tile8x8(0), lower_right: Tile8x8(0) };
 
 pub struct UiBlockEditor {
+    smw_rom:    Option<Arc<SmwRom>>,
+    text_error: String,
+
     editing_modes:    Vec<String>,
     editing_mode_idx: usize,
 
-    tile_palettes:         [u32; 4],
-    tile_horizontal_flips: [bool; 4],
-    tile_vertical_flips:   [bool; 4],
-    tile_priorities:       [bool; 4],
+    block_num: usize,
+    tileset:   usize,
+    palette:   Vec<Abgr1555>,
+    /// Copy of the selected Map16 block with the edits made to its 8x8 tiles.
+    block:     Block,
+    preview:   Option<TextureHandle>,
 
     collision_types:    Vec<String>,
     collision_type_idx: usize,
@@ -19,16 +48,25 @@ pub struct UiBlockEditor {
     highlight_same_type: bool,
 }
 
-impl Default for UiBlockEditor {
-    fn default() -> Self {
-        UiBlockEditor {
-            editing_modes:         vec![String::from("Blocks"), String::from("Tiles")],
-            editing_mode_idx:      0,
-            tile_palettes:         [5, 5, 5, 5],
-            tile_horizontal_flips: [false, true, false, true],
-            tile_vertical_flips:   [false, false, false, false],
-            tile_priorities:       [false, false, false, false],
-            collision_types:       vec![
+impl UiBlockEditor {
+    /// Creates the editor. Without a ROM, blocks can't be loaded or previewed.
+    pub fn new(smw_rom: Option<anyhow::Result<Arc<SmwRom>>>) -> Self {
+        let (smw_rom, text_error) = match smw_rom.transpose() {
+            Ok(smw_rom) => (smw_rom, String::new()),
+            Err(e) => (None, format!("Cannot parse ROM: {e}")),
+        };
+        let palette = smw_rom.as_deref().map_or_else(Vec::new, |smw_rom| level_cgram(smw_rom, 0));
+        let mut editor = UiBlockEditor {
+            smw_rom,
+            text_error,
+            editing_modes: vec![String::from("Blocks"), String::from("Tiles")],
+            editing_mode_idx: 0,
+            block_num: 0,
+            tileset: 0,
+            palette,
+            block: EMPTY_BLOCK,
+            preview: None,
+            collision_types: vec![
                 String::from("Hurt Mario"),
                 String::from("Collect coin"),
                 String::from("Eject coin"),
@@ -37,9 +75,11 @@ impl Default for UiBlockEditor {
                 String::from("Eject feather"),
                 String::from("Eject 1-up"),
             ],
-            collision_type_idx:    0,
-            highlight_same_type:   false,
-        }
+            collision_type_idx: 0,
+            highlight_same_type: false,
+        };
+        editor.select_block();
+        editor
     }
 }
 
@@ -63,11 +103,47 @@ impl DockableEditorTool for UiBlockEditor {
     fn title(&self) -> WidgetText {
         "Block editor".into()
     }
+
+    fn kind(&self) -> DockableEditorToolEnum {
+        DockableEditorToolEnum::BlockEditor
+    }
 }
 
 impl UiBlockEditor {
+    /// Loads the selected block from the ROM, discarding edits made to the previous one.
+    fn select_block(&mut self) {
+        let Some(smw_rom) = &self.smw_rom else { return };
+        self.block = smw_rom.map16_tilesets.get_map16_tile(self.block_num, self.tileset).unwrap_or(EMPTY_BLOCK);
+        self.preview = None;
+    }
+
     fn mappings(&mut self, ui: &mut Ui) {
         ui.heading("Mappings");
+
+        if !self.text_error.is_empty() {
+            ui.colored_label(ErrorStyle::get_from_egui(ui.ctx(), |style| style.text_color), &self.text_error);
+            return;
+        }
+        if self.smw_rom.is_none() {
+            ui.weak("Open a project to load blocks");
+            return;
+        }
+
+        let mut changed = false;
+        Grid::new("block-editor-mappings").num_columns(2).show(ui, |ui| {
+            ui.label("Block");
+            changed |= ui
+                .add(DragValue::new(&mut self.block_num).clamp_range(0..=MAX_BLOCK_NUM).hexadecimal(3, false, true))
+                .changed();
+            ui.end_row();
+
+            ui.label("Tileset");
+            changed |= ui.add(DragValue::new(&mut self.tileset).clamp_range(0..=TILESETS_COUNT - 1)).changed();
+            ui.end_row();
+        });
+        if changed {
+            self.select_block();
+        }
     }
 
     fn vram(&mut self, ui: &mut Ui) {
@@ -77,6 +153,16 @@ impl UiBlockEditor {
     fn appearance(&mut self, ui: &mut Ui) {
         ui.heading("Appearance");
 
+        if let Some(smw_rom) = &self.smw_rom {
+            let preview = self.preview.get_or_insert_with(|| {
+                let pixels =
+                    self.block.render(&smw_rom.gfx, &self.palette, self.tileset, &LevelPreviewState::default(), 0);
+                ui.ctx().load_texture("block_editor_preview", preview_image(pixels), TextureOptions::NEAREST)
+            });
+            ui.add(Image::new((preview.id(), Vec2::splat(PREVIEW_SIZE))));
+        }
+
+        let mut changed = false;
         TableBuilder::new(ui)
             .striped(true)
             .cell_layout(Layout::left_to_right(Align::Min))
@@ -86,7 +172,7 @@ impl UiBlockEditor {
                     ui.label("  ");
                 });
                 tr.col(|ui| {
-                    ui.label("Source");
+                    ui.label("Tile");
                 });
                 tr.col(|ui| {
                     ui.label("Palette");
@@ -102,29 +188,41 @@ impl UiBlockEditor {
                 });
             })
             .body(|tb| {
-                let mut i = 0;
+                let mut tiles = self.block.tiles_mut().into_iter().zip(SUBTILE_NAMES);
                 tb.rows(tweak!(15.0), 4, |mut tr| {
+                    let Some((tile, name)) = tiles.next() else { return };
+                    let (mut tile_num, mut palette) = (tile.tile_number(), tile.palette());
+                    let (mut flip_x, mut flip_y, mut priority) = (tile.flip_x(), tile.flip_y(), tile.priority());
                     tr.col(|ui| {
-                        ui.label(i.to_string());
+                        ui.label(name);
                     });
                     tr.col(|ui| {
-                        ui.label(format!("muncher: {i}"));
+                        let tile_num =
+                            DragValue::new(&mut tile_num).clamp_range(0..=MAX_TILE_NUM).hexadecimal(3, false, true);
+                        changed |= ui.add(tile_num).changed();
                     });
                     tr.col(|ui| {
-                        ui.add(DragValue::new(&mut self.tile_palettes[i]).clamp_range(0..=7));
+                        changed |= ui.add(DragValue::new(&mut palette).clamp_range(0..=7)).changed();
                     });
                     tr.col(|ui| {
-                        ui.checkbox(&mut self.tile_horizontal_flips[i], "");
+                        changed |= ui.checkbox(&mut flip_x, "").changed();
                     });
                     tr.col(|ui| {
-                        ui.checkbox(&mut self.tile_vertical_flips[i], "");
+                        changed |= ui.checkbox(&mut flip_y, "").changed();
                     });
                     tr.col(|ui| {
-                        ui.checkbox(&mut self.tile_priorities[i], "");
+                        changed |= ui.checkbox(&mut priority, "").changed();
                     });
-                    i += 1;
+                    tile.set_tile_number(tile_num);
+                    tile.set_palette(palette);
+                    tile.set_flip_x(flip_x);
+                    tile.set_flip_y(flip_y);
+                    tile.set_priority(priority);
                 })
             });
+        if changed {
+            self.preview = None;
+        }
     }
 
     fn behaviour(&mut self, ui: &mut Ui) {
@@ -145,3 +243,32 @@ impl UiBlockEditor {
         ui.checkbox(&mut self.highlight_same_type, "Highlight same type");
     }
 }
+
+/// Converts a rendered block into an image for the preview.
+fn preview_image(pixels: [Rgba; N_PIXELS_IN_BLOCK]) -> ColorImage {
+    ColorImage { size: [16, 16], pixels: pixels.into_iter().map(Color32::from).collect() }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use smwe_rom::graphics::gfx_file::{Tile, N_PIXELS_IN_TILE};
+
+    use super::*;
+
+    #[test]
+    fn test_flip_changes_preview() {
+        // Each column of the tile uses a different colour.
+        let tile = Tile { color_indices: (0..N_PIXELS_IN_TILE).map(|i| (i % 8) as u8 + 1).collect() };
+        let palette: Vec<Abgr1555> = (0..0x80).map(Abgr1555).collect();
+        let mut block = EMPTY_BLOCK;
+        let before = preview_image(block.render_tiles([&tile; 4], &palette));
+
+        block.tiles_mut()[0].set_flip_x(true);
+        let after = preview_image(block.render_tiles([&tile; 4], &palette));
+        assert_ne!(before.pixels, after.pixels);
+        assert_eq!(after.pixels[0], before.pixels[7], "upper left tile is mirrored");
+        assert_eq!(after.pixels[8..16], before.pixels[8..16], "upper right tile is unchanged");
+    }
+}
@@ -5,6 +5,10 @@ use super::UiLevelEditor;
 
 impl UiLevelEditor {
     pub(super) fn left_panel(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Level");
+            self.level.show(ui);
+        });
         if cfg!(debug_assertions) {
             ui.add_space(ui.spacing().item_spacing.y);
             ui.group(|ui| {
@@ -17,12 +21,6 @@ impl UiLevelEditor {
     fn debug_panel(&mut self, ui: &mut Ui) {
         let mut need_update_level = false;
         let mut need_update = false;
-        need_update_level |= {
-            let switcher = ValueSwitcher::new(&mut self.level_num, "Level", ValueSwitcherButtons::MinusPlus)
-                .range(0..=0x1FF)
-                .hexadecimal(3, false, true);
-            ui.add(switcher).changed()
-        };
         need_update_level |= {
             let switcher = ValueSwitcher::new(&mut self.sprite_id, "Sprite ID", ValueSwitcherButtons::MinusPlus)
                 .range(0..=0xFF)
@@ -49,12 +47,8 @@ impl UiLevelEditor {
         ui.checkbox(&mut self.always_show_grid, "Always show grid");
 
         if need_update_level {
-            self.update_cpu();
-            self.update_level_properties();
-            self.update_layer1();
-            self.update_cpu_sprite_id();
-        }
-        if need_update || need_update_level {
+            self.refresh_level();
+        } else if need_update {
             self.update_renderer();
         }
     }
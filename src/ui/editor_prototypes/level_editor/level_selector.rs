@@ -0,0 +1,68 @@
+use egui::{DragValue, Response, Ui};
+
+/// The level shown in the editor, flagged for a refresh of the emulator's memory whenever it changes.
+#[derive(Debug)]
+pub(super) struct LevelSelector {
+    level_num:     u16,
+    needs_refresh: bool,
+}
+
+impl LevelSelector {
+    pub const MAX_LEVEL_NUM: u16 = 0x1FF;
+
+    pub fn new(level_num: u16) -> Self {
+        Self { level_num: level_num.min(Self::MAX_LEVEL_NUM), needs_refresh: false }
+    }
+
+    pub fn level_num(&self) -> u16 {
+        self.level_num
+    }
+
+    /// Selects the level and flags it for a refresh. Returns `false` if the level doesn't exist or is already
+    /// selected.
+    pub fn select(&mut self, level_num: u16) -> bool {
+        if level_num > Self::MAX_LEVEL_NUM || level_num == self.level_num {
+            return false;
+        }
+        self.level_num = level_num;
+        self.needs_refresh = true;
+        true
+    }
+
+    /// Returns whether the level has changed since the last call.
+    pub fn take_refresh(&mut self) -> bool {
+        std::mem::take(&mut self.needs_refresh)
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) -> Response {
+        let mut level_num = self.level_num;
+        let response = ui.add(
+            DragValue::new(&mut level_num).clamp_range(0..=Self::MAX_LEVEL_NUM).hexadecimal(3, false, true).prefix("$"),
+        );
+        self.select(level_num);
+        response
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_level() {
+        let mut level = LevelSelector::new(0x105);
+        assert!(!level.take_refresh());
+
+        assert!(level.select(0x106));
+        assert_eq!(level.level_num(), 0x106);
+        assert!(level.take_refresh());
+        assert!(!level.take_refresh(), "the flag is cleared once taken");
+
+        assert!(!level.select(0x106));
+        assert!(!level.select(0x200));
+        assert_eq!(level.level_num(), 0x106);
+        assert!(!level.take_refresh());
+    }
+}
@@ -1,6 +1,7 @@
 mod central_panel;
 mod left_panel;
 mod level_renderer;
+mod level_selector;
 mod object_layer;
 mod properties;
 
@@ -9,15 +10,20 @@ use std::sync::{Arc, Mutex};
 use egui::{CentralPanel, SidePanel, Ui, WidgetText, *};
 use smwe_emu::{emu::CheckedMem, rom::Rom, Cpu};
 
-use self::{level_renderer::LevelRenderer, object_layer::EditableObjectLayer, properties::LevelProperties};
-use crate::ui::tool::DockableEditorTool;
+use self::{
+    level_renderer::LevelRenderer,
+    level_selector::LevelSelector,
+    object_layer::EditableObjectLayer,
+    properties::LevelProperties,
+};
+use crate::ui::tool::{DockableEditorTool, DockableEditorToolEnum};
 
 pub struct UiLevelEditor {
     gl:             Arc<glow::Context>,
     cpu:            Cpu,
     level_renderer: Arc<Mutex<LevelRenderer>>,
 
-    level_num:      u16,
+    level:          LevelSelector,
     blue_pswitch:   bool,
     silver_pswitch: bool,
     on_off_switch:  bool,
@@ -43,7 +49,7 @@ impl UiLevelEditor {
             gl,
             cpu: Cpu::new(CheckedMem::new(rom)),
             level_renderer,
-            level_num: 0x105,
+            level: LevelSelector::new(0x105),
             blue_pswitch: false,
             silver_pswitch: false,
             on_off_switch: false,
@@ -72,6 +78,9 @@ impl DockableEditorTool for UiLevelEditor {
         self.pixels_per_point = ui.ctx().pixels_per_point();
 
         SidePanel::left("level_editor.left_panel").resizable(false).show_inside(ui, |ui| self.left_panel(ui));
+        if self.level.take_refresh() {
+            self.refresh_level();
+        }
 
         CentralPanel::default()
             .frame(Frame::none().inner_margin(0.).fill(Color32::GRAY))
@@ -96,6 +105,10 @@ impl DockableEditorTool for UiLevelEditor {
         "Level Editor".into()
     }
 
+    fn kind(&self) -> DockableEditorToolEnum {
+        DockableEditorToolEnum::LevelEditor
+    }
+
     fn on_closed(&mut self) {
         self.level_renderer.lock().unwrap().destroy(&self.gl);
     }
@@ -104,7 +117,7 @@ impl DockableEditorTool for UiLevelEditor {
 // Internals
 impl UiLevelEditor {
     fn init_cpu(&mut self) {
-        smwe_emu::emu::decompress_sublevel(&mut self.cpu, self.level_num);
+        smwe_emu::emu::decompress_sublevel(&mut self.cpu, self.level.level_num());
         println!("Updated CPU");
         self.level_renderer.lock().unwrap().upload_level(&self.gl, &mut self.cpu);
         self.update_level_properties();
@@ -112,11 +125,20 @@ impl UiLevelEditor {
     }
 
     fn update_cpu(&mut self) {
-        smwe_emu::emu::decompress_extram(&mut self.cpu, self.level_num);
+        smwe_emu::emu::decompress_extram(&mut self.cpu, self.level.level_num());
         println!("Updated CPU");
         self.level_renderer.lock().unwrap().upload_level(&self.gl, &mut self.cpu);
     }
 
+    /// Decompresses the selected level and redraws it.
+    fn refresh_level(&mut self) {
+        self.update_cpu();
+        self.update_level_properties();
+        self.update_layer1();
+        self.update_cpu_sprite_id();
+        self.update_renderer();
+    }
+
     fn update_level_properties(&mut self) {
         self.level_properties = LevelProperties::parse_from_ram(&mut self.cpu);
     }
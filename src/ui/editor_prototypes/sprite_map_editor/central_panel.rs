@@ -9,7 +9,11 @@ use smwe_math::coordinates::*;
 use smwe_render::tile_renderer::TileUniforms;
 use smwe_widgets::value_switcher::{ValueSwitcher, ValueSwitcherButtons};
 
-use super::{keyboard_shortcuts::*, UiSpriteMapEditor};
+use super::{
+    keyboard_shortcuts::*,
+    sprite_tiles::{Alignment, Axis},
+    UiSpriteMapEditor,
+};
 use crate::ui::editing_mode::*;
 
 impl UiSpriteMapEditor {
@@ -36,6 +40,15 @@ impl UiSpriteMapEditor {
         self.editing_mode_selector(ui);
         ui.separator();
 
+        ui.toggle_value(&mut self.snap_new_tiles, icons::MAGNET)
+            .on_hover_text("Snap new tiles to the grid. Hold Shift while dragging to snap moved tiles.");
+        ui.add_enabled_ui(self.selected_sprite_tile_indices.len() >= 2, |ui| {
+            ui.menu_button(icons::ALIGN_LEFT, |ui| self.arrangement_menu(ui))
+                .response
+                .on_hover_text("Align or distribute selected tiles");
+        });
+        ui.separator();
+
         ui.horizontal(|ui| {
             let zoom_slider = Slider::new(&mut self.zoom, Self::MIN_ZOOM..=Self::MAX_ZOOM).step_by(0.25).suffix("x");
             ui.label(icons::MAGNIFYING_GLASS_PLUS);
@@ -45,6 +58,31 @@ impl UiSpriteMapEditor {
         ui.add_space(ui.available_width());
     }
 
+    fn arrangement_menu(&mut self, ui: &mut Ui) {
+        for (label, alignment) in [
+            ("Align left edges", Alignment::Left),
+            ("Align horizontal centers", Alignment::CenterX),
+            ("Align right edges", Alignment::Right),
+            ("Align top edges", Alignment::Top),
+            ("Align vertical centers", Alignment::CenterY),
+            ("Align bottom edges", Alignment::Bottom),
+        ] {
+            if ui.button(label).clicked() {
+                self.align_selected_tiles(alignment);
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        let can_distribute = self.selected_sprite_tile_indices.len() >= 3;
+        for (label, axis) in [("Distribute horizontally", Axis::Horizontal), ("Distribute vertically", Axis::Vertical)]
+        {
+            if ui.add_enabled(can_distribute, Button::new(label)).clicked() {
+                self.distribute_selected_tiles(axis);
+                ui.close_menu();
+            }
+        }
+    }
+
     pub(super) fn editing_mode_selector(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
             duplicate! {
@@ -161,9 +199,12 @@ impl UiSpriteMapEditor {
         if self.always_show_grid || ui.input(|i| i.modifiers.shift_only()) {
             let spacing = self.zoom * self.tile_size_px / self.pixels_per_point;
             let stroke = Stroke::new(1., Color32::from_white_alpha(tweak!(70)));
-            for cell in 0..33 {
-                let position = cell as f32 * spacing;
+            for row in 0..=self.grid_size.y as u32 {
+                let position = row as f32 * spacing;
                 ui.painter().hline(canvas_rect.min.x..=canvas_rect.max.x, canvas_rect.min.y + position, stroke);
+            }
+            for column in 0..=self.grid_size.x as u32 {
+                let position = column as f32 * spacing;
                 ui.painter().vline(canvas_rect.min.x + position, canvas_rect.min.y..=canvas_rect.max.y, stroke);
             }
         }
@@ -171,8 +212,7 @@ impl UiSpriteMapEditor {
         // DEBUG: show selection bounds
         #[cfg(debug_assertions)]
         if self.debug_selection_bounds {
-            if let Some(mut bounds) = self.selection_bounds {
-                bounds.0.max += Vec2::splat(self.tile_size_px);
+            if let Some(bounds) = self.selection_bounds {
                 ui.painter().rect_stroke(
                     bounds.to_screen(self.pixels_per_point, self.zoom).0.translate(canvas_rect.left_top().to_vec2()),
                     Rounding::ZERO,
@@ -188,11 +228,7 @@ impl UiSpriteMapEditor {
             let relative_pointer_offset = OnScreen(hover_pos - canvas_rect.left_top());
             let relative_pointer_pos = relative_pointer_offset.to_pos2();
 
-            let grid_cell_pos = relative_pointer_offset
-                .to_grid(self.pixels_per_point, self.zoom, self.tile_size_px)
-                .clamp(OnGrid::<Vec2>::ZERO, self.grid_size)
-                .to_canvas(self.tile_size_px)
-                .to_pos2();
+            let pointer_canvas_pos = relative_pointer_pos.to_canvas(self.pixels_per_point, self.zoom);
 
             let (holding_shift_only, holding_ctrl_only) =
                 ui.input(|input| (input.modifiers.shift_only(), input.modifiers.command_only()));
@@ -204,7 +240,7 @@ impl UiSpriteMapEditor {
 
             // Editing tools
             if self.editing_mode.inserted(&response) {
-                self.handle_edition_insert(grid_cell_pos);
+                self.handle_edition_insert(pointer_canvas_pos);
             }
 
             if let Some(selection) = self.editing_mode.selected(&response) {
@@ -24,12 +24,12 @@ impl UiSpriteMapEditor {
                         ui,
                         exact_tile_pos,
                         CellSelectorStyle::get_from_egui(ui.ctx(), |style| style.hovered_tile_highlight_color),
-                        1.,
+                        hovered_tile.size() as f32 / self.tile_size_px,
                     );
                 } else if matches!(self.editing_mode, EditingMode::Move(_)) {
                     let (selection_scale, max_selected_tile) = match self.vram_selection_mode {
-                        VramSelectionMode::SingleTile => (1., self.grid_size),
-                        VramSelectionMode::TwoByTwoTiles => (2., self.grid_size - OnGrid::splat(1.)),
+                        VramSelectionMode::SingleTile => (1., self.grid_size - OnGrid::splat(1.)),
+                        VramSelectionMode::TwoByTwoTiles => (2., self.grid_size - OnGrid::splat(2.)),
                     };
                     let tile_pos_in_canvas = relative_pointer_pos
                         .to_grid(self.pixels_per_point, self.zoom, self.tile_size_px)
@@ -52,7 +52,7 @@ impl UiSpriteMapEditor {
                         ui,
                         exact_tile_pos,
                         CellSelectorStyle::get_from_egui(ui.ctx(), |style| style.delete_highlight_color),
-                        1.,
+                        hovered_tile.size() as f32 / self.tile_size_px,
                     );
                 }
             }
@@ -78,7 +78,7 @@ impl UiSpriteMapEditor {
                         style.selection_highlight_color
                     }
                 }),
-                1.,
+                tile.size() as f32 / self.tile_size_px,
             );
         }
     }
@@ -1,15 +1,30 @@
-use egui::{PlatformOutput, Pos2, Rangef, Rect, Vec2};
+use egui::{PlatformOutput, Pos2, Rect, Vec2};
 use itertools::Itertools;
 use num::Integer;
 use smwe_math::coordinates::{OnCanvas, OnGrid, OnScreen};
 use smwe_render::tile_renderer::{Tile, TileJson};
+use smwe_widgets::vram_view::VramSelectionMode;
 
-use super::super::UiSpriteMapEditor;
+use super::super::{
+    sprite_tiles::{Alignment, Axis},
+    UiSpriteMapEditor,
+};
 use crate::ui::editing_mode::{FlipDirection, SnapToGrid};
 
 impl UiSpriteMapEditor {
     pub(in super::super) fn canvas_size(&self) -> OnScreen<Vec2> {
-        OnGrid::splat(32.).to_screen(self.pixels_per_point, self.zoom, self.tile_size_px)
+        self.grid_size.to_screen(self.pixels_per_point, self.zoom, self.tile_size_px)
+    }
+
+    pub(in super::super) fn canvas_size_px(&self) -> OnCanvas<Vec2> {
+        self.grid_size.to_canvas(self.tile_size_px)
+    }
+
+    /// The smallest grid, in cells, that all tiles fit on.
+    pub(in super::super) fn min_grid_size(&self) -> OnGrid<Vec2> {
+        let tiles_end =
+            self.sprite_tiles.read(|tiles| tiles.bounds()).map_or(Vec2::ZERO, |bounds| bounds.max.to_vec2());
+        OnGrid(tiles_end / self.tile_size_px).ceil()
     }
 
     pub(in super::super) fn any_selected_tile_contains_point(&self, point: OnCanvas<Pos2>) -> bool {
@@ -58,10 +73,7 @@ impl UiSpriteMapEditor {
         }
 
         let bounds = self.selection_bounds.expect("unset even though some tiles are selected");
-        let move_offset = move_offset.clamp(
-            -bounds.left_top().to_vec2(),
-            OnCanvas::splat(31. * self.tile_size_px) - bounds.right_bottom().to_vec2(),
-        );
+        let move_offset = self.clamp_move_offset(bounds, move_offset);
 
         self.sprite_tiles.write(|tiles| {
             for &idx in self.selected_sprite_tile_indices.iter() {
@@ -76,25 +88,37 @@ impl UiSpriteMapEditor {
         self.upload_tiles();
     }
 
-    pub(in super::super) fn add_selected_tile_at(&mut self, pos: OnCanvas<Pos2>) {
-        let tile_idx = (self.selected_vram_tile.0 + self.selected_vram_tile.1 * 16) as usize;
-        let tile = self.tile_palette[tile_idx + (32 * 16)];
-        self.add_tile_at(tile, pos);
+    /// Limits the offset so that tiles inside `bounds` stay on the canvas after moving.
+    pub(in super::super) fn clamp_move_offset(
+        &self, bounds: OnCanvas<Rect>, move_offset: OnCanvas<Vec2>,
+    ) -> OnCanvas<Vec2> {
+        clamp_move_offset(bounds, move_offset, self.canvas_size_px())
     }
 
-    pub(in super::super) fn add_tile_at(&mut self, mut tile: Tile, pos: OnCanvas<Pos2>) {
-        tile.move_to(pos.floor());
-        self.add_tiles([tile]);
+    /// Adds the tile selected in VRAM at `pos`, snapping it to the grid if new tiles should be snapped.
+    pub(in super::super) fn add_selected_tile_at(&mut self, pos: OnCanvas<Pos2>) {
+        let tile_idx = (self.selected_vram_tile.0 + self.selected_vram_tile.1 * 16) as usize;
+        let mut tile = self.tile_palette[tile_idx + (32 * 16)];
+        tile.set_large(self.vram_selection_mode == VramSelectionMode::TwoByTwoTiles);
+        let cell_size = self.snap_new_tiles.then_some(self.tile_size_px);
+        let idx = self.sprite_tiles.write(|tiles| tiles.insert(tile, pos, cell_size, self.canvas_size_px()));
+        self.selected_sprite_tile_indices.insert(idx);
     }
 
     pub(in super::super) fn add_tiles(&mut self, new_tiles: impl IntoIterator<Item = Tile>) {
-        self.sprite_tiles.write(|tiles| {
-            self.selected_sprite_tile_indices.insert(tiles.len());
+        let indices = self.sprite_tiles.write(|tiles| {
+            let start = tiles.len();
             tiles.extend(new_tiles);
+            start..tiles.len()
         });
+        self.selected_sprite_tile_indices.extend(indices);
     }
 
-    pub(in super::super) fn copy_selected_tiles(&self, platform_output: &mut PlatformOutput) {
+    pub(in super::super) fn copy_selected_tiles(&mut self, platform_output: &mut PlatformOutput) {
+        self.clipboard = self.sprite_tiles.read(|tiles| {
+            self.selected_sprite_tile_indices.iter().sorted().map(|&i| TileJson::from(tiles[i])).collect_vec()
+        });
+
         let selected_tiles = self
             .selected_sprite_tile_indices
             .iter()
@@ -108,6 +132,7 @@ impl UiSpriteMapEditor {
             .collect_vec();
         platform_output.copied_text =
             serde_json::to_string(&selected_tiles).expect("Failed to serialize selected tiles");
+        self.clipboard_text = platform_output.copied_text.clone();
     }
 
     pub(in super::super) fn paste_tiles_to(&mut self, tiles: Vec<TileJson>, paste_offset: OnCanvas<Vec2>) {
@@ -127,6 +152,24 @@ impl UiSpriteMapEditor {
         self.upload_tiles();
     }
 
+    /// Pastes the tiles copied in this editor next to where they were copied from and selects them.
+    pub(in super::super) fn paste_clipboard(&mut self) {
+        if self.clipboard.is_empty() {
+            return;
+        }
+
+        let rects = self.clipboard.iter().cloned().map(|tile| Tile::from(tile).rect());
+        let bounds =
+            OnCanvas::<Rect>::bounding_box(rects.flat_map(|rect| [rect.left_top(), rect.right_bottom()])).unwrap();
+        let paste_offset = self.clamp_move_offset(bounds, Self::PASTE_OFFSET);
+
+        self.unselect_all_tiles();
+        let pasted = self.sprite_tiles.write(|tiles| tiles.paste(&self.clipboard, paste_offset));
+        self.clipboard = self.sprite_tiles.read(|tiles| pasted.clone().map(|i| TileJson::from(tiles[i])).collect_vec());
+        self.mark_tiles_as_selected(pasted);
+        self.upload_tiles();
+    }
+
     pub(in super::super) fn delete_selected_tiles(&mut self) {
         self.sprite_tiles.write(|tiles| {
             for idx in self.selected_sprite_tile_indices.drain().sorted().rev() {
@@ -148,31 +191,80 @@ impl UiSpriteMapEditor {
             if let Some(tile) =
                 tiles.iter().rev().find(|&&tile| tile.contains_point(pos.to_canvas(self.pixels_per_point, self.zoom)))
             {
-                let (y, x) = tile.tile_num().div_rem(&16);
-                self.selected_vram_tile = (x, y - 96);
+                let (y, x) = (tile.tile_num() - Self::SPRITE_TILE_NUM_OFFSET).div_rem(&16);
+                self.selected_vram_tile = (x, y);
+                self.vram_selection_mode =
+                    if tile.is_large() { VramSelectionMode::TwoByTwoTiles } else { VramSelectionMode::SingleTile };
             }
         });
     }
 
     pub(in super::super) fn flip_selected_tiles(&mut self, flip_direction: FlipDirection) {
-        let selection_bounds = self.selection_bounds.expect("unset even though some tiles are selected");
-        let Rangef { min: x_min, max: x_max } = selection_bounds.x_range();
-        let Rangef { min: y_min, max: y_max } = selection_bounds.y_range();
+        let Some(selection_bounds) = self.selection_bounds else {
+            return;
+        };
         self.sprite_tiles.write(|tiles| {
-            for &i in self.selected_sprite_tile_indices.iter() {
-                let tile = &mut tiles[i];
-                match flip_direction {
-                    FlipDirection::Horizontal => {
-                        tile.toggle_flip_x();
-                        tile[0] = (x_min + (x_max - tile[0] as f32)) as u32;
-                    }
-                    FlipDirection::Vertical => {
-                        tile.toggle_flip_y();
-                        tile[1] = (y_min + (y_max - tile[1] as f32)) as u32;
-                    }
-                }
-            }
+            tiles.flip(self.selected_sprite_tile_indices.iter().copied(), selection_bounds, flip_direction)
         });
         self.upload_tiles();
     }
+
+    /// Lines up the selected tiles on an edge or the center of the selection. Does nothing unless at least two tiles
+    /// are selected.
+    pub(in super::super) fn align_selected_tiles(&mut self, alignment: Alignment) {
+        let Some(bounds) = self.selection_bounds.filter(|_| self.selected_sprite_tile_indices.len() >= 2) else {
+            return;
+        };
+        self.sprite_tiles
+            .write(|tiles| tiles.align(self.selected_sprite_tile_indices.iter().copied(), bounds, alignment));
+        self.compute_selection_bounds();
+        self.upload_tiles();
+    }
+
+    /// Spaces the selected tiles evenly inside the selection. Does nothing unless at least three tiles are selected.
+    pub(in super::super) fn distribute_selected_tiles(&mut self, axis: Axis) {
+        let Some(bounds) = self.selection_bounds.filter(|_| self.selected_sprite_tile_indices.len() >= 3) else {
+            return;
+        };
+        self.sprite_tiles
+            .write(|tiles| tiles.distribute(self.selected_sprite_tile_indices.iter().copied(), bounds, axis));
+        self.compute_selection_bounds();
+        self.upload_tiles();
+    }
+}
+
+/// Limits the offset so that `bounds` stay on a canvas of `canvas_size` pixels after moving. Bounds larger than the
+/// canvas are only moved towards its top left corner.
+fn clamp_move_offset(
+    bounds: OnCanvas<Rect>, move_offset: OnCanvas<Vec2>, canvas_size: OnCanvas<Vec2>,
+) -> OnCanvas<Vec2> {
+    let min = -bounds.left_top().to_vec2();
+    let max = canvas_size - bounds.right_bottom().to_vec2();
+    move_offset.clamp(min, max.max(min))
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use egui::{pos2, vec2};
+
+    use super::*;
+
+    #[test]
+    fn test_clamp_move_offset_to_grid_size() {
+        let bounds = OnCanvas(Rect::from_min_max(pos2(200., 16.), pos2(240., 32.)));
+        let offset = OnCanvas(vec2(100., -100.));
+
+        let default_grid = OnGrid::splat(32.).to_canvas(8.);
+        assert_eq!(clamp_move_offset(bounds, offset, default_grid).0, vec2(16., -16.));
+
+        let large_grid = OnGrid(vec2(64., 48.)).to_canvas(8.);
+        assert_eq!(clamp_move_offset(bounds, offset, large_grid).0, vec2(100., -16.));
+        let far_offset = OnCanvas(vec2(1000., 1000.));
+        assert_eq!(clamp_move_offset(bounds, far_offset, large_grid).0, vec2(272., 352.));
+
+        let small_canvas = OnCanvas(vec2(32., 32.));
+        assert_eq!(clamp_move_offset(bounds, offset, small_canvas).0, vec2(-200., -16.), "wider than the canvas");
+    }
 }
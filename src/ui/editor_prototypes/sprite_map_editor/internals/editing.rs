@@ -1,6 +1,5 @@
-use egui::{PlatformOutput, Pos2, Vec2};
+use egui::{PlatformOutput, Pos2};
 use smwe_math::coordinates::*;
-use smwe_widgets::vram_view::VramSelectionMode;
 
 use super::super::UiSpriteMapEditor;
 use crate::ui::editing_mode::{Drag, FlipDirection, Selection, SnapToGrid};
@@ -24,27 +23,18 @@ impl UiSpriteMapEditor {
         self.copy_selected_tiles(output);
     }
 
+    pub(in super::super) fn handle_paste(&mut self) {
+        self.paste_clipboard();
+    }
+
     pub(in super::super) fn handle_cut(&mut self, output: &mut PlatformOutput) {
         self.handle_copy(output);
         self.delete_selected_tiles();
     }
 
-    pub(in super::super) fn handle_edition_insert(&mut self, grid_cell_pos: OnCanvas<Pos2>) {
+    pub(in super::super) fn handle_edition_insert(&mut self, pointer_pos: OnCanvas<Pos2>) {
         self.unselect_all_tiles();
-        match self.vram_selection_mode {
-            VramSelectionMode::SingleTile => self.add_selected_tile_at(grid_cell_pos),
-            VramSelectionMode::TwoByTwoTiles => {
-                let current_selection = self.selected_vram_tile;
-                for offset in [(0, 0), (0, 1), (1, 0), (1, 1)] {
-                    self.selected_vram_tile.0 = current_selection.0 + offset.0;
-                    self.selected_vram_tile.1 = current_selection.1 + offset.1;
-                    let offset = OnGrid::<Vec2>::new(offset.0 as f32, offset.1 as f32).to_canvas(self.tile_size_px);
-                    let pos = OnCanvas(grid_cell_pos.0 + offset.0);
-                    self.add_selected_tile_at(pos);
-                }
-                self.selected_vram_tile = current_selection;
-            }
-        }
+        self.add_selected_tile_at(pointer_pos);
         self.compute_selection_bounds();
         self.upload_tiles();
     }
@@ -81,7 +71,7 @@ impl UiSpriteMapEditor {
                 let pointer_in_canvas = drag_data.from.relative_to(canvas_top_left_pos);
                 let hovered_tile_exact_offset = pointer_in_canvas
                     .to_grid(self.pixels_per_point, self.zoom, self.tile_size_px)
-                    .clamp(OnGrid::<Pos2>::ZERO, self.grid_size.to_pos2())
+                    .clamp(OnGrid::<Pos2>::ZERO, (self.grid_size - OnGrid::splat(1.)).to_pos2())
                     .to_screen(self.pixels_per_point, self.zoom, self.tile_size_px);
                 let cell_origin = pointer_in_canvas.relative_to(hovered_tile_exact_offset).to_vec2() / self.zoom;
                 SnapToGrid { cell_origin }
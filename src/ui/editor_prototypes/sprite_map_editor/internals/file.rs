@@ -1,14 +1,16 @@
 use std::path::PathBuf;
 
 use itertools::Itertools;
-use rfd::{MessageButtons, MessageDialog, MessageLevel};
 use smwe_render::tile_renderer::{Tile, TileJson};
 
-use super::super::{SpriteTiles, UiSpriteMapEditor};
+use super::super::{png_export::SpriteImage, SpriteTiles, UiSpriteMapEditor};
+use crate::ui::notifications::Notifications;
 
 impl UiSpriteMapEditor {
     pub(in super::super) fn create_new_map(&mut self) {
         self.sprite_tiles.write(|tiles| tiles.clear());
+        self.sprite_tiles.clear_stack();
+        self.selected_sprite_tile_indices.clear();
         self.upload_tiles();
     }
 
@@ -24,24 +26,33 @@ impl UiSpriteMapEditor {
         }
     }
 
+    pub(in super::super) fn export_png_dialog(&mut self) {
+        if let Some(path) = rfd::FileDialog::new().add_filter("PNG image (*.png)", &["png"]).save_file() {
+            self.export_png(path);
+        }
+    }
+
+    pub(in super::super) fn export_png(&mut self, path: PathBuf) {
+        let image =
+            self.sprite_tiles.read(|tiles| SpriteImage::composite(tiles, &self.cpu.mem.vram, &self.cpu.mem.cgram));
+        let result = std::fs::File::create(path)
+            .map_err(|e| format!("{e:?}"))
+            .and_then(|file| image.write_png(std::io::BufWriter::new(file)).map_err(|e| format!("{e:?}")));
+        if let Err(e) = result {
+            Notifications::with_global(|n| n.error("Failed to export sprite tile map to PNG.", e));
+        }
+    }
+
     pub(in super::super) fn open_map(&mut self, path: PathBuf) {
         match std::fs::read_to_string(path) {
             Err(e) => {
-                MessageDialog::new()
-                    .set_title("Failed to open selected file.")
-                    .set_description(format!("{e:?}"))
-                    .set_level(MessageLevel::Error)
-                    .set_buttons(MessageButtons::Ok)
-                    .show();
+                Notifications::with_global(|n| n.error("Failed to open selected file.", format!("{e:?}")));
             }
             Ok(s) => match serde_json::from_str::<Vec<TileJson>>(&s) {
                 Err(e) => {
-                    MessageDialog::new()
-                        .set_title("Failed to deserialize sprite tile map from JSON.")
-                        .set_description(format!("{e:?}"))
-                        .set_level(MessageLevel::Error)
-                        .set_buttons(MessageButtons::Ok)
-                        .show();
+                    Notifications::with_global(|n| {
+                        n.error("Failed to deserialize sprite tile map from JSON.", format!("{e:?}"))
+                    });
                 }
                 Ok(loaded_tiles) => {
                     self.sprite_tiles.write(move |tiles| {
@@ -59,21 +70,15 @@ impl UiSpriteMapEditor {
         let tiles = self.sprite_tiles.read(|tiles| tiles.iter().map(|&t| TileJson::from(t)).collect_vec());
         match serde_json::to_string_pretty(&tiles) {
             Err(e) => {
-                MessageDialog::new()
-                    .set_title("Failed to serialize sprite tile map into JSON.")
-                    .set_description(format!("{e:?}"))
-                    .set_level(MessageLevel::Error)
-                    .set_buttons(MessageButtons::Ok)
-                    .show();
+                Notifications::with_global(|n| {
+                    n.error("Failed to serialize sprite tile map into JSON.", format!("{e:?}"))
+                });
             }
             Ok(s) => {
                 if let Err(e) = std::fs::write(path, s) {
-                    MessageDialog::new()
-                        .set_title("Save sprite tile map to selected file.")
-                        .set_description(format!("{e:?}"))
-                        .set_level(MessageLevel::Error)
-                        .set_buttons(MessageButtons::Ok)
-                        .show();
+                    Notifications::with_global(|n| {
+                        n.error("Failed to save sprite tile map to selected file.", format!("{e:?}"))
+                    });
                 }
             }
         }
@@ -1,9 +1,4 @@
-use std::ops::Not;
-
-use duplicate::duplicate;
-use egui::{pos2, Rect};
-use itertools::Itertools;
-use paste::paste;
+use egui::Rect;
 use smwe_math::coordinates::OnCanvas;
 
 use super::super::UiSpriteMapEditor;
@@ -26,23 +21,9 @@ impl UiSpriteMapEditor {
     }
 
     pub(in super::super) fn compute_selection_bounds(&mut self) {
-        self.selection_bounds = self.selected_sprite_tile_indices.is_empty().not().then(|| {
-            duplicate! {
-                [dimension; [x]; [y]]
-                paste! {
-                    let ([<min_tile_ dimension>], [<max_tile_ dimension>]) = self.sprite_tiles.read(|tiles| {
-                        self
-                            .selected_sprite_tile_indices
-                            .iter()
-                            .map(|&i| tiles[i].pos())
-                            .minmax_by(|a, b| a.dimension.total_cmp(&b.dimension))
-                            .into_option()
-                            .map(|(min, max)| (min.dimension, max.dimension))
-                            .unwrap()
-                    });
-                }
-            }
-            OnCanvas(Rect::from_min_max(pos2(min_tile_x, min_tile_y), pos2(max_tile_x, max_tile_y)))
+        self.selection_bounds = self.sprite_tiles.read(|tiles| {
+            let selected = self.selected_sprite_tile_indices.iter().map(|&i| tiles[i].rect());
+            OnCanvas::<Rect>::bounding_box(selected.flat_map(|rect| [rect.left_top(), rect.right_bottom()]))
         });
     }
 }
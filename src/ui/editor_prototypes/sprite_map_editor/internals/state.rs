@@ -1,4 +1,5 @@
 use egui::Context;
+use smwe_render::tile_renderer::TileJson;
 
 use super::super::UiSpriteMapEditor;
 
@@ -22,11 +23,11 @@ impl UiSpriteMapEditor {
         self.gfx_bufs.upload_vram(&self.gl, &self.cpu.mem.vram);
     }
 
+    /// Uploads the tiles to the renderer and stores them in the project.
     pub(in super::super) fn upload_tiles(&self) {
-        self.sprite_renderer
-            .lock()
-            .expect("Cannot lock mutex on sprite renderer")
-            .set_tiles(&self.gl, self.sprite_tiles.read(|tiles| tiles.0.clone()));
+        let tiles = self.sprite_tiles.read(|tiles| tiles.0.clone());
+        self.project.borrow_mut().set_sprite_map(tiles.iter().map(|&tile| TileJson::from(tile)).collect());
+        self.sprite_renderer.lock().expect("Cannot lock mutex on sprite renderer").set_tiles(&self.gl, tiles);
     }
 
     pub(in super::super) fn update_tile_palette(&mut self) {
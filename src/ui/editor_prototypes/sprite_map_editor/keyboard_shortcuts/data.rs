@@ -6,13 +6,17 @@ pub(in super::super) const SHORTCUT_OPEN: Shortcut = Shortcut::new(Modifiers::CO
 
 pub(in super::super) const SHORTCUT_UNDO: Shortcut = Shortcut::new(Modifiers::COMMAND, Z);
 pub(in super::super) const SHORTCUT_REDO: Shortcut = Shortcut::new(Modifiers::COMMAND, Y);
+pub(in super::super) const SHORTCUT_REDO_ALT: Shortcut = Shortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Z);
 pub(in super::super) const SHORTCUT_COPY: Shortcut = Shortcut::new(Modifiers::COMMAND, C);
 pub(in super::super) const SHORTCUT_CUT: Shortcut = Shortcut::new(Modifiers::COMMAND, X);
+pub(in super::super) const SHORTCUT_PASTE: Shortcut = Shortcut::new(Modifiers::COMMAND, V);
 
 pub(in super::super) const SHORTCUT_SELECT_ALL: Shortcut = Shortcut::new(Modifiers::COMMAND, A);
 pub(in super::super) const SHORTCUT_UNSELECT_ALL: Shortcut = Shortcut::new(Modifiers::NONE, Escape);
 
 pub(in super::super) const SHORTCUT_DELETE_SELECTED: Shortcut = Shortcut::new(Modifiers::NONE, Delete);
+pub(in super::super) const SHORTCUT_FLIP_HORIZONTALLY: Shortcut = Shortcut::new(Modifiers::NONE, H);
+pub(in super::super) const SHORTCUT_FLIP_VERTICALLY: Shortcut = Shortcut::new(Modifiers::NONE, V);
 
 pub(in super::super) const SHORTCUT_ZOOM_IN: Shortcut = Shortcut::new(Modifiers::COMMAND, Plus);
 pub(in super::super) const SHORTCUT_ZOOM_OUT: Shortcut = Shortcut::new(Modifiers::COMMAND, Minus);
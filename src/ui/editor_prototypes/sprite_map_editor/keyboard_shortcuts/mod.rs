@@ -6,7 +6,29 @@ use smwe_math::coordinates::{OnCanvas, OnScreen};
 use smwe_render::tile_renderer::TileJson;
 
 use super::UiSpriteMapEditor;
-use crate::ui::editing_mode::{EditingMode, SnapToGrid};
+use crate::ui::editing_mode::{EditingMode, FlipDirection, SnapToGrid};
+
+/// Where the tiles pasted from the system clipboard come from.
+#[derive(Debug)]
+enum PasteSource {
+    /// Tiles copied in this editor, pasted from its internal clipboard.
+    Clipboard,
+    /// Tiles copied as JSON from elsewhere.
+    Json(Vec<TileJson>),
+}
+
+/// Decides what to paste for `pasted_text`. The internal clipboard is only used if the text is what was last copied
+/// from this editor, or if it isn't tile JSON at all.
+fn classify_paste(pasted_text: &str, clipboard: &[TileJson], clipboard_text: &str) -> Option<PasteSource> {
+    let has_clipboard = !clipboard.is_empty();
+    if has_clipboard && pasted_text == clipboard_text {
+        return Some(PasteSource::Clipboard);
+    }
+    match serde_json::from_str::<Vec<TileJson>>(pasted_text) {
+        Ok(pasted_tiles) => Some(PasteSource::Json(pasted_tiles)),
+        Err(_) => has_clipboard.then_some(PasteSource::Clipboard),
+    }
+}
 
 impl UiSpriteMapEditor {
     pub(super) fn handle_input(&mut self, ui: &Ui) {
@@ -20,12 +42,13 @@ impl UiSpriteMapEditor {
             if input.consume_shortcut(&SHORTCUT_OPEN) {
                 self.open_map_dialog();
             }
+            // Shift+Z has to be checked first, otherwise it would be consumed as undo.
+            if input.consume_shortcut(&SHORTCUT_REDO) || input.consume_shortcut(&SHORTCUT_REDO_ALT) {
+                self.handle_redo();
+            }
             if input.consume_shortcut(&SHORTCUT_UNDO) {
                 self.handle_undo();
             }
-            if input.consume_shortcut(&SHORTCUT_REDO) {
-                self.handle_redo();
-            }
             if input.consume_shortcut(&SHORTCUT_SELECT_ALL) {
                 self.select_all_tiles();
             }
@@ -35,6 +58,12 @@ impl UiSpriteMapEditor {
             if input.consume_shortcut(&SHORTCUT_DELETE_SELECTED) {
                 self.delete_selected_tiles();
             }
+            if input.consume_shortcut(&SHORTCUT_FLIP_HORIZONTALLY) {
+                self.flip_selected_tiles(FlipDirection::Horizontal);
+            }
+            if input.consume_shortcut(&SHORTCUT_FLIP_VERTICALLY) {
+                self.flip_selected_tiles(FlipDirection::Vertical);
+            }
             self.kb_shortcut_move_selection(input);
             self.kb_shortcuts_tools(input);
             self.handle_zoom(input);
@@ -46,22 +75,30 @@ impl UiSpriteMapEditor {
         if ui.input(|input| input.events.contains(&Event::Cut)) {
             ui.output_mut(|output| self.handle_cut(output));
         }
+        if let Some(PasteSource::Clipboard) = ui.input(|input| self.paste_source(input)) {
+            self.handle_paste();
+        }
     }
 
+    /// Pastes tiles copied as JSON from outside of this editor at the pointer. Tiles copied in this editor are
+    /// pasted by [`handle_input`](Self::handle_input) instead.
     pub(super) fn kb_shortcut_paste(&mut self, input: &InputState, canvas_top_left: OnScreen<Pos2>) {
-        for event in input.events.iter() {
-            if let Event::Paste(pasted_text) = event {
-                if let Ok(pasted_tiles) = serde_json::from_str::<Vec<TileJson>>(pasted_text) {
-                    let hover_pos = OnScreen(input.pointer.hover_pos().expect("Failed to get hover position"));
-                    let paste_offset =
-                        hover_pos.relative_to(canvas_top_left).to_vec2().to_canvas(self.pixels_per_point, self.zoom);
-                    self.paste_tiles_to(pasted_tiles, paste_offset);
-                }
-                break;
-            }
+        if let Some(PasteSource::Json(pasted_tiles)) = self.paste_source(input) {
+            let hover_pos = OnScreen(input.pointer.hover_pos().expect("Failed to get hover position"));
+            let paste_offset =
+                hover_pos.relative_to(canvas_top_left).to_vec2().to_canvas(self.pixels_per_point, self.zoom);
+            self.paste_tiles_to(pasted_tiles, paste_offset);
         }
     }
 
+    fn paste_source(&self, input: &InputState) -> Option<PasteSource> {
+        let pasted_text = input.events.iter().find_map(|event| match event {
+            Event::Paste(pasted_text) => Some(pasted_text),
+            _ => None,
+        })?;
+        classify_paste(pasted_text, &self.clipboard, &self.clipboard_text)
+    }
+
     fn kb_shortcut_move_selection(&mut self, input: &InputState) {
         let move_distance = if input.modifiers.shift_only() { self.tile_size_px } else { 1. };
         let moves = [
@@ -105,3 +142,31 @@ impl UiSpriteMapEditor {
         }
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use smwe_render::tile_renderer::Tile;
+
+    use super::*;
+
+    #[test]
+    fn test_classify_paste() {
+        let clipboard = vec![TileJson::from(Tile([8, 8, 0x20, 0x0A00]))];
+        let copied = vec![TileJson::from(Tile([0, 0, 0x20, 0x0A00]))];
+        let clipboard_text = serde_json::to_string(&copied).unwrap();
+        let other = vec![TileJson::from(Tile([0, 0, 0x31, 0x0A00])), TileJson::from(Tile([8, 0, 0x32, 0x0A00]))];
+        let other_text = serde_json::to_string(&other).unwrap();
+
+        assert!(matches!(classify_paste(&clipboard_text, &clipboard, &clipboard_text), Some(PasteSource::Clipboard)));
+        let pasted = classify_paste(&other_text, &clipboard, &clipboard_text);
+        assert!(
+            matches!(pasted, Some(PasteSource::Json(tiles)) if tiles.len() == 2),
+            "tiles copied elsewhere since the last copy in this editor"
+        );
+        assert!(matches!(classify_paste("hello", &clipboard, &clipboard_text), Some(PasteSource::Clipboard)));
+        assert!(matches!(classify_paste(&other_text, &[], ""), Some(PasteSource::Json(_))));
+        assert!(classify_paste("hello", &[], "").is_none());
+    }
+}
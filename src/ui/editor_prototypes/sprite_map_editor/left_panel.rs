@@ -3,7 +3,7 @@ use std::sync::Arc;
 use egui::*;
 use egui_glow::CallbackFn;
 use inline_tweak::tweak;
-use smwe_math::coordinates::OnCanvas;
+use smwe_math::coordinates::{OnCanvas, OnGrid};
 use smwe_render::tile_renderer::TileUniforms;
 use smwe_widgets::{
     palette_view::{PaletteView, SelectionType, ViewedPalettes},
@@ -29,6 +29,14 @@ impl UiSpriteMapEditor {
                 self.palette_row_selector(ui);
             });
 
+            if self.selected_sprite_tile_indices.len() == 1 {
+                ui.add_space(ui.spacing().item_spacing.y);
+                ui.group(|ui| {
+                    ui.allocate_space(vec2(ui.available_width(), 0.));
+                    self.selected_tile_properties(ui);
+                });
+            }
+
             if cfg!(debug_assertions) {
                 ui.add_space(ui.spacing().item_spacing.y);
                 ui.group(|ui| {
@@ -48,12 +56,16 @@ impl UiSpriteMapEditor {
         Frame::canvas(ui.style()).show(ui, |ui| {
             let vram_renderer = Arc::clone(&self.vram_renderer);
             let gfx_bufs = self.gfx_bufs;
-            ui.add(
+            let response = ui.add(
                 VramView::new(vram_renderer, gfx_bufs)
                     .viewed_tiles(ViewedVramTiles::SpritesOnly)
                     .selection(self.vram_selection_mode, &mut self.selected_vram_tile)
                     .zoom(2.),
             );
+            if let Some((x, y)) = VramView::hovered_tile(&response) {
+                let palette = self.selected_palette + 8;
+                response.on_hover_text(format!("Tile: ${:03X}\nPalette: {palette:X}", x + y * 16));
+            }
         });
     }
 
@@ -98,6 +110,51 @@ impl UiSpriteMapEditor {
         });
     }
 
+    fn selected_tile_properties(&mut self, ui: &mut Ui) {
+        let Some(&idx) = self.selected_sprite_tile_indices.iter().next() else {
+            return;
+        };
+        let tile = self.sprite_tiles.read(|tiles| tiles[idx]);
+        let max_pos = (self.grid_size - OnGrid::splat(1.)).to_canvas(self.tile_size_px).to_pos2();
+        let (mut x, mut y) = (tile[0], tile[1]);
+        let mut tile_num = tile.tile_num().saturating_sub(Self::SPRITE_TILE_NUM_OFFSET);
+        let mut palette = tile.color_row().saturating_sub(8);
+
+        ui.strong("Selected tile");
+        let responses = Grid::new("sprite_map_editor.selected_tile").num_columns(2).show(ui, |ui| {
+            ui.label("X");
+            let x_response = ui.add(DragValue::new(&mut x).clamp_range(0..=max_pos.x as u32));
+            ui.end_row();
+            ui.label("Y");
+            let y_response = ui.add(DragValue::new(&mut y).clamp_range(0..=max_pos.y as u32));
+            ui.end_row();
+            ui.label("Tile");
+            let tile_response =
+                ui.add(DragValue::new(&mut tile_num).clamp_range(0..=0x1FF).hexadecimal(3, false, true));
+            ui.end_row();
+            ui.label("Palette");
+            let palette_response = ui.add(DragValue::new(&mut palette).clamp_range(0..=7).hexadecimal(1, false, true));
+            ui.end_row();
+            [x_response, y_response, tile_response, palette_response]
+        });
+
+        // Edits are shown while dragging or typing, but only become an undo step once they're finished.
+        let changed = responses.inner.iter().any(Response::changed);
+        let finished = responses.inner.iter().any(|response| response.drag_stopped() || response.lost_focus());
+        if changed {
+            self.sprite_tiles.write_uncommitted(|tiles| {
+                tiles.set_position(idx, OnCanvas(pos2(x as f32, y as f32)), max_pos);
+                tiles[idx].set_tile_num(tile_num + Self::SPRITE_TILE_NUM_OFFSET);
+                tiles[idx].set_color_row(palette + 8);
+            });
+            self.compute_selection_bounds();
+            self.upload_tiles();
+        }
+        if finished {
+            self.sprite_tiles.commit();
+        }
+    }
+
     #[cfg(debug_assertions)]
     fn debug_toggles(&mut self, ui: &mut Ui) {
         ui.collapsing("Debug", |ui| {
@@ -1,6 +1,8 @@
 use egui::*;
+use smwe_math::coordinates::OnGrid;
 
 use super::{keyboard_shortcuts::*, UiSpriteMapEditor};
+use crate::ui::editing_mode::FlipDirection;
 
 impl UiSpriteMapEditor {
     pub(super) fn menu_bar(&mut self, ui: &mut Ui) {
@@ -22,6 +24,10 @@ impl UiSpriteMapEditor {
             self.open_map_dialog();
             ui.close_menu();
         }
+        if ui.add_enabled(self.sprite_tiles.read(|tiles| !tiles.is_empty()), Button::new("Export PNG")).clicked() {
+            self.export_png_dialog();
+            ui.close_menu();
+        }
     }
 
     fn menu_edit(&mut self, ui: &mut Ui) {
@@ -53,6 +59,16 @@ impl UiSpriteMapEditor {
             ui.output_mut(|output| self.handle_cut(output));
             ui.close_menu();
         }
+        if ui
+            .add_enabled(
+                !self.clipboard.is_empty(),
+                Button::new("Paste").shortcut_text(ui.ctx().format_shortcut(&SHORTCUT_PASTE)),
+            )
+            .clicked()
+        {
+            self.handle_paste();
+            ui.close_menu();
+        }
         if ui.add(Button::new("Select all").shortcut_text(ui.ctx().format_shortcut(&SHORTCUT_SELECT_ALL))).clicked() {
             self.select_all_tiles();
             ui.close_menu();
@@ -67,6 +83,23 @@ impl UiSpriteMapEditor {
             self.unselect_all_tiles();
             ui.close_menu();
         }
+        ui.separator();
+        let flips = [
+            ("Flip horizontally", &SHORTCUT_FLIP_HORIZONTALLY, FlipDirection::Horizontal),
+            ("Flip vertically", &SHORTCUT_FLIP_VERTICALLY, FlipDirection::Vertical),
+        ];
+        for (label, shortcut, direction) in flips {
+            if ui
+                .add_enabled(
+                    !self.selected_sprite_tile_indices.is_empty(),
+                    Button::new(label).shortcut_text(ui.ctx().format_shortcut(shortcut)),
+                )
+                .clicked()
+            {
+                self.flip_selected_tiles(direction);
+                ui.close_menu();
+            }
+        }
     }
 
     fn menu_view(&mut self, ui: &mut Ui) {
@@ -93,5 +126,21 @@ impl UiSpriteMapEditor {
         if ui.checkbox(&mut self.always_show_grid, "Always show grid").clicked() {
             ui.close_menu();
         }
+        ui.separator();
+        ui.horizontal(|ui| {
+            let (mut width, mut height) = (self.grid_size.x as u32, self.grid_size.y as u32);
+            // The grid can't be shrunk past the tiles on it.
+            let min_size = self.min_grid_size();
+            let min = |cells: f32| (cells as u32).clamp(Self::MIN_GRID_SIZE, Self::MAX_GRID_SIZE);
+            ui.label("Grid size");
+            let width_range = min(min_size.x)..=Self::MAX_GRID_SIZE;
+            let mut changed = ui.add(DragValue::new(&mut width).clamp_range(width_range)).changed();
+            ui.label("x");
+            let height_range = min(min_size.y)..=Self::MAX_GRID_SIZE;
+            changed |= ui.add(DragValue::new(&mut height).clamp_range(height_range)).changed();
+            if changed {
+                self.grid_size = OnGrid(vec2(width as f32, height as f32));
+            }
+        });
     }
 }
@@ -4,6 +4,7 @@ mod internals;
 mod keyboard_shortcuts;
 mod left_panel;
 mod menu_bar;
+mod png_export;
 mod sprite_tiles;
 
 use std::{
@@ -13,23 +14,29 @@ use std::{
 
 use egui::*;
 use glow::Context;
-use smwe_emu::{emu::CheckedMem, rom::Rom, Cpu};
+use smwe_emu::{emu::CheckedMem, Cpu};
 use smwe_math::coordinates::*;
 use smwe_render::{
     gfx_buffers::GfxBuffers,
     palette_renderer::PaletteRenderer,
-    tile_renderer::{Tile, TileRenderer},
+    tile_renderer::{Tile, TileJson, TileRenderer},
 };
 use smwe_widgets::vram_view::{VramSelectionMode, VramView};
 use sprite_tiles::SpriteTiles;
 
 use crate::{
-    ui::{editing_mode::EditingMode, tool::DockableEditorTool},
+    project::ProjectRef,
+    ui::{
+        editing_mode::EditingMode,
+        tool::{DockableEditorTool, DockableEditorToolEnum},
+    },
     undo::UndoableData,
 };
 
 pub struct UiSpriteMapEditor {
     gl:                Arc<Context>,
+    /// The project the sprite map is stored in.
+    project:           ProjectRef,
     cpu:               Cpu,
     tile_palette:      Vec<Tile>,
     vram_renderer:     Arc<Mutex<TileRenderer>>,
@@ -42,6 +49,7 @@ pub struct UiSpriteMapEditor {
     vram_selection_mode: VramSelectionMode,
     editing_mode:        EditingMode,
     always_show_grid:    bool,
+    snap_new_tiles:      bool,
 
     #[cfg(debug_assertions)]
     debug_selection_bounds: bool,
@@ -49,6 +57,7 @@ pub struct UiSpriteMapEditor {
     tile_size_px:           f32,
     zoom:                   f32,
     pixels_per_point:       f32,
+    /// Width and height of the canvas in 8x8 cells.
     grid_size:              OnGrid<Vec2>,
     hovering_selected_tile: bool,
     selection_bounds:       Option<OnCanvas<Rect>>,
@@ -58,16 +67,22 @@ pub struct UiSpriteMapEditor {
     selected_palette:             u32,
     sprite_tiles:                 UndoableData<SpriteTiles>,
     selected_sprite_tile_indices: HashSet<usize>,
+    clipboard:                    Vec<TileJson>,
+    /// The text last copied from this editor, to tell pasting it apart from pasting tiles copied elsewhere.
+    clipboard_text:               String,
 }
 
 impl UiSpriteMapEditor {
-    pub fn new(gl: Arc<Context>, rom: Arc<Rom>) -> Self {
+    pub fn new(gl: Arc<Context>, project: ProjectRef) -> Self {
+        let rom = Arc::clone(&project.borrow().rom);
+        let sprite_tiles = SpriteTiles(project.borrow().sprite_map().iter().cloned().map(Tile::from).collect());
         let (vram_renderer, tile_palette) = VramView::new_renderer(&gl);
         let sprite_renderer = TileRenderer::new(&gl);
         let palette_renderer = PaletteRenderer::new(&gl);
         let gfx_bufs = GfxBuffers::new(&gl);
         Self {
             gl,
+            project,
             cpu: Cpu::new(CheckedMem::new(rom)),
             tile_palette,
             vram_renderer: Arc::new(Mutex::new(vram_renderer)),
@@ -80,6 +95,7 @@ impl UiSpriteMapEditor {
             vram_selection_mode: VramSelectionMode::SingleTile,
             editing_mode: EditingMode::Move(None),
             always_show_grid: false,
+            snap_new_tiles: true,
 
             #[cfg(debug_assertions)]
             debug_selection_bounds: false,
@@ -87,15 +103,17 @@ impl UiSpriteMapEditor {
             tile_size_px: 8.,
             zoom: 3.,
             pixels_per_point: 0.,
-            grid_size: OnGrid::splat(31.),
+            grid_size: OnGrid::splat(32.),
             hovering_selected_tile: false,
             selection_bounds: None,
             selection_offset: None,
 
             selected_vram_tile: (0, 0),
             selected_palette: 0,
-            sprite_tiles: UndoableData::new(SpriteTiles(Vec::new())),
+            sprite_tiles: UndoableData::new(sprite_tiles),
             selected_sprite_tile_indices: HashSet::new(),
+            clipboard: Vec::new(),
+            clipboard_text: String::new(),
         }
     }
 
@@ -117,14 +135,23 @@ impl DockableEditorTool for UiSpriteMapEditor {
         "Sprite Tile Editor".into()
     }
 
+    fn kind(&self) -> DockableEditorToolEnum {
+        DockableEditorToolEnum::SpriteMapEditor
+    }
+
     fn on_closed(&mut self) {
         self.destroy();
     }
 }
 
 impl UiSpriteMapEditor {
+    const MAX_GRID_SIZE: u32 = 128;
     const MAX_ZOOM: f32 = 5.0;
+    const MIN_GRID_SIZE: u32 = 8;
     const MIN_ZOOM: f32 = 1.0;
+    const PASTE_OFFSET: OnCanvas<Vec2> = OnCanvas(vec2(4., 4.));
+    /// Number of the first sprite tile in VRAM, as stored in [`Tile`].
+    const SPRITE_TILE_NUM_OFFSET: u32 = 96 * 16;
 
     pub(super) fn layout(&mut self, ui: &mut Ui) {
         TopBottomPanel::top("sprite_map_editor.top_panel").show_inside(ui, |ui| {
@@ -0,0 +1,104 @@
+use std::io::Write;
+
+use egui::{Color32, Rect};
+use smwe_render::{color::Abgr1555, tile_renderer::Tile};
+
+/// RGBA pixels of the sprite tiles drawn with their graphics, cropped to the tiles' bounds.
+pub(super) struct SpriteImage {
+    pub pixels: Vec<u8>,
+    pub width:  usize,
+    pub height: usize,
+}
+
+impl SpriteImage {
+    /// Draws the tiles the same way as the tile renderer, using sprite graphics from `vram` and colors from `cgram`.
+    /// Later tiles are drawn over earlier ones, and pixels with color index 0 are left transparent.
+    pub fn composite(tiles: &[Tile], vram: &[u8], cgram: &[u8]) -> Self {
+        let Some(bounds) = tiles.iter().map(|tile| tile.rect().0).reduce(Rect::union) else {
+            return Self { pixels: Vec::new(), width: 0, height: 0 };
+        };
+        let (left, top) = (bounds.min.x as usize, bounds.min.y as usize);
+        let width = bounds.width() as usize;
+        let height = bounds.height() as usize;
+        let mut pixels = vec![0u8; width * height * 4];
+
+        for &tile in tiles {
+            let (scale, size) = (tile.scale().max(1) as usize, tile.size() as usize);
+            let sprite_size = if tile.is_large() { 16 } else { 8 };
+            for py in 0..size {
+                for px in 0..size {
+                    let mut sx = px * 8 / scale;
+                    let mut sy = py * 8 / scale;
+                    if tile.flip_x() {
+                        sx = sprite_size - 1 - sx;
+                    }
+                    if tile.flip_y() {
+                        sy = sprite_size - 1 - sy;
+                    }
+                    let tile_num = tile.tile_num() as usize + sx / 8 + (sy / 8) * 16;
+                    let color_col = color_index(vram, tile_num, sx % 8, sy % 8);
+                    if color_col == 0 {
+                        continue;
+                    }
+
+                    let cgram_idx = (color_col + tile.color_row() as usize * 0x10) * 2;
+                    let color = match cgram.get(cgram_idx..cgram_idx + 2) {
+                        Some(&[lo, hi]) => Abgr1555(u16::from_le_bytes([lo, hi]) & 0x7FFF),
+                        _ => Abgr1555::MAGENTA,
+                    };
+                    let x = tile[0] as usize + px - left;
+                    let y = tile[1] as usize + py - top;
+                    let pixel = (y * width + x) * 4;
+                    pixels[pixel..pixel + 4].copy_from_slice(&Color32::from(color).to_srgba_unmultiplied());
+                }
+            }
+        }
+
+        Self { pixels, width, height }
+    }
+
+    pub fn write_png(&self, writer: impl Write) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()
+    }
+}
+
+/// Reads the color index of a pixel in a 4bpp tile stored in VRAM. Tiles outside of VRAM are transparent.
+fn color_index(vram: &[u8], tile_num: usize, x: usize, y: usize) -> usize {
+    let row = tile_num * 32 + y * 2;
+    let plane = |offset: usize| vram.get(row + offset).map_or(0, |&byte| ((byte >> (7 - x)) & 1) as usize);
+    plane(0) | (plane(1) << 1) | (plane(16) << 2) | (plane(17) << 3)
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_composite_two_tiles() {
+        let mut vram = vec![0u8; 0x10000];
+        // Tile $600 uses color 1 in every pixel except for the top left one.
+        vram[0xC000..0xC010].fill(0xFF);
+        vram[0xC000] = 0x7F;
+        let mut cgram = vec![0u8; 0x200];
+        cgram[(0x81 * 2)..(0x81 * 2 + 2)].copy_from_slice(&Abgr1555::RED.0.to_le_bytes());
+
+        let tiles = [Tile([0, 0, 0x600, 0x0808]), Tile([16, 0, 0x600, 0x4808])];
+        let image = SpriteImage::composite(&tiles, &vram, &cgram);
+        assert_eq!((image.width, image.height), (24, 8));
+        assert_eq!(image.pixels.len(), 24 * 8 * 4);
+
+        let pixel = |x: usize, y: usize| &image.pixels[(y * 24 + x) * 4..(y * 24 + x) * 4 + 4];
+        assert_eq!(pixel(0, 0), [0, 0, 0, 0], "color 0 is transparent");
+        assert_eq!(pixel(1, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(8, 4), [0, 0, 0, 0], "gaps between tiles are transparent");
+        assert_eq!(pixel(23, 0), [0, 0, 0, 0], "second tile is flipped");
+        assert_eq!(pixel(16, 0), [255, 0, 0, 255]);
+    }
+}
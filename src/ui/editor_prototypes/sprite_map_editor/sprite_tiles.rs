@@ -1,12 +1,131 @@
+use std::ops::Range;
+
+use egui::{Pos2, Rangef, Rect, Vec2};
+use itertools::Itertools;
 use shrinkwraprs::Shrinkwrap;
-use smwe_render::tile_renderer::Tile;
+use smwe_math::coordinates::OnCanvas;
+use smwe_render::tile_renderer::{Tile, TileJson};
+
+use crate::{ui::editing_mode::FlipDirection, undo::Undo};
 
-use crate::undo::Undo;
+/// Edge or center of the selection that tiles are lined up on.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(super) enum Alignment {
+    Left,
+    CenterX,
+    Right,
+    Top,
+    CenterY,
+    Bottom,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(super) enum Axis {
+    Horizontal,
+    Vertical,
+}
 
 #[derive(Clone, Debug, Shrinkwrap)]
 #[shrinkwrap(mutable)]
 pub(super) struct SpriteTiles(pub Vec<Tile>);
 
+impl SpriteTiles {
+    /// Appends copies of the tiles moved by `offset` and returns the indices of the copies.
+    pub fn paste(&mut self, tiles: &[TileJson], offset: OnCanvas<Vec2>) -> Range<usize> {
+        let start = self.0.len();
+        self.0.extend(tiles.iter().cloned().map(Tile::from).map(|mut tile| {
+            tile.move_by(offset);
+            tile
+        }));
+        start..self.0.len()
+    }
+
+    /// Appends `tile` at `pos` and returns its index. If `cell_size` is given, the position is snapped down to a
+    /// multiple of it. Either way, the whole tile is kept on a canvas of `canvas_size` pixels.
+    pub fn insert(
+        &mut self, mut tile: Tile, pos: OnCanvas<Pos2>, cell_size: Option<f32>, canvas_size: OnCanvas<Vec2>,
+    ) -> usize {
+        let pos = match cell_size {
+            Some(cell_size) => OnCanvas(((pos.0.to_vec2() / cell_size).floor() * cell_size).to_pos2()),
+            None => pos.floor(),
+        };
+        let max = canvas_size.0 - Vec2::splat(tile.size() as f32);
+        tile.move_to(OnCanvas(pos.0.clamp(Pos2::ZERO, max.to_pos2())));
+        self.0.push(tile);
+        self.0.len() - 1
+    }
+
+    /// The smallest rectangle containing all tiles, or `None` if there are none.
+    pub fn bounds(&self) -> Option<OnCanvas<Rect>> {
+        OnCanvas::<Rect>::bounding_box(
+            self.0.iter().flat_map(|tile| [tile.rect().left_top(), tile.rect().right_bottom()]),
+        )
+    }
+
+    /// Moves the tile at `idx` to `pos`, keeping its coordinates between 0 and `max`.
+    pub fn set_position(&mut self, idx: usize, pos: OnCanvas<Pos2>, max: OnCanvas<Pos2>) {
+        let pos = OnCanvas(pos.0.clamp(Pos2::ZERO, max.0));
+        self.0[idx].move_to(pos);
+    }
+
+    /// Flips the tiles at `indices` and mirrors their positions inside `bounds`, so that the whole arrangement is
+    /// flipped rather than each tile in place.
+    pub fn flip(&mut self, indices: impl IntoIterator<Item = usize>, bounds: OnCanvas<Rect>, direction: FlipDirection) {
+        let Rangef { min: x_min, max: x_max } = bounds.x_range();
+        let Rangef { min: y_min, max: y_max } = bounds.y_range();
+        for i in indices {
+            let tile = &mut self.0[i];
+            match direction {
+                FlipDirection::Horizontal => {
+                    tile.toggle_flip_x();
+                    tile[0] = (x_min + (x_max - (tile[0] + tile.size()) as f32)) as u32;
+                }
+                FlipDirection::Vertical => {
+                    tile.toggle_flip_y();
+                    tile[1] = (y_min + (y_max - (tile[1] + tile.size()) as f32)) as u32;
+                }
+            }
+        }
+    }
+
+    /// Lines up the tiles at `indices` on an edge or the center of `bounds`.
+    pub fn align(&mut self, indices: impl IntoIterator<Item = usize>, bounds: OnCanvas<Rect>, alignment: Alignment) {
+        for i in indices {
+            let tile = &mut self.0[i];
+            let size = tile.size() as f32;
+            match alignment {
+                Alignment::Left => tile[0] = bounds.min.x as u32,
+                Alignment::CenterX => tile[0] = (bounds.center().x - size / 2.).floor() as u32,
+                Alignment::Right => tile[0] = (bounds.max.x - size) as u32,
+                Alignment::Top => tile[1] = bounds.min.y as u32,
+                Alignment::CenterY => tile[1] = (bounds.center().y - size / 2.).floor() as u32,
+                Alignment::Bottom => tile[1] = (bounds.max.y - size) as u32,
+            }
+        }
+    }
+
+    /// Spaces the tiles at `indices` along `axis` so that the gaps between them are equal, keeping the outermost
+    /// tiles at the edges of `bounds`.
+    pub fn distribute(&mut self, indices: impl IntoIterator<Item = usize>, bounds: OnCanvas<Rect>, axis: Axis) {
+        let (coord, Rangef { min, max }) = match axis {
+            Axis::Horizontal => (0, bounds.x_range()),
+            Axis::Vertical => (1, bounds.y_range()),
+        };
+        let indices = indices.into_iter().sorted_by_key(|&i| self.0[i][coord]).collect_vec();
+        if indices.len() < 2 {
+            return;
+        }
+
+        let total_size = indices.iter().map(|&i| self.0[i].size() as f32).sum::<f32>();
+        let gap = (max - min - total_size) / (indices.len() - 1) as f32;
+        let mut pos = min;
+        for i in indices {
+            self.0[i][coord] = pos.round() as u32;
+            pos += self.0[i].size() as f32 + gap;
+        }
+    }
+}
+
 impl Undo for SpriteTiles {
     fn from_bytes(bytes: Vec<u8>) -> Self {
         let tiles = bytes
@@ -25,3 +144,117 @@ impl Undo for SpriteTiles {
         self.0.len() * std::mem::size_of::<Tile>()
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::undo::UndoableData;
+
+    #[test]
+    fn test_undo_insert() {
+        let mut sprite_tiles = UndoableData::new(SpriteTiles(Vec::new()));
+        sprite_tiles.write(|tiles| tiles.push(Tile([8, 16, 0x20, 0x0A00])));
+        assert_eq!(sprite_tiles.read(|tiles| tiles.len()), 1);
+
+        sprite_tiles.undo();
+        assert!(sprite_tiles.read(|tiles| tiles.is_empty()));
+
+        sprite_tiles.redo();
+        assert_eq!(sprite_tiles.read(|tiles| tiles[0].0), [8, 16, 0x20, 0x0A00]);
+    }
+
+    #[test]
+    fn test_paste_copies() {
+        let mut tiles = SpriteTiles(vec![Tile([0, 0, 0x20, 0x0A00]), Tile([8, 0, 0x21, 0x0A00])]);
+        let copied = tiles.iter().map(|&tile| TileJson::from(tile)).collect::<Vec<_>>();
+
+        let pasted = tiles.paste(&copied, OnCanvas(Vec2::splat(4.)));
+        assert_eq!(pasted, 2..4);
+        assert_eq!(tiles.len(), 4);
+        assert_eq!(tiles[2].0, [4, 4, 0x20, 0x0A00]);
+        assert_eq!(tiles[3].0, [12, 4, 0x21, 0x0A00]);
+    }
+
+    #[test]
+    fn test_insert_snapped() {
+        let mut tiles = SpriteTiles(Vec::new());
+        let idx = tiles.insert(
+            Tile([0, 0, 0x20, 0x0A08]),
+            OnCanvas(Pos2::new(13.7, 21.2)),
+            Some(8.),
+            OnCanvas(Vec2::splat(256.)),
+        );
+        assert_eq!(idx, 0);
+        assert_eq!(tiles[0].pos().0, Pos2::new(8., 16.));
+
+        let mut large = Tile([0, 0, 0x20, 0x0A08]);
+        large.set_large(true);
+        tiles.insert(large, OnCanvas(Pos2::new(29.9, 250.)), Some(8.), OnCanvas(Vec2::splat(256.)));
+        assert_eq!(tiles[1].pos().0, Pos2::new(24., 240.), "the whole 16x16 tile stays on the canvas");
+
+        tiles.insert(large, OnCanvas(Pos2::new(29.9, 3.5)), None, OnCanvas(Vec2::splat(256.)));
+        assert_eq!(tiles[2].pos().0, Pos2::new(29., 3.), "not snapped when disabled");
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert!(SpriteTiles(Vec::new()).bounds().is_none());
+        let mut large = Tile([40, 4, 0x22, 0x0A08]);
+        large.set_large(true);
+        let tiles = SpriteTiles(vec![Tile([16, 8, 0x20, 0x0A08]), large]);
+        assert_eq!(tiles.bounds().unwrap().0, Rect::from_min_max(Pos2::new(16., 4.), Pos2::new(56., 20.)));
+    }
+
+    #[test]
+    fn test_set_position() {
+        let mut tiles = SpriteTiles(vec![Tile([16, 8, 0x20, 0x0A08])]);
+        tiles.set_position(0, OnCanvas(Pos2::new(100., 8.)), OnCanvas(Pos2::new(248., 248.)));
+        assert_eq!(tiles[0].pos().0, Pos2::new(100., 8.));
+        assert_eq!(tiles[0].tile_num(), 0x20, "only the position changes");
+
+        tiles.set_position(0, OnCanvas(Pos2::new(300., -4.)), OnCanvas(Pos2::new(248., 248.)));
+        assert_eq!(tiles[0].pos().0, Pos2::new(248., 0.));
+    }
+
+    #[test]
+    fn test_align_top() {
+        let mut large = Tile([40, 4, 0x22, 0x0A08]);
+        large.set_large(true);
+        let mut tiles = SpriteTiles(vec![Tile([0, 16, 0x20, 0x0A08]), Tile([16, 10, 0x21, 0x0A08]), large]);
+        let bounds = OnCanvas(tiles.iter().map(|tile| tile.rect().0).reduce(Rect::union).unwrap());
+
+        tiles.align([0, 1, 2], bounds, Alignment::Top);
+        assert_eq!(tiles.iter().map(|tile| (tile[0], tile[1])).collect_vec(), [(0, 4), (16, 4), (40, 4)]);
+
+        tiles.align([0, 1, 2], bounds, Alignment::Bottom);
+        assert_eq!(tiles.iter().map(|tile| tile[1]).collect_vec(), [16, 16, 8], "bottom edges are at 24");
+    }
+
+    #[test]
+    fn test_distribute_horizontally() {
+        let mut tiles =
+            SpriteTiles(vec![Tile([40, 0, 0x20, 0x0A08]), Tile([0, 8, 0x21, 0x0A08]), Tile([10, 0, 0x22, 0x0A08])]);
+        let bounds = OnCanvas(tiles.iter().map(|tile| tile.rect().0).reduce(Rect::union).unwrap());
+
+        tiles.distribute([0, 1, 2], bounds, Axis::Horizontal);
+        assert_eq!(tiles.iter().map(|tile| tile[0]).collect_vec(), [40, 0, 20]);
+        assert_eq!(tiles.iter().map(|tile| tile[1]).collect_vec(), [0, 8, 0], "only x changes");
+    }
+
+    #[test]
+    fn test_flip_horizontally() {
+        let mut tiles = SpriteTiles(vec![Tile([16, 8, 0x20, 0x0A08]), Tile([24, 8, 0x21, 0x0A08])]);
+        let bounds = OnCanvas(tiles[0].rect().0.union(tiles[1].rect().0));
+
+        tiles.flip([0, 1], bounds, FlipDirection::Horizontal);
+        assert!(tiles.iter().all(|tile| tile.flip_x() && !tile.flip_y()));
+        assert_eq!((tiles[0][0], tiles[1][0]), (24, 16), "positions are swapped");
+        assert_eq!((tiles[0][1], tiles[1][1]), (8, 8));
+
+        tiles.flip([0, 1], bounds, FlipDirection::Horizontal);
+        assert!(tiles.iter().all(|tile| !tile.flip_x()));
+        assert_eq!((tiles[0][0], tiles[1][0]), (16, 24));
+    }
+}
@@ -1,40 +1,63 @@
+mod command_palette;
 mod dev_utils;
 mod editing_mode;
 mod editor_prototypes;
+mod notifications;
 mod project_creator;
+mod recent_files;
+mod session;
 mod style;
 mod tab_viewer;
 mod tool;
+mod workspace;
 
-use std::sync::Arc;
+use std::{cell::RefCell, path::Path, rc::Rc, sync::Arc};
 
 use eframe::{CreationContext, Frame};
 use egui::*;
-use egui_dock::{DockArea, DockState, Style as DockStyle};
+use egui_dock::{DockArea, Style as DockStyle};
 use egui_phosphor::Variant;
-use smwe_emu::rom::Rom;
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
 
 use crate::{
-    project::{Project, ProjectRef},
+    project::{Project, ProjectFile, ProjectRef},
     ui::{
-        dev_utils::address_converter::UiAddressConverter,
+        command_palette::{Command, UiCommandPalette, SHORTCUT_COMMAND_PALETTE},
+        dev_utils::{
+            address_converter::UiAddressConverter,
+            code_editor::UiCodeEditor,
+            disassembler::UiDisassembler,
+            gfx_viewer::UiGfxViewer,
+            rom_info::UiRomInfo,
+            tiles16x16::UiTiles16x16,
+        },
         editor_prototypes::{
             block_editor::UiBlockEditor,
             level_editor::UiLevelEditor,
             sprite_map_editor::UiSpriteMapEditor,
         },
+        notifications::Notifications,
         project_creator::UiProjectCreator,
+        recent_files::RecentFiles,
+        session::{DockLayout, ProjectSessions},
         tab_viewer::EditorToolTabViewer,
-        tool::DockableEditorTool,
+        workspace::Workspace,
     },
 };
 
+const DOCK_LAYOUT_KEY: &str = "dock_layout";
+const RECENT_FILES_KEY: &str = "recent_files";
+
 pub struct UiMainWindow {
     gl:                 Arc<glow::Context>,
+    sessions:           ProjectSessions,
+    /// Set once the user has agreed to close the editor despite unsaved changes.
+    close_confirmed:    bool,
     project_creator:    Option<UiProjectCreator>,
+    command_palette:    Option<UiCommandPalette>,
+    notifications_open: bool,
+    recent_files:       RecentFiles,
     dock_style:         DockStyle,
-    dock_state:         DockState<Box<dyn DockableEditorTool>>,
-    last_open_tool_idx: usize,
 }
 
 impl UiMainWindow {
@@ -44,92 +67,347 @@ impl UiMainWindow {
         cc.egui_ctx.set_fonts(fonts);
         cc.egui_ctx.set_visuals(Visuals::dark());
 
-        if let Some(project) = project {
-            cc.egui_ctx.data_mut(|data| {
-                let project = project.borrow();
-                data.insert_temp(Project::project_title_id(), project.title.clone());
-                data.insert_temp(Project::rom_id(), Arc::clone(&project.rom));
-            });
-        }
-
         let mut dock_style = DockStyle::from_egui(&cc.egui_ctx.style());
         dock_style.tab.tab_body.inner_margin = Margin::ZERO;
 
+        let gl = Arc::clone(cc.gl.as_ref().expect("must use the glow renderer"));
+        let mut sessions = ProjectSessions::new(project);
+        if let Some(layout) = cc.storage.and_then(|storage| eframe::get_value::<DockLayout>(storage, DOCK_LAYOUT_KEY)) {
+            sessions.active_mut().restore_layout(&layout, &gl);
+        }
+        let recent_files =
+            cc.storage.and_then(|storage| eframe::get_value(storage, RECENT_FILES_KEY)).unwrap_or_default();
+
         Self {
-            gl: Arc::clone(cc.gl.as_ref().expect("must use the glow renderer")),
+            gl,
+            sessions,
+            close_confirmed: false,
             project_creator: None,
+            command_palette: None,
+            notifications_open: false,
+            recent_files,
             dock_style,
-            dock_state: DockState::new(vec![]),
-            last_open_tool_idx: 0,
         }
     }
 }
 
 impl eframe::App for UiMainWindow {
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+        if ctx.input(|i| i.viewport().close_requested()) && !self.close_confirmed {
+            if self.confirm_discarding_changes() {
+                self.close_confirmed = true;
+            } else {
+                ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+            }
+        }
+
         CentralPanel::default().show(ctx, |ui| {
             self.main_menu_bar(ctx);
+            self.session_tabs(ctx);
 
-            DockArea::new(&mut self.dock_state).style(self.dock_style.clone()).show(ctx, &mut EditorToolTabViewer);
+            DockArea::new(&mut self.sessions.active_mut().dock_state)
+                .style(self.dock_style.clone())
+                .show(ctx, &mut EditorToolTabViewer);
+
+            self.command_palette(ctx);
+            Notifications::with_global(|notifications| {
+                notifications.show_toasts(ctx);
+                if self.notifications_open {
+                    notifications.show_history(ctx, &mut self.notifications_open);
+                }
+            });
 
             if let Some(project_creator) = &mut self.project_creator {
-                if !project_creator.update(ui) {
+                let running = project_creator.update(ui);
+                let created_project = project_creator.take_created_project();
+                if !running {
                     self.project_creator = None;
                 }
+                if let Some(project) = created_project {
+                    if let Some(rom_path) = project.rom_path() {
+                        self.recent_files.push(rom_path);
+                    }
+                    self.sessions.open_project(Rc::new(RefCell::new(project)));
+                }
             }
         });
     }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, DOCK_LAYOUT_KEY, &self.sessions.active().layout());
+        eframe::set_value(storage, RECENT_FILES_KEY, &self.recent_files);
+    }
 }
 
 impl UiMainWindow {
-    fn open_tool<ToolType>(&mut self, tool: ToolType)
-    where
-        ToolType: 'static + DockableEditorTool,
-    {
-        if self.last_open_tool_idx < usize::MAX {
-            log::info!("Opened {}", tool.title().text());
-            self.dock_state.push_to_focused_leaf(Box::new(tool));
-            self.last_open_tool_idx += 1;
+    /// Asks the user whether to continue if any project has unsaved changes.
+    fn confirm_discarding_changes(&self) -> bool {
+        !self.sessions.any_dirty()
+            || MessageDialog::new()
+                .set_title("Exit")
+                .set_description("You have unsaved changes. Do you want to exit anyway?")
+                .set_level(MessageLevel::Warning)
+                .set_buttons(MessageButtons::YesNo)
+                .show()
+                == MessageDialogResult::Yes
+    }
+
+    /// Closes the session after the user confirms it if its project has unsaved changes.
+    fn close_session(&mut self, idx: usize) {
+        let Some(session) = self.sessions.iter().nth(idx) else { return };
+        if session.is_dirty() {
+            let confirmation = MessageDialog::new()
+                .set_title("Close project")
+                .set_description(format!("'{}' has unsaved changes. Do you want to close it anyway?", session.title()))
+                .set_level(MessageLevel::Warning)
+                .set_buttons(MessageButtons::YesNo)
+                .show();
+            if confirmation != MessageDialogResult::Yes {
+                return;
+            }
+        }
+        self.sessions.close(idx);
+    }
+
+    fn active_project(&self) -> Option<&ProjectRef> {
+        self.sessions.active().project.as_ref()
+    }
+
+    fn open_project_dialog(&mut self) {
+        let Some(path) =
+            FileDialog::new().add_filter("SMW Editor project (*.smwe)", &[ProjectFile::EXTENSION]).pick_file()
+        else {
+            return;
+        };
+        self.open_file(&path);
+    }
+
+    /// Opens a project file, or creates a project from a ROM file, and adds it to the recent files. Files that can't
+    /// be opened are removed from the recent files.
+    fn open_file(&mut self, path: &Path) {
+        let result = if path.extension().is_some_and(|ext| ext == ProjectFile::EXTENSION) {
+            Project::load(path)
+        } else {
+            Project::new(path)
+        };
+        match result {
+            Ok(project) => {
+                self.recent_files.push(path);
+                self.sessions.open_project(Rc::new(RefCell::new(project)));
+            }
+            Err(e) => {
+                self.recent_files.remove(path);
+                Notifications::with_global(|n| {
+                    n.error(format!("Failed to open '{}'.", path.display()), format!("{e:?}"))
+                });
+            }
+        }
+    }
+
+    fn save_project_dialog(&mut self) {
+        let Some(project) = self.active_project() else { return };
+        let Some(path) = FileDialog::new()
+            .add_filter("SMW Editor project (*.smwe)", &[ProjectFile::EXTENSION])
+            .set_file_name(format!("{}.{}", project.borrow().title, ProjectFile::EXTENSION))
+            .save_file()
+        else {
+            return;
+        };
+        match project.borrow_mut().save(&path) {
+            Ok(()) => self.recent_files.push(path),
+            Err(e) => Notifications::with_global(|n| n.error("Failed to save the project.", format!("{e:?}"))),
+        }
+    }
+
+    fn save_rom_dialog(&mut self) {
+        let Some(project) = self.active_project() else { return };
+        let Some(path) = FileDialog::new().add_filter("SNES ROM File (*.smc, *.sfc)", &["smc", "sfc"]).save_file()
+        else {
+            return;
+        };
+        if let Err(e) = project.borrow_mut().save_rom(&path) {
+            Notifications::with_global(|n| n.error("Failed to save the ROM.", format!("{e:?}")));
+        }
+    }
+
+    /// Discards the project's unsaved ROM edits after the user confirms it.
+    fn reload_project(&mut self) {
+        let Some(project) = self.active_project() else { return };
+        let mut project = project.borrow_mut();
+        if !project.patches().is_empty() {
+            let confirmation = MessageDialog::new()
+                .set_title("Reload from ROM")
+                .set_description("All unsaved changes to the ROM will be lost. Do you want to continue?")
+                .set_level(MessageLevel::Warning)
+                .set_buttons(MessageButtons::YesNo)
+                .show();
+            if confirmation != MessageDialogResult::Yes {
+                return;
+            }
+        }
+        project.reload();
+    }
+
+    /// Runs the command, unless it needs a project and none is open.
+    fn run_command(&mut self, ctx: &Context, command: Command) {
+        let rom = self.active_project().map(|project| Arc::clone(&project.borrow().rom));
+        let project = self.active_project().map(Rc::clone);
+        let smw_rom = || project.as_ref().map(|project| project.borrow_mut().smw_rom());
+        let had_smc_header = self.active_project().is_some_and(|project| project.borrow().had_smc_header());
+        if command.requires_project() && rom.is_none() {
+            return;
+        }
+        let session = self.sessions.active_mut();
+        match command {
+            Command::NewProject => self.project_creator = Some(UiProjectCreator::default()),
+            Command::OpenProject => self.open_project_dialog(),
+            Command::SaveProject => self.save_project_dialog(),
+            Command::SaveRomAs => self.save_rom_dialog(),
+            Command::ReloadFromRom => self.reload_project(),
+            Command::Exit => ctx.send_viewport_cmd(ViewportCommand::Close),
+            Command::OpenAddressConverter => session.open_tool(UiAddressConverter::default()),
+            Command::OpenBlockEditor => session.open_tool(UiBlockEditor::new(smw_rom())),
+            Command::OpenCodeEditor => session.open_tool(UiCodeEditor::new(rom.unwrap())),
+            Command::OpenDisassembler => session.open_tool(UiDisassembler::new(rom.unwrap(), smw_rom().unwrap())),
+            Command::OpenGfxViewer => session.open_tool(UiGfxViewer::new(smw_rom().unwrap())),
+            Command::OpenLevelEditor => session.open_tool(UiLevelEditor::new(Arc::clone(&self.gl), rom.unwrap())),
+            Command::OpenRomInfo => session.open_tool(UiRomInfo::new(rom.unwrap(), had_smc_header)),
+            Command::OpenSpriteMapEditor => {
+                session.open_tool(UiSpriteMapEditor::new(Arc::clone(&self.gl), Rc::clone(project.as_ref().unwrap())))
+            }
+            Command::OpenTiles16x16 => session.open_tool(UiTiles16x16::new(smw_rom().unwrap())),
+            Command::ResetLayout => session.restore_layout(&Workspace::Default.layout(), &self.gl),
+            Command::ApplyWorkspace(workspace) => session.restore_layout(&workspace.layout(), &self.gl),
+        }
+    }
+
+    fn command_palette(&mut self, ctx: &Context) {
+        if ctx.input_mut(|input| input.consume_shortcut(&SHORTCUT_COMMAND_PALETTE)) {
+            self.command_palette = match self.command_palette {
+                Some(_) => None,
+                None => Some(UiCommandPalette::default()),
+            };
+        }
+        let has_project = self.active_project().is_some();
+        let Some(command_palette) = &mut self.command_palette else { return };
+        let running = command_palette.update(ctx, has_project);
+        let chosen_command = command_palette.take_chosen_command();
+        if !running {
+            self.command_palette = None;
+        }
+        if let Some(command) = chosen_command {
+            self.run_command(ctx, command);
         }
     }
 
     fn main_menu_bar(&mut self, ctx: &Context) {
-        let rom: Option<Arc<Rom>> = ctx.data(|data| data.get_temp(Id::new("rom")));
+        let has_project = self.active_project().is_some();
+        let menu_item = |ui: &mut Ui, command: Command, text: &str| {
+            let enabled = has_project || !command.requires_project();
+            let clicked = ui.add_enabled(enabled, Button::new(text)).clicked();
+            if clicked {
+                ui.close_menu();
+            }
+            clicked.then_some(command)
+        };
 
+        let mut clicked_command = None;
         TopBottomPanel::top("main_top_bar").show(ctx, |ui| {
             menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
-                    if ui.button("New project").clicked() {
-                        self.project_creator = Some(UiProjectCreator::default());
+                    clicked_command = clicked_command
+                        .or(menu_item(ui, Command::NewProject, "New project"))
+                        .or(menu_item(ui, Command::OpenProject, "Open project..."));
+                    self.recent_files_menu(ui);
+                    clicked_command = clicked_command
+                        .or(menu_item(ui, Command::SaveProject, "Save project as..."))
+                        .or(menu_item(ui, Command::SaveRomAs, "Save ROM as..."))
+                        .or(menu_item(ui, Command::ReloadFromRom, "Reload from ROM"))
+                        .or(menu_item(ui, Command::Exit, "Exit"));
+                });
+
+                ui.menu_button("Tools", |ui| {
+                    clicked_command = clicked_command
+                        .or(menu_item(ui, Command::OpenAddressConverter, "Address converter"))
+                        .or(menu_item(ui, Command::OpenCodeEditor, "Code editor"))
+                        .or(menu_item(ui, Command::OpenDisassembler, "Disassembler"))
+                        .or(menu_item(ui, Command::OpenGfxViewer, "GFX viewer"))
+                        .or(menu_item(ui, Command::OpenRomInfo, "ROM info"))
+                        .or(menu_item(ui, Command::OpenTiles16x16, "16x16 tiles viewer"));
+                    if ui.button("Command palette...").clicked() {
+                        self.command_palette = Some(UiCommandPalette::default());
                         ui.close_menu();
                     }
-                    if ui.button("Exit").clicked() {
-                        ctx.send_viewport_cmd(ViewportCommand::Close);
-                    }
                 });
 
-                ui.menu_button("Tools", |ui| {
-                    if ui.button("Address converter").clicked() {
-                        self.open_tool(UiAddressConverter::default());
+                ui.menu_button("View", |ui| {
+                    clicked_command = clicked_command.or(menu_item(ui, Command::ResetLayout, "Reset layout"));
+                    ui.separator();
+                    for workspace in Workspace::ALL.into_iter().filter(|&workspace| workspace != Workspace::Default) {
+                        let text = format!("{} workspace", workspace.title());
+                        clicked_command = clicked_command.or(menu_item(ui, Command::ApplyWorkspace(workspace), &text));
+                    }
+                    ui.separator();
+                    if ui.button("Notifications").clicked() {
+                        self.notifications_open = true;
                         ui.close_menu();
                     }
                 });
 
                 ui.menu_button("Prototypes", |ui| {
-                    if ui.button("Block editor").clicked() {
-                        self.open_tool(UiBlockEditor::default());
+                    clicked_command = clicked_command
+                        .or(menu_item(ui, Command::OpenBlockEditor, "Block editor"))
+                        .or(menu_item(ui, Command::OpenLevelEditor, "Level editor"))
+                        .or(menu_item(ui, Command::OpenSpriteMapEditor, "Sprite map editor"));
+                });
+            });
+        });
+        if let Some(command) = clicked_command {
+            self.run_command(ctx, command);
+        }
+    }
+
+    fn recent_files_menu(&mut self, ui: &mut Ui) {
+        let mut opened_path = None;
+        ui.add_enabled_ui(!self.recent_files.is_empty(), |ui| {
+            ui.menu_button("Open recent", |ui| {
+                for path in self.recent_files.iter() {
+                    if ui.button(path.display().to_string()).clicked() {
+                        opened_path = Some(path.to_path_buf());
                         ui.close_menu();
                     }
-                    if ui.add_enabled(rom.is_some(), Button::new("Level editor")).clicked() {
-                        self.open_tool(UiLevelEditor::new(Arc::clone(&self.gl), rom.clone().unwrap()));
-                        ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Clear").clicked() {
+                    self.recent_files.clear();
+                    ui.close_menu();
+                }
+            });
+        });
+        if let Some(path) = opened_path {
+            self.open_file(&path);
+        }
+    }
+
+    /// Top-level tabs for switching between the opened projects.
+    fn session_tabs(&mut self, ctx: &Context) {
+        let mut closed_idx = None;
+        TopBottomPanel::top("main_session_tabs").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let titles: Vec<_> = self.sessions.iter().map(|session| session.title()).collect();
+                for (idx, title) in titles.into_iter().enumerate() {
+                    if ui.selectable_label(idx == self.sessions.active_idx(), title).clicked() {
+                        self.sessions.switch_to(idx);
                     }
-                    if ui.add_enabled(rom.is_some(), Button::new("Sprite map editor")).clicked() {
-                        self.open_tool(UiSpriteMapEditor::new(Arc::clone(&self.gl), rom.clone().unwrap()));
-                        ui.close_menu();
+                    if ui.small_button(egui_phosphor::regular::X).on_hover_text("Close project").clicked() {
+                        closed_idx = Some(idx);
                     }
-                });
+                    ui.separator();
+                }
             });
         });
+        if let Some(idx) = closed_idx {
+            self.close_session(idx);
+        }
     }
 }
@@ -0,0 +1,215 @@
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use egui::{Align2, Color32, Context, Frame, Id, RichText, ScrollArea, Window};
+
+static GLOBAL_NOTIFICATIONS: Mutex<Notifications> = Mutex::new(Notifications::new());
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct Notification {
+    pub severity:    Severity,
+    pub title:       String,
+    pub description: String,
+    pub created_at:  Instant,
+}
+
+/// Messages shown as toasts until they expire or are dismissed, after which they're kept in the history.
+#[derive(Debug, Default)]
+pub struct Notifications {
+    toasts:  VecDeque<Notification>,
+    history: VecDeque<Notification>,
+}
+
+impl Severity {
+    pub fn color(self) -> Color32 {
+        match self {
+            Severity::Info => Color32::LIGHT_BLUE,
+            Severity::Warning => Color32::YELLOW,
+            Severity::Error => Color32::RED,
+        }
+    }
+}
+
+impl Notifications {
+    /// How long toasts are shown for.
+    pub const LIFETIME: Duration = Duration::from_secs(8);
+    pub const MAX_HISTORY: usize = 100;
+
+    pub const fn new() -> Self {
+        Self { toasts: VecDeque::new(), history: VecDeque::new() }
+    }
+
+    /// Runs `f` on the notifications shown in the main window, which can be pushed to from anywhere.
+    pub fn with_global<R>(f: impl FnOnce(&mut Notifications) -> R) -> R {
+        f(&mut GLOBAL_NOTIFICATIONS.lock().expect("Cannot lock mutex on notifications"))
+    }
+
+    pub fn push(&mut self, severity: Severity, title: impl Into<String>, description: impl Into<String>) {
+        let notification =
+            Notification { severity, title: title.into(), description: description.into(), created_at: Instant::now() };
+        match severity {
+            Severity::Info => log::info!("{}: {}", notification.title, notification.description),
+            Severity::Warning => log::warn!("{}: {}", notification.title, notification.description),
+            Severity::Error => log::error!("{}: {}", notification.title, notification.description),
+        }
+        self.toasts.push_back(notification);
+    }
+
+    pub fn info(&mut self, title: impl Into<String>, description: impl Into<String>) {
+        self.push(Severity::Info, title, description);
+    }
+
+    pub fn warning(&mut self, title: impl Into<String>, description: impl Into<String>) {
+        self.push(Severity::Warning, title, description);
+    }
+
+    pub fn error(&mut self, title: impl Into<String>, description: impl Into<String>) {
+        self.push(Severity::Error, title, description);
+    }
+
+    /// Moves toasts that have been shown for at least [`LIFETIME`](Self::LIFETIME) by `now` to the history.
+    pub fn expire(&mut self, now: Instant) {
+        while self.toasts.front().is_some_and(|toast| now.duration_since(toast.created_at) >= Self::LIFETIME) {
+            let toast = self.toasts.pop_front().unwrap();
+            self.add_to_history(toast);
+        }
+    }
+
+    /// Moves the toast at `idx` to the history. Out of bounds indices are ignored.
+    pub fn dismiss(&mut self, idx: usize) {
+        if let Some(toast) = self.toasts.remove(idx) {
+            self.add_to_history(toast);
+        }
+    }
+
+    pub fn toasts(&self) -> impl Iterator<Item = &Notification> {
+        self.toasts.iter()
+    }
+
+    /// Notifications that are no longer shown as toasts, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &Notification> {
+        self.history.iter()
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    fn add_to_history(&mut self, notification: Notification) {
+        if self.history.len() == Self::MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(notification);
+    }
+
+    /// Shows the toasts in the bottom right corner of the screen.
+    pub fn show_toasts(&mut self, ctx: &Context) {
+        self.expire(Instant::now());
+        if self.toasts.is_empty() {
+            return;
+        }
+        ctx.request_repaint_after(Duration::from_secs(1));
+
+        let mut dismissed_idx = None;
+        egui::Area::new(Id::new("notification_toasts")).anchor(Align2::RIGHT_BOTTOM, [-8., -8.]).show(ctx, |ui| {
+            for (idx, toast) in self.toasts.iter().enumerate() {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(320.);
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(&toast.title).strong().color(toast.severity.color()));
+                        if ui.small_button(egui_phosphor::regular::X).clicked() {
+                            dismissed_idx = Some(idx);
+                        }
+                    });
+                    if !toast.description.is_empty() {
+                        ui.label(&toast.description);
+                    }
+                });
+            }
+        });
+        if let Some(idx) = dismissed_idx {
+            self.dismiss(idx);
+        }
+    }
+
+    /// Shows a window listing past notifications, newest first.
+    pub fn show_history(&mut self, ctx: &Context, open: &mut bool) {
+        let mut clear = false;
+        Window::new("Notifications").open(open).default_width(400.).show(ctx, |ui| {
+            if ui.button("Clear").clicked() {
+                clear = true;
+            }
+            ui.separator();
+            if self.history.is_empty() {
+                ui.weak("No notifications");
+            }
+            ScrollArea::vertical().show(ui, |ui| {
+                for notification in self.history.iter().rev() {
+                    ui.label(RichText::new(&notification.title).strong().color(notification.severity.color()));
+                    if !notification.description.is_empty() {
+                        ui.label(&notification.description);
+                    }
+                    ui.separator();
+                }
+            });
+        });
+        if clear {
+            self.clear_history();
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles<'a>(notifications: impl Iterator<Item = &'a Notification>) -> Vec<&'a str> {
+        notifications.map(|notification| notification.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_push_and_expire() {
+        let mut notifications = Notifications::new();
+        notifications.error("A", "Failed");
+        notifications.warning("B", "");
+        notifications.info("C", "");
+        assert_eq!(titles(notifications.toasts()), ["A", "B", "C"]);
+        assert_eq!(notifications.toasts().next().unwrap().severity, Severity::Error);
+
+        let created_at = notifications.toasts().next().unwrap().created_at;
+        notifications.expire(created_at);
+        assert_eq!(titles(notifications.toasts()), ["A", "B", "C"], "nothing expires before the lifetime passes");
+
+        notifications.dismiss(1);
+        notifications.dismiss(5);
+        assert_eq!(titles(notifications.toasts()), ["A", "C"]);
+        assert_eq!(titles(notifications.history()), ["B"]);
+
+        notifications.expire(Instant::now() + Notifications::LIFETIME);
+        assert_eq!(notifications.toasts().count(), 0);
+        assert_eq!(titles(notifications.history()), ["B", "A", "C"]);
+    }
+
+    #[test]
+    fn test_history_limit() {
+        let mut notifications = Notifications::new();
+        for i in 0..Notifications::MAX_HISTORY + 5 {
+            notifications.info(i.to_string(), "");
+        }
+        notifications.expire(Instant::now() + Notifications::LIFETIME);
+        assert_eq!(notifications.history().count(), Notifications::MAX_HISTORY);
+        assert_eq!(notifications.history().next().unwrap().title, "5");
+    }
+}
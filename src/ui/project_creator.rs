@@ -16,6 +16,8 @@ pub struct UiProjectCreator {
     err_project_title:    String,
     err_base_rom_path:    String,
     err_project_creation: String,
+
+    created_project: Option<Project>,
 }
 
 impl Default for UiProjectCreator {
@@ -28,6 +30,8 @@ impl Default for UiProjectCreator {
             err_project_title:    String::new(),
             err_base_rom_path:    String::new(),
             err_project_creation: String::new(),
+
+            created_project: None,
         };
         myself.handle_rom_file_path();
         myself
@@ -55,6 +59,11 @@ impl UiProjectCreator {
         running
     }
 
+    /// Returns the project if it's been created since the last call.
+    pub fn take_created_project(&mut self) -> Option<Project> {
+        self.created_project.take()
+    }
+
     fn input_project_title(&mut self, ui: &mut Ui) {
         ui.label("Project title");
         if ui.text_edit_singleline(&mut self.project_title).changed() {
@@ -114,7 +123,7 @@ impl UiProjectCreator {
         ui.horizontal(|ui| {
             if ui.add_enabled(self.no_creation_errors(), Button::new("Create").small()).clicked() {
                 log::info!("Attempting to create a new project");
-                self.handle_project_creation(ui, created_or_cancelled);
+                self.handle_project_creation(created_or_cancelled);
             }
             if ui.small_button("Cancel").clicked() {
                 log::info!("Cancelled project creation");
@@ -126,14 +135,12 @@ impl UiProjectCreator {
         }
     }
 
-    fn handle_project_creation(&mut self, ui: &Ui, created_or_cancelled: &mut bool) {
+    fn handle_project_creation(&mut self, created_or_cancelled: &mut bool) {
         match Project::new(&self.base_rom_path) {
-            Ok(project) => {
+            Ok(mut project) => {
                 log::info!("Success creating a new project");
-                ui.data_mut(|data| {
-                    data.insert_temp(Project::project_title_id(), project.title);
-                    data.insert_temp(Project::rom_id(), project.rom);
-                });
+                project.title = self.project_title.clone();
+                self.created_project = Some(project);
                 *created_or_cancelled = true;
                 self.err_project_creation.clear();
             }
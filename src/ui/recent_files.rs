@@ -0,0 +1,68 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Recently opened ROMs and project files, most recent first.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RecentFiles {
+    paths: VecDeque<PathBuf>,
+}
+
+impl RecentFiles {
+    pub const MAX_LEN: usize = 10;
+
+    /// Moves the path to the front of the list, dropping the oldest one if the list is full.
+    pub fn push(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        self.paths.retain(|p| *p != path);
+        self.paths.push_front(path);
+        self.paths.truncate(Self::MAX_LEN);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.paths.retain(|p| p != path);
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Path> {
+        self.paths.iter().map(PathBuf::as_path)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_dedupe_cap() {
+        let mut recent = RecentFiles::default();
+        recent.push("a.smc");
+        recent.push("b.smwe");
+        recent.push("a.smc");
+        assert_eq!(recent.iter().collect::<Vec<_>>(), [Path::new("a.smc"), Path::new("b.smwe")]);
+
+        for i in 0..RecentFiles::MAX_LEN {
+            recent.push(format!("{i}.smc"));
+        }
+        assert_eq!(recent.iter().count(), RecentFiles::MAX_LEN);
+        assert_eq!(recent.iter().next(), Some(Path::new("9.smc")));
+        assert!(recent.iter().all(|path| path != Path::new("a.smc")), "oldest entries are dropped");
+
+        recent.remove(Path::new("9.smc"));
+        assert_eq!(recent.iter().next(), Some(Path::new("8.smc")));
+        recent.clear();
+        assert!(recent.is_empty());
+    }
+}
@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use egui_dock::DockState;
+
+use crate::{
+    project::ProjectRef,
+    ui::tool::{DockableEditorTool, DockableEditorToolEnum},
+};
+
+/// Which tools are open in a session and how they're arranged, without their state.
+pub type DockLayout = DockState<DockableEditorToolEnum>;
+
+/// A project opened in the main window together with the tools opened for it.
+pub struct ProjectSession {
+    /// `None` for the session holding tools that were opened before any project.
+    pub project:        Option<ProjectRef>,
+    pub dock_state:     DockState<Box<dyn DockableEditorTool>>,
+    last_open_tool_idx: usize,
+}
+
+/// Sessions shown as top-level tabs in the main window. There's always at least one, and exactly one is active.
+pub struct ProjectSessions {
+    sessions:   Vec<ProjectSession>,
+    active_idx: usize,
+}
+
+impl ProjectSession {
+    pub fn new(project: Option<ProjectRef>) -> Self {
+        Self { project, dock_state: DockState::new(vec![]), last_open_tool_idx: 0 }
+    }
+
+    pub fn title(&self) -> String {
+        match &self.project {
+            Some(project) => project.borrow().title.clone(),
+            None => String::from("No project"),
+        }
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.project.as_ref().is_some_and(|project| project.borrow().is_dirty())
+    }
+
+    pub fn open_tool<ToolType>(&mut self, tool: ToolType)
+    where
+        ToolType: 'static + DockableEditorTool,
+    {
+        if self.last_open_tool_idx < usize::MAX {
+            log::info!("Opened {}", tool.title().text());
+            self.dock_state.push_to_focused_leaf(Box::new(tool));
+            self.last_open_tool_idx += 1;
+        }
+    }
+
+    pub fn layout(&self) -> DockLayout {
+        self.dock_state.map_tabs(|tool| tool.kind())
+    }
+
+    /// Replaces the session's tools with new ones arranged as in the layout. Tools that require a ROM are left out
+    /// if the session doesn't have a project.
+    pub fn restore_layout(&mut self, layout: &DockLayout, gl: &Arc<glow::Context>) {
+        self.close_tools();
+        self.dock_state = layout.filter_map_tabs(|kind| kind.create(gl, self.project.as_ref()));
+        self.last_open_tool_idx = self.dock_state.iter_all_tabs().count();
+        log::info!("Restored {} of {} tools", self.last_open_tool_idx, layout.iter_all_tabs().count());
+    }
+
+    /// Calls [`on_closed`](DockableEditorTool::on_closed) on all the session's tools.
+    fn close_tools(&mut self) {
+        for (_, tool) in self.dock_state.iter_all_tabs_mut() {
+            tool.on_closed();
+            log::info!("Closed {}", tool.title().text());
+        }
+    }
+}
+
+impl ProjectSessions {
+    pub fn new(project: Option<ProjectRef>) -> Self {
+        Self { sessions: vec![ProjectSession::new(project)], active_idx: 0 }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ProjectSession> {
+        self.sessions.iter()
+    }
+
+    pub fn active_idx(&self) -> usize {
+        self.active_idx
+    }
+
+    pub fn active(&self) -> &ProjectSession {
+        &self.sessions[self.active_idx]
+    }
+
+    pub fn active_mut(&mut self) -> &mut ProjectSession {
+        &mut self.sessions[self.active_idx]
+    }
+
+    /// Whether any of the sessions' projects has unsaved changes.
+    pub fn any_dirty(&self) -> bool {
+        self.sessions.iter().any(ProjectSession::is_dirty)
+    }
+
+    /// Opens the project in a new active session, or in the active one if it doesn't have a project yet.
+    pub fn open_project(&mut self, project: ProjectRef) {
+        if self.active().project.is_none() {
+            self.active_mut().project = Some(project);
+        } else {
+            self.sessions.push(ProjectSession::new(Some(project)));
+            self.active_idx = self.sessions.len() - 1;
+        }
+        log::info!("Opened project session {}", self.active().title());
+    }
+
+    /// Makes the session at `idx` active. Out of bounds indices are ignored.
+    pub fn switch_to(&mut self, idx: usize) {
+        if idx < self.sessions.len() {
+            self.active_idx = idx;
+        }
+    }
+
+    /// Closes the session at `idx` along with its tools. Closing the last session leaves an empty one in its place.
+    pub fn close(&mut self, idx: usize) {
+        if idx >= self.sessions.len() {
+            return;
+        }
+        let mut session = self.sessions.remove(idx);
+        session.close_tools();
+        log::info!("Closed project session {}", session.title());
+
+        if self.sessions.is_empty() {
+            self.sessions.push(ProjectSession::new(None));
+        }
+        if self.active_idx > idx || self.active_idx >= self.sessions.len() {
+            self.active_idx = self.active_idx.saturating_sub(1);
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use egui_dock::NodeIndex;
+
+    use super::*;
+    use crate::project::Project;
+
+    fn test_project(title: &str) -> ProjectRef {
+        Rc::new(RefCell::new(Project::from_rom_bytes(String::from(title), vec![0; 0x8000])))
+    }
+
+    fn titles(sessions: &ProjectSessions) -> Vec<String> {
+        sessions.iter().map(ProjectSession::title).collect()
+    }
+
+    #[test]
+    fn test_open_and_switch_sessions() {
+        let mut sessions = ProjectSessions::new(None);
+        assert_eq!(sessions.iter().count(), 1);
+        assert!(sessions.active().project.is_none());
+
+        sessions.open_project(test_project("A"));
+        assert_eq!(titles(&sessions), ["A"], "fills in the session without a project");
+
+        sessions.open_project(test_project("B"));
+        sessions.open_project(test_project("C"));
+        assert_eq!(titles(&sessions), ["A", "B", "C"]);
+        assert_eq!(sessions.active_idx(), 2);
+
+        sessions.switch_to(0);
+        assert_eq!(sessions.active().title(), "A");
+        sessions.switch_to(3);
+        assert_eq!(sessions.active().title(), "A", "ignores invalid indices");
+
+        sessions.active().project.as_ref().unwrap().borrow_mut().set_annotation(0x008000, String::from("Reset"));
+        assert!(sessions.any_dirty());
+        sessions.switch_to(1);
+        assert!(!sessions.active().is_dirty(), "projects are separate");
+    }
+
+    #[test]
+    fn test_dock_layout_serialization() {
+        use DockableEditorToolEnum::*;
+
+        let mut layout = DockLayout::new(vec![AddressConverter, LevelEditor]);
+        layout.main_surface_mut().split_right(NodeIndex::root(), 0.5, vec![SpriteMapEditor, BlockEditor]);
+
+        let json = serde_json::to_string(&layout).unwrap();
+        let restored: DockLayout = serde_json::from_str(&json).unwrap();
+        let tabs = |layout: &DockLayout| layout.iter_all_tabs().map(|(_, &kind)| kind).collect::<Vec<_>>();
+        assert_eq!(tabs(&restored), [AddressConverter, LevelEditor, SpriteMapEditor, BlockEditor]);
+        assert_eq!(restored.main_surface().num_tabs(), 4);
+
+        let without_rom = restored.filter_tabs(|kind| !kind.requires_rom());
+        assert_eq!(tabs(&without_rom), [AddressConverter, BlockEditor]);
+    }
+
+    #[test]
+    fn test_close_sessions() {
+        let mut sessions = ProjectSessions::new(Some(test_project("A")));
+        sessions.open_project(test_project("B"));
+        sessions.open_project(test_project("C"));
+
+        sessions.switch_to(2);
+        sessions.close(0);
+        assert_eq!(titles(&sessions), ["B", "C"]);
+        assert_eq!(sessions.active().title(), "C", "keeps the same session active");
+
+        sessions.close(1);
+        assert_eq!(titles(&sessions), ["B"]);
+        assert_eq!(sessions.active_idx(), 0);
+
+        sessions.close(0);
+        assert_eq!(sessions.iter().count(), 1);
+        assert!(sessions.active().project.is_none());
+    }
+}
@@ -1,10 +1,79 @@
 #![allow(clippy::enum_variant_names)]
 
+use std::{rc::Rc, sync::Arc};
+
 use eframe::egui::Ui;
 use egui::WidgetText;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    project::ProjectRef,
+    ui::{
+        dev_utils::{
+            address_converter::UiAddressConverter,
+            code_editor::UiCodeEditor,
+            disassembler::UiDisassembler,
+            gfx_viewer::UiGfxViewer,
+            rom_info::UiRomInfo,
+            tiles16x16::UiTiles16x16,
+        },
+        editor_prototypes::{
+            block_editor::UiBlockEditor,
+            level_editor::UiLevelEditor,
+            sprite_map_editor::UiSpriteMapEditor,
+        },
+    },
+};
 
 pub trait DockableEditorTool {
     fn update(&mut self, ui: &mut Ui);
     fn title(&self) -> WidgetText;
+    fn kind(&self) -> DockableEditorToolEnum;
     fn on_closed(&mut self) {}
 }
+
+/// Every kind of tool that can be docked, used to save which tools are open.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum DockableEditorToolEnum {
+    AddressConverter,
+    BlockEditor,
+    CodeEditor,
+    Disassembler,
+    GfxViewer,
+    LevelEditor,
+    RomInfo,
+    SpriteMapEditor,
+    Tiles16x16,
+}
+
+impl DockableEditorToolEnum {
+    pub fn requires_rom(self) -> bool {
+        matches!(
+            self,
+            Self::CodeEditor
+                | Self::Disassembler
+                | Self::GfxViewer
+                | Self::LevelEditor
+                | Self::RomInfo
+                | Self::SpriteMapEditor
+                | Self::Tiles16x16
+        )
+    }
+
+    /// Creates a tool of this kind, or returns `None` if it requires a ROM and no project is given.
+    pub fn create(self, gl: &Arc<glow::Context>, project: Option<&ProjectRef>) -> Option<Box<dyn DockableEditorTool>> {
+        let rom = || project.map(|project| Arc::clone(&project.borrow().rom));
+        let smw_rom = || project.map(|project| project.borrow_mut().smw_rom());
+        Some(match self {
+            Self::AddressConverter => Box::new(UiAddressConverter::default()),
+            Self::BlockEditor => Box::new(UiBlockEditor::new(smw_rom())),
+            Self::CodeEditor => Box::new(UiCodeEditor::new(rom()?)),
+            Self::Disassembler => Box::new(UiDisassembler::new(rom()?, smw_rom()?)),
+            Self::GfxViewer => Box::new(UiGfxViewer::new(smw_rom()?)),
+            Self::LevelEditor => Box::new(UiLevelEditor::new(Arc::clone(gl), rom()?)),
+            Self::RomInfo => Box::new(UiRomInfo::new(rom()?, project?.borrow().had_smc_header())),
+            Self::SpriteMapEditor => Box::new(UiSpriteMapEditor::new(Arc::clone(gl), Rc::clone(project?))),
+            Self::Tiles16x16 => Box::new(UiTiles16x16::new(smw_rom()?)),
+        })
+    }
+}
@@ -0,0 +1,65 @@
+use egui_dock::NodeIndex;
+
+use crate::ui::{session::DockLayout, tool::DockableEditorToolEnum};
+
+/// Preset dock layouts for common tasks.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Workspace {
+    Default,
+    Disassembly,
+    Graphics,
+    LevelEditing,
+}
+
+impl Workspace {
+    pub const ALL: [Workspace; 4] =
+        [Workspace::Default, Workspace::Disassembly, Workspace::Graphics, Workspace::LevelEditing];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Workspace::Default => "Default",
+            Workspace::Disassembly => "Disassembly",
+            Workspace::Graphics => "Graphics",
+            Workspace::LevelEditing => "Level editing",
+        }
+    }
+
+    /// Returns the workspace's tools, with the main one on the left and the rest stacked on the right.
+    pub fn layout(self) -> DockLayout {
+        use DockableEditorToolEnum::*;
+        let (main, side) = match self {
+            Workspace::Default => (vec![LevelEditor], vec![AddressConverter]),
+            Workspace::Disassembly => (vec![Disassembler], vec![AddressConverter]),
+            Workspace::Graphics => (vec![SpriteMapEditor], vec![BlockEditor]),
+            Workspace::LevelEditing => (vec![LevelEditor], vec![BlockEditor, AddressConverter]),
+        };
+        let mut layout = DockLayout::new(main);
+        if !side.is_empty() {
+            layout.main_surface_mut().split_right(NodeIndex::root(), 0.7, side);
+        }
+        layout
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_tools() {
+        use DockableEditorToolEnum::*;
+
+        let tools =
+            |workspace: Workspace| workspace.layout().iter_all_tabs().map(|(_, &tool)| tool).collect::<Vec<_>>();
+        assert_eq!(tools(Workspace::Default), [LevelEditor, AddressConverter]);
+        assert_eq!(tools(Workspace::Disassembly), [Disassembler, AddressConverter]);
+        assert_eq!(tools(Workspace::Graphics), [SpriteMapEditor, BlockEditor]);
+        assert_eq!(tools(Workspace::LevelEditing), [LevelEditor, BlockEditor, AddressConverter]);
+
+        for workspace in Workspace::ALL {
+            assert!(workspace.layout().main_surface().num_tabs() > 0, "{} workspace is empty", workspace.title());
+        }
+    }
+}
@@ -11,9 +11,12 @@ pub trait Undo: Clone {
 
 #[derive(Debug)]
 pub struct UndoableData<Data: Undo> {
-    data:            Data,
-    stack:           UndoStack,
-    max_size_so_far: usize,
+    data:             Data,
+    /// The data as it was before the changes made with [`write_uncommitted`](Self::write_uncommitted) since the last
+    /// commit.
+    uncommitted_from: Option<Data>,
+    stack:            UndoStack,
+    max_size_so_far:  usize,
 }
 
 #[derive(Debug, Default)]
@@ -33,21 +36,39 @@ pub struct UndoStep {
 impl<Data: Undo> UndoableData<Data> {
     pub fn new(data: Data) -> Self {
         let max_size_so_far = data.size_bytes();
-        Self { data, stack: UndoStack::default(), max_size_so_far }
+        Self { data, uncommitted_from: None, stack: UndoStack::default(), max_size_so_far }
     }
 
     pub fn write<F, R>(&mut self, writer: F) -> R
     where
         F: FnOnce(&mut Data) -> R,
     {
-        let old_data = self.data.clone();
-        let result = writer(&mut self.data);
-        let step = UndoStep::delta(&old_data.to_bytes(), &self.data.to_bytes());
-        self.stack.push(step);
-        self.max_size_so_far = self.max_size_so_far.max(self.data.size_bytes());
+        let result = self.write_uncommitted(writer);
+        self.commit();
         result
     }
 
+    /// Changes the data without adding an undo step. All uncommitted changes are undone together once they're
+    /// committed with [`commit`](Self::commit), or by the next [`write`](Self::write), undo or redo.
+    pub fn write_uncommitted<F, R>(&mut self, writer: F) -> R
+    where
+        F: FnOnce(&mut Data) -> R,
+    {
+        if self.uncommitted_from.is_none() {
+            self.uncommitted_from = Some(self.data.clone());
+        }
+        writer(&mut self.data)
+    }
+
+    /// Adds an undo step for the changes made with [`write_uncommitted`](Self::write_uncommitted), if there are any.
+    pub fn commit(&mut self) {
+        if let Some(old_data) = self.uncommitted_from.take() {
+            let step = UndoStep::delta(&old_data.to_bytes(), &self.data.to_bytes());
+            self.stack.push(step);
+            self.max_size_so_far = self.max_size_so_far.max(self.data.size_bytes());
+        }
+    }
+
     pub fn read<F, R>(&self, reader: F) -> R
     where
         F: FnOnce(&Data) -> R,
@@ -64,6 +85,7 @@ impl<Data: Undo> UndoableData<Data> {
     }
 
     pub fn undo(&mut self) {
+        self.commit();
         if let Some(step) = self.stack.undo() {
             let old_data_bytes = self.data.to_bytes();
             let mut new_data_bytes = step.apply_delta(&old_data_bytes);
@@ -73,6 +95,7 @@ impl<Data: Undo> UndoableData<Data> {
     }
 
     pub fn redo(&mut self) {
+        self.commit();
         if let Some(step) = self.stack.redo() {
             let old_data_bytes = self.data.to_bytes();
             let mut new_data_bytes = step.apply_delta(&old_data_bytes);
@@ -82,14 +105,22 @@ impl<Data: Undo> UndoableData<Data> {
     }
 
     pub fn clear_stack(&mut self) {
+        self.uncommitted_from = None;
         self.stack.step_stack.clear();
         self.stack.step_number = 0;
     }
 }
 
 impl UndoStack {
+    /// Number of steps kept before the oldest ones start being dropped.
+    pub const MAX_STEPS: usize = 256;
+
     pub fn push(&mut self, step: UndoStep) {
         self.step_stack.truncate(self.step_number);
+        if self.step_stack.len() == Self::MAX_STEPS {
+            self.step_stack.remove(0);
+            self.step_number -= 1;
+        }
         self.step_number += 1;
         self.step_stack.push(step);
     }
@@ -138,3 +169,82 @@ impl UndoStep {
         to
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    struct Bytes(Vec<u8>);
+
+    impl Undo for Bytes {
+        fn from_bytes(bytes: Vec<u8>) -> Self {
+            Self(bytes)
+        }
+
+        fn to_bytes(&self) -> Vec<u8> {
+            self.0.clone()
+        }
+
+        fn size_bytes(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut data = UndoableData::new(Bytes(vec![]));
+        data.write(|bytes| bytes.0.push(1));
+        data.write(|bytes| bytes.0.push(2));
+        assert!(!data.can_redo());
+
+        data.undo();
+        assert_eq!(data.read(|bytes| bytes.0.clone()), [1]);
+        data.redo();
+        assert_eq!(data.read(|bytes| bytes.0.clone()), [1, 2]);
+
+        data.undo();
+        data.write(|bytes| bytes.0[0] = 3);
+        assert!(!data.can_redo(), "writing discards undone steps");
+        data.undo();
+        data.undo();
+        assert!(data.read(|bytes| bytes.0.is_empty()));
+        assert!(!data.can_undo());
+    }
+
+    #[test]
+    fn test_uncommitted_writes_are_one_step() {
+        let mut data = UndoableData::new(Bytes(vec![0]));
+        for i in 1..=5 {
+            data.write_uncommitted(|bytes| bytes.0[0] = i);
+        }
+        data.commit();
+        data.commit();
+        data.undo();
+        assert_eq!(data.read(|bytes| bytes.0.clone()), [0]);
+        assert!(!data.can_undo());
+        data.redo();
+        assert_eq!(data.read(|bytes| bytes.0.clone()), [5]);
+
+        data.write_uncommitted(|bytes| bytes.0.push(6));
+        data.undo();
+        assert_eq!(data.read(|bytes| bytes.0.clone()), [5], "undo commits the pending changes first");
+    }
+
+    #[test]
+    fn test_stack_limit() {
+        let mut data = UndoableData::new(Bytes(vec![]));
+        for i in 0..UndoStack::MAX_STEPS + 10 {
+            data.write(|bytes| bytes.0.push(i as u8));
+        }
+        while data.can_undo() {
+            data.undo();
+        }
+        assert_eq!(data.read(|bytes| bytes.0.len()), 10, "oldest steps are dropped");
+
+        data.clear_stack();
+        assert!(!data.can_undo() && !data.can_redo());
+    }
+}